@@ -0,0 +1,162 @@
+//! Alert rules for layers streamed in remotely, so rscat can serve as a lightweight monitoring
+//! display during field tests. A layer that trips a rule blinks for a few seconds, and with
+//! `--alert-sound` the terminal bell rings.
+//!
+//! A rule is either a filter in the `expression` language, e.g. `z > 10`, which trips when any
+//! point pushed or appended matches it, or `silent SECONDS`, e.g. `silent 5`, which trips once a
+//! layer that has had points pushed to it goes that long without any more.
+
+use std::time::{Duration, Instant};
+
+use crate::expression::Program;
+use crate::rendering::{Line, LineBuffers};
+
+/// How long a layer blinks after tripping a rule.
+const FLASH_DURATION: Duration = Duration::from_secs(5);
+
+/// How often layers are checked for having gone silent.
+pub const CHECK_INTERVAL: Duration = Duration::from_millis(500);
+
+pub enum Condition {
+    /// Some of the points pushed match the filter.
+    Points(Program),
+    /// No points pushed for this long.
+    Silent(Duration),
+}
+
+pub struct Rule {
+    /// The rule as it was written, for the log.
+    pub text: String,
+    pub condition: Condition,
+}
+
+impl Rule {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let text = text.trim();
+        let words: Vec<&str> = text.split_whitespace().collect();
+        let condition = match words.as_slice() {
+            ["silent", seconds] => match seconds
+                .trim_end_matches('s')
+                .parse::<f64>()
+                .ok()
+                .filter(|&silence| silence > 0.0)
+                .and_then(crate::input::seconds)
+            {
+                Some(silence) => Condition::Silent(silence),
+                None => return Err(format!("Invalid silence '{}'", seconds)),
+            },
+            _ => Condition::Points(Program::parse(text).map_err(|e| e.to_string())?),
+        };
+        return Ok(Rule {
+            text: text.to_string(),
+            condition: condition,
+        });
+    }
+}
+
+pub struct Alerts {
+    pub rules: Vec<Rule>,
+    /// Whether tripping a rule rings the terminal bell.
+    pub sound: bool,
+    /// The silence rules that tripped, by index, and when the layer last had points pushed
+    /// then, so each silence is only reported once.
+    silences: Vec<(usize, Instant)>,
+}
+
+impl Alerts {
+    pub fn new(rules: Vec<Rule>, sound: bool) -> Self {
+        Alerts {
+            rules: rules,
+            sound: sound,
+            silences: Vec::new(),
+        }
+    }
+
+    /// Whether any rule waits for layers to go silent, which needs them checked periodically.
+    pub fn watches_silence(&self) -> bool {
+        self.rules.iter().any(|rule| match rule.condition {
+            Condition::Silent(_) => true,
+            Condition::Points(_) => false,
+        })
+    }
+
+    /// Check the points of layer `index` from the `from`th in draw order on, which were just
+    /// pushed, against the filter rules. Returns whether the layer tripped any.
+    pub fn check_points(
+        &self,
+        index: usize,
+        line: &Line,
+        buffers: &mut LineBuffers,
+        from: usize,
+    ) -> bool {
+        let mut tripped = false;
+        for rule in &self.rules {
+            let program = match &rule.condition {
+                Condition::Points(program) => program,
+                Condition::Silent(_) => continue,
+            };
+            match program.count_matches(line, from) {
+                Ok(0) => {}
+                Ok(matches) => {
+                    warn!(
+                        "Alert '{}': {} points of layer {} match",
+                        rule.text, matches, index
+                    );
+                    tripped = true;
+                }
+                Err(e) => error!("Alert '{}' failed on layer {}: {}", rule.text, index, e),
+            }
+        }
+        if tripped {
+            self.trip(buffers);
+        }
+        return tripped;
+    }
+
+    /// Trip the silence rules of the live layers that have gone quiet, and stop the flashes that
+    /// are over. Returns whether any layer needs redrawing.
+    pub fn update(&mut self, line_buffers: &mut [LineBuffers], now: Instant) -> bool {
+        let mut changed = false;
+        for (index, buffers) in line_buffers.iter_mut().enumerate() {
+            if buffers.flash_until.is_some_and(|until| now >= until) {
+                buffers.flash_until = None;
+                changed = true;
+            }
+            let last_pushed = match buffers.last_pushed {
+                Some(last_pushed) => last_pushed,
+                None => continue,
+            };
+            let mut tripped = false;
+            for (i, rule) in self.rules.iter().enumerate() {
+                let silence = match rule.condition {
+                    Condition::Silent(silence) => silence,
+                    Condition::Points(_) => continue,
+                };
+                if now.saturating_duration_since(last_pushed) < silence
+                    || self.silences.contains(&(i, last_pushed))
+                {
+                    continue;
+                }
+                warn!("Alert '{}': no points pushed to layer {}", rule.text, index);
+                self.silences.push((i, last_pushed));
+                tripped = true;
+            }
+            if tripped {
+                self.trip(buffers);
+                changed = true;
+            }
+        }
+        // Forget silences that have ended
+        let live: Vec<Instant> = line_buffers.iter().filter_map(|b| b.last_pushed).collect();
+        self.silences
+            .retain(|(_, last_pushed)| live.contains(last_pushed));
+        return changed;
+    }
+
+    fn trip(&self, buffers: &mut LineBuffers) {
+        buffers.flash_until = Some(Instant::now() + FLASH_DURATION);
+        if self.sound {
+            eprint!("\x07");
+        }
+    }
+}
@@ -0,0 +1,148 @@
+//! `rscat bench`: render a fixed camera sweep as fast as possible and write per-frame timings and
+//! renderer counters as JSON, so performance can be compared across commits and GPUs.
+
+use crate::rendering::stats::FrameStats;
+use std::time::{Duration, Instant};
+
+/// Settings for `rscat bench`.
+#[derive(Debug, Clone)]
+pub struct BenchOptions {
+    pub frames: u32,
+    pub output: std::path::PathBuf,
+}
+
+impl BenchOptions {
+    pub fn default() -> Self {
+        BenchOptions {
+            frames: 1000,
+            output: std::path::PathBuf::from("bench.json"),
+        }
+    }
+}
+
+struct Sample {
+    frame_time: Duration,
+    cpu_time: Duration,
+    draw_calls: u32,
+    points: u64,
+    points_culled: u64,
+    bytes_uploaded: u64,
+}
+
+/// Counter totals at the end of the previous frame, so each sample holds just its own frame.
+struct Totals {
+    draw_calls: u32,
+    points: u64,
+    points_culled: u64,
+    bytes_uploaded: u64,
+}
+
+impl Totals {
+    fn of(stats: &FrameStats) -> Self {
+        Totals {
+            draw_calls: stats.draw_calls,
+            points: stats.points,
+            points_culled: stats.points_culled,
+            bytes_uploaded: stats.bytes_uploaded,
+        }
+    }
+}
+
+pub struct Benchmark {
+    options: BenchOptions,
+    samples: Vec<Sample>,
+    previous: Totals,
+    last_frame: Instant,
+}
+
+impl Benchmark {
+    /// Start measuring from the renderer's current counters, so the initial upload isn't counted.
+    pub fn new(options: BenchOptions, stats: &FrameStats) -> Self {
+        Benchmark {
+            samples: Vec::with_capacity(options.frames as usize),
+            options: options,
+            previous: Totals::of(stats),
+            last_frame: Instant::now(),
+        }
+    }
+
+    /// Camera azimuth for the next frame, one full turn over the run.
+    pub fn azimuth(&self) -> f32 {
+        2.0 * std::f32::consts::PI * self.samples.len() as f32 / self.options.frames as f32
+    }
+
+    /// Record a rendered frame. `stats` must not have been reset since the benchmark started.
+    pub fn record(&mut self, stats: &FrameStats, cpu_time: Duration) {
+        let now = Instant::now();
+        let totals = Totals::of(stats);
+        self.samples.push(Sample {
+            frame_time: now - self.last_frame,
+            cpu_time: cpu_time,
+            draw_calls: totals.draw_calls - self.previous.draw_calls,
+            points: totals.points - self.previous.points,
+            points_culled: totals.points_culled - self.previous.points_culled,
+            bytes_uploaded: totals.bytes_uploaded - self.previous.bytes_uploaded,
+        });
+        self.previous = totals;
+        self.last_frame = now;
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.samples.len() >= self.options.frames as usize
+    }
+
+    /// Write the results to the output file.
+    pub fn write(&self, adapter: &str) -> std::io::Result<()> {
+        let total: Duration = self.samples.iter().map(|sample| sample.frame_time).sum();
+        let frames: Vec<String> = self
+            .samples
+            .iter()
+            .map(|sample| {
+                format!(
+                    concat!(
+                        "{{\"frame_ms\":{:.3},\"cpu_ms\":{:.3},\"draw_calls\":{},",
+                        "\"points\":{},\"points_culled\":{},\"bytes_uploaded\":{}}}"
+                    ),
+                    sample.frame_time.as_secs_f64() * 1000.0,
+                    sample.cpu_time.as_secs_f64() * 1000.0,
+                    sample.draw_calls,
+                    sample.points,
+                    sample.points_culled,
+                    sample.bytes_uploaded
+                )
+            })
+            .collect();
+        let json = format!(
+            concat!(
+                "{{\"adapter\":{},\"frames\":{},\"total_seconds\":{:.3},\"mean_fps\":{:.2},",
+                "\"samples\":[\n{}\n]}}\n"
+            ),
+            json_string(adapter),
+            self.samples.len(),
+            total.as_secs_f64(),
+            self.samples.len() as f64 / total.as_secs_f64().max(f64::EPSILON),
+            frames.join(",\n")
+        );
+        std::fs::write(&self.options.output, json)?;
+        info!(
+            "Wrote {} frames of benchmark results to {}",
+            self.samples.len(),
+            self.options.output.display()
+        );
+        return Ok(());
+    }
+}
+
+pub fn json_string(text: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    return quoted;
+}
@@ -0,0 +1,212 @@
+//! Camera poses such as those solved for by photogrammetry, read from COLMAP `images.txt` files
+//! or CSV, and drawn as wireframe frustums.
+
+use crate::rendering::glyphs::{GlyphLayer, Instance, Shape};
+
+/// Half the width and height of the image plane at unit distance of cameras with unknown
+/// intrinsics: a 4:3 image about 50 degrees across.
+const DEFAULT_HALF_EXTENT: [f64; 2] = [0.47, 0.35];
+
+/// Where a camera was and which way it faced. Cameras look along their Z axis with Y down the
+/// image, as in COLMAP and OpenCV.
+#[derive(Debug, Clone)]
+pub struct CameraPose {
+    pub name: String,
+    pub position: nalgebra::Vector3<f64>,
+    /// From the camera's axes to world space.
+    pub rotation: nalgebra::UnitQuaternion<f64>,
+    /// Half the width and height of the image plane at unit distance in front of the camera.
+    pub half_extent: [f64; 2],
+}
+
+impl CameraPose {
+    /// World direction the camera looks in.
+    pub fn forward(&self) -> nalgebra::Vector3<f64> {
+        return self.rotation * nalgebra::Vector3::z();
+    }
+
+    /// World direction of the top of the camera's image.
+    pub fn up(&self) -> nalgebra::Vector3<f64> {
+        return self.rotation * -nalgebra::Vector3::y();
+    }
+
+    /// Vertical field of view, in radians.
+    pub fn fovy(&self) -> f64 {
+        return 2.0 * self.half_extent[1].atan();
+    }
+}
+
+/// Read a COLMAP `images.txt`, along with the `cameras.txt` beside it for the intrinsics if there
+/// is one, or CSV rows of X, Y, Z, the camera to world rotation as a quaternion W, X, Y, Z and
+/// optionally image width, height and focal lengths FX, FY in pixels.
+pub fn load(path: &std::path::Path) -> Result<Vec<CameraPose>, String> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let extension = path.extension().and_then(|extension| extension.to_str());
+    if extension.map(|extension| extension.to_lowercase()) == Some("txt".to_string()) {
+        let intrinsics = match std::fs::read_to_string(path.with_file_name("cameras.txt")) {
+            Ok(cameras) => parse_colmap_cameras(&cameras)?,
+            Err(_) => std::collections::HashMap::new(),
+        };
+        return parse_colmap_images(&text, &intrinsics);
+    }
+    return parse_csv(&text);
+}
+
+fn parse_csv(text: &str) -> Result<Vec<CameraPose>, String> {
+    let mut poses = Vec::new();
+    for (i, row) in text.lines().enumerate() {
+        let fields: Vec<&str> = row.split(',').map(|field| field.trim()).collect();
+        if row.trim().is_empty() || (i == 0 && fields[0].parse::<f64>().is_err()) {
+            continue;
+        }
+        if fields.len() != 7 && fields.len() != 11 {
+            return Err(format!(
+                "Camera row {} needs 7 cols: X, Y, Z, QW, QX, QY, QZ, or 11 adding width, \
+                 height, FX, FY",
+                i + 1
+            ));
+        }
+        let values = fields
+            .iter()
+            .map(|field| field.parse::<f64>())
+            .collect::<Result<Vec<f64>, _>>()
+            .map_err(|_| format!("Invalid number in camera row {}", i + 1))?;
+        let quaternion = nalgebra::Quaternion::new(values[3], values[4], values[5], values[6]);
+        poses.push(CameraPose {
+            name: poses.len().to_string(),
+            position: nalgebra::Vector3::new(values[0], values[1], values[2]),
+            rotation: nalgebra::UnitQuaternion::from_quaternion(quaternion),
+            half_extent: if values.len() == 11 {
+                [
+                    values[7] / (2.0 * values[9]),
+                    values[8] / (2.0 * values[10]),
+                ]
+            } else {
+                DEFAULT_HALF_EXTENT
+            },
+        });
+    }
+    return Ok(poses);
+}
+
+/// Half extents of each camera in a COLMAP `cameras.txt`, by camera ID.
+fn parse_colmap_cameras(text: &str) -> Result<std::collections::HashMap<u64, [f64; 2]>, String> {
+    let mut cameras = std::collections::HashMap::new();
+    for (i, line) in text.lines().enumerate() {
+        // CAMERA_ID MODEL WIDTH HEIGHT PARAMS...
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.is_empty() || fields[0].starts_with('#') {
+            continue;
+        }
+        let invalid = || format!("Invalid camera on line {} of cameras.txt", i + 1);
+        if fields.len() < 5 {
+            return Err(invalid());
+        }
+        let id = fields[0].parse::<u64>().map_err(|_| invalid())?;
+        let numbers = fields[2..]
+            .iter()
+            .map(|field| field.parse::<f64>())
+            .collect::<Result<Vec<f64>, _>>()
+            .map_err(|_| invalid())?;
+        // Models with a single focal length list it first, the rest list FX then FY
+        let (fx, fy) = if fields[1].starts_with("SIMPLE") || fields[1].starts_with("RADIAL") {
+            (numbers[2], numbers[2])
+        } else if numbers.len() >= 4 {
+            (numbers[2], numbers[3])
+        } else {
+            return Err(invalid());
+        };
+        cameras.insert(id, [numbers[0] / (2.0 * fx), numbers[1] / (2.0 * fy)]);
+    }
+    return Ok(cameras);
+}
+
+/// Poses in a COLMAP `images.txt`, with the intrinsics of their camera IDs.
+fn parse_colmap_images(
+    text: &str,
+    intrinsics: &std::collections::HashMap<u64, [f64; 2]>,
+) -> Result<Vec<CameraPose>, String> {
+    let mut poses = Vec::new();
+    // Each image takes two lines, the second listing its 2D points, which may be empty
+    let lines: Vec<(usize, &str)> = text
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.starts_with('#'))
+        .collect();
+    for &(i, line) in lines.iter().step_by(2) {
+        // IMAGE_ID QW QX QY QZ TX TY TZ CAMERA_ID NAME
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.is_empty() {
+            continue;
+        }
+        let invalid = || format!("Invalid image on line {} of images.txt", i + 1);
+        if fields.len() < 10 {
+            return Err(invalid());
+        }
+        let values = fields[1..9]
+            .iter()
+            .map(|field| field.parse::<f64>())
+            .collect::<Result<Vec<f64>, _>>()
+            .map_err(|_| invalid())?;
+        let camera = fields[8].parse::<u64>().map_err(|_| invalid())?;
+        // COLMAP stores the transform from world to camera space
+        let quaternion = nalgebra::Quaternion::new(values[0], values[1], values[2], values[3]);
+        let to_camera = nalgebra::UnitQuaternion::from_quaternion(quaternion);
+        let translation = nalgebra::Vector3::new(values[4], values[5], values[6]);
+        poses.push(CameraPose {
+            name: fields[9..].join(" "),
+            position: -(to_camera.inverse() * translation),
+            rotation: to_camera.inverse(),
+            half_extent: intrinsics
+                .get(&camera)
+                .cloned()
+                .unwrap_or(DEFAULT_HALF_EXTENT),
+        });
+    }
+    return Ok(poses);
+}
+
+/// A frustum for each camera reaching `depth` in front of it, by default half the average
+/// distance between consecutive cameras, drawn as a wireframe in `color`.
+pub fn frustums(poses: &[CameraPose], depth: Option<f64>, color: [f32; 4]) -> GlyphLayer {
+    let depth = depth.unwrap_or_else(|| {
+        let gaps: Vec<f64> = poses
+            .windows(2)
+            .map(|pair| (pair[1].position - pair[0].position).norm())
+            .collect();
+        let average = gaps.iter().sum::<f64>() / gaps.len() as f64;
+        if average > 0.0 {
+            average / 2.0
+        } else {
+            1.0
+        }
+    });
+    // Relative to the first camera, so distant scenes keep their precision as f32
+    let origin = poses
+        .first()
+        .map_or(nalgebra::Vector3::zeros(), |pose| pose.position);
+    let instances = poses
+        .iter()
+        .map(|pose| {
+            let scale = nalgebra::Vector3::new(
+                pose.half_extent[0] * depth,
+                pose.half_extent[1] * depth,
+                depth,
+            );
+            let rotation = pose.rotation.to_rotation_matrix();
+            let axes = rotation.matrix() * nalgebra::Matrix3::from_diagonal(&scale);
+            let mut transform = axes.to_homogeneous();
+            transform
+                .fixed_slice_mut::<nalgebra::U3, nalgebra::U1>(0, 3)
+                .copy_from(&(pose.position - origin));
+            Instance::new(nalgebra::convert(transform), color)
+        })
+        .collect();
+    return GlyphLayer {
+        shape: Shape::Frustum,
+        instances: instances,
+        origin: origin,
+        wireframe: true,
+    };
+}
@@ -0,0 +1,129 @@
+use std::path::PathBuf;
+
+/// Per-user directory for settings files, e.g. `%APPDATA%\rscat` or `~/.config/rscat`.
+pub fn config_dir() -> Option<PathBuf> {
+    let base = if cfg!(windows) {
+        std::env::var_os("APPDATA").map(PathBuf::from)
+    } else {
+        std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+    };
+    return base.map(|base| base.join("rscat"));
+}
+
+/// Read a settings file of `key = value` lines. Blank lines and lines starting with `#` are
+/// ignored. A missing file reads as empty.
+pub fn read_settings(path: &std::path::Path) -> Vec<(String, String)> {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(_) => return Vec::new(),
+    };
+    let mut settings = Vec::new();
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match line.find('=') {
+            Some(split) => settings.push((
+                line[..split].trim().to_string(),
+                line[split + 1..].trim().to_string(),
+            )),
+            None => warn!("Ignoring malformed line in {}: {}", path.display(), line),
+        }
+    }
+    return settings;
+}
+
+/// Write `key = value` lines, creating the config directory if needed. They are written to a
+/// temporary file beside `path` that then replaces it, so a crash part-way through leaves the
+/// old file rather than a truncated one.
+pub fn write_settings(path: &std::path::Path, settings: &[(&str, String)]) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let mut text = String::new();
+    for (key, value) in settings {
+        text.push_str(&format!("{} = {}\n", key, value));
+    }
+    // Named for this process, so two instances saving at once don't write into the same file
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(format!(".{}.tmp", std::process::id()));
+    let temporary = PathBuf::from(temporary);
+    if let Err(e) = std::fs::write(&temporary, text) {
+        let _ = std::fs::remove_file(&temporary);
+        return Err(e);
+    }
+    return std::fs::rename(&temporary, path);
+}
+
+/// Size, position and maximized state of the main window, restored on the next launch.
+#[derive(Debug, Copy, Clone)]
+pub struct WindowState {
+    pub size: winit::dpi::PhysicalSize<u32>,
+    pub position: Option<winit::dpi::PhysicalPosition<i32>>,
+    pub maximized: bool,
+}
+
+impl WindowState {
+    fn path() -> Option<PathBuf> {
+        config_dir().map(|dir| dir.join("window.cfg"))
+    }
+
+    /// Returns `None` if no state has been saved yet or it couldn't be read.
+    pub fn load() -> Option<Self> {
+        let settings = read_settings(&WindowState::path()?);
+        let get = |key: &str| {
+            settings
+                .iter()
+                .find(|(k, _)| k == key)
+                .and_then(|(_, value)| value.parse::<i64>().ok())
+        };
+        let width = get("width")?;
+        let height = get("height")?;
+        if width <= 0 || height <= 0 {
+            return None;
+        }
+        let position = match (get("x"), get("y")) {
+            (Some(x), Some(y)) => Some(winit::dpi::PhysicalPosition::new(x as i32, y as i32)),
+            _ => None,
+        };
+        return Some(WindowState {
+            size: winit::dpi::PhysicalSize::new(width as u32, height as u32),
+            position: position,
+            maximized: get("maximized").is_some_and(|maximized| maximized != 0),
+        });
+    }
+
+    /// Capture the current state of `window`.
+    pub fn from_window(window: &winit::window::Window) -> Self {
+        // winit doesn't report whether a window is maximized, so treat a window that fills the
+        // width of its monitor as maximized
+        let maximized = window.outer_size().width >= window.current_monitor().size().width;
+        WindowState {
+            size: window.inner_size(),
+            position: window.outer_position().ok(),
+            maximized: maximized,
+        }
+    }
+
+    pub fn save(&self) {
+        let path = match WindowState::path() {
+            Some(path) => path,
+            None => return,
+        };
+        let mut settings = vec![
+            ("width", self.size.width.to_string()),
+            ("height", self.size.height.to_string()),
+            ("maximized", (self.maximized as u8).to_string()),
+        ];
+        if let Some(position) = self.position {
+            settings.push(("x", position.x.to_string()));
+            settings.push(("y", position.y.to_string()));
+        }
+        if let Err(e) = write_settings(&path, &settings) {
+            warn!("Failed to save window state to {}: {}", path.display(), e);
+        }
+    }
+}
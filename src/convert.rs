@@ -0,0 +1,154 @@
+//! `rscat convert`: load files or whole directories headlessly, optionally crop and thin them, and
+//! write them out in another format for data preparation pipelines.
+
+use crate::error::RscatError;
+use crate::rendering::Line;
+use std::path::{Path, PathBuf};
+
+/// Settings for `rscat convert`.
+#[derive(Debug, Clone)]
+pub struct ConvertOptions {
+    pub format: Format,
+    /// Keep one point per cube of this size.
+    pub voxel: Option<f64>,
+    /// Keep only points inside this box, as minimum and maximum corners.
+    pub crop: Option<([f64; 3], [f64; 3])>,
+    /// Directory to write to, next to each input if not given.
+    pub output: Option<PathBuf>,
+}
+
+impl ConvertOptions {
+    pub fn default() -> Self {
+        ConvertOptions {
+            format: Format::Csv,
+            voxel: None,
+            crop: None,
+            output: None,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Format {
+    Csv,
+    Ply,
+}
+
+/// Names used for output formats on the command line.
+pub const FORMAT_NAMES: &[(&str, Format)] = &[("csv", Format::Csv), ("ply", Format::Ply)];
+
+impl Format {
+    fn extension(self) -> &'static str {
+        match self {
+            Format::Csv => "csv",
+            Format::Ply => "ply",
+        }
+    }
+}
+
+/// Convert every file in `inputs`, and every file a loader handles in directories in `inputs`.
+pub fn run(
+    options: &ConvertOptions,
+    inputs: &[PathBuf],
+    loaders: &crate::loaders::Registry,
+) -> Result<(), RscatError> {
+    let mut files = Vec::new();
+    for input in inputs {
+        if input.is_dir() {
+            let mut entries = std::fs::read_dir(input)?
+                .map(|entry| entry.map(|entry| entry.path()))
+                .collect::<Result<Vec<PathBuf>, std::io::Error>>()?;
+            entries.retain(|path| path.is_file() && loaders.find(path).is_some());
+            entries.sort();
+            files.extend(entries);
+        } else {
+            files.push(input.clone());
+        }
+    }
+    if files.is_empty() {
+        return Err(RscatError::Invalid("Nothing to convert".to_string()));
+    }
+    for file in &files {
+        let lines = loaders.load(file)?;
+        let count = lines.len();
+        for (i, line) in lines.into_iter().enumerate() {
+            let line = filter(line, options);
+            let output = output_path(file, i, count, options);
+            if output.exists() && output.canonicalize()? == file.canonicalize()? {
+                let message = format!("Converting {} would overwrite it", file.display());
+                return Err(RscatError::Invalid(message));
+            }
+            let writer = std::io::BufWriter::new(std::fs::File::create(&output)?);
+            match options.format {
+                Format::Csv => crate::loaders::csv::write(writer, &line)?,
+                Format::Ply => crate::loaders::ply::write(writer, &line)?,
+            }
+            println!(
+                "Wrote {} points to {}",
+                line.indicies.len(),
+                output.display()
+            );
+        }
+    }
+    return Ok(());
+}
+
+/// Where layer `index` of the `count` loaded from `input` is written.
+fn output_path(input: &Path, index: usize, count: usize, options: &ConvertOptions) -> PathBuf {
+    let stem = input
+        .file_stem()
+        .map_or("points".into(), |stem| stem.to_string_lossy());
+    let name = if count == 1 {
+        format!("{}.{}", stem, options.format.extension())
+    } else {
+        format!("{}-{}.{}", stem, index, options.format.extension())
+    };
+    let directory = match &options.output {
+        Some(directory) => directory.as_path(),
+        None => input.parent().unwrap_or_else(|| Path::new("")),
+    };
+    return directory.join(name);
+}
+
+/// Drop the points outside the crop box, then all but the first point in each voxel.
+fn filter(mut line: Line, options: &ConvertOptions) -> Line {
+    let world = |line: &Line, index: u32| {
+        let position = line.verticies[index as usize].position;
+        [
+            line.origin.x + position[0] as f64,
+            line.origin.y + position[1] as f64,
+            line.origin.z + position[2] as f64,
+        ]
+    };
+    if let Some((min, max)) = options.crop {
+        let indicies = line
+            .indicies
+            .iter()
+            .cloned()
+            .filter(|&index| {
+                let p = world(&line, index);
+                (0..3).all(|axis| p[axis] >= min[axis] && p[axis] <= max[axis])
+            })
+            .collect();
+        line.indicies = indicies;
+    }
+    if let Some(voxel) = options.voxel {
+        let mut occupied = std::collections::HashSet::new();
+        let indicies = line
+            .indicies
+            .iter()
+            .cloned()
+            .filter(|&index| {
+                let p = world(&line, index);
+                let cell = [
+                    (p[0] / voxel).floor() as i64,
+                    (p[1] / voxel).floor() as i64,
+                    (p[2] / voxel).floor() as i64,
+                ];
+                occupied.insert(cell)
+            })
+            .collect();
+        line.indicies = indicies;
+    }
+    return line;
+}
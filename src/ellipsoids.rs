@@ -0,0 +1,77 @@
+//! Ellipsoids such as the uncertainties of SLAM landmarks, read from CSV rows of centre and either
+//! covariance or radii and orientation.
+
+use crate::rendering::glyphs::{GlyphLayer, Instance, Shape};
+
+/// Parse rows of centre X, Y, Z, in scene coordinates, followed by either the covariance XX, XY,
+/// XZ, YY, YZ, ZZ or the radii RX, RY, RZ and the orientation as a quaternion W, X, Y, Z. A
+/// header row is skipped. Covariances are drawn `sigma` standard deviations out.
+pub fn parse(
+    text: &str,
+    sigma: f64,
+    color: [f32; 4],
+    wireframe: bool,
+) -> Result<GlyphLayer, String> {
+    let mut rows = Vec::new();
+    for (i, row) in text.lines().enumerate() {
+        let fields: Vec<&str> = row.split(',').map(|field| field.trim()).collect();
+        if row.trim().is_empty() || (i == 0 && fields[0].parse::<f64>().is_err()) {
+            continue;
+        }
+        if fields.len() != 9 && fields.len() != 10 {
+            return Err(format!(
+                "Ellipsoid row {} needs 9 cols: X, Y, Z and covariance XX, XY, XZ, YY, YZ, ZZ, or \
+                 10 cols: X, Y, Z, radii RX, RY, RZ and quaternion W, X, Y, Z",
+                i + 1
+            ));
+        }
+        let values = fields
+            .iter()
+            .map(|field| field.parse::<f64>())
+            .collect::<Result<Vec<f64>, _>>()
+            .map_err(|_| format!("Invalid number in ellipsoid row {}", i + 1))?;
+        rows.push(values);
+    }
+    // Relative to the first ellipsoid, so distant ones keep their precision as f32
+    let origin = rows.first().map_or(nalgebra::Vector3::zeros(), |row| {
+        nalgebra::Vector3::new(row[0], row[1], row[2])
+    });
+    let instances = rows
+        .iter()
+        .map(|row| {
+            let (rotation, radii) = if row.len() == 9 {
+                let covariance = nalgebra::Matrix3::new(
+                    row[3], row[4], row[5], row[4], row[6], row[7], row[5], row[7], row[8],
+                );
+                // The axes of the ellipsoid are the eigenvectors, each as long as the standard
+                // deviation along it
+                let eigen = covariance.symmetric_eigen();
+                let radii = eigen
+                    .eigenvalues
+                    .map(|variance| sigma * variance.max(0.0).sqrt());
+                (eigen.eigenvectors, radii)
+            } else {
+                let quaternion = nalgebra::Quaternion::new(row[6], row[7], row[8], row[9]);
+                let rotation = nalgebra::UnitQuaternion::from_quaternion(quaternion);
+                let radii = nalgebra::Vector3::new(row[3].abs(), row[4].abs(), row[5].abs());
+                (*rotation.to_rotation_matrix().matrix(), radii)
+            };
+            // Flattened ellipsoids keep a sliver of thickness so they can still be shaded
+            let thinnest = radii.max() * 1e-6;
+            let radii = radii.map(|radius| radius.max(thinnest));
+            let center = nalgebra::Vector3::new(row[0], row[1], row[2]) - origin;
+            let axes = rotation * nalgebra::Matrix3::from_diagonal(&radii);
+            let mut transform = axes.to_homogeneous();
+            transform
+                .fixed_slice_mut::<nalgebra::U3, nalgebra::U1>(0, 3)
+                .copy_from(&center);
+            Instance::new(nalgebra::convert(transform), color)
+        })
+        .collect();
+    return Ok(GlyphLayer {
+        shape: Shape::Sphere,
+        instances: instances,
+        origin: origin,
+        wireframe: wireframe,
+    });
+}
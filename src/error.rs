@@ -0,0 +1,32 @@
+//! The crate's error type, so callers can tell failures apart and handle them rather than only
+//! show their message.
+
+use std::path::PathBuf;
+
+#[derive(Debug, thiserror::Error)]
+pub enum RscatError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error("No loader for {}", .0.display())]
+    NoLoader(PathBuf),
+    /// Input that isn't in the format its loader expects.
+    #[error("{0}")]
+    Format(String),
+    /// Something asked for that can't be done with what's loaded, e.g. a layer that isn't there.
+    #[error("{0}")]
+    Invalid(String),
+    /// The GPU couldn't do what was asked, e.g. compile a shader or read back a frame.
+    #[error("GPU error: {0}")]
+    Gpu(String),
+    #[error("Network error on {address}: {source}")]
+    Network {
+        address: String,
+        source: std::io::Error,
+    },
+    /// A loader plugin that couldn't be used, see `loaders::plugin`.
+    #[error("Plugin {}: {message}", path.display())]
+    Plugin { path: PathBuf, message: String },
+    /// An expression that couldn't be parsed or evaluated.
+    #[error("{0}")]
+    Script(String),
+}
@@ -0,0 +1,766 @@
+//! A small expression language for deriving a new layer from an existing one without a round trip
+//! through a script.
+//!
+//! A program is a list of statements separated by `;` or new lines, applied in order to every
+//! point of the layer:
+//! - `z > 1.5 && intensity < 100` keeps only the points for which the expression is non-zero
+//! - `color = colormap(intensity)` or `pos = pos - centroid` assigns to `x`, `y`, `z`, `pos`, `r`,
+//!   `g`, `b`, `color`, `size` or an attribute, adding the attribute if the layer doesn't have it
+//!
+//! Expressions use numbers such as `2`, `0.5` or `2.5e-3`, `+ - * /`, comparisons, `&& || !` and
+//! parentheses over the point's `x`, `y`, `z`, `r`, `g`, `b`, `size`, attributes, the vectors
+//! `pos`, `color` and `centroid` (the mean position of the points), and the functions `abs`,
+//! `sqrt`, `min`, `max`, `length`, `vec(x, y, z)` and `colormap(v)`, which colours `v` with
+//! viridis across its range.
+
+use crate::error::RscatError;
+use crate::rendering::{Attribute, Line, Vertex};
+
+pub struct Program {
+    source: String,
+    statements: Vec<Statement>,
+}
+
+enum Statement {
+    Filter(Expr),
+    Assign(String, Expr),
+}
+
+enum Expr {
+    Number(f32),
+    Variable(String),
+    Call(String, Vec<Expr>),
+    Negate(Box<Expr>),
+    Not(Box<Expr>),
+    Binary(&'static str, Box<Expr>, Box<Expr>),
+}
+
+/// An expression's value for every point.
+enum Column {
+    Scalar(Vec<f32>),
+    Vector(Vec<[f32; 3]>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f32),
+    Name(String),
+    Symbol(&'static str),
+}
+
+/// Longer symbols first, so `<=` isn't read as `<` then `=`.
+const SYMBOLS: &[&str] = &[
+    "==", "!=", "<=", ">=", "&&", "||", "+", "-", "*", "/", "<", ">", "!", "=", "(", ")", ",", ";",
+];
+
+impl Program {
+    pub fn parse(source: &str) -> Result<Self, RscatError> {
+        return Program::compile(source).map_err(RscatError::Script);
+    }
+
+    fn compile(source: &str) -> Result<Self, String> {
+        let tokens = tokenize(source)?;
+        let mut parser = Parser {
+            tokens: tokens,
+            position: 0,
+        };
+        let mut statements = Vec::new();
+        while parser.position < parser.tokens.len() {
+            if parser.eat(";") {
+                continue;
+            }
+            statements.push(parser.statement()?);
+            if parser.position < parser.tokens.len() && !parser.eat(";") {
+                return Err(format!("Expected ';' but found {}", parser.describe()));
+            }
+        }
+        if statements.is_empty() {
+            return Err("The expression is empty".to_string());
+        }
+        return Ok(Program {
+            source: source.trim().to_string(),
+            statements: statements,
+        });
+    }
+
+    /// Apply the program to the points of `line` in draw order, giving a new line.
+    pub fn evaluate(&self, line: &Line) -> Result<Line, RscatError> {
+        return self.run(line, &line.indicies).map_err(RscatError::Script);
+    }
+
+    /// How many of the points of `line` from the `from`th in draw order on the program keeps, e.g.
+    /// for the points just appended to a live layer.
+    pub fn count_matches(&self, line: &Line, from: usize) -> Result<usize, RscatError> {
+        let from = from.min(line.indicies.len());
+        let kept = self
+            .run(line, &line.indicies[from..])
+            .map_err(RscatError::Script)?;
+        return Ok(kept.indicies.len());
+    }
+
+    fn run(&self, line: &Line, indices: &[u32]) -> Result<Line, String> {
+        let mut points = Points {
+            vertices: indices
+                .iter()
+                .map(|&i| line.verticies[i as usize])
+                .collect(),
+            attributes: line
+                .attributes
+                .iter()
+                .map(|attribute| Attribute {
+                    name: attribute.name.clone(),
+                    values: indices
+                        .iter()
+                        .map(|&i| attribute.values[i as usize])
+                        .collect(),
+                })
+                .collect(),
+            ids: line
+                .ids
+                .as_ref()
+                .map(|ids| indices.iter().map(|&i| ids[i as usize]).collect()),
+        };
+        for statement in &self.statements {
+            match statement {
+                Statement::Filter(expr) => {
+                    let values = scalars(points.evaluate(expr)?, "a filter")?;
+                    let keep: Vec<bool> = values.iter().map(|&v| v != 0.0 && !v.is_nan()).collect();
+                    points.retain(&keep);
+                }
+                Statement::Assign(name, expr) => {
+                    let column = points.evaluate(expr)?;
+                    points.assign(name, column)?;
+                }
+            }
+        }
+        return Ok(Line {
+            indicies: crate::rendering::defaults::render_all_vertices(&points.vertices),
+            verticies: points.vertices,
+            origin: line.origin,
+            attributes: points.attributes,
+            ids: points.ids,
+            metadata: line
+                .metadata
+                .iter()
+                .cloned()
+                .chain(std::iter::once((
+                    "expression".to_string(),
+                    self.source.clone(),
+                )))
+                .collect(),
+        });
+    }
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut rest = source;
+    while let Some(c) = rest.chars().next() {
+        if c == '\n' {
+            tokens.push(Token::Symbol(";"));
+            rest = &rest[1..];
+        } else if c.is_whitespace() {
+            rest = &rest[c.len_utf8()..];
+        } else if c.is_ascii_digit() || c == '.' {
+            let mut end = rest
+                .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+                .unwrap_or(rest.len());
+            // An exponent, e.g. the e-3 of 2.5e-3
+            let exponent = rest[end..].strip_prefix(|c: char| c == 'e' || c == 'E');
+            if let Some(exponent) = exponent {
+                let digits = exponent
+                    .strip_prefix(|c: char| c == '+' || c == '-')
+                    .unwrap_or(exponent);
+                let count = digits
+                    .find(|c: char| !c.is_ascii_digit())
+                    .unwrap_or(digits.len());
+                if count > 0 {
+                    end = rest.len() - digits.len() + count;
+                }
+            }
+            let number = rest[..end]
+                .parse::<f32>()
+                .map_err(|_| format!("Invalid number '{}'", &rest[..end]))?;
+            tokens.push(Token::Number(number));
+            rest = &rest[end..];
+        } else if c.is_alphabetic() || c == '_' {
+            let end = rest
+                .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            tokens.push(Token::Name(rest[..end].to_string()));
+            rest = &rest[end..];
+        } else {
+            let symbol = SYMBOLS
+                .iter()
+                .find(|symbol| rest.starts_with(**symbol))
+                .ok_or_else(|| format!("Unexpected '{}'", c))?;
+            tokens.push(Token::Symbol(symbol));
+            rest = &rest[symbol.len()..];
+        }
+    }
+    return Ok(tokens);
+}
+
+/// Recursive descent over the tokens, one method per precedence level, loosest first.
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn statement(&mut self) -> Result<Statement, String> {
+        if let (Some(Token::Name(name)), Some(Token::Symbol("="))) = (
+            self.tokens.get(self.position),
+            self.tokens.get(self.position + 1),
+        ) {
+            let name = name.clone();
+            self.position += 2;
+            return Ok(Statement::Assign(name, self.expression()?));
+        }
+        return Ok(Statement::Filter(self.expression()?));
+    }
+
+    fn expression(&mut self) -> Result<Expr, String> {
+        let mut expr = self.and()?;
+        while self.eat("||") {
+            expr = Expr::Binary("||", Box::new(expr), Box::new(self.and()?));
+        }
+        return Ok(expr);
+    }
+
+    fn and(&mut self) -> Result<Expr, String> {
+        let mut expr = self.comparison()?;
+        while self.eat("&&") {
+            expr = Expr::Binary("&&", Box::new(expr), Box::new(self.comparison()?));
+        }
+        return Ok(expr);
+    }
+
+    fn comparison(&mut self) -> Result<Expr, String> {
+        let expr = self.sum()?;
+        for &op in &["==", "!=", "<=", ">=", "<", ">"] {
+            if self.eat(op) {
+                return Ok(Expr::Binary(op, Box::new(expr), Box::new(self.sum()?)));
+            }
+        }
+        return Ok(expr);
+    }
+
+    fn sum(&mut self) -> Result<Expr, String> {
+        let mut expr = self.product()?;
+        loop {
+            let op = if self.eat("+") {
+                "+"
+            } else if self.eat("-") {
+                "-"
+            } else {
+                return Ok(expr);
+            };
+            expr = Expr::Binary(op, Box::new(expr), Box::new(self.product()?));
+        }
+    }
+
+    fn product(&mut self) -> Result<Expr, String> {
+        let mut expr = self.unary()?;
+        loop {
+            let op = if self.eat("*") {
+                "*"
+            } else if self.eat("/") {
+                "/"
+            } else {
+                return Ok(expr);
+            };
+            expr = Expr::Binary(op, Box::new(expr), Box::new(self.unary()?));
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        if self.eat("-") {
+            return Ok(Expr::Negate(Box::new(self.unary()?)));
+        }
+        if self.eat("!") {
+            return Ok(Expr::Not(Box::new(self.unary()?)));
+        }
+        return self.atom();
+    }
+
+    fn atom(&mut self) -> Result<Expr, String> {
+        match self.tokens.get(self.position).cloned() {
+            Some(Token::Number(number)) => {
+                self.position += 1;
+                return Ok(Expr::Number(number));
+            }
+            Some(Token::Name(name)) => {
+                self.position += 1;
+                if !self.eat("(") {
+                    return Ok(Expr::Variable(name));
+                }
+                let mut args = Vec::new();
+                if !self.eat(")") {
+                    loop {
+                        args.push(self.expression()?);
+                        if self.eat(")") {
+                            break;
+                        }
+                        if !self.eat(",") {
+                            let found = self.describe();
+                            return Err(format!("Expected ',' or ')' but found {}", found));
+                        }
+                    }
+                }
+                return Ok(Expr::Call(name, args));
+            }
+            Some(Token::Symbol("(")) => {
+                self.position += 1;
+                let expr = self.expression()?;
+                if !self.eat(")") {
+                    return Err(format!("Expected ')' but found {}", self.describe()));
+                }
+                return Ok(expr);
+            }
+            _ => return Err(format!("Expected a value but found {}", self.describe())),
+        }
+    }
+
+    /// Skip the next token if it is `symbol`.
+    fn eat(&mut self, symbol: &str) -> bool {
+        match self.tokens.get(self.position) {
+            Some(Token::Symbol(next)) if *next == symbol => {
+                self.position += 1;
+                return true;
+            }
+            _ => return false,
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self.tokens.get(self.position) {
+            Some(Token::Number(number)) => format!("'{}'", number),
+            Some(Token::Name(name)) => format!("'{}'", name),
+            Some(Token::Symbol(symbol)) => format!("'{}'", symbol),
+            None => "the end".to_string(),
+        }
+    }
+}
+
+/// The points being derived, in draw order.
+struct Points {
+    vertices: Vec<Vertex>,
+    attributes: Vec<Attribute>,
+    ids: Option<Vec<u32>>,
+}
+
+impl Points {
+    fn evaluate(&self, expr: &Expr) -> Result<Column, String> {
+        let len = self.vertices.len();
+        match expr {
+            Expr::Number(number) => Ok(Column::Scalar(vec![*number; len])),
+            Expr::Variable(name) => self.variable(name),
+            Expr::Negate(inner) => Ok(map(self.evaluate(inner)?, |v| -v)),
+            Expr::Not(inner) => {
+                let values = scalars(self.evaluate(inner)?, "!")?;
+                Ok(Column::Scalar(
+                    values.iter().map(|&v| truth(v == 0.0)).collect(),
+                ))
+            }
+            Expr::Binary(op, left, right) => {
+                binary(op, self.evaluate(left)?, self.evaluate(right)?)
+            }
+            Expr::Call(name, args) => {
+                let args = args
+                    .iter()
+                    .map(|arg| self.evaluate(arg))
+                    .collect::<Result<Vec<Column>, String>>()?;
+                call(name, args)
+            }
+        }
+    }
+
+    fn variable(&self, name: &str) -> Result<Column, String> {
+        let component =
+            |get: &dyn Fn(&Vertex) -> f32| Column::Scalar(self.vertices.iter().map(get).collect());
+        let column = match name {
+            "x" => component(&|vertex| vertex.position[0]),
+            "y" => component(&|vertex| vertex.position[1]),
+            "z" => component(&|vertex| vertex.position[2]),
+            "r" => component(&|vertex| vertex.color[0]),
+            "g" => component(&|vertex| vertex.color[1]),
+            "b" => component(&|vertex| vertex.color[2]),
+            "size" => component(&|vertex| vertex.size),
+            "pos" => Column::Vector(self.vertices.iter().map(|v| xyz(&v.position)).collect()),
+            "color" => Column::Vector(self.vertices.iter().map(|v| xyz(&v.color)).collect()),
+            "centroid" => {
+                let mut sum = [0.0_f64; 3];
+                for vertex in &self.vertices {
+                    for (total, &coordinate) in sum.iter_mut().zip(&vertex.position) {
+                        *total += coordinate as f64;
+                    }
+                }
+                let count = self.vertices.len().max(1) as f64;
+                let mean = [
+                    (sum[0] / count) as f32,
+                    (sum[1] / count) as f32,
+                    (sum[2] / count) as f32,
+                ];
+                Column::Vector(vec![mean; self.vertices.len()])
+            }
+            _ => match self
+                .attributes
+                .iter()
+                .find(|attribute| attribute.name == name)
+            {
+                Some(attribute) => Column::Scalar(attribute.values.clone()),
+                None => return Err(format!("Unknown variable '{}'", name)),
+            },
+        };
+        return Ok(column);
+    }
+
+    fn assign(&mut self, name: &str, column: Column) -> Result<(), String> {
+        match (name, column) {
+            ("pos", Column::Vector(values)) => {
+                for (vertex, value) in self.vertices.iter_mut().zip(values) {
+                    vertex.position[..3].copy_from_slice(&value);
+                }
+            }
+            ("color", Column::Vector(values)) => {
+                for (vertex, value) in self.vertices.iter_mut().zip(values) {
+                    vertex.color[..3].copy_from_slice(&value);
+                }
+            }
+            ("pos", _) | ("color", _) => {
+                return Err(format!("'{}' must be assigned a vector", name));
+            }
+            (_, Column::Vector(_)) => return Err(format!("'{}' must be assigned a number", name)),
+            (_, Column::Scalar(values)) => {
+                let set: Option<fn(&mut Vertex, f32)> = match name {
+                    "x" => Some(|vertex, value| vertex.position[0] = value),
+                    "y" => Some(|vertex, value| vertex.position[1] = value),
+                    "z" => Some(|vertex, value| vertex.position[2] = value),
+                    "r" => Some(|vertex, value| vertex.color[0] = value),
+                    "g" => Some(|vertex, value| vertex.color[1] = value),
+                    "b" => Some(|vertex, value| vertex.color[2] = value),
+                    "size" => Some(|vertex, value| vertex.size = value),
+                    _ => None,
+                };
+                match set {
+                    Some(set) => {
+                        for (vertex, value) in self.vertices.iter_mut().zip(values) {
+                            set(vertex, value);
+                        }
+                    }
+                    None => match self.attributes.iter_mut().find(|a| a.name == name) {
+                        Some(attribute) => attribute.values = values,
+                        None => self.attributes.push(Attribute {
+                            name: name.to_string(),
+                            values: values,
+                        }),
+                    },
+                }
+            }
+        }
+        return Ok(());
+    }
+
+    fn retain(&mut self, keep: &[bool]) {
+        let mut keep_vertex = keep.iter();
+        self.vertices.retain(|_| *keep_vertex.next().unwrap());
+        for attribute in self.attributes.iter_mut() {
+            let mut keep_value = keep.iter();
+            attribute.values.retain(|_| *keep_value.next().unwrap());
+        }
+        if let Some(ids) = &mut self.ids {
+            let mut keep_id = keep.iter();
+            ids.retain(|_| *keep_id.next().unwrap());
+        }
+    }
+}
+
+fn xyz(values: &[f32; 4]) -> [f32; 3] {
+    [values[0], values[1], values[2]]
+}
+
+fn truth(value: bool) -> f32 {
+    if value {
+        1.0
+    } else {
+        0.0
+    }
+}
+
+fn map(column: Column, f: impl Fn(f32) -> f32) -> Column {
+    match column {
+        Column::Scalar(values) => Column::Scalar(values.into_iter().map(f).collect()),
+        Column::Vector(values) => Column::Vector(
+            values
+                .into_iter()
+                .map(|v| [f(v[0]), f(v[1]), f(v[2])])
+                .collect(),
+        ),
+    }
+}
+
+fn scalars(column: Column, what: &str) -> Result<Vec<f32>, String> {
+    match column {
+        Column::Scalar(values) => Ok(values),
+        Column::Vector(_) => Err(format!("'{}' needs numbers, not vectors", what)),
+    }
+}
+
+fn binary(op: &str, left: Column, right: Column) -> Result<Column, String> {
+    let arithmetic: Option<fn(f32, f32) -> f32> = match op {
+        "+" => Some(|a, b| a + b),
+        "-" => Some(|a, b| a - b),
+        "*" => Some(|a, b| a * b),
+        "/" => Some(|a, b| a / b),
+        _ => None,
+    };
+    if let Some(f) = arithmetic {
+        return Ok(elementwise(left, right, f));
+    }
+    let f: fn(f32, f32) -> bool = match op {
+        "==" => |a, b| a == b,
+        "!=" => |a, b| a != b,
+        "<" => |a, b| a < b,
+        "<=" => |a, b| a <= b,
+        ">" => |a, b| a > b,
+        ">=" => |a, b| a >= b,
+        "&&" => |a, b| a != 0.0 && b != 0.0,
+        _ => |a, b| a != 0.0 || b != 0.0,
+    };
+    let left = scalars(left, op)?;
+    let right = scalars(right, op)?;
+    return Ok(Column::Scalar(
+        left.iter()
+            .zip(right.iter())
+            .map(|(&a, &b)| truth(f(a, b)))
+            .collect(),
+    ));
+}
+
+/// Apply `f` to each pair of values, applying numbers to every component of vectors.
+fn elementwise(left: Column, right: Column, f: fn(f32, f32) -> f32) -> Column {
+    let both = |a: [f32; 3], b: [f32; 3]| [f(a[0], b[0]), f(a[1], b[1]), f(a[2], b[2])];
+    match (left, right) {
+        (Column::Scalar(a), Column::Scalar(b)) => {
+            Column::Scalar(a.iter().zip(b.iter()).map(|(&a, &b)| f(a, b)).collect())
+        }
+        (Column::Vector(a), Column::Vector(b)) => {
+            Column::Vector(a.iter().zip(b.iter()).map(|(&a, &b)| both(a, b)).collect())
+        }
+        (Column::Scalar(a), Column::Vector(b)) => Column::Vector(
+            a.iter()
+                .zip(b.iter())
+                .map(|(&a, &b)| both([a; 3], b))
+                .collect(),
+        ),
+        (Column::Vector(a), Column::Scalar(b)) => Column::Vector(
+            a.iter()
+                .zip(b.iter())
+                .map(|(&a, &b)| both(a, [b; 3]))
+                .collect(),
+        ),
+    }
+}
+
+fn call(name: &str, mut args: Vec<Column>) -> Result<Column, String> {
+    let expected = match name {
+        "abs" | "sqrt" | "length" | "colormap" => 1,
+        "min" | "max" => 2,
+        "vec" => 3,
+        _ => return Err(format!("Unknown function '{}'", name)),
+    };
+    if args.len() != expected {
+        return Err(format!("'{}' takes {} arguments", name, expected));
+    }
+    let column = match name {
+        "abs" => map(args.pop().unwrap(), f32::abs),
+        "sqrt" => map(args.pop().unwrap(), f32::sqrt),
+        "min" => {
+            let right = args.pop().unwrap();
+            elementwise(args.pop().unwrap(), right, f32::min)
+        }
+        "max" => {
+            let right = args.pop().unwrap();
+            elementwise(args.pop().unwrap(), right, f32::max)
+        }
+        "length" => match args.pop().unwrap() {
+            Column::Vector(values) => Column::Scalar(
+                values
+                    .iter()
+                    .map(|v| (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt())
+                    .collect(),
+            ),
+            Column::Scalar(values) => Column::Scalar(values.iter().map(|v| v.abs()).collect()),
+        },
+        "vec" => {
+            let z = scalars(args.pop().unwrap(), name)?;
+            let y = scalars(args.pop().unwrap(), name)?;
+            let x = scalars(args.pop().unwrap(), name)?;
+            Column::Vector((0..x.len()).map(|i| [x[i], y[i], z[i]]).collect())
+        }
+        _ => {
+            let values = scalars(args.pop().unwrap(), name)?;
+            let finite = values.iter().cloned().filter(|v| v.is_finite());
+            let low = finite.clone().fold(f32::INFINITY, f32::min);
+            let high = finite.fold(f32::NEG_INFINITY, f32::max);
+            let span = if high > low { high - low } else { 1.0 };
+            Column::Vector(
+                values
+                    .iter()
+                    .map(|&v| {
+                        // NaNs take the bottom of the colour map
+                        let t = (v - low) / span;
+                        viridis(if t.is_nan() { 0.0 } else { t.clamp(0.0, 1.0) })
+                    })
+                    .collect(),
+            )
+        }
+    };
+    return Ok(column);
+}
+
+/// Polynomial fit of matplotlib's viridis colormap, as in `density_colormap.frag`.
+pub fn viridis(t: f32) -> [f32; 3] {
+    const C: [[f32; 3]; 7] = [
+        [0.277_727_33, 0.005_407_344_5, 0.334_099_8],
+        [0.105_093_04, 1.404_613_5, 1.384_590_2],
+        [-0.330_861_83, 0.214_847_56, 0.095_095_16],
+        [-4.634_230_5, -5.799_101, -19.332_441],
+        [6.228_27, 14.179_933, 56.690_55],
+        [4.776_385, -13.745_145, -65.353_03],
+        [-5.435_456, 4.645_852_6, 26.312_435],
+    ];
+    let mut color = [0.0; 3];
+    for channel in 0..3 {
+        color[channel] = C.iter().rev().fold(0.0, |sum, c| sum * t + c[channel]);
+    }
+    return color;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A layer of white points of size 1 at `positions`.
+    fn line(positions: &[[f32; 3]]) -> Line {
+        let vertices: Vec<Vertex> = positions
+            .iter()
+            .map(|p| Vertex {
+                position: [p[0], p[1], p[2], 1.0],
+                color: [1.0, 1.0, 1.0, 1.0],
+                size: 1.0,
+            })
+            .collect();
+        Line {
+            indicies: (0..vertices.len() as u32).collect(),
+            verticies: vertices,
+            origin: nalgebra::Vector3::zeros(),
+            attributes: Vec::new(),
+            ids: None,
+            metadata: Vec::new(),
+        }
+    }
+
+    /// The x of each point of a single point layer after running `source`.
+    fn x(source: &str) -> f32 {
+        let derived = Program::parse(source)
+            .unwrap()
+            .evaluate(&line(&[[0.0, 0.0, 0.0]]))
+            .unwrap();
+        return derived.verticies[0].position[0];
+    }
+
+    fn error(source: &str) -> String {
+        match Program::parse(source).and_then(|program| program.evaluate(&line(&[[1.0; 3]]))) {
+            Ok(_) => panic!("'{}' should fail", source),
+            Err(e) => return e.to_string(),
+        }
+    }
+
+    #[test]
+    fn precedence() {
+        assert_eq!(x("x = 1 + 2 * 3"), 7.0);
+        assert_eq!(x("x = (1 + 2) * 3"), 9.0);
+        assert_eq!(x("x = 8 / 4 / 2"), 1.0);
+        assert_eq!(x("x = 10 - 4 - 3"), 3.0);
+        assert_eq!(x("x = 1 + 1 == 2"), 1.0);
+        assert_eq!(x("x = 1 < 2 && 3 < 2 || 1"), 1.0);
+        assert_eq!(x("x = 0 || 1 && 0"), 0.0);
+    }
+
+    #[test]
+    fn unary_minus() {
+        assert_eq!(x("x = -2 * 3"), -6.0);
+        assert_eq!(x("x = 2 - -1"), 3.0);
+        assert_eq!(x("x = --4"), 4.0);
+        assert_eq!(x("x = -(1 + 2)"), -3.0);
+        assert_eq!(x("x = !0 - !1"), 1.0);
+    }
+
+    #[test]
+    fn scientific_notation() {
+        assert_eq!(x("x = 1e5"), 1.0e5);
+        assert_eq!(x("x = 2.5E-3"), 2.5e-3);
+        assert_eq!(x("x = 1e+2 + 1"), 101.0);
+        assert_eq!(x("x = .5e1"), 5.0);
+        // Without exponent digits the e is a name
+        assert_eq!(error("x = 2e"), "Expected ';' but found 'e'");
+    }
+
+    #[test]
+    fn functions() {
+        assert_eq!(x("x = abs(-3)"), 3.0);
+        assert_eq!(x("x = sqrt(16)"), 4.0);
+        assert_eq!(x("x = min(2, -1)"), -1.0);
+        assert_eq!(x("x = max(2, -1)"), 2.0);
+        assert_eq!(x("x = length(vec(3, 4, 0))"), 5.0);
+        assert_eq!(x("pos = vec(1, 2, 3) * 2; x = x + y + z"), 12.0);
+
+        let points = line(&[[0.0, 0.0, 0.0], [1.0, 0.0, 0.0]]);
+        let program = Program::parse("color = colormap(x)").unwrap();
+        let derived = program.evaluate(&points).unwrap();
+        assert_eq!(xyz(&derived.verticies[0].color), viridis(0.0));
+        assert_eq!(xyz(&derived.verticies[1].color), viridis(1.0));
+    }
+
+    #[test]
+    fn filters() {
+        let points = line(&[[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [2.0, 0.0, 0.0]]);
+        let program = Program::parse("x >= 1\nx * 2 < 4").unwrap();
+        let derived = program.evaluate(&points).unwrap();
+        assert_eq!(derived.verticies.len(), 1);
+        assert_eq!(derived.verticies[0].position[0], 1.0);
+        assert_eq!(program.count_matches(&points, 0).unwrap(), 1);
+        assert_eq!(program.count_matches(&points, 2).unwrap(), 0);
+        // Past the end of the layer
+        assert_eq!(program.count_matches(&points, 10).unwrap(), 0);
+    }
+
+    #[test]
+    fn division_by_zero() {
+        assert_eq!(x("x = 1 / 0"), f32::INFINITY);
+        assert_eq!(x("x = -1 / 0"), f32::NEG_INFINITY);
+        assert!(x("x = 0 / 0").is_nan());
+        // Filters drop points where they are NaN, and keep them where they are infinite
+        let points = line(&[[0.0, 0.0, 0.0], [1.0, 0.0, 0.0]]);
+        let program = Program::parse("x / 0").unwrap();
+        assert_eq!(program.evaluate(&points).unwrap().verticies.len(), 1);
+    }
+
+    #[test]
+    fn error_messages() {
+        assert_eq!(error(""), "The expression is empty");
+        assert_eq!(error("x +"), "Expected a value but found the end");
+        assert_eq!(error("(x"), "Expected ')' but found the end");
+        assert_eq!(error("min(x y)"), "Expected ',' or ')' but found 'y'");
+        assert_eq!(error("x $ 1"), "Unexpected '$'");
+        assert_eq!(error("1.2.3"), "Invalid number '1.2.3'");
+        assert_eq!(error("speed > 1"), "Unknown variable 'speed'");
+        assert_eq!(error("x = cbrt(8)"), "Unknown function 'cbrt'");
+        assert_eq!(error("x = abs(1, 2)"), "'abs' takes 1 arguments");
+        assert_eq!(error("pos = 1"), "'pos' must be assigned a vector");
+        assert_eq!(error("x = pos"), "'x' must be assigned a number");
+        assert_eq!(error("pos > 1"), "'>' needs numbers, not vectors");
+    }
+}
@@ -0,0 +1,253 @@
+//! Conversions from geodetic and earth-centred coordinates into a local east, north, up frame,
+//! so data from different sources can be placed in one scene.
+
+/// WGS84 semi-major axis in metres.
+const A: f64 = 6378137.0;
+/// WGS84 first eccentricity squared.
+const E2: f64 = 6.69437999014e-3;
+
+/// Latitude and longitude in degrees and altitude in metres above the WGS84 ellipsoid.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Geodetic {
+    pub latitude: f64,
+    pub longitude: f64,
+    pub altitude: f64,
+}
+
+impl Geodetic {
+    /// Parse `lat,lon,alt`.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let values = text
+            .split(',')
+            .map(|value| value.trim().parse::<f64>())
+            .collect::<Result<Vec<f64>, _>>()
+            .map_err(|_| format!("Invalid coordinates '{}'", text))?;
+        if values.len() != 3 {
+            return Err(format!("Expected lat,lon,alt but got '{}'", text));
+        }
+        return Ok(Geodetic {
+            latitude: values[0],
+            longitude: values[1],
+            altitude: values[2],
+        });
+    }
+
+    pub fn to_ecef(self) -> nalgebra::Vector3<f64> {
+        let (sin_lat, cos_lat) = self.latitude.to_radians().sin_cos();
+        let (sin_lon, cos_lon) = self.longitude.to_radians().sin_cos();
+        // Radius of curvature in the prime vertical
+        let n = A / (1.0 - E2 * sin_lat * sin_lat).sqrt();
+        nalgebra::Vector3::new(
+            (n + self.altitude) * cos_lat * cos_lon,
+            (n + self.altitude) * cos_lat * sin_lon,
+            (n * (1.0 - E2) + self.altitude) * sin_lat,
+        )
+    }
+
+    /// Inverse of `to_ecef`, using Bowring's method, which is accurate to well under a millimetre
+    /// near the surface. The altitude is measured along the normal so it holds at the poles too.
+    pub fn from_ecef(ecef: &nalgebra::Vector3<f64>) -> Self {
+        let b = A * (1.0 - E2).sqrt();
+        let ep2 = (A * A - b * b) / (b * b);
+        let p = (ecef.x * ecef.x + ecef.y * ecef.y).sqrt();
+        let theta = (ecef.z * A).atan2(p * b);
+        let (sin_theta, cos_theta) = theta.sin_cos();
+        let latitude = (ecef.z + ep2 * b * sin_theta.powi(3)).atan2(p - E2 * A * cos_theta.powi(3));
+        let (sin_lat, cos_lat) = latitude.sin_cos();
+        Geodetic {
+            latitude: latitude.to_degrees(),
+            longitude: ecef.y.atan2(ecef.x).to_degrees(),
+            altitude: p * cos_lat + ecef.z * sin_lat - A * (1.0 - E2 * sin_lat * sin_lat).sqrt(),
+        }
+    }
+}
+
+/// East, north, up frame tangent to the ellipsoid at `origin`.
+pub struct Enu {
+    origin: nalgebra::Vector3<f64>,
+    rotation: nalgebra::Matrix3<f64>,
+}
+
+impl Enu {
+    pub fn new(origin: Geodetic) -> Self {
+        let (sin_lat, cos_lat) = origin.latitude.to_radians().sin_cos();
+        let (sin_lon, cos_lon) = origin.longitude.to_radians().sin_cos();
+        #[rustfmt::skip]
+        let rotation = nalgebra::Matrix3::new(
+            -sin_lon,            cos_lon,           0.0,
+            -sin_lat * cos_lon, -sin_lat * sin_lon, cos_lat,
+             cos_lat * cos_lon,  cos_lat * sin_lon, sin_lat,
+        );
+        Enu {
+            origin: origin.to_ecef(),
+            rotation: rotation,
+        }
+    }
+
+    pub fn ecef_to_enu(&self, ecef: &nalgebra::Vector3<f64>) -> nalgebra::Vector3<f64> {
+        self.rotation * (ecef - self.origin)
+    }
+}
+
+/// How the X, Y and Z columns of input data are interpreted.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum InputFrame {
+    /// Already in the scene's cartesian coordinates.
+    Cartesian,
+    /// Latitude and longitude in degrees, then altitude in metres.
+    Geodetic,
+    /// Earth-centred, earth-fixed metres.
+    Ecef,
+}
+
+/// Converts input positions into the scene frame. Geographic input is converted to east, north, up
+/// metres around `origin`; if no origin is given, the first point converted becomes the origin
+/// for everything loaded afterwards.
+pub struct Converter {
+    pub frame: InputFrame,
+    enu: std::cell::RefCell<Option<Enu>>,
+}
+
+impl Converter {
+    pub fn new(frame: InputFrame, origin: Option<Geodetic>) -> Self {
+        Converter {
+            frame: frame,
+            enu: std::cell::RefCell::new(origin.map(Enu::new)),
+        }
+    }
+
+    /// The frame positions are converted from, and the local origin once there is one.
+    pub fn describe(&self) -> String {
+        let frame = match self.frame {
+            InputFrame::Cartesian => "cartesian",
+            InputFrame::Geodetic => "geodetic",
+            InputFrame::Ecef => "ECEF",
+        };
+        match &*self.enu.borrow() {
+            Some(enu) if self.frame != InputFrame::Cartesian => {
+                let origin = Geodetic::from_ecef(&enu.origin);
+                return format!(
+                    "{}, as east/north/up metres around {:.6}, {:.6}, {:.1}",
+                    frame, origin.latitude, origin.longitude, origin.altitude
+                );
+            }
+            _ => return frame.to_string(),
+        }
+    }
+
+    /// Convert positions in place.
+    pub fn convert(&self, positions: &mut [nalgebra::Vector3<f64>]) {
+        if self.frame == InputFrame::Cartesian || positions.is_empty() {
+            return;
+        }
+        let to_ecef = |position: &nalgebra::Vector3<f64>| match self.frame {
+            InputFrame::Geodetic => Geodetic {
+                latitude: position.x,
+                longitude: position.y,
+                altitude: position.z,
+            }
+            .to_ecef(),
+            _ => *position,
+        };
+        let mut enu = self.enu.borrow_mut();
+        let enu = enu.get_or_insert_with(|| {
+            let origin = Geodetic::from_ecef(&to_ecef(&positions[0]));
+            info!(
+                "Using {:.6}, {:.6}, {:.1} as the local origin",
+                origin.latitude, origin.longitude, origin.altitude
+            );
+            Enu::new(origin)
+        });
+        for position in positions.iter_mut() {
+            *position = enu.ecef_to_enu(&to_ecef(position));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn geodetic(latitude: f64, longitude: f64, altitude: f64) -> Geodetic {
+        Geodetic {
+            latitude: latitude,
+            longitude: longitude,
+            altitude: altitude,
+        }
+    }
+
+    #[test]
+    fn ecef_round_trip() {
+        let places = [
+            geodetic(90.0, 0.0, 0.0),
+            geodetic(-90.0, 0.0, 1500.0),
+            geodetic(0.0, 0.0, 0.0),
+            geodetic(0.0, 90.0, -20.0),
+            geodetic(0.0, 180.0, 100.0),
+            geodetic(-33.9, 180.0, 8848.0),
+            geodetic(51.5, -0.1, 35.0),
+        ];
+        for place in places.iter() {
+            let back = Geodetic::from_ecef(&place.to_ecef());
+            assert!(
+                (back.latitude - place.latitude).abs() < 1e-9,
+                "{:?} {:?}",
+                place,
+                back
+            );
+            assert!(
+                (back.altitude - place.altitude).abs() < 1e-3,
+                "{:?} {:?}",
+                place,
+                back
+            );
+            // Longitude is arbitrary at the poles, and -180 is the same as 180
+            let longitude = (back.longitude - place.longitude + 540.0) % 360.0 - 180.0;
+            if place.latitude.abs() < 90.0 {
+                assert!(longitude.abs() < 1e-9, "{:?} {:?}", place, back);
+            }
+        }
+    }
+
+    #[test]
+    fn ecef_axes() {
+        let equator = geodetic(0.0, 0.0, 0.0).to_ecef();
+        assert!((equator - nalgebra::Vector3::new(A, 0.0, 0.0)).norm() < 1e-6);
+        let pole = geodetic(90.0, 0.0, 0.0).to_ecef();
+        let b = A * (1.0 - E2).sqrt();
+        assert!((pole - nalgebra::Vector3::new(0.0, 0.0, b)).norm() < 1e-6);
+    }
+
+    #[test]
+    fn enu() {
+        let places = [
+            geodetic(90.0, 0.0, 0.0),
+            geodetic(-90.0, 0.0, 0.0),
+            geodetic(0.0, 0.0, 0.0),
+            geodetic(0.0, 180.0, 0.0),
+            geodetic(45.0, -180.0, 200.0),
+        ];
+        for origin in places.iter() {
+            let enu = Enu::new(*origin);
+            assert!(enu.ecef_to_enu(&origin.to_ecef()).norm() < 1e-6);
+            let above = geodetic(origin.latitude, origin.longitude, origin.altitude + 100.0);
+            let up = enu.ecef_to_enu(&above.to_ecef());
+            assert!((up - nalgebra::Vector3::new(0.0, 0.0, 100.0)).norm() < 1e-6);
+        }
+
+        // A little east across the antimeridian is a little east, not half way round the world
+        let enu = Enu::new(geodetic(0.0, 179.9999, 0.0));
+        let east = enu.ecef_to_enu(&geodetic(0.0, -179.9999, 0.0).to_ecef());
+        assert!(
+            (east.x - 2.0e-4_f64.to_radians() * A).abs() < 1e-3,
+            "{:?}",
+            east
+        );
+        assert!(east.y.abs() < 1e-6 && east.z.abs() < 1e-3, "{:?}", east);
+
+        // North of the equator is north
+        let enu = Enu::new(geodetic(0.0, 0.0, 0.0));
+        let north = enu.ecef_to_enu(&geodetic(0.001, 0.0, 0.0).to_ecef());
+        assert!(north.y > 100.0 && north.x.abs() < 1e-6, "{:?}", north);
+    }
+}
@@ -0,0 +1,259 @@
+//! Recording of keyboard and mouse input, and replaying it later to reproduce a session exactly,
+//! e.g. for a rendering bug or a scripted demo.
+//!
+//! A recording is a text file of one entry per line, `SECONDS KIND ARGS...`, giving the time since
+//! recording started followed by one of:
+//! - `resize WIDTH HEIGHT`, the window's inner size in pixels, which the recording starts with
+//! - `cursor X Y`, the cursor position in pixels
+//! - `button pressed|released BUTTON`, the button named as in `mouse.cfg`, or the left button if
+//!   left out
+//! - `wheel lines|pixels X Y`
+//! - `touch started|moved|ended|cancelled ID X Y`, a finger on a touchscreen
+//! - `key NAME`, a key press named as in `keys.cfg`
+//! - `modifiers BITS`, the modifier keys held
+//! - `step SECONDS`, how far smoothed camera movement was advanced
+//!
+//! Camera movement is advanced by the recorded steps rather than the time between frames while
+//! replaying, so the views come out the same however fast the replay runs.
+
+use std::io::{BufRead, Write};
+use winit::event::{
+    ElementState, ModifiersState, MouseButton, MouseScrollDelta, TouchPhase, VirtualKeyCode,
+    WindowEvent,
+};
+
+/// Keyboard and mouse input the viewer responds to.
+#[derive(Debug, Copy, Clone)]
+pub enum Input {
+    CursorMoved(winit::dpi::PhysicalPosition<f64>),
+    MouseButton(ElementState, MouseButton),
+    MouseWheel(MouseScrollDelta),
+    /// A finger with a touch ID.
+    Touch(TouchPhase, u64, winit::dpi::PhysicalPosition<f64>),
+    KeyPressed(VirtualKeyCode),
+    Modifiers(ModifiersState),
+}
+
+impl Input {
+    pub fn from_window_event(event: &WindowEvent) -> Option<Self> {
+        match event {
+            WindowEvent::CursorMoved { position, .. } => Some(Input::CursorMoved(*position)),
+            WindowEvent::MouseInput { state, button, .. } => {
+                Some(Input::MouseButton(*state, *button))
+            }
+            WindowEvent::MouseWheel { delta, .. } => Some(Input::MouseWheel(*delta)),
+            WindowEvent::Touch(touch) => Some(Input::Touch(touch.phase, touch.id, touch.location)),
+            WindowEvent::KeyboardInput {
+                input:
+                    winit::event::KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(key),
+                        ..
+                    },
+                ..
+            } => Some(Input::KeyPressed(*key)),
+            WindowEvent::ModifiersChanged(modifiers) => Some(Input::Modifiers(*modifiers)),
+            _ => None,
+        }
+    }
+}
+
+/// Names used for touch phases in recordings.
+const TOUCH_PHASES: &[(&str, TouchPhase)] = &[
+    ("started", TouchPhase::Started),
+    ("moved", TouchPhase::Moved),
+    ("ended", TouchPhase::Ended),
+    ("cancelled", TouchPhase::Cancelled),
+];
+
+/// One line of a recording.
+#[derive(Debug, Copy, Clone)]
+pub enum Entry {
+    Input(Input),
+    /// Smoothed camera movement was advanced by this long, see `Viewports::step`.
+    Step(std::time::Duration),
+    Resize(winit::dpi::PhysicalSize<u32>),
+}
+
+impl Entry {
+    /// The entry's kind and arguments, or `None` for keys that have no name to record them by.
+    fn format(&self) -> Option<String> {
+        let text = match self {
+            Entry::Input(Input::CursorMoved(position)) => {
+                format!("cursor {} {}", position.x, position.y)
+            }
+            Entry::Input(Input::MouseButton(state, button)) => {
+                let state = match state {
+                    ElementState::Pressed => "pressed",
+                    ElementState::Released => "released",
+                };
+                format!("button {} {}", state, crate::mouse::button_name(*button))
+            }
+            Entry::Input(Input::MouseWheel(MouseScrollDelta::LineDelta(x, y))) => {
+                format!("wheel lines {} {}", x, y)
+            }
+            Entry::Input(Input::MouseWheel(MouseScrollDelta::PixelDelta(delta))) => {
+                format!("wheel pixels {} {}", delta.x, delta.y)
+            }
+            Entry::Input(Input::Touch(phase, id, position)) => {
+                let phase = TOUCH_PHASES.iter().find(|(_, p)| p == phase)?.0;
+                format!("touch {} {} {} {}", phase, id, position.x, position.y)
+            }
+            Entry::Input(Input::KeyPressed(key)) => {
+                format!("key {}", crate::keymap::key_name(*key)?)
+            }
+            Entry::Input(Input::Modifiers(modifiers)) => format!("modifiers {}", modifiers.bits()),
+            Entry::Step(step) => format!("step {}", step.as_secs_f64()),
+            Entry::Resize(size) => format!("resize {} {}", size.width, size.height),
+        };
+        return Some(text);
+    }
+
+    fn parse(text: &str) -> Option<Self> {
+        let fields: Vec<&str> = text.split_whitespace().collect();
+        let number = |i: usize| {
+            fields
+                .get(i)
+                .and_then(|field| field.parse::<f64>().ok())
+                .filter(|number| number.is_finite())
+        };
+        let entry = match (fields.first()?, fields.get(1)) {
+            (&"cursor", _) => Entry::Input(Input::CursorMoved(winit::dpi::PhysicalPosition::new(
+                number(1)?,
+                number(2)?,
+            ))),
+            (&"button", Some(state)) => {
+                let state = match *state {
+                    "pressed" => ElementState::Pressed,
+                    "released" => ElementState::Released,
+                    _ => return None,
+                };
+                // Recordings from before buttons were recorded
+                let button = match fields.get(2) {
+                    Some(name) => crate::mouse::parse_button(name)?,
+                    None => MouseButton::Left,
+                };
+                Entry::Input(Input::MouseButton(state, button))
+            }
+            (&"wheel", Some(&"lines")) => Entry::Input(Input::MouseWheel(
+                MouseScrollDelta::LineDelta(number(2)? as f32, number(3)? as f32),
+            )),
+            (&"wheel", Some(&"pixels")) => {
+                Entry::Input(Input::MouseWheel(MouseScrollDelta::PixelDelta(
+                    winit::dpi::LogicalPosition::new(number(2)?, number(3)?),
+                )))
+            }
+            (&"touch", Some(phase)) => {
+                let phase = TOUCH_PHASES.iter().find(|(name, _)| name == phase)?.1;
+                let id = fields.get(2)?.parse::<u64>().ok()?;
+                let position = winit::dpi::PhysicalPosition::new(number(3)?, number(4)?);
+                Entry::Input(Input::Touch(phase, id, position))
+            }
+            (&"key", Some(name)) => {
+                Entry::Input(Input::KeyPressed(crate::keymap::parse_key(name)?))
+            }
+            (&"modifiers", Some(bits)) => Entry::Input(Input::Modifiers(
+                ModifiersState::from_bits(bits.parse::<u32>().ok()?)?,
+            )),
+            (&"step", _) => Entry::Step(seconds(number(1)?)?),
+            (&"resize", _) => Entry::Resize(winit::dpi::PhysicalSize::new(
+                number(1)? as u32,
+                number(2)? as u32,
+            )),
+            _ => return None,
+        };
+        return Some(entry);
+    }
+}
+
+/// Longer than any recording, so times past it are taken to be corrupt rather than waited for.
+const MAX_SECONDS: f64 = 1.0e9;
+
+/// `value` seconds, if it is a time a recording could hold.
+pub fn seconds(value: f64) -> Option<std::time::Duration> {
+    if !(0.0..=MAX_SECONDS).contains(&value) {
+        return None;
+    }
+    return Some(std::time::Duration::from_secs_f64(value));
+}
+
+pub struct Recorder {
+    file: std::io::BufWriter<std::fs::File>,
+    start: std::time::Instant,
+}
+
+impl Recorder {
+    /// Start a recording of a window of inner `size`.
+    pub fn create(
+        path: &std::path::Path,
+        size: winit::dpi::PhysicalSize<u32>,
+    ) -> std::io::Result<Self> {
+        let file = std::fs::File::create(path)?;
+        info!("Recording input to {}", path.display());
+        let mut recorder = Recorder {
+            file: std::io::BufWriter::new(file),
+            start: std::time::Instant::now(),
+        };
+        recorder.record(&Entry::Resize(size));
+        return Ok(recorder);
+    }
+
+    pub fn record(&mut self, entry: &Entry) {
+        let text = match entry.format() {
+            Some(text) => text,
+            None => return,
+        };
+        let elapsed = self.start.elapsed().as_secs_f64();
+        let written = writeln!(self.file, "{:.6} {}", elapsed, text)
+            // Flush each entry so a crash still leaves a usable recording
+            .and_then(|_| self.file.flush());
+        if let Err(e) = written {
+            error!("Failed to record input: {}", e);
+        }
+    }
+}
+
+/// Replay a recording in a background thread, sending each entry to the event loop at its
+/// recorded time and then `Command::InputReplayFinished`.
+pub fn replay(
+    path: &std::path::Path,
+    proxy: winit::event_loop::EventLoopProxy<crate::remote::Request>,
+) -> std::io::Result<()> {
+    let reader = std::io::BufReader::new(std::fs::File::open(path)?);
+    let path = path.to_path_buf();
+    std::thread::spawn(move || {
+        let start = std::time::Instant::now();
+        for (i, line) in reader.lines().enumerate() {
+            let line = match line {
+                Ok(line) => line,
+                Err(e) => {
+                    error!("Stopped replaying {}: {}", path.display(), e);
+                    break;
+                }
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+            let mut split = line.trim().splitn(2, ' ');
+            let due = split
+                .next()
+                .and_then(|field| field.parse::<f64>().ok())
+                .and_then(seconds);
+            let entry = split.next().and_then(Entry::parse);
+            let (due, entry) = match (due, entry) {
+                (Some(due), Some(entry)) => (due, entry),
+                _ => {
+                    warn!("Skipping invalid input entry on line {}: '{}'", i + 1, line);
+                    continue;
+                }
+            };
+            if let Some(wait) = due.checked_sub(start.elapsed()) {
+                std::thread::sleep(wait);
+            }
+            crate::remote::dispatch(crate::remote::Command::ReplayInput(entry), &proxy);
+        }
+        crate::remote::dispatch(crate::remote::Command::InputReplayFinished, &proxy);
+        info!("Finished replaying {}", path.display());
+    });
+    return Ok(());
+}
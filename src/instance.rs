@@ -0,0 +1,100 @@
+use std::io::{BufRead, Write};
+
+/// Loopback address the first instance listens on for files opened by later invocations.
+const ADDRESS: &str = "127.0.0.1:47809";
+
+/// Sent by both ends before any paths, so neither mistakes some other program on the port, or an
+/// incompatible version of rscat, for the instance it expects.
+const HELLO: &str = "rscat-instance 1";
+
+/// Sent by the running instance once it has all the paths.
+const ACCEPTED: &str = "ok";
+
+/// How long either end waits on the other before giving up.
+const TIMEOUT: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// Try to hand `paths` to an already running instance. Returns `true` if one accepted them, in
+/// which case this process should exit, or `false` if the files should be opened here.
+pub fn forward(paths: &[std::path::PathBuf]) -> bool {
+    let address = ADDRESS.parse().expect("Invalid instance address");
+    let mut stream = match std::net::TcpStream::connect_timeout(&address, TIMEOUT) {
+        Ok(stream) => stream,
+        Err(_) => return false,
+    };
+    let mut reader = match stream
+        .set_read_timeout(Some(TIMEOUT))
+        .and_then(|_| stream.try_clone())
+    {
+        Ok(reader) => std::io::BufReader::new(reader),
+        Err(_) => return false,
+    };
+    if writeln!(stream, "{}", HELLO).is_err() || read_line(&mut reader).as_deref() != Some(HELLO) {
+        warn!(
+            "{} is in use by something other than rscat, opening the files here",
+            ADDRESS
+        );
+        return false;
+    }
+    for path in paths {
+        // Send absolute paths, the running instance may have a different working directory
+        let path = std::fs::canonicalize(path).unwrap_or_else(|_| path.clone());
+        if writeln!(stream, "{}", path.display()).is_err() {
+            return false;
+        }
+    }
+    if stream.shutdown(std::net::Shutdown::Write).is_err() {
+        return false;
+    }
+    return read_line(&mut reader).as_deref() == Some(ACCEPTED);
+}
+
+/// The next line from `reader` without its line ending, or `None` if there isn't one in time.
+fn read_line<R: BufRead>(reader: &mut R) -> Option<String> {
+    let mut line = String::new();
+    match reader.read_line(&mut line) {
+        Ok(0) | Err(_) => return None,
+        Ok(_) => return Some(line.trim_end().to_string()),
+    }
+}
+
+/// Listen for files forwarded by later invocations and load them in this instance.
+pub fn listen(proxy: winit::event_loop::EventLoopProxy<crate::remote::Request>) {
+    let listener = match std::net::TcpListener::bind(ADDRESS) {
+        Ok(listener) => listener,
+        Err(e) => {
+            // Most likely another instance already has it, which opened with --new-window
+            info!("Not accepting files from other instances: {}", e);
+            return;
+        }
+    };
+    std::thread::spawn(move || {
+        for mut stream in listener.incoming().filter_map(|stream| stream.ok()) {
+            // A client that stalls mustn't stop later ones from being heard
+            let mut reader = match stream
+                .set_read_timeout(Some(TIMEOUT))
+                .and_then(|_| stream.try_clone())
+            {
+                Ok(reader) => std::io::BufReader::new(reader),
+                Err(_) => continue,
+            };
+            if read_line(&mut reader).as_deref() != Some(HELLO)
+                || writeln!(stream, "{}", HELLO).is_err()
+            {
+                continue;
+            }
+            let paths: Vec<std::path::PathBuf> = reader
+                .lines()
+                .map_while(Result::ok)
+                .map(std::path::PathBuf::from)
+                .collect();
+            if paths.is_empty() || writeln!(stream, "{}", ACCEPTED).is_err() {
+                continue;
+            }
+            info!("Opening files from another instance: {:?}", paths);
+            let response = crate::remote::dispatch(crate::remote::Command::Load(paths), &proxy);
+            if response.status != 200 {
+                error!("{}", String::from_utf8_lossy(&response.body));
+            }
+        }
+    });
+}
@@ -0,0 +1,267 @@
+//! Isosurfaces of volume layers, extracted with marching cubes into glyph layers of a single
+//! mesh.
+//!
+//! Rather than the usual lookup table of triangles for each of the 256 ways a cell's corners can
+//! lie on either side of the threshold, the polygons of each case are found by joining up where
+//! the surface crosses the cell's faces. Faces crossed four times are split so their corners above
+//! the threshold are kept apart, which every cell sharing the face agrees on, so the surface has
+//! no holes.
+
+use crate::rendering::glyphs::{GlyphLayer, Instance, Mesh, Shape};
+use crate::rendering::volume::Volume;
+
+/// Cell corner `i` is offset by bit 0 of `i` along X, bit 1 along Y and bit 2 along Z.
+fn corner_offset(corner: usize) -> [usize; 3] {
+    return [corner & 1, (corner >> 1) & 1, (corner >> 2) & 1];
+}
+
+/// The 12 edges of a cell as pairs of corners, the first nearer the origin.
+fn cell_edges() -> Vec<(usize, usize)> {
+    let mut edges = Vec::with_capacity(12);
+    for axis in 0..3 {
+        for corner in (0..8).filter(|corner| corner & (1 << axis) == 0) {
+            edges.push((corner, corner | (1 << axis)));
+        }
+    }
+    return edges;
+}
+
+/// For each case of which corners are at or above the threshold, one bit per corner, the loops of
+/// edges the surface crosses the cell through.
+fn cases(edges: &[(usize, usize)]) -> Vec<Vec<Vec<usize>>> {
+    let edge = |a: usize, b: usize| {
+        edges
+            .iter()
+            .position(|&edge| edge == (a.min(b), a.max(b)))
+            .unwrap()
+    };
+    (0..256_usize)
+        .map(|case| {
+            let above = |corner: usize| case & (1 << corner) != 0;
+            let mut segments = Vec::new();
+            for axis in 0..3 {
+                for side in 0..2 {
+                    // The face's corners in order around it
+                    let (u, v) = ((axis + 1) % 3, (axis + 2) % 3);
+                    let corners: Vec<usize> = [(0_usize, 0_usize), (1, 0), (1, 1), (0, 1)]
+                        .iter()
+                        .map(|(a, b)| (side << axis) | (a << u) | (b << v))
+                        .collect();
+                    let crossed: Vec<usize> = (0..4)
+                        .filter(|&i| above(corners[i]) != above(corners[(i + 1) % 4]))
+                        .collect();
+                    let side_edge = |i: usize| edge(corners[i], corners[(i + 1) % 4]);
+                    if crossed.len() == 2 {
+                        segments.push((side_edge(crossed[0]), side_edge(crossed[1])));
+                    } else if crossed.len() == 4 {
+                        // Cut off each corner above the threshold
+                        for i in (0..4).filter(|&i| above(corners[i])) {
+                            segments.push((side_edge((i + 3) % 4), side_edge(i)));
+                        }
+                    }
+                }
+            }
+            // Every crossed edge is on two faces, so the segments join up into closed loops
+            let mut loops = Vec::new();
+            while let Some((first, mut next)) = segments.pop() {
+                let mut polygon = vec![first];
+                while next != first {
+                    polygon.push(next);
+                    let joined = segments
+                        .iter()
+                        .position(|&(a, b)| a == next || b == next)
+                        .unwrap();
+                    let (a, b) = segments.swap_remove(joined);
+                    next = if a == next { b } else { a };
+                }
+                loops.push(polygon);
+            }
+            loops
+        })
+        .collect()
+}
+
+/// The surface where `volume` crosses `threshold`, in `color`, with normals facing lower
+/// values. NaNs count as below the threshold.
+pub fn extract(volume: &Volume, threshold: f32, color: [f32; 4]) -> Result<GlyphLayer, String> {
+    let [width, height, depth] = volume.dims;
+    let value = |x: usize, y: usize, z: usize| volume.values[(z * height + y) * width + x];
+    // Central differences, one sided at the edges of the grid
+    let gradient = |point: [usize; 3]| {
+        let mut gradient = nalgebra::Vector3::zeros();
+        for axis in 0..3 {
+            let (mut low, mut high) = (point, point);
+            low[axis] = low[axis].saturating_sub(1);
+            high[axis] = (high[axis] + 1).min(volume.dims[axis] - 1);
+            if low[axis] != high[axis] {
+                let difference = value(high[0], high[1], high[2]) - value(low[0], low[1], low[2]);
+                let distance = (high[axis] - low[axis]) as f64 * volume.spacing[axis];
+                gradient[axis] = difference / distance as f32;
+            }
+        }
+        gradient
+    };
+    let edges = cell_edges();
+    let cases = cases(&edges);
+    let mut mesh = Mesh {
+        positions: Vec::new(),
+        normals: Vec::new(),
+        triangles: Vec::new(),
+    };
+    // Vertices are shared between the cells around each grid edge, keyed by the edge's first
+    // grid point and axis
+    let mut vertices = std::collections::HashMap::<(usize, usize), u32>::new();
+    for z in 0..depth.saturating_sub(1) {
+        for y in 0..height.saturating_sub(1) {
+            for x in 0..width.saturating_sub(1) {
+                let case = (0..8).fold(0, |case, corner| {
+                    let [dx, dy, dz] = corner_offset(corner);
+                    if value(x + dx, y + dy, z + dz) >= threshold {
+                        case | (1 << corner)
+                    } else {
+                        case
+                    }
+                });
+                for polygon in &cases[case] {
+                    let mut indices = Vec::with_capacity(polygon.len());
+                    for &edge in polygon {
+                        let (a, b) = edges[edge];
+                        let offset = corner_offset(a);
+                        let start = [x + offset[0], y + offset[1], z + offset[2]];
+                        let axis = (0..3).find(|&axis| a ^ b == 1 << axis).unwrap();
+                        let key = ((start[2] * height + start[1]) * width + start[0], axis);
+                        let index = *vertices.entry(key).or_insert_with(|| {
+                            let mut end = start;
+                            end[axis] += 1;
+                            let low = value(start[0], start[1], start[2]);
+                            let high = value(end[0], end[1], end[2]);
+                            let mut t = (threshold - low) / (high - low);
+                            if !t.is_finite() {
+                                t = 0.5;
+                            }
+                            let t = t.clamp(0.0, 1.0);
+                            let mut position = [0, 1, 2]
+                                .map(|axis| (start[axis] as f64 * volume.spacing[axis]) as f32);
+                            position[axis] += t * volume.spacing[axis] as f32;
+                            let normal = -gradient(start).lerp(&gradient(end), t);
+                            let normal = normal
+                                .try_normalize(1e-12)
+                                .unwrap_or_else(nalgebra::Vector3::z);
+                            mesh.positions.push(position);
+                            mesh.normals.push([normal.x, normal.y, normal.z]);
+                            (mesh.positions.len() - 1) as u32
+                        });
+                        indices.push(index);
+                    }
+                    for i in 1..polygon.len() - 1 {
+                        let mut triangle = [indices[0], indices[i], indices[i + 1]];
+                        // Wind each triangle anticlockwise seen from the side its normals face
+                        let corner =
+                            |index: u32| nalgebra::Vector3::from(mesh.positions[index as usize]);
+                        let facing = (corner(triangle[1]) - corner(triangle[0]))
+                            .cross(&(corner(triangle[2]) - corner(triangle[0])));
+                        let normal: nalgebra::Vector3<f32> = triangle
+                            .iter()
+                            .map(|&index| nalgebra::Vector3::from(mesh.normals[index as usize]))
+                            .sum();
+                        if facing.dot(&normal) < 0.0 {
+                            triangle.swap(1, 2);
+                        }
+                        mesh.triangles.push(triangle);
+                    }
+                }
+            }
+        }
+    }
+    if mesh.triangles.is_empty() {
+        return Err(format!("The volume doesn't cross {}", threshold));
+    }
+    return Ok(GlyphLayer {
+        shape: Shape::Mesh(mesh),
+        instances: vec![Instance::new(nalgebra::Matrix4::identity(), color)],
+        origin: volume.origin,
+        wireframe: false,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A `size` cube grid of unit spacing holding `field` at each grid point.
+    fn volume(size: usize, field: impl Fn([f64; 3]) -> f32) -> Volume {
+        let mut values = Vec::with_capacity(size * size * size);
+        for z in 0..size {
+            for y in 0..size {
+                for x in 0..size {
+                    values.push(field([x as f64, y as f64, z as f64]));
+                }
+            }
+        }
+        Volume {
+            dims: [size; 3],
+            values: values,
+            origin: nalgebra::Vector3::zeros(),
+            spacing: [1.0; 3],
+        }
+    }
+
+    fn mesh(layer: &GlyphLayer) -> &Mesh {
+        match &layer.shape {
+            Shape::Mesh(mesh) => mesh,
+            _ => panic!("Not a mesh"),
+        }
+    }
+
+    #[test]
+    fn sphere() {
+        let centre = nalgebra::Vector3::new(7.3, 7.6, 7.45);
+        let radius = 5.0;
+        let sphere = volume(16, |p| {
+            (nalgebra::Vector3::new(p[0] as f32, p[1] as f32, p[2] as f32) - centre).norm()
+        });
+        let layer = extract(&sphere, radius, [1.0; 4]).unwrap();
+        let mesh = mesh(&layer);
+        assert!(mesh.triangles.len() > 100);
+        for (position, normal) in mesh.positions.iter().zip(&mesh.normals) {
+            let outwards = nalgebra::Vector3::from(*position) - centre;
+            assert!(
+                (outwards.norm() - radius).abs() < 0.1,
+                "Vertex {:?} is {} from the centre",
+                position,
+                outwards.norm()
+            );
+            // Normals face lower values, towards the centre
+            assert!(outwards.dot(&nalgebra::Vector3::from(*normal)) < 0.0);
+        }
+        // Watertight: every edge is shared by exactly two triangles, wound in opposite directions
+        let mut edges = std::collections::HashMap::<(u32, u32), Vec<bool>>::new();
+        for triangle in &mesh.triangles {
+            for i in 0..3 {
+                let (a, b) = (triangle[i], triangle[(i + 1) % 3]);
+                edges.entry((a.min(b), a.max(b))).or_default().push(a < b);
+            }
+        }
+        for (edge, directions) in edges {
+            assert!(
+                directions.len() == 2 && directions[0] != directions[1],
+                "Edge {:?} is open",
+                edge
+            );
+        }
+    }
+
+    #[test]
+    fn all_inside() {
+        let constant = volume(4, |_| 2.0);
+        assert!(extract(&constant, 1.0, [1.0; 4]).is_err());
+    }
+
+    #[test]
+    fn all_outside() {
+        let constant = volume(4, |_| 0.0);
+        assert!(extract(&constant, 1.0, [1.0; 4]).is_err());
+        let empty = volume(0, |_| 0.0);
+        assert!(extract(&empty, 1.0, [1.0; 4]).is_err());
+    }
+}
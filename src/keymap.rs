@@ -0,0 +1,390 @@
+use winit::event::{ModifiersState, VirtualKeyCode};
+
+/// Something a keyboard shortcut can trigger.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Action {
+    CopyView,
+    CopyCoordinates,
+    CycleLayout,
+    ToggleLinkedViews,
+    CycleStereo,
+    IncreaseEyeSeparation,
+    DecreaseEyeSeparation,
+    IncreaseExposure,
+    DecreaseExposure,
+    RollLeft,
+    RollRight,
+    ToggleUpAxis,
+    ToggleDensity,
+    ToggleComparison,
+    CycleAttribute,
+    CycleAttributeTarget,
+    ToggleLegend,
+    CycleScaleBar,
+    ToggleProfile,
+    CycleBoundsOverlay,
+    ToggleAutoColors,
+    ToggleHoverReadout,
+    ToggleFollow,
+    ShowMetadata,
+    ToggleAnimation,
+    ToggleDepthPrepass,
+    NextCamera,
+    PreviousCamera,
+    ToggleFullscreen,
+    TogglePresentation,
+    RestoreSession,
+    /// Look from the direction of one of `matrix::PRESET_NAMES`.
+    ViewPreset(&'static str),
+}
+
+/// Names used for actions in the keymap file.
+const ACTION_NAMES: &[(&str, Action)] = &[
+    ("copy_view", Action::CopyView),
+    ("copy_coordinates", Action::CopyCoordinates),
+    ("cycle_layout", Action::CycleLayout),
+    ("toggle_linked_views", Action::ToggleLinkedViews),
+    ("cycle_stereo", Action::CycleStereo),
+    ("increase_eye_separation", Action::IncreaseEyeSeparation),
+    ("decrease_eye_separation", Action::DecreaseEyeSeparation),
+    ("increase_exposure", Action::IncreaseExposure),
+    ("decrease_exposure", Action::DecreaseExposure),
+    ("roll_left", Action::RollLeft),
+    ("roll_right", Action::RollRight),
+    ("toggle_up_axis", Action::ToggleUpAxis),
+    ("toggle_density", Action::ToggleDensity),
+    ("toggle_comparison", Action::ToggleComparison),
+    ("cycle_attribute", Action::CycleAttribute),
+    ("cycle_attribute_target", Action::CycleAttributeTarget),
+    ("toggle_legend", Action::ToggleLegend),
+    ("cycle_scale_bar", Action::CycleScaleBar),
+    ("toggle_profile", Action::ToggleProfile),
+    ("cycle_bounds_overlay", Action::CycleBoundsOverlay),
+    ("toggle_auto_colors", Action::ToggleAutoColors),
+    ("toggle_hover_readout", Action::ToggleHoverReadout),
+    ("toggle_follow", Action::ToggleFollow),
+    ("show_metadata", Action::ShowMetadata),
+    ("toggle_animation", Action::ToggleAnimation),
+    ("toggle_depth_prepass", Action::ToggleDepthPrepass),
+    ("next_camera", Action::NextCamera),
+    ("previous_camera", Action::PreviousCamera),
+    ("toggle_fullscreen", Action::ToggleFullscreen),
+    ("toggle_presentation", Action::TogglePresentation),
+    ("restore_session", Action::RestoreSession),
+    ("view_top", Action::ViewPreset("top")),
+    ("view_front", Action::ViewPreset("front")),
+    ("view_side", Action::ViewPreset("side")),
+    ("view_iso", Action::ViewPreset("iso")),
+];
+
+/// A key plus the modifiers that must be held with it.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct KeyBinding {
+    pub key: VirtualKeyCode,
+    pub ctrl: bool,
+    pub shift: bool,
+    pub alt: bool,
+}
+
+impl KeyBinding {
+    fn new(key: VirtualKeyCode) -> Self {
+        KeyBinding {
+            key: key,
+            ctrl: false,
+            shift: false,
+            alt: false,
+        }
+    }
+
+    fn with_ctrl(mut self) -> Self {
+        self.ctrl = true;
+        return self;
+    }
+
+    fn with_shift(mut self) -> Self {
+        self.shift = true;
+        return self;
+    }
+
+    /// Parse a binding such as `V`, `Ctrl+C` or `Ctrl+Shift+F5`.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut parts: Vec<&str> = text.split('+').map(|part| part.trim()).collect();
+        let key_name = parts.pop().unwrap_or("");
+        let key = parse_key(key_name).ok_or_else(|| format!("Unknown key '{}'", key_name))?;
+        let mut binding = KeyBinding::new(key);
+        for modifier in parts {
+            match modifier.to_lowercase().as_str() {
+                "ctrl" | "control" => binding.ctrl = true,
+                "shift" => binding.shift = true,
+                "alt" => binding.alt = true,
+                _ => return Err(format!("Unknown modifier '{}'", modifier)),
+            }
+        }
+        return Ok(binding);
+    }
+
+    fn matches(&self, key: VirtualKeyCode, modifiers: ModifiersState) -> bool {
+        self.key == key
+            && self.ctrl == modifiers.ctrl()
+            && self.shift == modifiers.shift()
+            && self.alt == modifiers.alt()
+    }
+}
+
+/// Maps key presses to actions. Defaults can be overridden from `keys.cfg` in the config
+/// directory, one `action = binding[, binding...]` per line, e.g. `cycle_layout = Ctrl+V`.
+pub struct Keymap {
+    bindings: Vec<(KeyBinding, Action)>,
+}
+
+impl Keymap {
+    pub fn default() -> Self {
+        Keymap {
+            bindings: vec![
+                (
+                    KeyBinding::new(VirtualKeyCode::C).with_ctrl(),
+                    Action::CopyView,
+                ),
+                (
+                    KeyBinding::new(VirtualKeyCode::C).with_ctrl().with_shift(),
+                    Action::CopyCoordinates,
+                ),
+                (KeyBinding::new(VirtualKeyCode::V), Action::CycleLayout),
+                (
+                    KeyBinding::new(VirtualKeyCode::L),
+                    Action::ToggleLinkedViews,
+                ),
+                (KeyBinding::new(VirtualKeyCode::S), Action::CycleStereo),
+                (
+                    KeyBinding::new(VirtualKeyCode::RBracket),
+                    Action::IncreaseEyeSeparation,
+                ),
+                (
+                    KeyBinding::new(VirtualKeyCode::LBracket),
+                    Action::DecreaseEyeSeparation,
+                ),
+                (
+                    KeyBinding::new(VirtualKeyCode::Equals),
+                    Action::IncreaseExposure,
+                ),
+                (
+                    KeyBinding::new(VirtualKeyCode::Minus),
+                    Action::DecreaseExposure,
+                ),
+                (KeyBinding::new(VirtualKeyCode::Q), Action::RollLeft),
+                (KeyBinding::new(VirtualKeyCode::E), Action::RollRight),
+                (KeyBinding::new(VirtualKeyCode::U), Action::ToggleUpAxis),
+                (KeyBinding::new(VirtualKeyCode::H), Action::ToggleDensity),
+                (KeyBinding::new(VirtualKeyCode::B), Action::ToggleComparison),
+                (KeyBinding::new(VirtualKeyCode::I), Action::CycleAttribute),
+                (
+                    KeyBinding::new(VirtualKeyCode::I).with_shift(),
+                    Action::CycleAttributeTarget,
+                ),
+                (KeyBinding::new(VirtualKeyCode::G), Action::ToggleLegend),
+                (KeyBinding::new(VirtualKeyCode::O), Action::CycleScaleBar),
+                (KeyBinding::new(VirtualKeyCode::K), Action::ToggleProfile),
+                (
+                    KeyBinding::new(VirtualKeyCode::X),
+                    Action::CycleBoundsOverlay,
+                ),
+                (KeyBinding::new(VirtualKeyCode::T), Action::ToggleAutoColors),
+                (
+                    KeyBinding::new(VirtualKeyCode::P),
+                    Action::ToggleHoverReadout,
+                ),
+                (KeyBinding::new(VirtualKeyCode::F), Action::ToggleFollow),
+                (KeyBinding::new(VirtualKeyCode::M), Action::ShowMetadata),
+                (KeyBinding::new(VirtualKeyCode::A), Action::ToggleAnimation),
+                (
+                    KeyBinding::new(VirtualKeyCode::D),
+                    Action::ToggleDepthPrepass,
+                ),
+                (KeyBinding::new(VirtualKeyCode::N), Action::NextCamera),
+                (
+                    KeyBinding::new(VirtualKeyCode::N).with_shift(),
+                    Action::PreviousCamera,
+                ),
+                (
+                    KeyBinding::new(VirtualKeyCode::F11),
+                    Action::ToggleFullscreen,
+                ),
+                (
+                    KeyBinding::new(VirtualKeyCode::F5),
+                    Action::TogglePresentation,
+                ),
+                (
+                    KeyBinding::new(VirtualKeyCode::R).with_ctrl(),
+                    Action::RestoreSession,
+                ),
+                (
+                    KeyBinding::new(VirtualKeyCode::Key1),
+                    Action::ViewPreset("top"),
+                ),
+                (
+                    KeyBinding::new(VirtualKeyCode::Key2),
+                    Action::ViewPreset("front"),
+                ),
+                (
+                    KeyBinding::new(VirtualKeyCode::Key3),
+                    Action::ViewPreset("side"),
+                ),
+                (
+                    KeyBinding::new(VirtualKeyCode::Key4),
+                    Action::ViewPreset("iso"),
+                ),
+            ],
+        }
+    }
+
+    /// Load the user's keymap, falling back to the default binding for anything not set there.
+    pub fn load() -> Self {
+        let mut keymap = Keymap::default();
+        let path = match crate::config::config_dir() {
+            Some(dir) => dir.join("keys.cfg"),
+            None => return keymap,
+        };
+        for (name, value) in crate::config::read_settings(&path) {
+            let action = match ACTION_NAMES.iter().find(|(n, _)| *n == name) {
+                Some((_, action)) => *action,
+                None => {
+                    warn!("Unknown action '{}' in {}", name, path.display());
+                    continue;
+                }
+            };
+            let bindings: Result<Vec<KeyBinding>, String> = value
+                .split(',')
+                .filter(|binding| !binding.trim().is_empty())
+                .map(KeyBinding::parse)
+                .collect();
+            match bindings {
+                Ok(bindings) => keymap.set(action, bindings),
+                Err(e) => warn!(
+                    "Invalid binding for '{}' in {}: {}",
+                    name,
+                    path.display(),
+                    e
+                ),
+            }
+        }
+        return keymap;
+    }
+
+    /// Replace all of the bindings for `action`.
+    pub fn set(&mut self, action: Action, bindings: Vec<KeyBinding>) {
+        self.bindings.retain(|(_, a)| *a != action);
+        self.bindings
+            .extend(bindings.into_iter().map(|binding| (binding, action)));
+    }
+
+    pub fn action(&self, key: VirtualKeyCode, modifiers: ModifiersState) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|(binding, _)| binding.matches(key, modifiers))
+            .map(|(_, action)| *action)
+    }
+
+    /// The first binding for `action` written as in `keys.cfg`, e.g. `Ctrl+R`, for hints.
+    pub fn describe(&self, action: Action) -> Option<String> {
+        let (binding, _) = self.bindings.iter().find(|(_, a)| *a == action)?;
+        let mut text = String::new();
+        let modifiers = [
+            (binding.ctrl, "Ctrl+"),
+            (binding.shift, "Shift+"),
+            (binding.alt, "Alt+"),
+        ];
+        for (held, modifier) in &modifiers {
+            if *held {
+                text.push_str(modifier);
+            }
+        }
+        let name = key_name(binding.key)?;
+        let mut chars = name.chars();
+        text.extend(chars.next().map(|c| c.to_ascii_uppercase()));
+        text.push_str(chars.as_str());
+        return Some(text);
+    }
+}
+
+/// A key's name as `parse_key` reads it, if it has one.
+pub fn key_name(key: VirtualKeyCode) -> Option<String> {
+    const OTHER_NAMES: [&str; 20] = [
+        "escape",
+        "space",
+        "enter",
+        "tab",
+        "backspace",
+        "insert",
+        "delete",
+        "home",
+        "end",
+        "pageup",
+        "pagedown",
+        "left",
+        "right",
+        "up",
+        "down",
+        "grave",
+        "minus",
+        "equals",
+        "lbracket",
+        "rbracket",
+    ];
+    let letters = (b'a'..=b'z').map(|c| (c as char).to_string());
+    let digits = (0..10).map(|n: u32| n.to_string());
+    let function_keys = (1..=12).map(|n| format!("f{}", n));
+    let others = OTHER_NAMES.iter().map(|name| name.to_string());
+    return letters
+        .chain(digits)
+        .chain(function_keys)
+        .chain(others)
+        .find(|name| parse_key(name) == Some(key));
+}
+
+pub fn parse_key(name: &str) -> Option<VirtualKeyCode> {
+    use VirtualKeyCode::*;
+    const LETTERS: [VirtualKeyCode; 26] = [
+        A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z,
+    ];
+    const DIGITS: [VirtualKeyCode; 10] =
+        [Key0, Key1, Key2, Key3, Key4, Key5, Key6, Key7, Key8, Key9];
+    const FUNCTION_KEYS: [VirtualKeyCode; 12] = [F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12];
+
+    let name = name.to_lowercase();
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        if c.is_ascii_lowercase() {
+            return Some(LETTERS[(c as u8 - b'a') as usize]);
+        }
+        if c.is_ascii_digit() {
+            return Some(DIGITS[(c as u8 - b'0') as usize]);
+        }
+    }
+    if let Some(Ok(n)) = name.strip_prefix('f').map(|n| n.parse::<usize>()) {
+        return FUNCTION_KEYS.get(n.wrapping_sub(1)).copied();
+    }
+    let key = match name.as_str() {
+        "escape" | "esc" => Escape,
+        "space" => Space,
+        "enter" | "return" => Return,
+        "tab" => Tab,
+        "backspace" => Back,
+        "insert" => Insert,
+        "delete" => Delete,
+        "home" => Home,
+        "end" => End,
+        "pageup" => PageUp,
+        "pagedown" => PageDown,
+        "left" => Left,
+        "right" => Right,
+        "up" => Up,
+        "down" => Down,
+        "grave" | "`" => Grave,
+        "minus" | "-" => Minus,
+        "equals" | "=" => Equals,
+        "lbracket" | "[" => LBracket,
+        "rbracket" | "]" => RBracket,
+        _ => return None,
+    };
+    return Some(key);
+}
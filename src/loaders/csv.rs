@@ -0,0 +1,361 @@
+use super::Loader;
+use crate::error::RscatError;
+use crate::rendering::{self, Line, Vertex};
+use rayon::prelude::*;
+use std::io::{Read, Write};
+use std::path::Path;
+
+/// Comma separated X, Y, Z, R, G, B, Size rows plus any attributes and IDs, loaded as a single
+/// layer.
+pub struct CsvLoader {
+    pub converter: std::rc::Rc<crate::geo::Converter>,
+}
+
+impl Loader for CsvLoader {
+    fn name(&self) -> &str {
+        "CSV"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["csv", "txt"]
+    }
+
+    fn load(&self, path: &Path) -> Result<Vec<Line>, RscatError> {
+        let file = std::fs::File::open(path)?;
+        return Ok(vec![read(file, &self.converter)?]);
+    }
+}
+
+/// Inputs smaller than this are parsed on a single thread, as splitting them up would cost more
+/// than it saves.
+const PARALLEL_THRESHOLD: usize = 1 << 22;
+
+/// How much of a file is read at a time, so loading takes memory for the points rather than for
+/// the points and the whole file's text.
+const READ_CHUNK: usize = 1 << 26;
+
+const COLUMNS_ERROR: &str =
+    "Input needs 7 cols: X, Y, Z, R, G, B, Size, then the same attribute cols per row";
+
+/// The columns of every row, worked out from the first.
+struct Layout {
+    columns: usize,
+    /// The column of point IDs, if there is one.
+    id_column: Option<usize>,
+    /// The attributes' names, from the header row if there is one.
+    names: Option<Vec<String>>,
+}
+
+impl Layout {
+    /// The layout of rows like `first`, and whether `first` is a header row rather than data.
+    fn new(first: &[u8]) -> Result<(Self, bool), RscatError> {
+        let first = String::from_utf8_lossy(first);
+        let first: Vec<&str> = first.trim_end_matches('\r').split(',').collect();
+        if first.len() < 7 {
+            return Err(RscatError::Format(COLUMNS_ERROR.to_string()));
+        }
+        let header = first[0].trim().parse::<f64>().is_err();
+        let mut names: Option<Vec<String>> = if header {
+            Some(
+                first[7..]
+                    .iter()
+                    .map(|name| name.trim().to_string())
+                    .collect(),
+            )
+        } else {
+            None
+        };
+        let id_column = names.as_ref().and_then(|names| {
+            names
+                .iter()
+                .position(|name| name.eq_ignore_ascii_case("id"))
+        });
+        if let (Some(names), Some(id)) = (&mut names, id_column) {
+            names.remove(id);
+        }
+        let layout = Layout {
+            columns: first.len(),
+            id_column: id_column.map(|id| id + 7),
+            names: names,
+        };
+        return Ok((layout, header));
+    }
+
+    fn attribute_count(&self) -> usize {
+        self.columns - 7 - self.id_column.is_some() as usize
+    }
+}
+
+/// The points of a run of rows, in order.
+struct Rows {
+    positions: Vec<nalgebra::Vector3<f64>>,
+    vertices: Vec<Vertex>,
+    attributes: Vec<Vec<f32>>,
+    ids: Vec<u32>,
+    /// How many lines, blank or not, the rows took up.
+    lines: usize,
+}
+
+impl Rows {
+    fn new(attribute_count: usize) -> Self {
+        Rows {
+            positions: Vec::new(),
+            vertices: Vec::new(),
+            attributes: vec![Vec::new(); attribute_count],
+            ids: Vec::new(),
+            lines: 0,
+        }
+    }
+
+    fn append(&mut self, other: &mut Rows) {
+        self.positions.append(&mut other.positions);
+        self.vertices.append(&mut other.vertices);
+        for (values, other_values) in self.attributes.iter_mut().zip(other.attributes.iter_mut()) {
+            values.append(other_values);
+        }
+        self.ids.append(&mut other.ids);
+        self.lines += other.lines;
+    }
+}
+
+/// `field` without surrounding spaces.
+fn trim(field: &[u8]) -> &[u8] {
+    let start = field
+        .iter()
+        .position(|byte| !byte.is_ascii_whitespace())
+        .unwrap_or(field.len());
+    let end = field
+        .iter()
+        .rposition(|byte| !byte.is_ascii_whitespace())
+        .map_or(start, |end| end + 1);
+    return &field[start..end];
+}
+
+/// Parse the rows of `text`. Blank rows are skipped. Errors come with the byte offset of the row
+/// they are in.
+fn parse_rows(text: &[u8], layout: &Layout) -> Result<Rows, (usize, String)> {
+    let mut rows = Rows::new(layout.attribute_count());
+    let mut fields = Vec::with_capacity(layout.columns);
+    let mut start = 0;
+    while start < text.len() {
+        let offset = start;
+        let end = memchr::memchr(b'\n', &text[start..]).map_or(text.len(), |end| start + end);
+        start = end + 1;
+        rows.lines += 1;
+        let row = &text[offset..end];
+        let row = row.strip_suffix(b"\r").unwrap_or(row);
+        if row.is_empty() {
+            continue;
+        }
+        fields.clear();
+        fields.extend(row.split(|&byte| byte == b','));
+        if fields.len() != layout.columns {
+            return Err((offset, COLUMNS_ERROR.to_string()));
+        }
+        let number = |field: &[u8]| {
+            fast_float::parse::<f64, _>(trim(field)).map_err(|_| {
+                let field = String::from_utf8_lossy(field);
+                (offset, format!("Invalid number '{}'", field))
+            })
+        };
+        let mut attribute = 0;
+        for (column, field) in fields.iter().enumerate().skip(7) {
+            if Some(column) == layout.id_column {
+                let id = std::str::from_utf8(trim(field))
+                    .ok()
+                    .and_then(|id| id.parse::<u32>().ok())
+                    .ok_or_else(|| {
+                        let field = String::from_utf8_lossy(field);
+                        (offset, format!("Invalid ID '{}'", field))
+                    })?;
+                rows.ids.push(id);
+            } else {
+                rows.attributes[attribute].push(number(field)? as f32);
+                attribute += 1;
+            }
+        }
+        rows.positions.push(nalgebra::Vector3::new(
+            number(fields[0])?,
+            number(fields[1])?,
+            number(fields[2])?,
+        ));
+        rows.vertices.push(Vertex {
+            position: [0.0, 0.0, 0.0, 1.0_f32],
+            color: [
+                number(fields[3])? as f32,
+                number(fields[4])? as f32,
+                number(fields[5])? as f32,
+                1.0_f32,
+            ],
+            size: number(fields[6])? as f32,
+        });
+    }
+    return Ok(rows);
+}
+
+/// Parse the whole rows of `text`, which starts on line `line` of the input, onto the end of
+/// `rows`, moving `line` on past them. Large texts are split at row boundaries into a run of
+/// rows per thread, parsed in parallel.
+fn parse_text(
+    text: &[u8],
+    layout: &Layout,
+    rows: &mut Rows,
+    line: &mut usize,
+) -> Result<(), RscatError> {
+    let threads = if text.len() < PARALLEL_THRESHOLD {
+        1
+    } else {
+        rayon::current_num_threads()
+    };
+    let mut runs = Vec::with_capacity(threads);
+    let mut start = 0;
+    for i in 1..=threads {
+        // Move each split on to the start of the next row
+        let mut end = (text.len() / threads * i).max(start);
+        if i == threads {
+            end = text.len();
+        } else if end < text.len() {
+            end = memchr::memchr(b'\n', &text[end..]).map_or(text.len(), |row| end + row + 1);
+        }
+        if end > start {
+            runs.push(&text[start..end]);
+            start = end;
+        }
+    }
+    let parsed: Vec<Result<Rows, (usize, String)>> =
+        runs.par_iter().map(|run| parse_rows(run, layout)).collect();
+    for (run, run_rows) in runs.iter().zip(parsed) {
+        match run_rows {
+            Ok(mut run_rows) => {
+                *line += run_rows.lines;
+                rows.append(&mut run_rows);
+            }
+            Err((offset, message)) => {
+                let line = *line + memchr::memchr_iter(b'\n', &run[..offset]).count();
+                return Err(RscatError::Format(format!("Line {}: {}", line, message)));
+            }
+        }
+    }
+    return Ok(());
+}
+
+/// Parse rows of X, Y, Z, R, G, B, Size into a layer, converting positions into the scene frame.
+/// Any further columns are loaded as attributes, named by an optional header row. Without a
+/// header an eighth column is taken to be intensity. A column named `id` holds whole number
+/// point IDs, such as track or detection IDs, reported when a point is picked.
+pub fn parse(text: &[u8], converter: &crate::geo::Converter) -> Result<Line, RscatError> {
+    return read(text, converter);
+}
+
+/// Like `parse`, reading the rows from `reader` a chunk at a time.
+pub fn read<R: Read>(mut reader: R, converter: &crate::geo::Converter) -> Result<Line, RscatError> {
+    let mut layout: Option<Layout> = None;
+    let mut rows = Rows::new(0);
+    let mut line = 1;
+    let mut buffer: Vec<u8> = Vec::new();
+    let mut finished = false;
+    while !finished {
+        let start = buffer.len();
+        let read = (&mut reader)
+            .take(READ_CHUNK as u64)
+            .read_to_end(&mut buffer)?;
+        finished = read < READ_CHUNK;
+        // Parse up to the end of the last whole row, keeping the rest for the next chunk
+        let end = if finished {
+            buffer.len()
+        } else {
+            match memchr::memrchr(b'\n', &buffer[start..]) {
+                Some(end) => start + end + 1,
+                // A row longer than a chunk
+                None => continue,
+            }
+        };
+        let mut text = &buffer[..end];
+        if layout.is_none() && !text.is_empty() {
+            let first_end = memchr::memchr(b'\n', text).unwrap_or(text.len());
+            let (first, header) = Layout::new(&text[..first_end])?;
+            if header {
+                text = &text[(first_end + 1).min(text.len())..];
+                line += 1;
+            }
+            rows = Rows::new(first.attribute_count());
+            layout = Some(first);
+        }
+        if let Some(layout) = &layout {
+            parse_text(text, layout, &mut rows, &mut line)?;
+        }
+        buffer.drain(..end);
+    }
+
+    // Empty input loads as an empty layer
+    let (names, id_column) = match layout {
+        Some(layout) => (layout.names, layout.id_column),
+        None => (None, None),
+    };
+    let mut positions = rows.positions;
+    let mut vertices = rows.vertices;
+    let ids = rows.ids;
+    converter.convert(&mut positions);
+    let origin = super::local_origin(&positions);
+    for (vertex, position) in vertices.iter_mut().zip(positions.iter()) {
+        for axis in 0..3 {
+            vertex.position[axis] = (position[axis] - origin[axis]) as f32;
+        }
+    }
+    return Ok(Line {
+        indicies: rendering::defaults::render_all_vertices(&vertices),
+        verticies: vertices,
+        origin: origin,
+        attributes: rows
+            .attributes
+            .into_iter()
+            .enumerate()
+            .map(|(i, values)| rendering::Attribute {
+                name: match &names {
+                    Some(names) => names[i].clone(),
+                    None if i == 0 => "intensity".to_string(),
+                    None => format!("column {}", i + 8),
+                },
+                values: values,
+            })
+            .collect(),
+        ids: id_column.map(|_| ids),
+        metadata: vec![("input frame".to_string(), converter.describe())],
+    });
+}
+
+/// Write a layer in the format `parse` reads, with a header row naming any attributes and IDs.
+pub fn write<W: Write>(mut writer: W, line: &Line) -> std::io::Result<()> {
+    if !line.attributes.is_empty() || line.ids.is_some() {
+        write!(writer, "x,y,z,r,g,b,size")?;
+        if line.ids.is_some() {
+            write!(writer, ",id")?;
+        }
+        for attribute in &line.attributes {
+            write!(writer, ",{}", attribute.name)?;
+        }
+        writeln!(writer)?;
+    }
+    for &index in &line.indicies {
+        let vertex = &line.verticies[index as usize];
+        write!(
+            writer,
+            "{},{},{},{},{},{},{}",
+            line.origin.x + vertex.position[0] as f64,
+            line.origin.y + vertex.position[1] as f64,
+            line.origin.z + vertex.position[2] as f64,
+            vertex.color[0],
+            vertex.color[1],
+            vertex.color[2],
+            vertex.size
+        )?;
+        if let Some(ids) = &line.ids {
+            write!(writer, ",{}", ids[index as usize])?;
+        }
+        for attribute in &line.attributes {
+            write!(writer, ",{}", attribute.values[index as usize])?;
+        }
+        writeln!(writer)?;
+    }
+    return writer.flush();
+}
@@ -0,0 +1,113 @@
+pub mod csv;
+pub mod plugin;
+pub mod ply;
+
+use crate::error::RscatError;
+use crate::rendering::Line;
+use std::path::Path;
+
+/// Reads a file format into layers that can be drawn.
+pub trait Loader {
+    /// Human readable format name, used in log messages.
+    fn name(&self) -> &str;
+    /// Lower case file extensions, without the dot, this loader handles.
+    fn extensions(&self) -> &[&str];
+    fn load(&self, path: &Path) -> Result<Vec<Line>, RscatError>;
+}
+
+/// Coordinates further than this from the origin lose too much precision as f32, so the layer is
+/// shifted to be centred on its centroid instead.
+pub const SHIFT_THRESHOLD: f64 = 1.0e4;
+
+/// The world position to store a layer of `positions` relative to, so it keeps its precision as
+/// f32.
+pub fn local_origin(positions: &[nalgebra::Vector3<f64>]) -> nalgebra::Vector3<f64> {
+    if positions.is_empty() {
+        return nalgebra::Vector3::zeros();
+    }
+    let centroid = positions.iter().sum::<nalgebra::Vector3<f64>>() / positions.len() as f64;
+    if centroid.amax() > SHIFT_THRESHOLD {
+        return centroid.map(|c| c.round());
+    }
+    return nalgebra::Vector3::zeros();
+}
+
+/// The set of known loaders, chosen between by file extension.
+pub struct Registry {
+    loaders: Vec<Box<dyn Loader>>,
+    /// Shared by the loaders, so geographic input loaded by any of them shares an origin.
+    converter: std::rc::Rc<crate::geo::Converter>,
+}
+
+impl Registry {
+    /// A registry with the built in loaders, which convert positions with `converter`.
+    pub fn new(converter: crate::geo::Converter) -> Self {
+        let converter = std::rc::Rc::new(converter);
+        let mut registry = Registry {
+            loaders: Vec::new(),
+            converter: converter.clone(),
+        };
+        registry.register(Box::new(csv::CsvLoader {
+            converter: converter,
+        }));
+        return registry;
+    }
+
+    /// Register the loader in the plugin library at `path`, see `plugin`.
+    pub fn load_plugin(&mut self, path: &Path) -> Result<(), RscatError> {
+        let loader = plugin::PluginLoader::open(path, self.converter.clone())?;
+        info!(
+            "Loaded plugin {} for .{} files",
+            loader.name(),
+            loader.extensions().join(", .")
+        );
+        self.register(Box::new(loader));
+        return Ok(());
+    }
+
+    /// Add a loader. Loaders registered later take priority for shared extensions.
+    pub fn register(&mut self, loader: Box<dyn Loader>) {
+        self.loaders.insert(0, loader);
+    }
+
+    pub fn find(&self, path: &Path) -> Option<&dyn Loader> {
+        let extension = path.extension()?.to_str()?.to_lowercase();
+        self.loaders
+            .iter()
+            .find(|loader| loader.extensions().contains(&extension.as_str()))
+            .map(|loader| loader.as_ref())
+    }
+
+    pub fn load(&self, path: &Path) -> Result<Vec<Line>, RscatError> {
+        let loader = self
+            .find(path)
+            .ok_or_else(|| RscatError::NoLoader(path.to_path_buf()))?;
+        info!("Loading {} as {}", path.display(), loader.name());
+        let start = std::time::Instant::now();
+        let mut lines = loader.load(path)?;
+        let provenance = [
+            ("source", path.display().to_string()),
+            ("format", loader.name().to_string()),
+            (
+                "load time",
+                format!("{:.3}s", start.elapsed().as_secs_f64()),
+            ),
+        ];
+        for line in lines.iter_mut() {
+            let loaded = provenance
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.clone()));
+            line.metadata.splice(0..0, loaded);
+        }
+        return Ok(lines);
+    }
+
+    /// Load several files into one list of layers, stopping at the first that fails.
+    pub fn load_all(&self, paths: &[std::path::PathBuf]) -> Result<Vec<Line>, RscatError> {
+        let mut lines = Vec::new();
+        for path in paths {
+            lines.extend(self.load(path)?);
+        }
+        return Ok(lines);
+    }
+}
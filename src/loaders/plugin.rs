@@ -0,0 +1,244 @@
+//! Loaders built as separate shared libraries, so teams can read proprietary formats without
+//! forking rscat. They are loaded with `--plugin`.
+//!
+//! A plugin exports a C function `rscat_loader_v1` returning a pointer to a static `PluginV1`,
+//! whose `abi_version` must be `ABI_VERSION`. In C:
+//!
+//! ```c
+//! typedef struct {
+//!     size_t count;
+//!     const double *positions; /* X, Y, Z per point, in the input frame */
+//!     const float *colors;     /* R, G, B per point from 0 to 1, or NULL for white */
+//!     const float *sizes;      /* per point, or NULL for 1 */
+//!     const uint32_t *ids;     /* per point, or NULL if the points have no IDs */
+//!     void *data;              /* for the plugin's own use, e.g. to free the arrays */
+//! } RscatPointsV1;
+//!
+//! typedef struct {
+//!     uint32_t abi_version;   /* 1 */
+//!     const char *name;       /* e.g. "Acme scan" */
+//!     const char *extensions; /* comma-separated, lower case, without dots */
+//!     /* Read the file at the UTF-8 `path` into `points`, returning 0, or write a message of at
+//!        most `error_len` bytes including the terminator into `error` and return non-zero. */
+//!     int32_t (*load)(const char *path, RscatPointsV1 *points, char *error, size_t error_len);
+//!     /* Release what `load` put in `points`, once rscat has copied it. */
+//!     void (*free)(RscatPointsV1 *points);
+//! } RscatPluginV1;
+//!
+//! const RscatPluginV1 *rscat_loader_v1(void);
+//! ```
+//!
+//! `load` is called from rscat's main thread, one file at a time. Positions are converted with
+//! `--input-frame` like CSV files.
+
+use super::Loader;
+use crate::error::RscatError;
+use crate::rendering::{self, Line, Vertex};
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_void};
+use std::path::{Path, PathBuf};
+
+/// The version of `PluginV1` and `PointsV1` this build understands. An incompatible change
+/// bumps it, along with the entry point's name, so older plugins are turned down rather than
+/// misread.
+pub const ABI_VERSION: u32 = 1;
+
+const ENTRY_POINT: &[u8] = b"rscat_loader_v1\0";
+
+/// Room given to plugins for an error message.
+const ERROR_LEN: usize = 1024;
+
+#[repr(C)]
+pub struct PointsV1 {
+    pub count: usize,
+    pub positions: *const f64,
+    pub colors: *const f32,
+    pub sizes: *const f32,
+    pub ids: *const u32,
+    pub data: *mut c_void,
+}
+
+#[repr(C)]
+pub struct PluginV1 {
+    pub abi_version: u32,
+    pub name: *const c_char,
+    pub extensions: *const c_char,
+    pub load: unsafe extern "C" fn(
+        path: *const c_char,
+        points: *mut PointsV1,
+        error: *mut c_char,
+        error_len: usize,
+    ) -> i32,
+    pub free: unsafe extern "C" fn(points: *mut PointsV1),
+}
+
+pub struct PluginLoader {
+    path: PathBuf,
+    name: String,
+    /// Leaked so they can be lent out as `&str`, plugins being loaded once for the whole run.
+    extensions: Vec<&'static str>,
+    plugin: *const PluginV1,
+    converter: std::rc::Rc<crate::geo::Converter>,
+    /// Kept open for as long as `plugin` is used, so declared after it to be dropped after it.
+    _library: libloading::Library,
+}
+
+impl PluginLoader {
+    pub fn open(
+        path: &Path,
+        converter: std::rc::Rc<crate::geo::Converter>,
+    ) -> Result<Self, RscatError> {
+        let failed = |message: String| RscatError::Plugin {
+            path: path.to_path_buf(),
+            message: message,
+        };
+        // The library's initialisers run here, which is as far as it can be trusted
+        let library =
+            unsafe { libloading::Library::new(path) }.map_err(|e| failed(e.to_string()))?;
+        let plugin = unsafe {
+            let entry_point = library
+                .get::<unsafe extern "C" fn() -> *const PluginV1>(ENTRY_POINT)
+                .map_err(|e| failed(e.to_string()))?;
+            entry_point()
+        };
+        if plugin.is_null() {
+            return Err(failed("rscat_loader_v1 returned nothing".to_string()));
+        }
+        let descriptor = unsafe { &*plugin };
+        if descriptor.abi_version != ABI_VERSION {
+            return Err(failed(format!(
+                "Built for plugin ABI version {}, rscat uses {}",
+                descriptor.abi_version, ABI_VERSION
+            )));
+        }
+        let text = |text: *const c_char| {
+            if text.is_null() {
+                return String::new();
+            }
+            unsafe { CStr::from_ptr(text) }
+                .to_string_lossy()
+                .into_owned()
+        };
+        let extensions: Vec<&'static str> = text(descriptor.extensions)
+            .split(',')
+            .map(|extension| extension.trim().trim_start_matches('.').to_lowercase())
+            .filter(|extension| !extension.is_empty())
+            .map(|extension| &*Box::leak(extension.into_boxed_str()))
+            .collect();
+        if extensions.is_empty() {
+            return Err(failed("The plugin handles no file extensions".to_string()));
+        }
+        let name = match text(descriptor.name) {
+            name if name.is_empty() => path.display().to_string(),
+            name => name,
+        };
+        return Ok(PluginLoader {
+            path: path.to_path_buf(),
+            name: name,
+            extensions: extensions,
+            plugin: plugin,
+            converter: converter,
+            _library: library,
+        });
+    }
+
+    /// Copy the points a plugin returned into a layer, converting positions into the scene
+    /// frame.
+    fn to_line(&self, points: &PointsV1) -> Result<Line, RscatError> {
+        let count = points.count;
+        if count > 0 && points.positions.is_null() {
+            return Err(RscatError::Plugin {
+                path: self.path.clone(),
+                message: format!("Returned {} points without positions", count),
+            });
+        }
+        let slice = |data: *const f32, per_point: usize| {
+            if data.is_null() || count == 0 {
+                return None;
+            }
+            Some(unsafe { std::slice::from_raw_parts(data, count * per_point) })
+        };
+        let coordinates: &[f64] = if count == 0 {
+            &[]
+        } else {
+            unsafe { std::slice::from_raw_parts(points.positions, count * 3) }
+        };
+        let colors = slice(points.colors, 3);
+        let sizes = slice(points.sizes, 1);
+        let ids = if points.ids.is_null() || count == 0 {
+            None
+        } else {
+            Some(unsafe { std::slice::from_raw_parts(points.ids, count) }.to_vec())
+        };
+
+        let mut positions: Vec<nalgebra::Vector3<f64>> = coordinates
+            .chunks_exact(3)
+            .map(|p| nalgebra::Vector3::new(p[0], p[1], p[2]))
+            .collect();
+        self.converter.convert(&mut positions);
+        let origin = super::local_origin(&positions);
+        let vertices: Vec<Vertex> = positions
+            .iter()
+            .enumerate()
+            .map(|(i, position)| {
+                let p = position - origin;
+                let color =
+                    colors.map_or([1.0, 1.0, 1.0], |c| [c[3 * i], c[3 * i + 1], c[3 * i + 2]]);
+                Vertex {
+                    position: [p.x as f32, p.y as f32, p.z as f32, 1.0],
+                    color: [color[0], color[1], color[2], 1.0],
+                    size: sizes.map_or(1.0, |sizes| sizes[i]),
+                }
+            })
+            .collect();
+        return Ok(Line {
+            indicies: rendering::defaults::render_all_vertices(&vertices),
+            verticies: vertices,
+            origin: origin,
+            attributes: Vec::new(),
+            ids: ids,
+            metadata: vec![
+                ("plugin".to_string(), self.path.display().to_string()),
+                ("input frame".to_string(), self.converter.describe()),
+            ],
+        });
+    }
+}
+
+impl Loader for PluginLoader {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &self.extensions
+    }
+
+    fn load(&self, path: &Path) -> Result<Vec<Line>, RscatError> {
+        let text = path
+            .to_str()
+            .and_then(|text| CString::new(text).ok())
+            .ok_or_else(|| RscatError::Format(format!("Unusable path {}", path.display())))?;
+        let mut points = PointsV1 {
+            count: 0,
+            positions: std::ptr::null(),
+            colors: std::ptr::null(),
+            sizes: std::ptr::null(),
+            ids: std::ptr::null(),
+            data: std::ptr::null_mut(),
+        };
+        let mut error: Vec<c_char> = vec![0; ERROR_LEN];
+        let plugin = unsafe { &*self.plugin };
+        let status =
+            unsafe { (plugin.load)(text.as_ptr(), &mut points, error.as_mut_ptr(), ERROR_LEN) };
+        if status != 0 {
+            // In case the plugin filled the buffer without terminating it
+            error[ERROR_LEN - 1] = 0;
+            let message = unsafe { CStr::from_ptr(error.as_ptr()) };
+            return Err(RscatError::Format(message.to_string_lossy().into_owned()));
+        }
+        let line = self.to_line(&points);
+        unsafe { (plugin.free)(&mut points) };
+        return Ok(vec![line?]);
+    }
+}
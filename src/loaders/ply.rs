@@ -0,0 +1,105 @@
+use crate::rendering::glyphs::GlyphLayer;
+use crate::rendering::Line;
+use std::io::Write;
+
+/// Write a layer as binary little endian PLY, with double precision positions so georeferenced
+/// coordinates survive, 8 bit colours, any point IDs, and the size and any attributes as float
+/// properties.
+pub fn write<W: Write>(mut writer: W, line: &Line) -> std::io::Result<()> {
+    writeln!(writer, "ply")?;
+    writeln!(writer, "format binary_little_endian 1.0")?;
+    writeln!(writer, "comment written by rscat")?;
+    writeln!(writer, "element vertex {}", line.indicies.len())?;
+    for axis in &["x", "y", "z"] {
+        writeln!(writer, "property double {}", axis)?;
+    }
+    for channel in &["red", "green", "blue"] {
+        writeln!(writer, "property uchar {}", channel)?;
+    }
+    writeln!(writer, "property float size")?;
+    if line.ids.is_some() {
+        writeln!(writer, "property uint id")?;
+    }
+    for attribute in &line.attributes {
+        writeln!(writer, "property float {}", attribute.name)?;
+    }
+    writeln!(writer, "end_header")?;
+    for &index in &line.indicies {
+        let vertex = &line.verticies[index as usize];
+        for axis in 0..3 {
+            let position = line.origin[axis] + vertex.position[axis] as f64;
+            writer.write_all(&position.to_le_bytes())?;
+        }
+        for channel in 0..3 {
+            let value = (vertex.color[channel].clamp(0.0, 1.0) * 255.0).round() as u8;
+            writer.write_all(&[value])?;
+        }
+        writer.write_all(&vertex.size.to_le_bytes())?;
+        if let Some(ids) = &line.ids {
+            writer.write_all(&ids[index as usize].to_le_bytes())?;
+        }
+        for attribute in &line.attributes {
+            writer.write_all(&attribute.values[index as usize].to_le_bytes())?;
+        }
+    }
+    return writer.flush();
+}
+
+/// Write every glyph of a layer as one binary little endian PLY mesh, with double precision
+/// vertex positions, normals, and each glyph's colour with alpha as 8 bit vertex colours.
+pub fn write_mesh<W: Write>(mut writer: W, layer: &GlyphLayer) -> std::io::Result<()> {
+    let mesh = layer.shape.mesh();
+    writeln!(writer, "ply")?;
+    writeln!(writer, "format binary_little_endian 1.0")?;
+    writeln!(writer, "comment written by rscat")?;
+    let vertices = mesh.positions.len() * layer.instances.len();
+    writeln!(writer, "element vertex {}", vertices)?;
+    for axis in &["x", "y", "z"] {
+        writeln!(writer, "property double {}", axis)?;
+    }
+    for axis in &["nx", "ny", "nz"] {
+        writeln!(writer, "property float {}", axis)?;
+    }
+    for channel in &["red", "green", "blue", "alpha"] {
+        writeln!(writer, "property uchar {}", channel)?;
+    }
+    writeln!(
+        writer,
+        "element face {}",
+        mesh.triangles.len() * layer.instances.len()
+    )?;
+    writeln!(writer, "property list uchar uint vertex_indices")?;
+    writeln!(writer, "end_header")?;
+    for instance in &layer.instances {
+        let transform = nalgebra::Matrix4::from(instance.transform);
+        let normal_transform = transform
+            .fixed_slice::<nalgebra::U3, nalgebra::U3>(0, 0)
+            .try_inverse()
+            .map_or(nalgebra::Matrix3::identity(), |inverse| inverse.transpose());
+        for (position, normal) in mesh.positions.iter().zip(&mesh.normals) {
+            let position = transform.transform_point(&nalgebra::Point3::from(*position));
+            for axis in 0..3 {
+                let world = layer.origin[axis] + position[axis] as f64;
+                writer.write_all(&world.to_le_bytes())?;
+            }
+            let normal = (normal_transform * nalgebra::Vector3::from(*normal)).normalize();
+            for axis in 0..3 {
+                writer.write_all(&normal[axis].to_le_bytes())?;
+            }
+            for channel in 0..4 {
+                let value = (instance.color[channel].clamp(0.0, 1.0) * 255.0).round() as u8;
+                writer.write_all(&[value])?;
+            }
+        }
+    }
+    for (i, _) in layer.instances.iter().enumerate() {
+        let first = (i * mesh.positions.len()) as u32;
+        for triangle in &mesh.triangles {
+            writer.write_all(&[3])?;
+            for &index in triangle {
+                writer.write_all(&(first + index).to_le_bytes())?;
+            }
+        }
+    }
+    return writer.flush();
+}
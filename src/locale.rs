@@ -0,0 +1,134 @@
+//! Translations of the text rscat shows in its window, for operators who don't read English. The
+//! language is set in `locale.cfg` in the config directory, e.g. `language = es`, or otherwise
+//! taken from `LANG`. The same file can replace any message by its name, e.g.
+//! `drop_hint = arrastre un CSV aquí`. The log and command line stay in English.
+
+use std::collections::HashMap;
+
+/// Messages by name. `{0}`, `{1}` and so on are replaced by the values the message is shown with,
+/// so translations can put them in whichever order reads naturally.
+const ENGLISH: &[(&str, &str)] = &[
+    ("drop_hint", "drop a CSV file here to open it"),
+    ("restore_hint", "press {0} to restore the last session"),
+    ("thinned", "over the memory budget, thinned layers {0}"),
+    (
+        "pick",
+        "point {0} of layer {1}: position ({2}, {3}, {4}), colour ({5}, {6}, {7}), size {8}",
+    ),
+    ("point_id", "ID {0}"),
+    ("load_failed", "failed to load {0}: {1}"),
+    ("restore_failed", "failed to restore the last session: {0}"),
+];
+
+const SPANISH: &[(&str, &str)] = &[
+    ("drop_hint", "suelte aquí un archivo CSV para abrirlo"),
+    ("restore_hint", "pulse {0} para restaurar la última sesión"),
+    (
+        "thinned",
+        "por encima del presupuesto de memoria, capas reducidas {0}",
+    ),
+    (
+        "pick",
+        "punto {0} de la capa {1}: posición ({2}, {3}, {4}), color ({5}, {6}, {7}), tamaño {8}",
+    ),
+    ("point_id", "ID {0}"),
+    ("load_failed", "no se pudo cargar {0}: {1}"),
+    (
+        "restore_failed",
+        "no se pudo restaurar la última sesión: {0}",
+    ),
+];
+
+const JAPANESE: &[(&str, &str)] = &[
+    ("drop_hint", "CSVファイルをここにドロップすると開きます"),
+    ("restore_hint", "{0}キーで前回のセッションを復元します"),
+    (
+        "thinned",
+        "メモリ予算を超えたため、レイヤー{0}を間引きました",
+    ),
+    (
+        "pick",
+        "レイヤー{1}の点{0}: 位置 ({2}, {3}, {4})、色 ({5}, {6}, {7})、サイズ {8}",
+    ),
+    ("point_id", "ID {0}"),
+    ("load_failed", "{0}を読み込めませんでした: {1}"),
+    (
+        "restore_failed",
+        "前回のセッションを復元できませんでした: {0}",
+    ),
+];
+
+/// Built-in translations by ISO 639-1 language code.
+const LANGUAGES: &[(&str, &[(&str, &str)])] = &[("en", ENGLISH), ("es", SPANISH), ("ja", JAPANESE)];
+
+pub struct Strings {
+    messages: HashMap<String, String>,
+}
+
+impl Strings {
+    pub fn english() -> Self {
+        Strings {
+            messages: ENGLISH
+                .iter()
+                .map(|(name, text)| (name.to_string(), text.to_string()))
+                .collect(),
+        }
+    }
+
+    /// The messages in the configured language, with any overrides from `locale.cfg`. Messages a
+    /// translation lacks are shown in English.
+    pub fn load() -> Self {
+        let mut strings = Strings::english();
+        let path = crate::config::config_dir().map(|dir| dir.join("locale.cfg"));
+        let settings = path
+            .as_ref()
+            .map_or(Vec::new(), |path| crate::config::read_settings(path));
+        let configured = settings
+            .iter()
+            .find(|(name, _)| name == "language")
+            .map(|(_, value)| value.clone());
+        let language = match &configured {
+            Some(language) => language.clone(),
+            // e.g. es_ES.UTF-8
+            None => std::env::var("LANG")
+                .unwrap_or_default()
+                .split(['_', '.'])
+                .next()
+                .unwrap_or("")
+                .to_string(),
+        };
+        match LANGUAGES.iter().find(|(code, _)| *code == language) {
+            Some((_, messages)) => {
+                for (name, text) in messages.iter() {
+                    strings.messages.insert(name.to_string(), text.to_string());
+                }
+            }
+            None if configured.is_some() => warn!("No translation for language '{}'", language),
+            None => {}
+        }
+        for (name, value) in settings {
+            if name == "language" {
+                continue;
+            }
+            if !strings.messages.contains_key(&name) {
+                warn!("Unknown message '{}' in locale.cfg", name);
+                continue;
+            }
+            strings.messages.insert(name, value);
+        }
+        return strings;
+    }
+
+    /// The message called `name` showing `values`.
+    pub fn get(&self, name: &str, values: &[&dyn std::fmt::Display]) -> String {
+        let mut text = self
+            .messages
+            .get(name)
+            .cloned()
+            .unwrap_or_else(|| name.to_string());
+        for (i, value) in values.iter().enumerate() {
+            text = text.replace(&format!("{{{}}}", i), &value.to_string());
+        }
+        return text;
+    }
+}
@@ -4,211 +4,2628 @@ extern crate log;
 
 use nalgebra;
 
+mod alerts;
+mod bench;
+mod cameras;
+mod config;
+mod convert;
+mod ellipsoids;
+mod error;
+mod expression;
+mod geo;
+mod input;
+mod instance;
+mod isosurface;
+mod keymap;
+mod loaders;
+mod locale;
+mod matrix;
+mod mouse;
+mod options;
+mod picking;
+mod quiver;
+mod readout;
+mod recording;
+mod remote;
 mod rendering;
+mod section;
+mod session;
+mod spatial;
+mod touch;
+mod volume;
+mod voxels;
+mod watch;
 
-use std::io::BufRead;
 use winit::{
     event::{Event, WindowEvent},
     event_loop::{ControlFlow, EventLoop},
-    window::WindowBuilder,
+    window::{Fullscreen, Window, WindowBuilder},
 };
 
 enum MouseMode {
     Cursor,
     CameraLook,
     CameraPan,
+    CameraZoom,
+    /// Dragging the comparison divider of the view occupying this rect.
+    DragDivider(rendering::viewport::Rect),
 }
 
 
 fn main() {
-    
-    let mut lines = Vec::<rendering::Line>::new();
-    lines.push(rendering::defaults::get_random_walk(1.0,0.0,0.0,1000000));
-    lines.push(rendering::defaults::get_random_walk(0.0,1.0,0.0,1000000));
-    lines.push(rendering::defaults::get_random_walk(0.0,0.0,1.0,1000000));
-
-    let vertices = rendering::defaults::get_sinc_vertices();
-    let line = rendering::Line{        
-        indicies: rendering::defaults::render_all_vertices(&vertices),
-        verticies: vertices,
-    }; 
+    let mut options = match options::Options::parse(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{}\n\n{}", message, options::USAGE);
+            std::process::exit(1);
+        }
+    };
+    if options.help {
+        println!("{}", options::USAGE);
+        return;
+    }
+    if options.list_adapters {
+        for (i, info) in rendering::list_adapters(options.backends)
+            .iter()
+            .enumerate()
+        {
+            println!(
+                "{}: {} ({:?}, {:?})",
+                i, info.name, info.device_type, info.backend
+            );
+        }
+        return;
+    }
 
-    lines.push(line);
+    env_logger::init();
+    if let Some(generate) = &options.generate {
+        let line = generate.dataset.generate(generate.points);
+        let written = std::fs::File::create(&generate.output)
+            .and_then(|file| loaders::csv::write(std::io::BufWriter::new(file), &line));
+        if let Err(e) = written {
+            eprintln!("Failed to write {}: {}", generate.output.display(), e);
+            std::process::exit(1);
+        }
+        println!(
+            "Wrote {} points to {}",
+            line.indicies.len(),
+            generate.output.display()
+        );
+        return;
+    }
+    if let Some(convert) = &options.convert {
+        let loaders = loader_registry(&options);
+        if let Err(e) = convert::run(convert, &options.files, &loaders) {
+            eprintln!("Failed to convert: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+    let benchmarking = options.bench.is_some();
+    // Rendering a screenshot matrix, which exits as soon as it is written
+    let capturing_matrix = options.matrix.is_some();
+    if !options.files.is_empty()
+        && !options.new_window
+        && options.watch.is_none()
+        && !benchmarking
+        && !capturing_matrix
+        && instance::forward(&options.files)
+    {
+        return;
+    }
 
+    let loaders = loader_registry(&options);
+    // The session a run that didn't exit cleanly left behind, offered for restoring until
+    // something else is opened
+    let owns_session = !benchmarking && !capturing_matrix && session::Session::claim();
+    let mut recovery = if owns_session {
+        session::Session::recover()
+    } else {
+        None
+    };
+    if options.restore && recovery.is_none() {
+        warn!("There is no autosaved session to restore");
+    }
+    let restored = if options.restore {
+        recovery.take()
+    } else {
+        None
+    };
+    // Benchmarks and screenshot matrices need something to draw
+    let mut lines = if let Some(session) = &restored {
+        match loaders.load_all(&session.files) {
+            Ok(loaded) => loaded,
+            Err(e) => {
+                eprintln!("Failed to restore the last session: {}", e);
+                std::process::exit(1);
+            }
+        }
+    } else if options.demo || ((benchmarking || capturing_matrix) && options.files.is_empty()) {
+        rendering::defaults::demo_scene()
+    } else if options.files.is_empty() {
+        Vec::new()
+    } else {
+        match loaders.load_all(&options.files) {
+            Ok(loaded) => loaded,
+            Err(e) => {
+                eprintln!("Failed to load input: {}", e);
+                std::process::exit(1);
+            }
+        }
+    };
+    if !lines.is_empty() && recovery.take().is_some() {
+        warn!("Replacing the session autosaved before rscat last exited abnormally");
+    }
 
-    env_logger::init();
-    let event_loop = EventLoop::new();
-    let window = WindowBuilder::new()
-        .with_title("Rapid Scene Composition & Analysis Tool")
-        .with_inner_size(winit::dpi::LogicalSize::new(800.0, 600.0))
-        .build(&event_loop)
-        .unwrap();
+    let event_loop = EventLoop::<remote::Request>::with_user_event();
+    if let Some(address) = &options.serve {
+        let recorder = options.record.as_ref().and_then(|path| {
+            recording::Recorder::create(path)
+                .map_err(|e| error!("Unable to record to {}: {}", path.display(), e))
+                .ok()
+        });
+        let matrix_dir = options.matrix_dir.clone();
+        let proxy = event_loop.create_proxy();
+        if let Err(e) = remote::serve(address, proxy, recorder, matrix_dir) {
+            error!("Unable to serve remote control: {}", e);
+        }
+    }
+    if let Some(path) = &options.replay {
+        let speed = options.replay_speed;
+        if let Err(e) = recording::replay(path, speed, event_loop.create_proxy()) {
+            error!("Unable to replay {}: {}", path.display(), e);
+        }
+    }
+    instance::listen(event_loop.create_proxy());
+    // For work handed off to other threads to send its results back
+    let proxy = event_loop.create_proxy();
+    // Benchmarks and screenshot matrices use the default size so runs are comparable
+    let window_state = if benchmarking || capturing_matrix {
+        None
+    } else {
+        config::WindowState::load()
+    };
+    let strings = locale::Strings::load();
+    let mut window_builder = WindowBuilder::new().with_title(window_title(&lines, &strings));
+    window_builder = match window_state {
+        Some(state) => window_builder
+            .with_inner_size(state.size)
+            .with_maximized(state.maximized),
+        None => window_builder.with_inner_size(winit::dpi::LogicalSize::new(800.0, 600.0)),
+    };
+    let window = window_builder.build(&event_loop).unwrap();
+    if let Some(position) = window_state.and_then(|state| state.position) {
+        window.set_outer_position(position);
+    }
+    if let Some(monitor) = options.monitor {
+        let count = window.available_monitors().count();
+        if monitor >= count {
+            warn!("No monitor {}, there are {}", monitor, count);
+        }
+    }
+    if options.fullscreen {
+        window.set_fullscreen(Some(fullscreen_on(&window, options.monitor)));
+    }
 
     let size = window.inner_size();
 
     let surface = wgpu::Surface::create(&window);
 
-    let mut renderer = rendering::Renderer::new(surface, size);
+    let mut renderer = match rendering::Renderer::new(surface, size, &options) {
+        Ok(renderer) => renderer,
+        Err(e) => {
+            error!("Unable to start the renderer: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let mut viewports = rendering::viewport::Viewports::new(size);
+    viewports.set_damping(options.damping);
+    viewports.set_up_axis(options.up_axis);
+    viewports.stereo = options.stereo;
+    viewports.eye_separation = options.eye_separation;
+    viewports.comparison = options
+        .compare
+        .map(|layers| rendering::viewport::Comparison {
+            layers: layers,
+            split: 0.5,
+        });
+
+    let mut line_buffers = upload_lines(&mut renderer, &lines, options.evict);
+    let mut axes_buffers = upload_axes(&mut renderer);
+    let mut extra_layers = ExtraLayers::new();
+    for &layer in &options.on_top {
+        match line_buffers.get_mut(layer) {
+            Some(line) => line.depth = rendering::DepthMode::OnTop,
+            None => warn!(
+                "Can't draw layer {} on top, there are {}",
+                layer,
+                lines.len()
+            ),
+        }
+    }
+    for &layer in &options.paths {
+        match line_buffers.get_mut(layer) {
+            Some(line) => line.path = true,
+            None => warn!(
+                "Can't draw layer {} as a path, there are {}",
+                layer,
+                lines.len()
+            ),
+        }
+    }
+    if let Some(session) = &restored {
+        session.apply(&mut line_buffers, &mut viewports);
+    }
+    if let Some(matrix) = &mut options.matrix {
+        matrix.exact |= options.exact_captures;
+        let written = render_matrix(
+            &mut renderer,
+            &viewports,
+            &axes_buffers,
+            &mut line_buffers,
+            &ExtraLayers::new(),
+            &lines,
+            matrix,
+        );
+        match written {
+            Ok(sheet) => println!("Wrote {}", sheet.display()),
+            Err(e) => {
+                eprintln!("Failed to write the screenshot matrix: {}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+    let mut benchmark = options
+        .bench
+        .clone()
+        .map(|bench| bench::Benchmark::new(bench, &renderer.stats));
+    // Whether the point under the cursor is shown in the title bar and highlighted
+    let mut hover_readout = false;
+    let mut highlight: Option<rendering::wireframe::Highlight> = None;
+    // Built the first time a point in the layer is picked
+    let mut trees: Vec<Option<spatial::KdTree>> = Vec::new();
+    // The first point of a cross-section, once it has been Alt-clicked
+    let mut section_start: Option<nalgebra::Vector3<f64>> = None;
+    // The last point picked or section measured, for copying its coordinates
+    let mut last_readout: Option<readout::Readout> = None;
+    // Whether the camera target tracks the latest point as layers arrive
+    let mut follow = options.follow;
+    // Which camera of the latest camera layer the view was last snapped to
+    let mut camera_step: Option<usize> = None;
+    // What presentation mode hid, while it's on
+    let mut presentation: Option<Presentation> = None;
+    if options.presentation {
+        presentation = Some(Presentation::start(
+            &window,
+            options.monitor,
+            &mut renderer,
+            false,
+        ));
+    }
+    // Whether each layer is drawn in its own colour rather than the file's
+    let mut auto_colors = false;
+    // Only redraw when something visible has changed
+    let mut needs_redraw = true;
 
     let mut prev_mouse = winit::dpi::PhysicalPosition::new(0.0, 0.0);
     let mut mouse_mode = MouseMode::Cursor;
     let mut modifiers = winit::event::ModifiersState::empty();
+    let keymap = keymap::Keymap::load();
+    let mouse_map = mouse::MouseMap::load();
+    let mut touches = touch::Touches::new();
+    if recovery.is_some() {
+        info!("rscat didn't exit cleanly last time, its session can be restored");
+        if let Some(key) = keymap.describe(keymap::Action::RestoreSession) {
+            let hint = strings.get("restore_hint", &[&key]);
+            window.set_title(&format!("{} - {}", TITLE, hint));
+        }
+    }
+    let mut autosave = if owns_session {
+        Some(session::Autosave::new())
+    } else {
+        None
+    };
 
-    event_loop.run(move |event, _, control_flow| {
-        // If we have time-varying data, poll as fast as possible so we can update.
-        //*control_flow = ControlFlow::Poll;
+    let frame_interval = options
+        .fps_cap
+        .map(|fps| std::time::Duration::from_secs_f64(1.0 / fps as f64));
+    let mut next_frame = std::time::Instant::now();
+    let mut last_step = std::time::Instant::now();
+
+    let mut input_recorder = options.record_input.as_ref().and_then(|path| {
+        input::Recorder::create(path, window.inner_size())
+            .map_err(|e| error!("Unable to record input to {}: {}", path.display(), e))
+            .ok()
+    });
+    let mut replaying_input = false;
+    if let Some(path) = &options.replay_input {
+        match input::replay(path, event_loop.create_proxy()) {
+            Ok(()) => replaying_input = true,
+            Err(e) => error!("Unable to replay {}: {}", path.display(), e),
+        }
+    }
 
-        // If we don't have any time varying data right now, start sleeping when we don't need to work.
-        *control_flow = ControlFlow::Wait;
+    #[cfg(feature = "hot-reload")]
+    let mut shader_watcher = rendering::hot_reload::ShaderWatcher::new(
+        std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("src")
+            .join("shaders"),
+    );
 
+    let mut alerts = alerts::Alerts::new(std::mem::take(&mut options.alerts), options.alert_sound);
+    let mut folder_watcher = options.watch.clone().map(|dir| {
+        if !dir.is_dir() {
+            warn!("{} isn't a directory yet, watching for it", dir.display());
+        }
+        watch::FolderWatcher::new(dir)
+    });
+
+    event_loop.run(move |event, _, control_flow| {
+        // Keyboard and mouse input, which comes from the recording instead while one is replayed
+        let mut input = match &event {
+            Event::WindowEvent { event, .. } if !replaying_input => {
+                input::Input::from_window_event(event)
+            }
+            _ => None,
+        };
         match event {
+            Event::NewEvents(_) => {
+                // If we don't have any time varying data right now, start sleeping when we don't
+                // need to work. This is only reset once per loop iteration so that later events
+                // can ask to be woken up sooner.
+                *control_flow = ControlFlow::Wait;
+                if benchmarking {
+                    *control_flow = ControlFlow::Poll;
+                }
+
+                // Wake up periodically to check whether any shaders have been edited.
+                #[cfg(feature = "hot-reload")]
+                {
+                    *control_flow = ControlFlow::WaitUntil(
+                        std::time::Instant::now() + std::time::Duration::from_millis(500),
+                    );
+                }
+            }
             Event::WindowEvent {
                 event: WindowEvent::DroppedFile(path),
                 ..
             } => {
                 lines.clear();
-                let result = file_to_vertices(&path);
-                if result.is_ok() {
-                    let vertices = result.unwrap();
-                    let line = rendering::Line{        
-                        indicies: rendering::defaults::render_all_vertices(&vertices),
-                        verticies: vertices,
-                    }; 
-                    lines.push(line)
-                } else {
-                    error!("Input contained invalid data: {}", path.as_path().display());
+                recovery = None;
+                let mut failure = None;
+                match loaders.load(&path) {
+                    Ok(loaded) => lines.extend(loaded),
+                    Err(e) => {
+                        error!("Failed to load {}: {}", path.as_path().display(), e);
+                        failure = Some(strings.get("load_failed", &[&path.display(), &e]));
+                    }
+                }
+                line_buffers = upload_lines(&mut renderer, &lines, options.evict);
+                axes_buffers = upload_axes(&mut renderer);
+                // Glyph and volume layers belong to the scene being replaced
+                extra_layers = ExtraLayers::new();
+                viewports.set_profile(false);
+                section_start = None;
+                highlight = None;
+                trees.clear();
+                if auto_colors {
+                    assign_distinct_colors(&mut line_buffers);
+                }
+                match failure {
+                    Some(failure) => show_error(&window, &failure),
+                    None => window.set_title(&window_title(&lines, &strings)),
                 }
+                needs_redraw = true;
+            }
+            Event::UserEvent(remote::Request { command, reply }) => {
+                let reads_points = match &command {
+                    remote::Command::AppendPoints(..)
+                    | remote::Command::CopyLayers(_)
+                    | remote::Command::Derive(..)
+                    | remote::Command::Voxelize(..)
+                    | remote::Command::Pick(..)
+                    | remote::Command::Svg
+                    | remote::Command::Matrix(_) => true,
+                    // Trimming old points works on the host copy
+                    remote::Command::SetLayer(change) => change.retain.is_some(),
+                    // Moving a layer uploads its points again
+                    remote::Command::SetGroup(change) => change.translate.is_some(),
+                    _ => false,
+                };
+                if reads_points {
+                    restore_layers(
+                        &mut renderer,
+                        &loaders,
+                        &mut lines,
+                        &mut line_buffers,
+                        &mut trees,
+                        "a remote request",
+                    );
+                }
+                let response = match command {
+                    remote::Command::ListLayers => {
+                        let layers: Vec<String> = lines
+                            .iter()
+                            .enumerate()
+                            .map(|(i, line)| {
+                                let metadata: Vec<String> = line
+                                    .metadata
+                                    .iter()
+                                    .map(|(name, value)| {
+                                        let name = bench::json_string(name);
+                                        format!("{}:{}", name, bench::json_string(value))
+                                    })
+                                    .collect();
+                                format!(
+                                    "{{\"index\":{},\"points\":{},\"memory\":{},\
+                                     \"gpu_memory\":{},\"draws\":{},\"metadata\":{{{}}}}}",
+                                    i,
+                                    line.indicies.len(),
+                                    line.memory_size(),
+                                    line_buffers[i].memory_size(),
+                                    line_buffers[i].draws().to_json(),
+                                    metadata.join(",")
+                                )
+                            })
+                            .collect();
+                        let json = format!("[{}]", layers.join(","));
+                        remote::Response::ok("application/json", json.into_bytes())
+                    }
+                    remote::Command::Load(paths) => match loaders.load_all(&paths) {
+                        Ok(loaded) => {
+                            lines = loaded;
+                            recovery = None;
+                            line_buffers = upload_lines(&mut renderer, &lines, options.evict);
+                            axes_buffers = upload_axes(&mut renderer);
+                            extra_layers = ExtraLayers::new();
+                            viewports.set_profile(false);
+                            section_start = None;
+                            highlight = None;
+                            trees.clear();
+                            if auto_colors {
+                                assign_distinct_colors(&mut line_buffers);
+                            }
+                            window.set_title(&window_title(&lines, &strings));
+                            needs_redraw = true;
+                            remote::Response::ok("text/plain", Vec::new())
+                        }
+                        Err(e) => remote::Response::error(400, &e.to_string()),
+                    },
+                    remote::Command::PushPoints(mut line) => {
+                        if !line.metadata.iter().any(|(name, _)| name == "source") {
+                            line.metadata
+                                .insert(0, ("source".to_string(), "remote".to_string()));
+                        }
+                        let mut buffers = renderer.upload(&line);
+                        buffers.retention = options.retain;
+                        buffers.last_pushed = Some(std::time::Instant::now());
+                        alerts.check_points(lines.len(), &line, &mut buffers, 0);
+                        line_buffers.push(buffers);
+                        if auto_colors {
+                            assign_distinct_colors(&mut line_buffers);
+                        }
+                        lines.push(line);
+                        if let Some(origin) = moved_origin(&renderer, &lines) {
+                            restore_layers(
+                                &mut renderer,
+                                &loaders,
+                                &mut lines,
+                                &mut line_buffers,
+                                &mut trees,
+                                "moving the origin",
+                            );
+                            rebase(
+                                &mut renderer,
+                                origin,
+                                &lines,
+                                &mut line_buffers,
+                                &mut extra_layers,
+                                &mut axes_buffers,
+                                &mut viewports,
+                            );
+                            highlight = None;
+                        }
+                        window.set_title(&window_title(&lines, &strings));
+                        if follow {
+                            follow_latest(&mut viewports, &lines, &renderer.origin);
+                        }
+                        needs_redraw = true;
+                        let index = (lines.len() - 1).to_string();
+                        remote::Response::ok("text/plain", index.into_bytes())
+                    }
+                    remote::Command::AppendPoints(index, points) => match lines.get_mut(index) {
+                        Some(line) => {
+                            let from = line.indicies.len();
+                            line.append(&points);
+                            let buffers = &mut line_buffers[index];
+                            renderer.append(buffers, line, from);
+                            buffers.last_pushed = Some(std::time::Instant::now());
+                            alerts.check_points(index, line, buffers, from);
+                            if let Some(tree) = trees.get_mut(index) {
+                                *tree = None;
+                            }
+                            if follow {
+                                follow_latest(&mut viewports, &lines, &renderer.origin);
+                            }
+                            needs_redraw = true;
+                            remote::Response::ok("text/plain", index.to_string().into_bytes())
+                        }
+                        None => remote::Response::error(400, "No such layer"),
+                    },
+                    remote::Command::Derive(index, program) => match lines.get(index) {
+                        Some(line) => {
+                            let line = line.clone();
+                            let proxy = proxy.clone();
+                            // Reply once the derived layer has been added, without holding up
+                            // the event loop while it is evaluated
+                            std::thread::spawn(move || {
+                                let response = match program.evaluate(&line) {
+                                    Ok(derived) => remote::dispatch(
+                                        remote::Command::PushPoints(derived),
+                                        &proxy,
+                                    ),
+                                    Err(e) => remote::Response::error(400, &e.to_string()),
+                                };
+                                reply.send(response);
+                            });
+                            return;
+                        }
+                        None => remote::Response::error(400, "No such layer"),
+                    },
+                    remote::Command::Voxelize(index, size) => match lines.get(index) {
+                        Some(line) => {
+                            let voxels = voxels::voxelize(line, size);
+                            let index = extra_layers.add_glyphs(&mut renderer, voxels);
+                            needs_redraw = true;
+                            remote::Response::ok("text/plain", index.to_string().into_bytes())
+                        }
+                        None => remote::Response::error(400, "No such layer"),
+                    },
+                    remote::Command::PushGlyphs(glyphs) => {
+                        let index = extra_layers.add_glyphs(&mut renderer, glyphs);
+                        needs_redraw = true;
+                        remote::Response::ok("text/plain", index.to_string().into_bytes())
+                    }
+                    remote::Command::PushCameras(frustums, poses) => {
+                        let index = extra_layers.add_glyphs(&mut renderer, frustums);
+                        extra_layers.cameras.push((index, poses));
+                        camera_step = None;
+                        needs_redraw = true;
+                        remote::Response::ok("text/plain", index.to_string().into_bytes())
+                    }
+                    remote::Command::SnapToCamera(layer, camera) => {
+                        let pose = extra_layers
+                            .cameras
+                            .iter()
+                            .find(|(index, _)| *index == layer)
+                            .and_then(|(_, poses)| poses.get(camera));
+                        match pose {
+                            Some(pose) => {
+                                snap_to_camera(&mut viewports, pose, &renderer.origin);
+                                needs_redraw = true;
+                                remote::Response::ok("text/plain", Vec::new())
+                            }
+                            None => remote::Response::error(400, "No such camera"),
+                        }
+                    }
+                    remote::Command::ReplayInput(entry) => {
+                        match entry {
+                            input::Entry::Input(replayed) => input = Some(replayed),
+                            input::Entry::Step(step) => {
+                                viewports.step(step);
+                                needs_redraw = true;
+                            }
+                            input::Entry::Resize(size) => window.set_inner_size(size),
+                        }
+                        remote::Response::ok("text/plain", Vec::new())
+                    }
+                    remote::Command::InputReplayFinished => {
+                        replaying_input = false;
+                        remote::Response::ok("text/plain", Vec::new())
+                    }
+                    remote::Command::PushVolume(volume) => {
+                        let index = extra_layers.add_volume(&mut renderer, volume);
+                        needs_redraw = true;
+                        remote::Response::ok("text/plain", index.to_string().into_bytes())
+                    }
+                    remote::Command::Isosurface(index, threshold, color) => {
+                        match extra_layers.volumes.get(index) {
+                            Some(volume) => {
+                                let volume = volume.clone();
+                                let proxy = proxy.clone();
+                                // Like derived layers, extracted without holding up the event
+                                // loop
+                                std::thread::spawn(move || {
+                                    let response =
+                                        match isosurface::extract(&volume, threshold, color) {
+                                            Ok(surface) => remote::dispatch(
+                                                remote::Command::PushGlyphs(surface),
+                                                &proxy,
+                                            ),
+                                            Err(message) => remote::Response::error(400, &message),
+                                        };
+                                    reply.send(response);
+                                });
+                                return;
+                            }
+                            None => remote::Response::error(400, "No such volume layer"),
+                        }
+                    }
+                    remote::Command::Mesh(index) => match extra_layers.glyphs.get(index) {
+                        Some(glyphs) => {
+                            let mut ply = Vec::new();
+                            match loaders::ply::write_mesh(&mut ply, glyphs) {
+                                Ok(()) => remote::Response::ok("application/octet-stream", ply),
+                                Err(e) => remote::Response::error(500, &e.to_string()),
+                            }
+                        }
+                        None => remote::Response::error(400, "No such glyph layer"),
+                    },
+                    remote::Command::SetVolume(change) => {
+                        match extra_layers.volume_buffers.get_mut(change.index) {
+                            Some(volume) => {
+                                if let Some(transfer) = change.transfer {
+                                    volume.transfer = transfer;
+                                }
+                                volume.window = change.window.unwrap_or(volume.window);
+                                volume.opacity = change.opacity.unwrap_or(volume.opacity);
+                                volume.clip = change.clip.unwrap_or(volume.clip);
+                                volume.slices = change.slices.unwrap_or(volume.slices);
+                                volume.visible = change.visible.unwrap_or(volume.visible);
+                                needs_redraw = true;
+                                remote::Response::ok("text/plain", Vec::new())
+                            }
+                            None => remote::Response::error(400, "No such volume layer"),
+                        }
+                    }
+                    remote::Command::SetGrading(change) => {
+                        let grading = &mut renderer.grading;
+                        grading.exposure = change.exposure.unwrap_or(grading.exposure);
+                        grading.gamma = change.gamma.unwrap_or(grading.gamma);
+                        grading.srgb_points = change.srgb_points.unwrap_or(grading.srgb_points);
+                        needs_redraw = true;
+                        remote::Response::ok("text/plain", Vec::new())
+                    }
+                    remote::Command::SetCamera(change) => {
+                        viewports.update(|camera| {
+                            if let Some(target) = change.target {
+                                camera.set_target(target);
+                            }
+                            if let Some(range) = change.range {
+                                camera.set_range(range);
+                            }
+                            if let Some(azimuth) = change.azimuth {
+                                camera.set_azimuth(azimuth);
+                            }
+                            if let Some(elevation) = change.elevation {
+                                camera.set_elevation(elevation);
+                            }
+                        });
+                        needs_redraw = true;
+                        remote::Response::ok("text/plain", Vec::new())
+                    }
+                    remote::Command::CopyLayers(layers) => {
+                        match copy_layers(&mut renderer, &mut lines, &mut line_buffers, &layers) {
+                            Ok(index) => {
+                                if auto_colors {
+                                    assign_distinct_colors(&mut line_buffers);
+                                }
+                                window.set_title(&window_title(&lines, &strings));
+                                needs_redraw = true;
+                                remote::Response::ok("text/plain", index.to_string().into_bytes())
+                            }
+                            Err(message) => remote::Response::error(400, &message),
+                        }
+                    }
+                    remote::Command::SetGroup(change) => {
+                        let changed = set_group(
+                            &mut renderer,
+                            &mut lines,
+                            &mut line_buffers,
+                            &mut trees,
+                            &change,
+                        );
+                        match changed {
+                            Ok(()) => {
+                                needs_redraw = true;
+                                remote::Response::ok("text/plain", Vec::new())
+                            }
+                            Err(message) => remote::Response::error(400, &message),
+                        }
+                    }
+                    remote::Command::SetLayer(change) => match line_buffers.get_mut(change.index) {
+                        Some(line) => {
+                            change.apply(line);
+                            // Labels read the points, which an evicted layer has dropped
+                            if line.labels > 0 && lines[change.index].is_evicted() {
+                                restore_layers(
+                                    &mut renderer,
+                                    &loaders,
+                                    &mut lines,
+                                    &mut line_buffers,
+                                    &mut trees,
+                                    "labels",
+                                );
+                            }
+                            needs_redraw = true;
+                            remote::Response::ok("text/plain", Vec::new())
+                        }
+                        None => remote::Response::error(400, "No such layer"),
+                    },
+                    remote::Command::Screenshot(exact) => {
+                        let scene = Scene {
+                            axes: &axes_buffers,
+                            lines: &line_buffers,
+                            points: &lines,
+                            extras: &extra_layers,
+                        };
+                        let exact = exact || options.exact_captures;
+                        match capture_scene(&mut renderer, &viewports, &scene, exact) {
+                            Some(image) => {
+                                remote::Response::ok("image/x-portable-pixmap", image.to_ppm())
+                            }
+                            None => remote::Response::error(500, "Capture failed"),
+                        }
+                    }
+                    remote::Command::Depth => {
+                        match renderer.capture_point_map(&viewports, &line_buffers) {
+                            Some(map) => remote::Response::ok("image/x-pfm", map.depth_to_pfm()),
+                            None => remote::Response::error(500, "Capture failed"),
+                        }
+                    }
+                    remote::Command::Pick(x, y) => {
+                        let cursor = winit::dpi::PhysicalPosition::new(x, y);
+                        match pick_at(&renderer, &viewports, &lines, options.pick_radius, cursor) {
+                            Some(pick) => remote::Response::ok(
+                                "application/json",
+                                readout::Readout::point(&lines, &pick)
+                                    .format(readout::CopyFormat::Json)
+                                    .into_bytes(),
+                            ),
+                            None => remote::Response::error(404, "No point there"),
+                        }
+                    }
+                    remote::Command::PointIds => {
+                        match renderer.capture_point_map(&viewports, &line_buffers) {
+                            Some(mut map) => {
+                                // Report points by vertex index, like picking does
+                                for id in map.ids.iter_mut().filter(|id| id[0] != 0) {
+                                    id[1] = lines[id[0] as usize - 1].indicies[id[1] as usize];
+                                }
+                                remote::Response::ok("text/csv", map.ids_to_csv())
+                            }
+                            None => remote::Response::error(500, "Capture failed"),
+                        }
+                    }
+                    remote::Command::Camera => remote::Response::ok(
+                        "text/plain",
+                        remote::camera_query(viewports.camera()).into_bytes(),
+                    ),
+                    remote::Command::Capabilities => remote::Response::ok(
+                        "text/plain",
+                        renderer.capabilities.report().into_bytes(),
+                    ),
+                    remote::Command::Profile => match &extra_layers.profile {
+                        Some((profile, _)) => remote::Response::ok("text/csv", profile.to_csv()),
+                        None => remote::Response::error(400, "No profile has been extracted"),
+                    },
+                    remote::Command::Svg => remote::Response::ok(
+                        "image/svg+xml",
+                        renderer
+                            .export_svg(&viewports, &lines, &line_buffers)
+                            .into_bytes(),
+                    ),
+                    remote::Command::Matrix(mut matrix) => {
+                        matrix.exact |= options.exact_captures;
+                        let written = render_matrix(
+                            &mut renderer,
+                            &viewports,
+                            &axes_buffers,
+                            &mut line_buffers,
+                            &extra_layers,
+                            &lines,
+                            &matrix,
+                        );
+                        match written {
+                            Ok(sheet) => remote::Response::ok(
+                                "text/plain",
+                                sheet.display().to_string().into_bytes(),
+                            ),
+                            Err(e @ error::RscatError::Invalid(_)) => {
+                                remote::Response::error(400, &e.to_string())
+                            }
+                            Err(e) => remote::Response::error(500, &e.to_string()),
+                        }
+                    }
+                };
+                reply.send(response);
             }
             Event::WindowEvent {
                 event: WindowEvent::CloseRequested,
                 ..
             } => {
                 info!("Received WindowEvent::CloseRequested - Closing");
+                // Keep the windowed size and position rather than the monitor's
+                if window.fullscreen().is_none() {
+                    config::WindowState::from_window(&window).save();
+                }
+                // Keep a session that was offered but not restored for the next launch
+                if autosave.is_some() && recovery.is_none() {
+                    session::Session::discard();
+                }
+                if owns_session {
+                    session::Session::release();
+                }
                 *control_flow = ControlFlow::Exit
             }
             Event::WindowEvent {
-                event: WindowEvent::KeyboardInput { .. },
-                ..
-            } => {}
-            Event::WindowEvent {
-                event: WindowEvent::MouseWheel { delta, .. },
+                event: WindowEvent::Resized(size),
                 ..
             } => {
-                match delta {
-                    winit::event::MouseScrollDelta::LineDelta(x, y) => {
-                        renderer
-                            .camera
-                            .move_focus(nalgebra::Vector2::<f32>::new(-x, 0.0));
-                        renderer.camera.move_longitudinally(y);
+                if let Some(recorder) = &mut input_recorder {
+                    recorder.record(&input::Entry::Resize(size));
+                }
+                renderer.resize(size);
+                viewports.resize(size);
+                needs_redraw = true;
+            }
+            Event::MainEventsCleared => {
+                #[cfg(feature = "hot-reload")]
+                {
+                    if shader_watcher.poll() {
+                        renderer.reload_shaders(shader_watcher.dir());
+                        needs_redraw = true;
                     }
-                    _ => {} // TODO: Handle this arm
+                }
+                let now = std::time::Instant::now();
+                if apply_retention(
+                    &mut renderer,
+                    &mut lines,
+                    &mut line_buffers,
+                    &mut trees,
+                    now,
+                ) {
+                    highlight = None;
+                    needs_redraw = true;
+                }
+                if let Some(budget) = options.memory_budget {
+                    let thinned =
+                        apply_memory_budget(&mut renderer, &mut lines, &mut line_buffers, budget);
+                    if !thinned.is_empty() {
+                        let names: Vec<String> = thinned.iter().map(|i| i.to_string()).collect();
+                        let names = names.join(", ");
+                        warn!(
+                            "Memory use is over the memory budget, thinned layers {}",
+                            names
+                        );
+                        let message = strings.get("thinned", &[&names]);
+                        window.set_title(&format!("{} - {}", TITLE, message));
+                        for &i in &thinned {
+                            if let Some(tree) = trees.get_mut(i) {
+                                *tree = None;
+                            }
+                        }
+                        highlight = None;
+                        needs_redraw = true;
+                    }
+                }
+                // The hover readout picks on every cursor movement, so keeps the points around
+                if !hover_readout {
+                    evict_layers(&mut lines, &line_buffers);
+                }
+                if alerts.update(&mut line_buffers, now) {
+                    needs_redraw = true;
+                }
+                let expiry = line_buffers
+                    .iter()
+                    .filter_map(|line| line.next_expiry())
+                    .min();
+                if let (Some(expiry), ControlFlow::Wait) = (expiry, *control_flow) {
+                    // Wake up to drop points as they get too old
+                    *control_flow = ControlFlow::WaitUntil(expiry);
+                }
+                // Wake up to end alert flashes and to check for layers gone silent
+                let silence_check = if alerts.watches_silence() {
+                    Some(now + alerts::CHECK_INTERVAL)
+                } else {
+                    None
+                };
+                let due = line_buffers
+                    .iter()
+                    .filter_map(|line| line.flash_until)
+                    .chain(silence_check)
+                    .min();
+                match (due, *control_flow) {
+                    (Some(due), ControlFlow::Wait) => *control_flow = ControlFlow::WaitUntil(due),
+                    (Some(due), ControlFlow::WaitUntil(wake)) => {
+                        *control_flow = ControlFlow::WaitUntil(wake.min(due));
+                    }
+                    _ => {}
+                }
+                if renderer.animating(&line_buffers) {
+                    // Only poll while something is animated, so a still scene goes back to waiting
+                    *control_flow = ControlFlow::Poll;
+                    needs_redraw = true;
+                }
+                // Replayed input brings its own steps
+                if !replaying_input && viewports.step(now - last_step) {
+                    if let Some(recorder) = &mut input_recorder {
+                        recorder.record(&input::Entry::Step(now - last_step));
+                    }
+                    // Keep the loop running until the camera comes to rest
+                    *control_flow = ControlFlow::Poll;
+                    needs_redraw = true;
+                }
+                last_step = now;
+                if let Some(benchmark) = &benchmark {
+                    let azimuth = benchmark.azimuth();
+                    viewports.update(|camera| camera.set_azimuth(azimuth));
+                    needs_redraw = true;
+                }
+                // Hold off while the last session is on offer, so it isn't overwritten
+                if let (Some(autosave), None) = (&mut autosave, &recovery) {
+                    let due = autosave.update(&lines, &line_buffers, viewports.camera(), now);
+                    match (due, *control_flow) {
+                        (Some(due), ControlFlow::Wait) => {
+                            *control_flow = ControlFlow::WaitUntil(due);
+                        }
+                        (Some(due), ControlFlow::WaitUntil(wake)) => {
+                            *control_flow = ControlFlow::WaitUntil(wake.min(due));
+                        }
+                        _ => {}
+                    }
+                }
+                if let Some(watcher) = &mut folder_watcher {
+                    let changes = watcher.poll(|path| loaders.find(path).is_some());
+                    if !changes.is_empty() {
+                        ingest_changes(
+                            &mut renderer,
+                            &loaders,
+                            changes,
+                            &options,
+                            &mut lines,
+                            &mut line_buffers,
+                            &mut trees,
+                        );
+                        if let Some(origin) = moved_origin(&renderer, &lines) {
+                            restore_layers(
+                                &mut renderer,
+                                &loaders,
+                                &mut lines,
+                                &mut line_buffers,
+                                &mut trees,
+                                "moving the origin",
+                            );
+                            rebase(
+                                &mut renderer,
+                                origin,
+                                &lines,
+                                &mut line_buffers,
+                                &mut extra_layers,
+                                &mut axes_buffers,
+                                &mut viewports,
+                            );
+                        }
+                        if auto_colors {
+                            assign_distinct_colors(&mut line_buffers);
+                        }
+                        window.set_title(&window_title(&lines, &strings));
+                        highlight = None;
+                        needs_redraw = true;
+                    }
+                    // Wake up to look at the directory again
+                    let due = now + watch::POLL_INTERVAL;
+                    match *control_flow {
+                        ControlFlow::Wait => *control_flow = ControlFlow::WaitUntil(due),
+                        ControlFlow::WaitUntil(wake) => {
+                            *control_flow = ControlFlow::WaitUntil(wake.min(due));
+                        }
+                        _ => {}
+                    }
+                }
+                if !needs_redraw {
+                    return;
+                }
+                match frame_interval {
+                    Some(interval) => {
+                        // Hold back the redraw until the frame interval has passed
+                        let now = std::time::Instant::now();
+                        if now >= next_frame {
+                            next_frame = now + interval;
+                            window.request_redraw();
+                        } else {
+                            *control_flow = ControlFlow::WaitUntil(next_frame);
+                        }
+                    }
+                    None => window.request_redraw(),
                 }
             }
-            Event::WindowEvent {
-                event: WindowEvent::Resized(size),
-                ..
-            } => {
-                renderer.resize(size);
+            Event::RedrawRequested(_) => {
+                // Redraw the application.
+                let frame_start = std::time::Instant::now();
+                let frame = match renderer.next_frame() {
+                    Some(frame) => frame,
+                    None => {
+                        // Skip this frame and try again the next time the loop wakes up
+                        error!("Unable to acquire a swap chain texture, skipping frame");
+                        needs_redraw = true;
+                        return;
+                    }
+                };
+                let mut commands = renderer
+                    .device
+                    .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+                let scene = Scene {
+                    axes: &axes_buffers,
+                    lines: &line_buffers,
+                    points: &lines,
+                    extras: &extra_layers,
+                };
+                render_scene(
+                    &mut renderer,
+                    &mut commands,
+                    &frame.view,
+                    &viewports,
+                    &scene,
+                    highlight.as_ref(),
+                );
+
+                renderer.queue.submit(&[commands.finish()]);
+
+                needs_redraw = false;
+
+                renderer.stats.record_frame(frame_start.elapsed());
+                match &mut benchmark {
+                    Some(benchmark) => {
+                        benchmark.record(&renderer.stats, frame_start.elapsed());
+                        if benchmark.is_finished() {
+                            if let Err(e) = benchmark.write(&renderer.adapter.get_info().name) {
+                                error!("Failed to write benchmark results: {}", e);
+                            }
+                            *control_flow = ControlFlow::Exit;
+                        }
+                    }
+                    None => {
+                        let interval = std::time::Duration::from_secs(1);
+                        if let Some(summary) = renderer.stats.report(interval) {
+                            info!("{}", summary);
+                        }
+                    }
+                }
             }
-            Event::WindowEvent {
-                event:
-                    WindowEvent::MouseInput {
-                        device_id: _,
-                        state,
-                        button: _,
-                        ..
+            _ => {}
+        }
+        if let (Some(recorder), Some(input)) = (&mut input_recorder, input) {
+            recorder.record(&input::Entry::Input(input));
+        }
+        match input {
+            Some(input::Input::KeyPressed(key)) => match keymap.action(key, modifiers) {
+                Some(keymap::Action::CopyView) => {
+                    let scene = Scene {
+                        axes: &axes_buffers,
+                        lines: &line_buffers,
+                        points: &lines,
+                        extras: &extra_layers,
+                    };
+                    let exact = options.exact_captures;
+                    if let Some(image) = capture_scene(&mut renderer, &viewports, &scene, exact) {
+                        copy_image_to_clipboard(image);
+                    }
+                }
+                Some(keymap::Action::CopyCoordinates) => match &last_readout {
+                    Some(picked) => copy_text_to_clipboard(&picked.format(options.copy_format)),
+                    None => info!("No point picked or section measured to copy"),
+                },
+                Some(keymap::Action::CycleLayout) => {
+                    viewports.cycle_layout();
+                    needs_redraw = true;
+                }
+                Some(keymap::Action::ToggleLinkedViews) => {
+                    viewports.linked = !viewports.linked;
+                    info!("Viewport navigation linked: {}", viewports.linked);
+                }
+                Some(keymap::Action::CycleStereo) => {
+                    viewports.cycle_stereo();
+                    info!("Stereo mode: {:?}", viewports.stereo);
+                    needs_redraw = true;
+                }
+                Some(keymap::Action::IncreaseExposure) => {
+                    renderer.grading.exposure += EXPOSURE_STEP;
+                    info!("Exposure: {:+} stops", renderer.grading.exposure);
+                    needs_redraw = true;
+                }
+                Some(keymap::Action::DecreaseExposure) => {
+                    renderer.grading.exposure -= EXPOSURE_STEP;
+                    info!("Exposure: {:+} stops", renderer.grading.exposure);
+                    needs_redraw = true;
+                }
+                Some(keymap::Action::IncreaseEyeSeparation) => {
+                    viewports.eye_separation *= EYE_SEPARATION_STEP;
+                    needs_redraw = true;
+                }
+                Some(keymap::Action::DecreaseEyeSeparation) => {
+                    viewports.eye_separation /= EYE_SEPARATION_STEP;
+                    needs_redraw = true;
+                }
+                Some(keymap::Action::RollLeft) => {
+                    viewports.update(|camera| camera.roll(-ROLL_STEP.to_radians()));
+                    needs_redraw = true;
+                }
+                Some(keymap::Action::RollRight) => {
+                    viewports.update(|camera| camera.roll(ROLL_STEP.to_radians()));
+                    needs_redraw = true;
+                }
+                Some(keymap::Action::ToggleUpAxis) => {
+                    let up_axis = match viewports.up_axis() {
+                        rendering::UpAxis::Y => rendering::UpAxis::Z,
+                        rendering::UpAxis::Z => rendering::UpAxis::Y,
+                    };
+                    viewports.set_up_axis(up_axis);
+                    info!("Up axis: {:?}", up_axis);
+                    needs_redraw = true;
+                }
+                Some(keymap::Action::ToggleDensity) => {
+                    renderer.mode = match renderer.mode {
+                        rendering::RenderMode::Points => rendering::RenderMode::Density,
+                        rendering::RenderMode::Density => rendering::RenderMode::Points,
+                    };
+                    info!("Render mode: {:?}", renderer.mode);
+                    needs_redraw = true;
+                }
+                Some(keymap::Action::CycleAttribute) => {
+                    // Step through each loaded attribute, then back to none
+                    let names = renderer.attribute_names();
+                    let next = match &renderer.attribute {
+                        Some(current) => {
+                            names.iter().position(|name| name == current).map(|i| i + 1)
+                        }
+                        None => Some(0),
+                    };
+                    renderer.attribute = next.and_then(|i| names.get(i)).cloned();
+                    info!(
+                        "Attribute: {:?} ({:?})",
+                        renderer.attribute, renderer.attribute_target
+                    );
+                    needs_redraw = true;
+                }
+                Some(keymap::Action::CycleAttributeTarget) => {
+                    renderer.attribute_target = renderer.attribute_target.next();
+                    info!("Attribute drives {:?}", renderer.attribute_target);
+                    needs_redraw = true;
+                }
+                Some(keymap::Action::ToggleProfile) => {
+                    viewports.set_profile(!viewports.profile());
+                    info!("Profile view: {}", viewports.profile());
+                    needs_redraw = true;
+                }
+                Some(keymap::Action::ToggleLegend) => {
+                    let legend = &mut renderer.legend_overlay;
+                    legend.visible = !legend.visible;
+                    info!("Colormap legend: {}", legend.visible);
+                    needs_redraw = true;
+                }
+                Some(keymap::Action::CycleScaleBar) => {
+                    renderer.scale_bar.mode = renderer.scale_bar.mode.next();
+                    info!("Scale bar: {:?}", renderer.scale_bar.mode);
+                    needs_redraw = true;
+                }
+                Some(keymap::Action::ToggleHoverReadout) => {
+                    hover_readout = !hover_readout;
+                    highlight = if hover_readout {
+                        restore_layers(
+                            &mut renderer,
+                            &loaders,
+                            &mut lines,
+                            &mut line_buffers,
+                            &mut trees,
+                            "the hover readout",
+                        );
+                        hover(
+                            &mut renderer,
+                            &window,
+                            &viewports,
+                            &lines,
+                            options.pick_radius,
+                            prev_mouse,
+                            &strings,
+                        )
+                    } else {
+                        window.set_title(&window_title(&lines, &strings));
+                        None
+                    };
+                    needs_redraw = true;
+                }
+                Some(keymap::Action::ShowMetadata) => {
+                    let mut total = rendering::stats::LayerDraws::default();
+                    let mut gpu_memory = 0;
+                    for (i, line) in lines.iter().enumerate() {
+                        info!("{}", describe_layer(i, line, &line_buffers[i]));
+                        total.add(line_buffers[i].draws());
+                        gpu_memory += line_buffers[i].memory_size();
+                    }
+                    info!(
+                        "Scene: {}, {:.1} MB GPU memory",
+                        describe_draws(&total),
+                        gpu_memory as f64 / 1e6
+                    );
+                }
+                Some(keymap::Action::ToggleDepthPrepass) => {
+                    renderer.depth_prepass = !renderer.depth_prepass;
+                    info!("Depth pre-pass: {}", renderer.depth_prepass);
+                    needs_redraw = true;
+                }
+                Some(keymap::Action::ToggleAnimation) => {
+                    renderer.animate = !renderer.animate;
+                    info!("Animating layers: {}", renderer.animate);
+                    needs_redraw = true;
+                }
+                Some(action @ keymap::Action::NextCamera)
+                | Some(action @ keymap::Action::PreviousCamera) => {
+                    let poses = extra_layers.cameras.last().map(|(_, poses)| poses);
+                    if let Some(poses) = poses.filter(|poses| !poses.is_empty()) {
+                        let count = poses.len();
+                        let step = match camera_step {
+                            None => 0,
+                            Some(step) if action == keymap::Action::NextCamera => step + 1,
+                            Some(step) => step + count - 1,
+                        } % count;
+                        snap_to_camera(&mut viewports, &poses[step], &renderer.origin);
+                        camera_step = Some(step);
+                        needs_redraw = true;
+                    }
+                }
+                Some(keymap::Action::ToggleFollow) => {
+                    follow = !follow;
+                    if follow {
+                        follow_latest(&mut viewports, &lines, &renderer.origin);
+                    }
+                    info!("Following the latest point: {}", follow);
+                    needs_redraw = true;
+                }
+                Some(keymap::Action::ToggleAutoColors) => {
+                    auto_colors = !auto_colors;
+                    if auto_colors {
+                        assign_distinct_colors(&mut line_buffers);
+                    } else {
+                        for line in line_buffers.iter_mut() {
+                            line.tint.mode = rendering::TintMode::Off;
+                        }
+                    }
+                    info!("Distinct layer colours: {}", auto_colors);
+                    needs_redraw = true;
+                }
+                Some(keymap::Action::ToggleFullscreen) => {
+                    if window.fullscreen().is_some() {
+                        window.set_fullscreen(None);
+                    } else {
+                        window.set_fullscreen(Some(fullscreen_on(&window, options.monitor)));
+                    }
+                }
+                Some(keymap::Action::TogglePresentation) => {
+                    presentation = match presentation.take() {
+                        Some(hidden) => {
+                            hover_readout = hidden.end(&window, &mut renderer);
+                            None
+                        }
+                        None => {
+                            let monitor = options.monitor;
+                            let hidden =
+                                Presentation::start(&window, monitor, &mut renderer, hover_readout);
+                            hover_readout = false;
+                            highlight = None;
+                            Some(hidden)
+                        }
+                    };
+                    window.set_title(&window_title(&lines, &strings));
+                    needs_redraw = true;
+                }
+                Some(keymap::Action::RestoreSession) => match recovery.take() {
+                    Some(session) => match loaders.load_all(&session.files) {
+                        Ok(loaded) => {
+                            lines = loaded;
+                            line_buffers = upload_lines(&mut renderer, &lines, options.evict);
+                            axes_buffers = upload_axes(&mut renderer);
+                            extra_layers = ExtraLayers::new();
+                            viewports.set_profile(false);
+                            section_start = None;
+                            highlight = None;
+                            trees.clear();
+                            if auto_colors {
+                                assign_distinct_colors(&mut line_buffers);
+                            }
+                            session.apply(&mut line_buffers, &mut viewports);
+                            window.set_title(&window_title(&lines, &strings));
+                            needs_redraw = true;
+                        }
+                        Err(e) => {
+                            error!("Failed to restore the last session: {}", e);
+                            show_error(&window, &strings.get("restore_failed", &[&e]));
+                            // Keep offering it, e.g. until a network drive is back
+                            recovery = Some(session);
+                        }
                     },
-                ..
-            } => match state {
-                winit::event::ElementState::Pressed => match modifiers {
-                    m if m.shift() => mouse_mode = MouseMode::CameraPan,
-                    _ => mouse_mode = MouseMode::CameraLook,
+                    None => info!("There is no session to restore"),
                 },
+                Some(keymap::Action::CycleBoundsOverlay) => {
+                    renderer.bounds_overlay = renderer.bounds_overlay.next();
+                    info!("Bounding boxes: {:?}", renderer.bounds_overlay);
+                    needs_redraw = true;
+                }
+                Some(keymap::Action::ViewPreset(name)) => {
+                    if let Ok(preset) = matrix::Preset::parse(name) {
+                        viewports.update(|camera| {
+                            camera.set_azimuth(preset.azimuth.to_radians());
+                            camera.set_elevation(preset.elevation.to_radians());
+                        });
+                        info!("Camera: {}", remote::camera_query(viewports.camera()));
+                        needs_redraw = true;
+                    }
+                }
+                Some(keymap::Action::ToggleComparison) => {
+                    viewports.comparison = match viewports.comparison {
+                        Some(_) => None,
+                        None => {
+                            let layers = options.compare.unwrap_or([0, 1]);
+                            if layers.iter().any(|&layer| layer >= lines.len()) {
+                                warn!("Comparing layers {:?} of {}", layers, lines.len());
+                            }
+                            Some(rendering::viewport::Comparison {
+                                layers: layers,
+                                split: 0.5,
+                            })
+                        }
+                    };
+                    needs_redraw = true;
+                }
+                None => {}
+            },
+            Some(input::Input::MouseWheel(delta)) => {
+                match delta {
+                    winit::event::MouseScrollDelta::LineDelta(x, y) => {
+                        viewports.activate_at(prev_mouse);
+                        viewports.update(|camera| {
+                            let pan = nalgebra::Vector2::<f32>::new(-x * PIXELS_PER_LINE, 0.0);
+                            camera.move_focus(mouse_map.pan(pan));
+                            camera.move_longitudinally(mouse_map.zoom(y));
+                        });
+                        needs_redraw = true;
+                    }
+                    winit::event::MouseScrollDelta::PixelDelta(delta) => {
+                        // Touchpads scroll in pixels: two-finger scrolling pans, and pinching is
+                        // reported as scrolling with Ctrl held, which zooms
+                        let delta = delta.to_physical::<f32>(window.scale_factor());
+                        viewports.activate_at(prev_mouse);
+                        if modifiers.ctrl() {
+                            let zoom = mouse_map.zoom(delta.y / PIXELS_PER_LINE);
+                            viewports.update(|camera| camera.move_longitudinally(zoom));
+                        } else {
+                            let pan = nalgebra::Vector2::<f32>::new(delta.x, delta.y);
+                            let pan = mouse_map.pan(pan);
+                            viewports.update(|camera| camera.move_focus(pan));
+                        }
+                        needs_redraw = true;
+                    }
+                }
+            }
+            Some(input::Input::Touch(phase, id, position)) => {
+                if touches.first().is_none() {
+                    viewports.activate_at(position);
+                }
+                match touches.update(phase, id, position) {
+                    Some(touch::Gesture::Orbit(delta)) => {
+                        let orbit = mouse_map.orbit(delta);
+                        viewports.update(|camera| camera.move_on_orbit(orbit));
+                        needs_redraw = true;
+                    }
+                    Some(touch::Gesture::PanZoom { pan, zoom }) => {
+                        let pan = mouse_map.pan(pan);
+                        let zoom = mouse_map.zoom(zoom);
+                        viewports.update(|camera| {
+                            camera.move_focus(pan);
+                            camera.move_longitudinally(zoom);
+                        });
+                        needs_redraw = true;
+                    }
+                    None => {}
+                }
+            }
+            Some(input::Input::MouseButton(state, button)) => match state {
+                winit::event::ElementState::Pressed => {
+                    viewports.activate_at(prev_mouse);
+                    if modifiers.ctrl() || modifiers.alt() {
+                        restore_layers(
+                            &mut renderer,
+                            &loaders,
+                            &mut lines,
+                            &mut line_buffers,
+                            &mut trees,
+                            "picking",
+                        );
+                    }
+                    match viewports.divider_at(prev_mouse) {
+                        Some(rect) => mouse_mode = MouseMode::DragDivider(rect),
+                        None if modifiers.ctrl() => {
+                            trees.resize_with(lines.len(), || None);
+                            let picked = log_pick(
+                                &renderer,
+                                &viewports,
+                                &lines,
+                                &mut trees,
+                                options.pick_radius,
+                                prev_mouse,
+                            );
+                            if let Some(picked) = picked {
+                                // Right-clicking also copies the point's coordinates
+                                if button == winit::event::MouseButton::Right {
+                                    copy_text_to_clipboard(&picked.format(options.copy_format));
+                                }
+                                last_readout = Some(picked);
+                            }
+                        }
+                        None if modifiers.alt() => {
+                            let radius = options.pick_radius;
+                            match pick_at(&renderer, &viewports, &lines, radius, prev_mouse) {
+                                Some(pick) => match section_start.take() {
+                                    Some(start) => {
+                                        let section = readout::Readout::Section {
+                                            start: start,
+                                            end: pick.position,
+                                        };
+                                        if button == winit::event::MouseButton::Right {
+                                            let text = section.format(options.copy_format);
+                                            copy_text_to_clipboard(&text);
+                                        }
+                                        last_readout = Some(section);
+                                        extra_layers.profile = show_profile(
+                                            &mut renderer,
+                                            &mut viewports,
+                                            &lines,
+                                            start,
+                                            pick.position,
+                                            options.section_thickness,
+                                        );
+                                        needs_redraw = true;
+                                    }
+                                    None => {
+                                        let p = pick.position;
+                                        info!(
+                                            "Section from ({}, {}, {}), Alt-click where it ends",
+                                            p.x, p.y, p.z
+                                        );
+                                        section_start = Some(p);
+                                    }
+                                },
+                                None => info!("No point under the cursor"),
+                            }
+                        }
+                        None => {
+                            mouse_mode = match mouse_map.drag(button, modifiers) {
+                                Some(mouse::Drag::Orbit) => MouseMode::CameraLook,
+                                Some(mouse::Drag::Pan) => MouseMode::CameraPan,
+                                Some(mouse::Drag::Zoom) => MouseMode::CameraZoom,
+                                None => MouseMode::Cursor,
+                            }
+                        }
+                    }
+                }
                 winit::event::ElementState::Released => {
                     mouse_mode = MouseMode::Cursor;
                 }
             },
-            Event::WindowEvent {
-                event: WindowEvent::ModifiersChanged(modifiers_state),
-                ..
-            } => {
+            Some(input::Input::Modifiers(modifiers_state)) => {
                 modifiers = modifiers_state;
             }
-            Event::WindowEvent {
-                event: WindowEvent::CursorMoved { position, .. },
-                ..
-            } => {
+            Some(input::Input::CursorMoved(position)) => {
                 let mouse_delta = nalgebra::Vector2::<f32>::new(
                     (position.x - prev_mouse.x) as f32,
                     (position.y - prev_mouse.y) as f32,
                 );
                 match &mouse_mode {
+                    MouseMode::Cursor if hover_readout => {
+                        highlight = hover(
+                            &mut renderer,
+                            &window,
+                            &viewports,
+                            &lines,
+                            options.pick_radius,
+                            position,
+                            &strings,
+                        );
+                        needs_redraw = true;
+                    }
                     MouseMode::Cursor => {}
-                    MouseMode::CameraLook => renderer.camera.move_on_orbit(mouse_delta),
-                    MouseMode::CameraPan => renderer.camera.move_focus(mouse_delta),
+                    MouseMode::CameraLook => {
+                        let orbit = mouse_map.orbit(mouse_delta);
+                        viewports.update(|camera| camera.move_on_orbit(orbit));
+                        needs_redraw = true;
+                    }
+                    MouseMode::CameraPan => {
+                        let pan = mouse_map.pan(mouse_delta);
+                        viewports.update(|camera| camera.move_focus(pan));
+                        needs_redraw = true;
+                    }
+                    MouseMode::CameraZoom => {
+                        // Dragging up zooms in, as scrolling up does
+                        let zoom = mouse_map.zoom(-mouse_delta.y / PIXELS_PER_LINE);
+                        viewports.update(|camera| camera.move_longitudinally(zoom));
+                        needs_redraw = true;
+                    }
+                    MouseMode::DragDivider(rect) => {
+                        viewports.drag_divider(*rect, position);
+                        needs_redraw = true;
+                    }
                 }
                 prev_mouse = position;
             }
-            Event::MainEventsCleared => {
-                window.request_redraw();
+            None => {}
+        }
+    });
+
+
+}
+
+/// Drop the points that have fallen out of each layer's retention window, returning whether any
+/// were dropped.
+fn apply_retention(
+    renderer: &mut rendering::Renderer,
+    lines: &mut [rendering::Line],
+    line_buffers: &mut [rendering::LineBuffers],
+    trees: &mut [Option<spatial::KdTree>],
+    now: std::time::Instant,
+) -> bool {
+    let mut trimmed = false;
+    for (i, (line, buffers)) in lines.iter_mut().zip(line_buffers.iter_mut()).enumerate() {
+        let expired = buffers.expired(now);
+        if expired == 0 {
+            continue;
+        }
+        renderer.trim(buffers, expired);
+        line.drain_front(expired);
+        if let Some(tree) = trees.get_mut(i) {
+            *tree = None;
+        }
+        trimmed = true;
+    }
+    return trimmed;
+}
+
+/// Layers aren't thinned below this many points, however far over the memory budget they are.
+const MIN_THINNED_POINTS: usize = 1024;
+
+/// Thin the least recently drawn point layers to every other point until the layers fit in
+/// `budget` bytes of host and GPU memory. Returns the layers thinned.
+fn apply_memory_budget(
+    renderer: &mut rendering::Renderer,
+    lines: &mut [rendering::Line],
+    line_buffers: &mut [rendering::LineBuffers],
+    budget: usize,
+) -> Vec<usize> {
+    let mut thinned = Vec::new();
+    loop {
+        let used: usize = lines
+            .iter()
+            .zip(line_buffers.iter())
+            .map(|(line, buffers)| line.memory_size() + buffers.memory_size())
+            .sum();
+        if used <= budget {
+            break;
+        }
+        // Evicted layers have nothing left on the host to thin
+        let oldest = (0..lines.len())
+            .filter(|&i| lines[i].indicies.len() >= 2 * MIN_THINNED_POINTS)
+            .filter(|&i| !lines[i].is_evicted())
+            .min_by_key(|&i| line_buffers[i].last_drawn());
+        let i = match oldest {
+            Some(i) => i,
+            None => break,
+        };
+        let line = &mut lines[i];
+        line.thin();
+        renderer.thin(&mut line_buffers[i], line);
+        // Record how much is left, e.g. for the metadata readout
+        let kept = match line.metadata.iter().position(|(name, _)| name == "thinned") {
+            Some(entry) => {
+                let kept = line.metadata.remove(entry).1;
+                kept.trim_start_matches("1 in ")
+                    .parse::<usize>()
+                    .unwrap_or(1)
+                    * 2
             }
-            Event::RedrawRequested(_) => {
-                // Redraw the application.
-                let frame = renderer
-                    .swap_chain
-                    .get_next_texture()
-                    .expect("Timeout when acquiring next swap chain texture");
-                let mut commands = renderer
-                    .device
-                    .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
-                renderer.render(&mut commands, &frame.view, &rendering::defaults::axes(), &rendering::defaults::render_all_vertices(&rendering::defaults::axes()), true);
-                //renderer.render(&mut commands, &frame.view, &vertices, &indecies, false);
-                for i in 0..lines.len() {
-                    let v = &lines[i].verticies;
-                    let i = &lines[i].indicies;
-                    renderer.render(&mut commands, &frame.view, v, &i, false);
-                }
-                
-                renderer.queue.submit(&[commands.finish()]);
+            None => 2,
+        };
+        line.metadata
+            .push(("thinned".to_string(), format!("1 in {}", kept)));
+        if !thinned.contains(&i) {
+            thinned.push(i);
+        }
+    }
+    return thinned;
+}
+
+/// Add a layer of the points of `layers`, drawn like the first of them, returning its index. The
+/// points are moved to the first layer's origin, so the new layer needs nothing else to be placed.
+/// Merging several layers hides them, so the scene still looks the same.
+fn copy_layers(
+    renderer: &mut rendering::Renderer,
+    lines: &mut Vec<rendering::Line>,
+    line_buffers: &mut Vec<rendering::LineBuffers>,
+    layers: &[usize],
+) -> Result<usize, String> {
+    if let Some(layer) = layers.iter().find(|&&layer| layer >= lines.len()) {
+        return Err(format!("No layer {}", layer));
+    }
+    let first = *layers.first().ok_or("No layers to copy")?;
+    let mut line = lines[first].clone();
+    for &layer in &layers[1..] {
+        line.append(&lines[layer]);
+    }
+    let listed: Vec<String> = layers.iter().map(|layer| layer.to_string()).collect();
+    let source = match layers {
+        [layer] => format!("duplicate of layer {}", layer),
+        _ => format!("merge of layers {}", listed.join(", ")),
+    };
+    // The copy isn't the file's layer, so isn't reloaded or restored from it
+    line.metadata
+        .retain(|(name, _)| name != "source" && name != "format");
+    line.metadata.insert(0, ("source".to_string(), source));
+    let mut buffers = renderer.upload(&line);
+    let original = &line_buffers[first];
+    buffers.depth = original.depth;
+    buffers.priority = original.priority;
+    buffers.tint = original.tint;
+    buffers.path = original.path;
+    buffers.animation = original.animation;
+    buffers.color_range = original.color_range;
+    buffers.channel_order = original.channel_order;
+    buffers.shape = original.shape;
+    buffers.min_size = original.min_size;
+    buffers.max_size = original.max_size;
+    if layers.len() > 1 {
+        for &layer in layers {
+            line_buffers[layer].visible = false;
+        }
+    }
+    line_buffers.push(buffers);
+    lines.push(line);
+    info!(
+        "Added layer {} from layers {}",
+        lines.len() - 1,
+        listed.join(", ")
+    );
+    return Ok(lines.len() - 1);
+}
+
+/// Move `change.layers` into the group `change.name`, then apply the change to every layer in
+/// the group.
+fn set_group(
+    renderer: &mut rendering::Renderer,
+    lines: &mut [rendering::Line],
+    line_buffers: &mut [rendering::LineBuffers],
+    trees: &mut [Option<spatial::KdTree>],
+    change: &remote::GroupChange,
+) -> Result<(), String> {
+    if let Some(layer) = change.layers.iter().find(|&&layer| layer >= lines.len()) {
+        return Err(format!("No layer {}", layer));
+    }
+    for &layer in &change.layers {
+        let metadata = &mut lines[layer].metadata;
+        metadata.retain(|(name, _)| name != "group");
+        metadata.push(("group".to_string(), change.name.clone()));
+    }
+    let members: Vec<usize> = (0..lines.len())
+        .filter(|&i| {
+            let group = lines[i].metadata.iter().find(|(name, _)| name == "group");
+            group.is_some_and(|(_, name)| *name == change.name)
+        })
+        .collect();
+    if members.is_empty() {
+        return Err(format!("No layers in group '{}'", change.name));
+    }
+    for &i in &members {
+        let buffers = &mut line_buffers[i];
+        buffers.visible = change.visible.unwrap_or(buffers.visible);
+        buffers.tint.alpha = change.alpha.unwrap_or(buffers.tint.alpha);
+        if let Some(offset) = change.translate {
+            // Points are stored relative to the layer's origin, so only it moves
+            lines[i].origin += offset;
+            renderer.replace(buffers, &lines[i]);
+            if let Some(tree) = trees.get_mut(i) {
+                *tree = None;
             }
-            _ => {}
         }
+    }
+    info!("Group '{}': layers {:?}", change.name, members);
+    return Ok(());
+}
+
+/// Drop the points of the layers set to be evicted, see `LineBuffers::evict`. Layers that were
+/// thinned, or trim their oldest points, keep them, as their file no longer holds what they do.
+/// So do labelled layers, which read their points every frame.
+fn evict_layers(lines: &mut [rendering::Line], line_buffers: &[rendering::LineBuffers]) {
+    for (i, (line, buffers)) in lines.iter_mut().zip(line_buffers).enumerate() {
+        let has_metadata = |name: &str| line.metadata.iter().any(|(key, _)| key == name);
+        // Only the file loaders record a format
+        let reloadable = has_metadata("format")
+            && !has_metadata("thinned")
+            && buffers.retention == rendering::Retention::All;
+        if !buffers.evict || !reloadable || buffers.labels > 0 || line.is_evicted() {
+            continue;
+        }
+        let size = line.memory_size();
+        line.evict();
+        info!(
+            "Dropped the host copy of layer {}, freeing {:.1} MB",
+            i,
+            (size - line.memory_size()) as f64 / 1e6
+        );
+    }
+}
+
+/// Load the points of every evicted layer from their files again, for `feature` that reads them.
+/// The layers keep their points from then on. A layer whose file no longer holds the same points
+/// is replaced with what it holds now.
+fn restore_layers(
+    renderer: &mut rendering::Renderer,
+    loaders: &loaders::Registry,
+    lines: &mut [rendering::Line],
+    line_buffers: &mut [rendering::LineBuffers],
+    trees: &mut [Option<spatial::KdTree>],
+    feature: &str,
+) {
+    let source = |line: &rendering::Line| {
+        line.metadata
+            .iter()
+            .find(|(name, _)| name == "source")
+            .map(|(_, value)| value.clone())
+    };
+    // Each file is read once, however many of its layers were evicted
+    let mut files: Vec<(String, Vec<rendering::Line>)> = Vec::new();
+    for i in 0..lines.len() {
+        if !lines[i].is_evicted() {
+            continue;
+        }
+        let path = source(&lines[i]).unwrap_or_default();
+        if !files.iter().any(|(loaded, _)| *loaded == path) {
+            info!("Reloading {} for {}", path, feature);
+            let loaded = loaders
+                .load(std::path::Path::new(&path))
+                .unwrap_or_else(|e| {
+                    error!("Failed to reload {}: {}", path, e);
+                    Vec::new()
+                });
+            files.push((path.clone(), loaded));
+        }
+        // Files load into consecutive layers, so this is the file's nth layer
+        let nth = lines[..i]
+            .iter()
+            .filter(|line| source(line).as_ref() == Some(&path))
+            .count();
+        let reloaded = files
+            .iter_mut()
+            .find(|(loaded, _)| *loaded == path)
+            .and_then(|(_, loaded)| loaded.get_mut(nth));
+        let line = &mut lines[i];
+        match reloaded {
+            Some(reloaded) if reloaded.indicies == line.indicies => {
+                line.verticies = std::mem::take(&mut reloaded.verticies);
+                line.attributes = std::mem::take(&mut reloaded.attributes);
+                line.ids = reloaded.ids.take();
+            }
+            Some(reloaded) => {
+                warn!(
+                    "{} changed since layer {} was loaded from it, replacing the layer",
+                    path, i
+                );
+                *line = reloaded.clone();
+                renderer.replace(&mut line_buffers[i], line);
+            }
+            None => {
+                // Leave the layer drawn, but without points to pick or export
+                error!("Layer {} couldn't be reloaded from {}", i, path);
+                line.indicies.clear();
+            }
+        }
+        line_buffers[i].evict = false;
+        if let Some(tree) = trees.get_mut(i) {
+            *tree = None;
+        }
+    }
+}
+
+/// Layers drawn as something other than points, which are replaced along with the point layers.
+/// Like the point layers, they are kept on the CPU as well as the GPU.
+struct ExtraLayers {
+    glyphs: Vec<rendering::glyphs::GlyphLayer>,
+    glyph_buffers: Vec<rendering::glyphs::GlyphBuffers>,
+    volumes: Vec<rendering::volume::Volume>,
+    volume_buffers: Vec<rendering::volume::VolumeBuffers>,
+    /// Poses of camera layers, with the index of the glyph layer of their frustums.
+    cameras: Vec<(usize, Vec<cameras::CameraPose>)>,
+    /// The cross-section profile, drawn only in the profile view.
+    profile: Option<(section::Profile, rendering::LineBuffers)>,
+}
+
+impl ExtraLayers {
+    fn new() -> Self {
+        ExtraLayers {
+            glyphs: Vec::new(),
+            glyph_buffers: Vec::new(),
+            volumes: Vec::new(),
+            volume_buffers: Vec::new(),
+            cameras: Vec::new(),
+            profile: None,
+        }
+    }
+
+    /// Upload and add a glyph layer, returning its index.
+    fn add_glyphs(
+        &mut self,
+        renderer: &mut rendering::Renderer,
+        glyphs: rendering::glyphs::GlyphLayer,
+    ) -> usize {
+        self.glyph_buffers.push(renderer.upload_glyphs(&glyphs));
+        self.glyphs.push(glyphs);
+        return self.glyphs.len() - 1;
+    }
+
+    /// Upload and add a volume layer, returning its index.
+    fn add_volume(
+        &mut self,
+        renderer: &mut rendering::Renderer,
+        volume: rendering::volume::Volume,
+    ) -> usize {
+        self.volume_buffers.push(renderer.upload_volume(&volume));
+        self.volumes.push(volume);
+        return self.volumes.len() - 1;
+    }
+}
+
+/// Everything drawn in each view, apart from overlays such as the highlighted point.
+struct Scene<'a> {
+    axes: &'a rendering::LineBuffers,
+    lines: &'a [rendering::LineBuffers],
+    /// The points of `lines`, for labelling them.
+    points: &'a [rendering::Line],
+    extras: &'a ExtraLayers,
+}
+
+fn render_scene(
+    renderer: &mut rendering::Renderer,
+    commands: &mut wgpu::CommandEncoder,
+    target: &wgpu::TextureView,
+    viewports: &rendering::viewport::Viewports,
+    scene: &Scene,
+    highlight: Option<&rendering::wireframe::Highlight>,
+) {
+    let (axes, lines) = (scene.axes, scene.lines);
+    for line in lines {
+        line.reset_draws();
+    }
+    renderer.update_attributes(commands);
+    for (i, view) in viewports.views().iter().enumerate() {
+        renderer.update_camera(commands, &view.camera);
+        // Only the very first pass clears the colour, the other views draw alongside or, for
+        // anaglyph stereo, over it
+        let clear = if i == 0 {
+            rendering::Clear::Everything
+        } else {
+            rendering::Clear::Depth
+        };
+        if view.profile {
+            if let Some((_, profile)) = &scene.extras.profile {
+                renderer.render(commands, target, profile, view, clear);
+            }
+            continue;
+        }
+        match renderer.mode {
+            rendering::RenderMode::Points => {
+                renderer.render(commands, target, axes, view, clear);
+                let order = rendering::draw_order(lines);
+                for &layer in &order {
+                    let view = match viewports.comparison {
+                        Some(comparison) => comparison.clip(view, layer),
+                        None => *view,
+                    };
+                    renderer.render_depth_prepass(commands, target, &lines[layer], &view);
+                }
+                // Glyphs and volumes go after the other layers, so translucent ones blend over
+                // them, but before the on-top layers
+                let on_top = order
+                    .iter()
+                    .position(|&layer| lines[layer].depth == rendering::DepthMode::OnTop)
+                    .unwrap_or(order.len());
+                for position in 0..=order.len() {
+                    if position == on_top {
+                        for glyphs in &scene.extras.glyph_buffers {
+                            renderer.render_glyphs(commands, target, glyphs, view);
+                        }
+                        for volume in &scene.extras.volume_buffers {
+                            renderer.render_volume(commands, target, volume, view);
+                        }
+                    }
+                    let layer = match order.get(position) {
+                        Some(&layer) => layer,
+                        None => break,
+                    };
+                    let line = &lines[layer];
+                    let view = match viewports.comparison {
+                        Some(comparison) => comparison.clip(view, layer),
+                        None => *view,
+                    };
+                    renderer.render(commands, target, line, &view, rendering::Clear::Nothing);
+                    renderer.render_bounds(commands, target, line, &view);
+                }
+            }
+            rendering::RenderMode::Density => {
+                // Stereo eye masks don't apply here, each eye simply draws its own heatmap
+                renderer.accumulate_density(commands, lines, view);
+                renderer.resolve_density(commands, target, view, i == 0);
+                renderer.render(commands, target, axes, view, rendering::Clear::Depth);
+            }
+        }
+        if let Some(highlight) = highlight {
+            renderer.render_highlight(commands, target, highlight, view);
+        }
+    }
+    let views = viewports.views();
+    let comparison = viewports.comparison;
+    renderer.render_labels(commands, target, &views, comparison, scene.points, lines);
+    renderer.render_scale_bar(commands, target, &views);
+    renderer.render_legend(commands, target);
+}
+
+/// Move the camera target to the last point of the last layer, e.g. the newest sample of a track
+/// being streamed in.
+fn follow_latest(
+    viewports: &mut rendering::viewport::Viewports,
+    lines: &[rendering::Line],
+    origin: &nalgebra::Vector3<f64>,
+) {
+    let line = match lines.last() {
+        Some(line) => line,
+        None => return,
+    };
+    // An evicted layer has nothing to follow until it is loaded again
+    let last = line.indicies.last();
+    if let Some(vertex) = last.and_then(|&index| line.verticies.get(index as usize)) {
+        let p = vertex.position;
+        let offset: nalgebra::Vector3<f32> = nalgebra::convert(line.origin - origin);
+        let target = nalgebra::Point3::new(p[0], p[1], p[2]) + offset;
+        viewports.update(|camera| camera.set_target(target));
+    }
+}
+
+/// Borderless fullscreen on `monitor`, counted from 0, or on the monitor the window is on if it
+/// isn't given or doesn't exist.
+fn fullscreen_on(window: &Window, monitor: Option<usize>) -> Fullscreen {
+    let handle = monitor
+        .and_then(|index| window.available_monitors().nth(index))
+        .unwrap_or_else(|| window.current_monitor());
+    info!("Going fullscreen on {}", handle.name().unwrap_or_default());
+    return Fullscreen::Borderless(handle);
+}
+
+/// Presentation mode, for showing datasets on meeting room screens: fullscreen with the cursor,
+/// hover readout and bounding boxes hidden, leaving the axes as the only overlay. Holds what it
+/// hid to bring back when it ends.
+struct Presentation {
+    was_fullscreen: bool,
+    bounds_overlay: rendering::wireframe::BoundsOverlay,
+    hover_readout: bool,
+}
+
+impl Presentation {
+    fn start(
+        window: &Window,
+        monitor: Option<usize>,
+        renderer: &mut rendering::Renderer,
+        hover_readout: bool,
+    ) -> Self {
+        let hidden = Presentation {
+            was_fullscreen: window.fullscreen().is_some(),
+            bounds_overlay: renderer.bounds_overlay,
+            hover_readout: hover_readout,
+        };
+        if !hidden.was_fullscreen {
+            window.set_fullscreen(Some(fullscreen_on(window, monitor)));
+        }
+        window.set_cursor_visible(false);
+        renderer.bounds_overlay = rendering::wireframe::BoundsOverlay::Off;
+        return hidden;
+    }
+
+    /// Bring back what was hidden, returning whether the hover readout was on.
+    fn end(self, window: &Window, renderer: &mut rendering::Renderer) -> bool {
+        if !self.was_fullscreen {
+            window.set_fullscreen(None);
+        }
+        window.set_cursor_visible(true);
+        renderer.bounds_overlay = self.bounds_overlay;
+        return self.hover_readout;
+    }
+}
+
+/// Look through the camera of `pose` in every view.
+fn snap_to_camera(
+    viewports: &mut rendering::viewport::Viewports,
+    pose: &cameras::CameraPose,
+    origin: &nalgebra::Vector3<f64>,
+) {
+    let eye: nalgebra::Vector3<f32> = nalgebra::convert(pose.position - origin);
+    viewports.update(|camera| {
+        camera.look_from(
+            nalgebra::Point3::from(eye),
+            nalgebra::convert(pose.forward()),
+            nalgebra::convert(pose.up()),
+            pose.fovy() as f32,
+        )
     });
+    info!("Viewing from camera {}", pose.name);
+}
+
+/// Draw each layer in its own colour, see `rendering::distinct_color`.
+fn assign_distinct_colors(lines: &mut [rendering::LineBuffers]) {
+    let count = lines.len();
+    for (i, line) in lines.iter_mut().enumerate() {
+        line.tint.mode = rendering::TintMode::Replace;
+        line.tint.color = rendering::distinct_color(i, count);
+    }
+}
+
+const TITLE: &str = "Rapid Scene Composition & Analysis Tool";
+
+/// The window title, which doubles as a hint on how to load data while the scene is empty.
+fn window_title(lines: &[rendering::Line], strings: &locale::Strings) -> String {
+    if lines.is_empty() {
+        return format!("{} - {}", TITLE, strings.get("drop_hint", &[]));
+    }
+    return TITLE.to_string();
+}
 
+/// Show the failure of something done in the window in its title, until the title next changes,
+/// as whoever did it may not be watching the log.
+fn show_error(window: &winit::window::Window, message: &str) {
+    window.set_title(&format!("{} - {}", TITLE, message));
+}
+
+/// Upload a new set of lines, drawing the scene relative to the origin they call for, see
+/// `scene_origin`. The axes must be uploaded again after. `evict` sets whether the lines drop
+/// their points once they are uploaded, see `LineBuffers::evict`.
+fn upload_lines(
+    renderer: &mut rendering::Renderer,
+    lines: &[rendering::Line],
+    evict: bool,
+) -> Vec<rendering::LineBuffers> {
+    renderer.origin = scene_origin(lines);
+    renderer.reset_attributes();
+    return lines
+        .iter()
+        .map(|line| {
+            let mut buffers = renderer.upload(line);
+            buffers.evict = evict;
+            buffers
+        })
+        .collect();
+}
+
+/// Upload the axes, which mark world zero, relative to the current origin.
+fn upload_axes(renderer: &mut rendering::Renderer) -> rendering::LineBuffers {
+    let axes = rendering::defaults::axes();
+    return renderer.upload(&rendering::Line {
+        indicies: rendering::defaults::render_all_vertices(&axes),
+        verticies: axes,
+        origin: nalgebra::Vector3::zeros(),
+        attributes: Vec::new(),
+        ids: None,
+        metadata: Vec::new(),
+    });
+}
+
+/// The smallest and largest world coordinates of the points of `lines`, or `None` if there are no
+/// layers. Evicted layers count as their origin.
+fn scene_bounds(lines: &[rendering::Line]) -> Option<[nalgebra::Vector3<f64>; 2]> {
+    let mut bounds: Option<[nalgebra::Vector3<f64>; 2]> = None;
+    for line in lines {
+        let vertices = line
+            .indicies
+            .iter()
+            .filter_map(|&i| line.verticies.get(i as usize));
+        let (low, high) = match rendering::bounds::Aabb::from_vertices(vertices) {
+            Some(aabb) => {
+                let low: nalgebra::Vector3<f64> = nalgebra::convert(aabb.min.coords);
+                let high: nalgebra::Vector3<f64> = nalgebra::convert(aabb.max.coords);
+                (line.origin + low, line.origin + high)
+            }
+            None => (line.origin, line.origin),
+        };
+        bounds = Some(match bounds {
+            Some([min, max]) => [min.zip_map(&low, f64::min), max.zip_map(&high, f64::max)],
+            None => [low, high],
+        });
+    }
+    return bounds;
+}
+
+/// The origin to draw `lines` relative to: the centre of their combined bounds once that is far
+/// enough from zero to cost precision as f32, like each layer's own origin.
+fn scene_origin(lines: &[rendering::Line]) -> nalgebra::Vector3<f64> {
+    return scene_bounds(lines).map_or(nalgebra::Vector3::zeros(), |bounds| {
+        loaders::local_origin(&bounds)
+    });
+}
+
+/// The origin to draw the scene relative to now that `lines` have changed, or `None` to keep the
+/// current one, which is kept while all the points are close enough to it to keep their
+/// precision, so streaming into a scene doesn't upload all of it again every time.
+fn moved_origin(
+    renderer: &rendering::Renderer,
+    lines: &[rendering::Line],
+) -> Option<nalgebra::Vector3<f64>> {
+    let [low, high] = scene_bounds(lines)?;
+    let threshold = loaders::SHIFT_THRESHOLD;
+    if (low - renderer.origin).amax() <= threshold && (high - renderer.origin).amax() <= threshold {
+        return None;
+    }
+    let origin = scene_origin(lines);
+    if origin == renderer.origin {
+        return None;
+    }
+    return Some(origin);
+}
+
+/// Draw the scene relative to `origin` from now on. Everything uploaded relative to the old one is
+/// uploaded again, and the cameras move with the scene so the view doesn't jump. Evicted layers
+/// must have been restored first, see `restore_layers`, and the highlighted point is stale.
+fn rebase(
+    renderer: &mut rendering::Renderer,
+    origin: nalgebra::Vector3<f64>,
+    lines: &[rendering::Line],
+    line_buffers: &mut [rendering::LineBuffers],
+    extras: &mut ExtraLayers,
+    axes: &mut rendering::LineBuffers,
+    viewports: &mut rendering::viewport::Viewports,
+) {
+    info!(
+        "Drawing the scene relative to {}, {}, {}",
+        origin.x, origin.y, origin.z
+    );
+    let offset: nalgebra::Vector3<f32> = nalgebra::convert(renderer.origin - origin);
+    viewports.translate(offset);
+    renderer.origin = origin;
+    for (line, buffers) in lines.iter().zip(line_buffers.iter_mut()) {
+        renderer.replace(buffers, line);
+    }
+    // Volumes are placed relative to the origin as they are drawn, but glyphs are offset on upload
+    for (glyphs, buffers) in extras.glyphs.iter().zip(extras.glyph_buffers.iter_mut()) {
+        let mut uploaded = renderer.upload_glyphs(glyphs);
+        uploaded.tint = buffers.tint;
+        uploaded.visible = buffers.visible;
+        uploaded.wireframe = buffers.wireframe;
+        *buffers = uploaded;
+    }
+    if let Some((profile, buffers)) = &mut extras.profile {
+        renderer.replace(buffers, &profile.line(&origin));
+    }
+    *axes = upload_axes(renderer);
+}
+
+/// Load the files that appeared in or were rewritten in the watched directory, replacing the
+/// layers loaded from them before. Layers of deleted files are removed with `--watch-remove`.
+fn ingest_changes(
+    renderer: &mut rendering::Renderer,
+    loaders: &loaders::Registry,
+    changes: Vec<watch::FolderChange>,
+    options: &options::Options,
+    lines: &mut Vec<rendering::Line>,
+    line_buffers: &mut Vec<rendering::LineBuffers>,
+    trees: &mut Vec<Option<spatial::KdTree>>,
+) {
+    for change in changes {
+        match change {
+            watch::FolderChange::Added(path) => {
+                let source = path.display().to_string();
+                let replaced = remove_source(&source, lines, line_buffers, trees);
+                let loaded = match loaders.load(&path) {
+                    Ok(loaded) => loaded,
+                    Err(e) => {
+                        error!("Failed to load {}: {}", source, e);
+                        continue;
+                    }
+                };
+                if replaced > 0 {
+                    info!("Reloaded {} from the watched directory", source);
+                } else {
+                    info!("Loaded {} from the watched directory", source);
+                }
+                for line in loaded {
+                    let mut buffers = renderer.upload(&line);
+                    buffers.evict = options.evict;
+                    line_buffers.push(buffers);
+                    lines.push(line);
+                }
+            }
+            watch::FolderChange::Removed(path) => {
+                if !options.watch_remove {
+                    continue;
+                }
+                let source = path.display().to_string();
+                if remove_source(&source, lines, line_buffers, trees) > 0 {
+                    info!(
+                        "Removed the layers of {}, deleted from the watched directory",
+                        source
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Remove the layers loaded from `source`, returning how many there were.
+fn remove_source(
+    source: &str,
+    lines: &mut Vec<rendering::Line>,
+    line_buffers: &mut Vec<rendering::LineBuffers>,
+    trees: &mut Vec<Option<spatial::KdTree>>,
+) -> usize {
+    let mut removed = 0;
+    let mut i = 0;
+    while i < lines.len() {
+        let from_source = lines[i]
+            .metadata
+            .iter()
+            .any(|(name, value)| name == "source" && value == source);
+        if !from_source {
+            i += 1;
+            continue;
+        }
+        lines.remove(i);
+        line_buffers.remove(i);
+        if i < trees.len() {
+            trees.remove(i);
+        }
+        removed += 1;
+    }
+    return removed;
+}
 
+/// Render the scene offscreen and read it back.
+fn capture_scene(
+    renderer: &mut rendering::Renderer,
+    viewports: &rendering::viewport::Viewports,
+    scene: &Scene,
+    exact: bool,
+) -> Option<rendering::capture::Image> {
+    let target = renderer.create_capture_target();
+    let mut commands = renderer
+        .device
+        .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    renderer.exact = exact;
+    render_scene(
+        renderer,
+        &mut commands,
+        &target.view,
+        viewports,
+        scene,
+        None,
+    );
+    renderer.exact = false;
+    return renderer.read_capture(commands, &target);
 }
 
-fn file_to_vertices(
-    path: &std::path::PathBuf,
-) -> Result<Vec<rendering::Vertex>, Box<dyn std::error::Error>> {
-    let file = std::fs::File::open(path)?;
-    let reader = std::io::BufReader::new(file);
-    let mut vertices = Vec::<rendering::Vertex>::new();
-    for line in reader.lines() {
-        let line = line?;
-        let split: Vec<&str> = line.split(',').collect();
-        if split.len() != 7 {
-            return Err(Box::new(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                "Input needs 7 cols: X, Y, Z, R, G, B, Size",
+/// Render a screenshot of each of the matrix's layer combinations from each of its presets into
+/// its output directory, then write the contact sheet and return its path. Each screenshot is a
+/// single view framed on the layers shown in it. Glyph and volume layers are only shown with all
+/// the layers.
+fn render_matrix(
+    renderer: &mut rendering::Renderer,
+    viewports: &rendering::viewport::Viewports,
+    axes: &rendering::LineBuffers,
+    line_buffers: &mut [rendering::LineBuffers],
+    extras: &ExtraLayers,
+    lines: &[rendering::Line],
+    options: &matrix::MatrixOptions,
+) -> Result<std::path::PathBuf, error::RscatError> {
+    for layers in options.layer_sets.iter().flatten() {
+        if let Some(layer) = layers.iter().find(|&&layer| layer >= lines.len()) {
+            return Err(error::RscatError::Invalid(format!(
+                "No layer {}, there are {}",
+                layer,
+                lines.len()
             )));
-        } else {
-            vertices.push(rendering::Vertex {
-                position: [
-                    split[0].parse()?,
-                    split[1].parse()?,
-                    split[2].parse()?,
-                    1.0_f32,
-                ],
-                color: [
-                    split[3].parse()?,
-                    split[4].parse()?,
-                    split[5].parse()?,
-                    1.0_f32,
-                ],
-                size: split[6].parse()?,
-            });
-        }
-    }
-    return Ok(vertices);
+        }
+    }
+    std::fs::create_dir_all(&options.output)?;
+    let mut viewports = viewports.clone();
+    viewports.layout = rendering::viewport::Layout::Single;
+    viewports.set_profile(false);
+    viewports.stereo = rendering::viewport::StereoMode::Off;
+    viewports.comparison = None;
+    let visible: Vec<bool> = line_buffers.iter().map(|buffers| buffers.visible).collect();
+    let no_extras = ExtraLayers::new();
+    let mut capture_all = || -> Result<(), error::RscatError> {
+        for layers in &options.layer_sets {
+            let mut bounds: Option<rendering::bounds::Aabb> = None;
+            for (i, (line, buffers)) in lines.iter().zip(line_buffers.iter_mut()).enumerate() {
+                buffers.visible = layers.as_ref().is_none_or(|layers| layers.contains(&i));
+                let vertices = line
+                    .indicies
+                    .iter()
+                    .map(|&index| &line.verticies[index as usize]);
+                if let (true, Some(mut aabb)) = (
+                    buffers.visible,
+                    rendering::bounds::Aabb::from_vertices(vertices),
+                ) {
+                    let offset: nalgebra::Vector3<f32> =
+                        nalgebra::convert(line.origin - renderer.origin);
+                    aabb.min += offset;
+                    aabb.max += offset;
+                    bounds = Some(bounds.map_or(aabb, |bounds| bounds.union(&aabb)));
+                }
+            }
+            for preset in &options.presets {
+                viewports.update(|camera| {
+                    if let Some(bounds) = &bounds {
+                        camera.frame(bounds);
+                    }
+                    camera.set_azimuth(preset.azimuth.to_radians());
+                    camera.set_elevation(preset.elevation.to_radians());
+                });
+                let scene = Scene {
+                    axes: axes,
+                    lines: line_buffers,
+                    points: lines,
+                    extras: if layers.is_none() { extras } else { &no_extras },
+                };
+                let image = capture_scene(renderer, &viewports, &scene, options.exact)
+                    .ok_or_else(|| error::RscatError::Gpu("Capture failed".to_string()))?;
+                std::fs::write(
+                    options.output.join(matrix::file_name(preset, layers)),
+                    image.to_png(),
+                )?;
+            }
+        }
+        return Ok(());
+    };
+    let captured = capture_all();
+    for (buffers, visible) in line_buffers.iter_mut().zip(visible) {
+        buffers.visible = visible;
+    }
+    captured?;
+    return Ok(matrix::write_sheet(options, lines)?);
+}
+
+/// Factor the stereo eye separation changes by per key press.
+const EYE_SEPARATION_STEP: f32 = 1.25;
+
+/// Degrees the view is rolled by per key press.
+const ROLL_STEP: f32 = 5.0;
+
+/// Stops the exposure changes by per key press.
+const EXPOSURE_STEP: f32 = 0.5;
+
+/// How many pixels of scrolling one line of mouse wheel scrolling is equivalent to.
+const PIXELS_PER_LINE: f32 = 20.0;
+
+/// How many neighbours a picked point's spacing is measured over.
+const PICK_NEIGHBOURS: usize = 8;
+
+/// The built in loaders, converting positions as the options say, and those of the plugins
+/// given.
+fn loader_registry(options: &options::Options) -> loaders::Registry {
+    let converter = geo::Converter::new(options.input_frame, options.geo_origin);
+    let mut loaders = loaders::Registry::new(converter);
+    for path in &options.plugins {
+        if let Err(e) = loaders.load_plugin(path) {
+            error!("{}", e);
+        }
+    }
+    return loaders;
+}
+
+/// Log the point under the cursor and how densely packed the points around it are, returning the
+/// point. `trees` holds the spatial index of each layer that has had a point picked, one entry
+/// per layer.
+fn log_pick(
+    renderer: &rendering::Renderer,
+    viewports: &rendering::viewport::Viewports,
+    lines: &[rendering::Line],
+    trees: &mut [Option<spatial::KdTree>],
+    radius: f32,
+    cursor: winit::dpi::PhysicalPosition<f64>,
+) -> Option<readout::Readout> {
+    let pick = match pick_at(renderer, viewports, lines, radius, cursor) {
+        Some(pick) => pick,
+        None => {
+            info!("No point under the cursor");
+            return None;
+        }
+    };
+    info!(
+        "Picked {}",
+        describe_pick(lines, &pick, &locale::Strings::english())
+    );
+    let picked = readout::Readout::point(lines, &pick);
+    let tree = trees[pick.line].get_or_insert_with(|| spatial::KdTree::build(&lines[pick.line]));
+    let p = pick.vertex.position;
+    let p = [p[0], p[1], p[2]];
+    // The nearest point is the picked point itself
+    let neighbours = &tree.nearest(p, PICK_NEIGHBOURS + 1)[1..];
+    if neighbours.is_empty() {
+        return Some(picked);
+    }
+    let spacing =
+        neighbours.iter().map(|(_, distance)| distance).sum::<f32>() / neighbours.len() as f32;
+    let nearby = tree.radius(p, 2.0 * spacing).len() - 1;
+    info!(
+        "Mean distance to the {} nearest points {}, {} points within twice that",
+        neighbours.len(),
+        spacing,
+        nearby
+    );
+    return Some(picked);
+}
+
+/// Extract the cross-section profile from `start` to `end`, frame the profile view on it and show
+/// it, returning the profile and its upload.
+fn show_profile(
+    renderer: &mut rendering::Renderer,
+    viewports: &mut rendering::viewport::Viewports,
+    lines: &[rendering::Line],
+    start: nalgebra::Vector3<f64>,
+    end: nalgebra::Vector3<f64>,
+    thickness: f64,
+) -> Option<(section::Profile, rendering::LineBuffers)> {
+    let up_axis = viewports.up_axis();
+    let profile = match section::Profile::extract(lines, start, end, thickness, up_axis) {
+        Ok(profile) => profile,
+        Err(e) => {
+            error!("Failed to extract the profile: {}", e);
+            return None;
+        }
+    };
+    info!(
+        "Profile of {} points within {} of the section",
+        profile.points.len(),
+        profile.thickness / 2.0
+    );
+    let line = profile.line(&renderer.origin);
+    viewports.set_profile(true);
+    if let Some(aabb) = rendering::bounds::Aabb::from_vertices(&line.verticies) {
+        viewports.frame_profile(&aabb);
+    }
+    let buffers = renderer.upload(&line);
+    return Some((profile, buffers));
+}
+
+/// Show the point under the cursor, if any, in the title bar and highlight it.
+fn hover(
+    renderer: &mut rendering::Renderer,
+    window: &winit::window::Window,
+    viewports: &rendering::viewport::Viewports,
+    lines: &[rendering::Line],
+    radius: f32,
+    cursor: winit::dpi::PhysicalPosition<f64>,
+    strings: &locale::Strings,
+) -> Option<rendering::wireframe::Highlight> {
+    let pick = pick_at(renderer, viewports, lines, radius, cursor);
+    let title = match &pick {
+        Some(pick) => format!(
+            "{} - {}",
+            window_title(lines, strings),
+            describe_pick(lines, pick, strings)
+        ),
+        None => window_title(lines, strings),
+    };
+    window.set_title(&title);
+    let pick = pick?;
+    let origin = lines[pick.line].origin;
+    return Some(renderer.upload_highlight(&pick.vertex, &origin, viewports.up_axis()));
+}
+
+/// The point nearest the camera within `radius` pixels of the cursor.
+fn pick_at(
+    renderer: &rendering::Renderer,
+    viewports: &rendering::viewport::Viewports,
+    lines: &[rendering::Line],
+    radius: f32,
+    cursor: winit::dpi::PhysicalPosition<f64>,
+) -> Option<picking::Pick> {
+    let view = viewports.view_at(cursor)?;
+    // The profile view's points are drawn away from where they are in the scene
+    if view.profile {
+        return None;
+    }
+    return picking::pick(
+        lines,
+        &renderer.origin,
+        &view.camera.view_projection(),
+        nalgebra::Point2::new(
+            cursor.x as f32 - view.rect.x as f32,
+            cursor.y as f32 - view.rect.y as f32,
+        ),
+        nalgebra::Vector2::new(view.rect.width as f32, view.rect.height as f32),
+        radius,
+    );
+}
+
+/// Where a layer came from and how much memory it uses, one detail per line.
+fn describe_layer(
+    index: usize,
+    line: &rendering::Line,
+    buffers: &rendering::LineBuffers,
+) -> String {
+    let mut description = format!("Layer {}: {} points", index, line.indicies.len());
+    for (name, value) in &line.metadata {
+        description += &format!("\n  {}: {}", name, value);
+    }
+    description += &format!(
+        "\n  memory: {:.1} MB, GPU memory: {:.1} MB",
+        line.memory_size() as f64 / 1e6,
+        buffers.memory_size() as f64 / 1e6
+    );
+    description += &format!("\n  last frame: {}", describe_draws(&buffers.draws()));
+    return description;
+}
+
+/// Points submitted and left after culling, and chunks drawn, as the M key logs them.
+fn describe_draws(draws: &rendering::stats::LayerDraws) -> String {
+    format!(
+        "{} points submitted, {} after culling, {} chunks drawn",
+        draws.submitted, draws.drawn, draws.chunks
+    )
+}
+
+fn describe_pick(
+    lines: &[rendering::Line],
+    pick: &picking::Pick,
+    strings: &locale::Strings,
+) -> String {
+    let v = pick.vertex;
+    // The point's own ID is stable across edits of the file, unlike its index
+    let point = match &lines[pick.line].ids {
+        Some(ids) => strings.get("point_id", &[&ids[pick.index]]),
+        None => pick.index.to_string(),
+    };
+    let mut description = strings.get(
+        "pick",
+        &[
+            &point,
+            &pick.line,
+            &pick.position.x,
+            &pick.position.y,
+            &pick.position.z,
+            &v.color[0],
+            &v.color[1],
+            &v.color[2],
+            &v.size,
+        ],
+    );
+    for attribute in &lines[pick.line].attributes {
+        description += &format!(", {} {}", attribute.name, attribute.values[pick.index]);
+    }
+    return description;
+}
+
+fn copy_text_to_clipboard(text: &str) {
+    let result =
+        arboard::Clipboard::new().and_then(|mut clipboard| clipboard.set_text(text.to_string()));
+    match result {
+        Ok(()) => info!("Copied coordinates to clipboard"),
+        Err(e) => error!("Failed to copy coordinates to clipboard: {}", e),
+    }
+}
+
+fn copy_image_to_clipboard(image: rendering::capture::Image) {
+    let result = arboard::Clipboard::new().and_then(|mut clipboard| {
+        clipboard.set_image(arboard::ImageData {
+            width: image.width as usize,
+            height: image.height as usize,
+            bytes: std::borrow::Cow::Owned(image.rgba),
+        })
+    });
+    match result {
+        Ok(()) => info!("Copied view to clipboard"),
+        Err(e) => error!("Failed to copy view to clipboard: {}", e),
+    }
 }
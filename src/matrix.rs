@@ -0,0 +1,175 @@
+//! Screenshot matrices for dataset QA reports: the scene rendered from each of a set of camera
+//! presets with each of a set of layer combinations, plus an HTML contact sheet laying the
+//! screenshots out in a grid.
+
+/// Named camera directions as azimuth and elevation in degrees, matching the split views.
+pub const PRESET_NAMES: [(&str, f32, f32); 4] = [
+    ("iso", -45.0, 30.0),
+    ("top", -90.0, 90.0),
+    ("front", -90.0, 0.0),
+    ("side", 0.0, 0.0),
+];
+
+/// A direction to look at the scene from. The camera is always framed on the visible layers.
+#[derive(Debug, Clone)]
+pub struct Preset {
+    pub name: String,
+    /// In degrees.
+    pub azimuth: f32,
+    /// In degrees.
+    pub elevation: f32,
+}
+
+impl Preset {
+    /// One of `PRESET_NAMES`, or `AZ:EL` in degrees.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let text = text.trim();
+        if let Some((name, azimuth, elevation)) = PRESET_NAMES
+            .iter()
+            .find(|(name, _, _)| *name == text.to_lowercase())
+        {
+            return Ok(Preset {
+                name: name.to_string(),
+                azimuth: *azimuth,
+                elevation: *elevation,
+            });
+        }
+        let angles: Vec<f32> = text
+            .split(':')
+            .map(|angle| angle.parse::<f32>())
+            .collect::<Result<Vec<f32>, _>>()
+            .map_err(|_| format!("Unknown camera preset '{}'", text))?;
+        match angles.as_slice() {
+            [azimuth, elevation] => Ok(Preset {
+                name: text.to_string(),
+                azimuth: *azimuth,
+                elevation: *elevation,
+            }),
+            _ => Err(format!("Unknown camera preset '{}'", text)),
+        }
+    }
+}
+
+/// Settings for `rscat matrix` and the remote `/matrix` endpoint.
+#[derive(Debug, Clone)]
+pub struct MatrixOptions {
+    pub presets: Vec<Preset>,
+    /// Layers shown together in each row of the matrix, `None` for all of them.
+    pub layer_sets: Vec<Option<Vec<usize>>>,
+    pub output: std::path::PathBuf,
+    /// Whether the screenshots are drawn exactly, see `Renderer::exact`.
+    pub exact: bool,
+}
+
+impl MatrixOptions {
+    pub fn default() -> Self {
+        MatrixOptions {
+            presets: PRESET_NAMES
+                .iter()
+                .map(|(name, _, _)| Preset::parse(name).unwrap())
+                .collect(),
+            layer_sets: vec![None],
+            output: std::path::PathBuf::from("matrix"),
+            exact: false,
+        }
+    }
+}
+
+/// Comma-separated presets, see `Preset::parse`.
+pub fn parse_presets(text: &str) -> Result<Vec<Preset>, String> {
+    text.split(',').map(Preset::parse).collect()
+}
+
+/// Comma-separated layer combinations, each `all` or layers joined by `+`, e.g. `all,0,1+2`.
+pub fn parse_layer_sets(text: &str) -> Result<Vec<Option<Vec<usize>>>, String> {
+    text.split(',')
+        .map(|set| {
+            let set = set.trim();
+            if set == "all" {
+                return Ok(None);
+            }
+            set.split('+')
+                .map(|layer| layer.trim().parse::<usize>())
+                .collect::<Result<Vec<usize>, _>>()
+                .map(Some)
+                .map_err(|_| format!("Invalid layer combination '{}'", set))
+        })
+        .collect()
+}
+
+/// How a layer combination is shown in file names and the contact sheet.
+pub fn layer_set_label(layers: &Option<Vec<usize>>) -> String {
+    match layers {
+        Some(layers) => {
+            let layers: Vec<String> = layers.iter().map(|layer| layer.to_string()).collect();
+            layers.join("+")
+        }
+        None => "all".to_string(),
+    }
+}
+
+/// File name of the screenshot of `layers` from `preset`, relative to the output directory.
+pub fn file_name(preset: &Preset, layers: &Option<Vec<usize>>) -> String {
+    // Custom presets contain a colon, which isn't allowed in file names on Windows
+    return format!(
+        "{}_{}.png",
+        layer_set_label(layers),
+        preset.name.replace(':', "_")
+    );
+}
+
+/// Write `index.html` to the output directory, with a row of screenshots per layer combination
+/// and a column per preset, followed by where each layer came from. Returns the sheet's path.
+pub fn write_sheet(
+    options: &MatrixOptions,
+    lines: &[crate::rendering::Line],
+) -> std::io::Result<std::path::PathBuf> {
+    let mut html = String::from(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>rscat screenshot matrix</title>\n<style>\n\
+         body { font-family: sans-serif; }\n\
+         td, th { padding: 4px; text-align: center; vertical-align: top; }\n\
+         img { max-width: 320px; border: 1px solid #ccc; }\n\
+         </style>\n</head>\n<body>\n<table>\n<tr><th>Layers</th>",
+    );
+    for preset in &options.presets {
+        html.push_str(&format!("<th>{}</th>", escape(&preset.name)));
+    }
+    html.push_str("</tr>\n");
+    for layers in &options.layer_sets {
+        html.push_str(&format!("<tr><th>{}</th>", layer_set_label(layers)));
+        for preset in &options.presets {
+            let file = file_name(preset, layers);
+            html.push_str(&format!(
+                "<td><a href=\"{0}\"><img src=\"{0}\" alt=\"{0}\"></a></td>",
+                escape(&file)
+            ));
+        }
+        html.push_str("</tr>\n");
+    }
+    html.push_str("</table>\n<ul>\n");
+    for (i, line) in lines.iter().enumerate() {
+        let source = line
+            .metadata
+            .iter()
+            .find(|(name, _)| name == "source")
+            .map_or("unknown source", |(_, value)| value.as_str());
+        html.push_str(&format!(
+            "<li>{}: {} ({} points)</li>\n",
+            i,
+            escape(source),
+            line.indicies.len()
+        ));
+    }
+    html.push_str("</ul>\n</body>\n</html>\n");
+    let path = options.output.join("index.html");
+    std::fs::write(&path, html)?;
+    return Ok(path);
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,199 @@
+use winit::event::{ModifiersState, MouseButton};
+
+/// What dragging with a mouse button does to the camera.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Drag {
+    Orbit,
+    Pan,
+    Zoom,
+}
+
+/// Names used for drags in the mouse settings file.
+const DRAG_NAMES: &[(&str, Drag)] = &[
+    ("orbit", Drag::Orbit),
+    ("pan", Drag::Pan),
+    ("zoom", Drag::Zoom),
+];
+
+/// A mouse button, and whether Shift must be held with it. Ctrl and Alt clicks pick points.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ButtonBinding {
+    pub button: MouseButton,
+    pub shift: bool,
+}
+
+impl ButtonBinding {
+    /// Parse a binding such as `Middle` or `Shift+Left`.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut parts: Vec<&str> = text.split('+').map(|part| part.trim()).collect();
+        let button_name = parts.pop().unwrap_or("");
+        let button = parse_button(button_name)
+            .ok_or_else(|| format!("Unknown mouse button '{}'", button_name))?;
+        let mut binding = ButtonBinding {
+            button: button,
+            shift: false,
+        };
+        for modifier in parts {
+            match modifier.to_lowercase().as_str() {
+                "shift" => binding.shift = true,
+                _ => return Err(format!("Unknown modifier '{}'", modifier)),
+            }
+        }
+        return Ok(binding);
+    }
+}
+
+/// Maps mouse buttons to camera drags, and how far the camera moves for the mouse. Defaults can
+/// be overridden from `mouse.cfg` in the config directory, e.g. `pan = Middle, Shift+Left` or
+/// `orbit_sensitivity = 0.5`.
+pub struct MouseMap {
+    bindings: Vec<(ButtonBinding, Drag)>,
+    /// Multiplies how far orbiting turns for the mouse movement.
+    pub orbit_sensitivity: f32,
+    /// Multiplies how far panning moves, where 1 keeps the target under the cursor.
+    pub pan_sensitivity: f32,
+    /// Multiplies how far the wheel and zoom drags zoom.
+    pub zoom_sensitivity: f32,
+    pub invert_orbit_x: bool,
+    pub invert_orbit_y: bool,
+    pub invert_pan: bool,
+    pub invert_zoom: bool,
+}
+
+impl MouseMap {
+    /// Any button orbits, and pans with Shift held.
+    pub fn default() -> Self {
+        let buttons = [MouseButton::Left, MouseButton::Right, MouseButton::Middle];
+        let mut bindings = Vec::new();
+        for &button in &buttons {
+            let binding = ButtonBinding {
+                button: button,
+                shift: false,
+            };
+            bindings.push((binding, Drag::Orbit));
+            bindings.push((
+                ButtonBinding {
+                    shift: true,
+                    ..binding
+                },
+                Drag::Pan,
+            ));
+        }
+        MouseMap {
+            bindings: bindings,
+            orbit_sensitivity: 1.0,
+            pan_sensitivity: 1.0,
+            zoom_sensitivity: 1.0,
+            invert_orbit_x: false,
+            invert_orbit_y: false,
+            invert_pan: false,
+            invert_zoom: false,
+        }
+    }
+
+    /// Load the user's mouse settings, falling back to the defaults for anything not set there.
+    pub fn load() -> Self {
+        let mut map = MouseMap::default();
+        let path = match crate::config::config_dir() {
+            Some(dir) => dir.join("mouse.cfg"),
+            None => return map,
+        };
+        for (name, value) in crate::config::read_settings(&path) {
+            if let Some((_, drag)) = DRAG_NAMES.iter().find(|(n, _)| *n == name) {
+                let bindings: Result<Vec<ButtonBinding>, String> = value
+                    .split(',')
+                    .filter(|binding| !binding.trim().is_empty())
+                    .map(ButtonBinding::parse)
+                    .collect();
+                match bindings {
+                    Ok(bindings) => map.set(*drag, bindings),
+                    Err(e) => warn!(
+                        "Invalid binding for '{}' in {}: {}",
+                        name,
+                        path.display(),
+                        e
+                    ),
+                }
+                continue;
+            }
+            let setting = match name.as_str() {
+                "orbit_sensitivity" => value.parse::<f32>().map(|s| map.orbit_sensitivity = s).ok(),
+                "pan_sensitivity" => value.parse::<f32>().map(|s| map.pan_sensitivity = s).ok(),
+                "zoom_sensitivity" => value.parse::<f32>().map(|s| map.zoom_sensitivity = s).ok(),
+                "invert_orbit_x" => value.parse::<bool>().map(|b| map.invert_orbit_x = b).ok(),
+                "invert_orbit_y" => value.parse::<bool>().map(|b| map.invert_orbit_y = b).ok(),
+                "invert_pan" => value.parse::<bool>().map(|b| map.invert_pan = b).ok(),
+                "invert_zoom" => value.parse::<bool>().map(|b| map.invert_zoom = b).ok(),
+                _ => {
+                    warn!("Unknown mouse setting '{}' in {}", name, path.display());
+                    continue;
+                }
+            };
+            if setting.is_none() {
+                warn!(
+                    "Invalid value for '{}' in {}: {}",
+                    name,
+                    path.display(),
+                    value
+                );
+            }
+        }
+        return map;
+    }
+
+    /// Replace all of the bindings for `drag`, taking the buttons from any other drag.
+    pub fn set(&mut self, drag: Drag, bindings: Vec<ButtonBinding>) {
+        self.bindings
+            .retain(|(binding, d)| *d != drag && !bindings.contains(binding));
+        self.bindings
+            .extend(bindings.into_iter().map(|binding| (binding, drag)));
+    }
+
+    pub fn drag(&self, button: MouseButton, modifiers: ModifiersState) -> Option<Drag> {
+        self.bindings
+            .iter()
+            .find(|(binding, _)| binding.button == button && binding.shift == modifiers.shift())
+            .map(|(_, drag)| *drag)
+    }
+
+    /// The orbit for moving the mouse by `delta` pixels.
+    pub fn orbit(&self, delta: nalgebra::Vector2<f32>) -> nalgebra::Vector2<f32> {
+        let sign = |invert: bool| if invert { -1.0 } else { 1.0 };
+        let x = delta.x * sign(self.invert_orbit_x);
+        let y = delta.y * sign(self.invert_orbit_y);
+        return nalgebra::Vector2::new(x, y) * self.orbit_sensitivity;
+    }
+
+    /// The pan for moving the mouse, or scrolling, by `delta` pixels.
+    pub fn pan(&self, delta: nalgebra::Vector2<f32>) -> nalgebra::Vector2<f32> {
+        let sign = if self.invert_pan { -1.0 } else { 1.0 };
+        return delta * sign * self.pan_sensitivity;
+    }
+
+    /// The zoom, in wheel lines towards the target, for scrolling by `lines`.
+    pub fn zoom(&self, lines: f32) -> f32 {
+        let sign = if self.invert_zoom { -1.0 } else { 1.0 };
+        return lines * sign * self.zoom_sensitivity;
+    }
+}
+
+/// A button's name as `parse_button` reads it.
+pub fn button_name(button: MouseButton) -> String {
+    match button {
+        MouseButton::Left => "left".to_string(),
+        MouseButton::Right => "right".to_string(),
+        MouseButton::Middle => "middle".to_string(),
+        MouseButton::Other(n) => n.to_string(),
+    }
+}
+
+/// Parse `left`, `right`, `middle`, or the number of another button.
+pub fn parse_button(name: &str) -> Option<MouseButton> {
+    let button = match name.to_lowercase().as_str() {
+        "left" => MouseButton::Left,
+        "right" => MouseButton::Right,
+        "middle" => MouseButton::Middle,
+        other => MouseButton::Other(other.parse::<u8>().ok()?),
+    };
+    return Some(button);
+}
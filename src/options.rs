@@ -0,0 +1,581 @@
+pub const USAGE: &str = "\
+Usage: rscat [OPTIONS] [FILE]...
+       rscat bench [OPTIONS] [FILE]... [--frames <N>] [--output <FILE>]
+       rscat gen <DATASET> [--points <N>] [--output <FILE>]
+       rscat convert <INPUT>... [--to <FORMAT>] [--voxel <SIZE>] [--crop <BOX>] [--output <DIR>]
+       rscat matrix [OPTIONS] [FILE]... [--views <PRESETS>] [--layers <SETS>] [--output <DIR>]
+
+Files are opened in an already running rscat if there is one. Without files the window starts
+empty, ready for a file to be dropped onto it.
+
+bench renders N frames [default: 1000] of a camera orbit around the files (or the demo scene) as
+fast as possible and writes frame times and renderer counters as JSON to the output file
+[default: bench.json].
+
+gen writes test data as CSV to the output file [default: DATASET.csv]. DATASET is random-walk,
+sinc, spheres, clusters or lorenz, with N points [default: 1000000].
+
+convert writes each input file, or each loadable file in an input directory, as FORMAT (csv or
+ply) [default: csv] to the output directory [default: next to the input]. --voxel keeps one point
+per cube of SIZE, and --crop keeps the points inside minx,miny,minz,maxx,maxy,maxz.
+
+matrix writes a PNG screenshot of the files (or the demo scene) for each camera preset and layer
+combination, and an index.html contact sheet of them, to the output directory [default: matrix].
+PRESETS are iso, top, front, side or AZ:EL in degrees [default: iso,top,front,side], and SETS are
+all or layers joined by +, e.g. all,0,1+2 [default: all].
+
+Options:
+    --backend <NAME>     Graphics backend: primary, secondary, vulkan, dx12, dx11, metal or gl
+    --power <PREF>       Adapter power preference: default, low or high
+    --adapter <ADAPTER>  Adapter index or (case-insensitive) name substring, see --list-adapters
+    --list-adapters      Print the adapters available for the chosen backend and exit
+    --present-mode <M>   Swap chain present mode: mailbox, fifo or immediate [default: mailbox]
+    --fps-cap <FPS>      Limit how often the view is redrawn
+    --plugin <LIB>       Load a file loader plugin built as a shared library. May be repeated
+    --input-frame <F>    Input X, Y, Z columns: cartesian, geodetic (lat, lon, alt) or ecef
+                         [default: cartesian]
+    --geo-origin <LLA>   Local origin for geographic input as lat,lon,alt [default: first point]
+    --up-axis <AXIS>     World axis that points up: y or z [default: z]
+    --serve <ADDR>       Accept remote control commands over HTTP, e.g. 127.0.0.1:8080
+    --record <FILE>      Record points pushed to the remote control server, see --serve
+    --matrix-dir <DIR>   Directory the remote /matrix endpoint writes its matrices under
+    --replay <FILE>      Push the points from a recording at the times they were recorded
+    --replay-speed <N>   Replay N times faster than recorded [default: 1]
+    --record-input <FILE> Record keyboard and mouse input to reproduce the session exactly
+    --replay-input <FILE> Replay recorded input in place of the keyboard and mouse
+    --new-window         Open the files in a new window rather than a running instance
+    --stereo <MODE>      Stereo rendering: off, anaglyph or sbs [default: off]
+    --eye-separation <F> Stereo eye separation as a fraction of the camera distance [default: 0.033]
+    --damping <D>        Smooth camera navigation, from 0 (off) up to but excluding 1 [default: 0]
+    --exposure <STOPS>   Brighten (or with a negative value darken) points and glyphs [default: 0]
+    --gamma <G>          Gamma applied after the exposure, above 1 to lift dark tones [default: 1]
+    --linear-colors      Point colours in files are linear rather than sRGB encoded
+    --depth-prepass      Hide points showing through surfaces of dense clouds, at some frame time
+    --exact-captures     Draw every point in screenshots and matrices, without culling or pre-pass
+    --compare <A,B>      Start in swipe comparison between layers A and B (counted from 0)
+    --on-top <LAYERS>    Draw the given comma-separated layers over everything else
+    --path <LAYERS>      Join the points of the given comma-separated layers into paths
+    --follow             Keep the camera on the newest point as layers are pushed remotely
+    --alert <RULE>       Blink layers pushed remotely when points match a filter such as 'z > 10',
+                         or after 'silent 5' seconds without points. May be repeated
+    --alert-sound        Also ring the terminal bell when an alert trips
+    --retain <POLICY>    How much of each layer pushed remotely to keep: all, a number of points,
+                         or seconds such as 30s [default: all]
+    --memory-budget <MB> Thin the least recently viewed layers to every other point while the
+                         layers take more host and GPU memory than this
+    --evict              Drop the host copy of layers loaded from files once they are drawn,
+                         reloading the files when picking or an export needs the points
+    --watch <DIR>        Load supported files as they appear in DIR, and again when rewritten
+    --watch-remove       Remove the layers of files deleted from the watched directory
+    --copy-format <FORMAT> Coordinates copied from picked points: csv or json [default: csv]
+    --pick-radius <PX>   How close to the cursor, in pixels, a point must be to pick it [default: 5]
+    --section-thickness <T> Width of the slab a cross-section profile takes points from [default: 1]
+    --monitor <N>        Monitor (counted from 0) to go fullscreen on [default: the window's]
+    --fullscreen         Start borderless fullscreen, toggled with F11
+    --presentation       Start in presentation mode, toggled with F5
+    --demo               Start with the demo scene of random walks and a sinc surface
+    --restore            Reopen the session autosaved before rscat last exited abnormally
+    -h, --help           Print this message and exit";
+
+/// Settings for `rscat gen`.
+pub struct GenerateOptions {
+    pub dataset: crate::rendering::defaults::Dataset,
+    pub points: usize,
+    pub output: std::path::PathBuf,
+}
+
+pub struct Options {
+    pub backends: wgpu::BackendBit,
+    pub power_preference: wgpu::PowerPreference,
+    pub adapter: Option<String>,
+    pub list_adapters: bool,
+    pub present_mode: wgpu::PresentMode,
+    pub fps_cap: Option<u32>,
+    pub input_frame: crate::geo::InputFrame,
+    pub geo_origin: Option<crate::geo::Geodetic>,
+    pub up_axis: crate::rendering::UpAxis,
+    pub stereo: crate::rendering::viewport::StereoMode,
+    pub eye_separation: f32,
+    pub damping: f32,
+    /// See `rendering::Grading`.
+    pub exposure: f32,
+    pub gamma: f32,
+    pub linear_colors: bool,
+    /// See `rendering::Renderer::render_depth_prepass`.
+    pub depth_prepass: bool,
+    /// Whether screenshots are always drawn exactly, see `Renderer::exact`.
+    pub exact_captures: bool,
+    pub compare: Option<[usize; 2]>,
+    pub demo: bool,
+    pub restore: bool,
+    pub on_top: Vec<usize>,
+    pub paths: Vec<usize>,
+    pub follow: bool,
+    pub pick_radius: f32,
+    pub copy_format: crate::readout::CopyFormat,
+    /// Width of the slab of points a cross-section profile takes in.
+    pub section_thickness: f64,
+    /// Bytes of host and GPU memory the point layers may use between them.
+    pub memory_budget: Option<usize>,
+    /// Whether layers loaded from files drop their host copy once uploaded.
+    pub evict: bool,
+    /// Directory whose new files are loaded as they appear.
+    pub watch: Option<std::path::PathBuf>,
+    /// Whether layers are removed when their file is deleted from the watched directory.
+    pub watch_remove: bool,
+    pub monitor: Option<usize>,
+    pub fullscreen: bool,
+    pub presentation: bool,
+    pub serve: Option<String>,
+    /// Shared libraries of loaders for more file formats, see `loaders::plugin`.
+    pub plugins: Vec<std::path::PathBuf>,
+    pub record: Option<std::path::PathBuf>,
+    /// Directory screenshot matrices requested remotely are written under.
+    pub matrix_dir: Option<std::path::PathBuf>,
+    pub replay: Option<std::path::PathBuf>,
+    pub replay_speed: f64,
+    pub record_input: Option<std::path::PathBuf>,
+    pub replay_input: Option<std::path::PathBuf>,
+    /// Rules checked against layers pushed to the remote control server.
+    pub alerts: Vec<crate::alerts::Rule>,
+    pub alert_sound: bool,
+    /// Retention of layers pushed to the remote control server.
+    pub retain: crate::rendering::Retention,
+    pub new_window: bool,
+    pub files: Vec<std::path::PathBuf>,
+    pub bench: Option<crate::bench::BenchOptions>,
+    pub generate: Option<GenerateOptions>,
+    pub convert: Option<crate::convert::ConvertOptions>,
+    pub matrix: Option<crate::matrix::MatrixOptions>,
+    pub help: bool,
+}
+
+impl Options {
+    pub fn default() -> Self {
+        Options {
+            backends: wgpu::BackendBit::PRIMARY,
+            power_preference: wgpu::PowerPreference::Default,
+            adapter: None,
+            list_adapters: false,
+            present_mode: wgpu::PresentMode::Mailbox,
+            fps_cap: None,
+            input_frame: crate::geo::InputFrame::Cartesian,
+            geo_origin: None,
+            up_axis: crate::rendering::UpAxis::Z,
+            stereo: crate::rendering::viewport::StereoMode::Off,
+            eye_separation: 1.0 / 30.0,
+            damping: 0.0,
+            exposure: 0.0,
+            gamma: 1.0,
+            linear_colors: false,
+            depth_prepass: false,
+            exact_captures: false,
+            compare: None,
+            demo: false,
+            restore: false,
+            on_top: Vec::new(),
+            paths: Vec::new(),
+            follow: false,
+            pick_radius: 5.0,
+            copy_format: crate::readout::CopyFormat::Csv,
+            section_thickness: 1.0,
+            memory_budget: None,
+            evict: false,
+            watch: None,
+            watch_remove: false,
+            monitor: None,
+            fullscreen: false,
+            presentation: false,
+            serve: None,
+            plugins: Vec::new(),
+            record: None,
+            matrix_dir: None,
+            replay: None,
+            replay_speed: 1.0,
+            record_input: None,
+            replay_input: None,
+            alerts: Vec::new(),
+            alert_sound: false,
+            retain: crate::rendering::Retention::All,
+            new_window: false,
+            files: Vec::new(),
+            bench: None,
+            generate: None,
+            convert: None,
+            matrix: None,
+            help: false,
+        }
+    }
+
+    /// Parse options from the command line arguments, excluding the program name.
+    pub fn parse<I: Iterator<Item = String>>(args: I) -> Result<Self, String> {
+        let mut options = Options::default();
+        let mut args = args.peekable();
+        match args.peek().map(|arg| arg.as_str()) {
+            Some("bench") => {
+                args.next();
+                options.bench = Some(crate::bench::BenchOptions::default());
+            }
+            Some("gen") => {
+                args.next();
+                let name = next_value(&mut args, "gen")?;
+                options.generate = Some(GenerateOptions {
+                    dataset: parse_dataset(&name)?,
+                    points: 1_000_000,
+                    output: std::path::PathBuf::from(format!("{}.csv", name)),
+                });
+            }
+            Some("convert") => {
+                args.next();
+                options.convert = Some(crate::convert::ConvertOptions::default());
+            }
+            Some("matrix") => {
+                args.next();
+                options.matrix = Some(crate::matrix::MatrixOptions::default());
+            }
+            _ => {}
+        }
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--backend" => options.backends = parse_backend(&next_value(&mut args, &arg)?)?,
+                "--power" => {
+                    options.power_preference =
+                        parse_power_preference(&next_value(&mut args, &arg)?)?
+                }
+                "--adapter" => options.adapter = Some(next_value(&mut args, &arg)?),
+                "--list-adapters" => options.list_adapters = true,
+                "--present-mode" => {
+                    options.present_mode = parse_present_mode(&next_value(&mut args, &arg)?)?
+                }
+                "--fps-cap" => {
+                    let value = next_value(&mut args, &arg)?;
+                    match value.parse::<u32>() {
+                        Ok(fps) if fps > 0 => options.fps_cap = Some(fps),
+                        _ => return Err(format!("Invalid frame rate '{}'", value)),
+                    }
+                }
+                "--plugin" => {
+                    let path = next_value(&mut args, &arg)?;
+                    options.plugins.push(std::path::PathBuf::from(path))
+                }
+                "--input-frame" => {
+                    options.input_frame = parse_input_frame(&next_value(&mut args, &arg)?)?
+                }
+                "--geo-origin" => {
+                    let origin = crate::geo::Geodetic::parse(&next_value(&mut args, &arg)?)?;
+                    options.geo_origin = Some(origin)
+                }
+                "--up-axis" => options.up_axis = parse_up_axis(&next_value(&mut args, &arg)?)?,
+                "--stereo" => options.stereo = parse_stereo(&next_value(&mut args, &arg)?)?,
+                "--eye-separation" => {
+                    let value = next_value(&mut args, &arg)?;
+                    match value.parse::<f32>() {
+                        Ok(separation) if separation > 0.0 => options.eye_separation = separation,
+                        _ => return Err(format!("Invalid eye separation '{}'", value)),
+                    }
+                }
+                "--exposure" => {
+                    let value = next_value(&mut args, &arg)?;
+                    match value.parse::<f32>() {
+                        Ok(exposure) if exposure.is_finite() => options.exposure = exposure,
+                        _ => return Err(format!("Invalid exposure '{}'", value)),
+                    }
+                }
+                "--gamma" => {
+                    let value = next_value(&mut args, &arg)?;
+                    match value.parse::<f32>() {
+                        Ok(gamma) if gamma > 0.0 => options.gamma = gamma,
+                        _ => return Err(format!("Invalid gamma '{}'", value)),
+                    }
+                }
+                "--linear-colors" => options.linear_colors = true,
+                "--depth-prepass" => options.depth_prepass = true,
+                "--exact-captures" => options.exact_captures = true,
+                "--damping" => {
+                    let value = next_value(&mut args, &arg)?;
+                    match value.parse::<f32>() {
+                        Ok(damping) if (0.0..1.0).contains(&damping) => options.damping = damping,
+                        _ => return Err(format!("Invalid damping '{}'", value)),
+                    }
+                }
+                "--compare" => {
+                    options.compare = Some(parse_layer_pair(&next_value(&mut args, &arg)?)?)
+                }
+                "--serve" => options.serve = Some(next_value(&mut args, &arg)?),
+                "--record" => {
+                    options.record = Some(std::path::PathBuf::from(next_value(&mut args, &arg)?))
+                }
+                "--matrix-dir" => {
+                    let dir = next_value(&mut args, &arg)?;
+                    options.matrix_dir = Some(std::path::PathBuf::from(dir))
+                }
+                "--replay" => {
+                    options.replay = Some(std::path::PathBuf::from(next_value(&mut args, &arg)?))
+                }
+                "--record-input" => {
+                    let path = next_value(&mut args, &arg)?;
+                    options.record_input = Some(std::path::PathBuf::from(path));
+                }
+                "--replay-input" => {
+                    let path = next_value(&mut args, &arg)?;
+                    options.replay_input = Some(std::path::PathBuf::from(path));
+                }
+                "--replay-speed" => {
+                    let value = next_value(&mut args, &arg)?;
+                    match value.parse::<f64>() {
+                        Ok(speed) if speed > 0.0 && speed.is_finite() => {
+                            options.replay_speed = speed
+                        }
+                        _ => return Err(format!("Invalid replay speed '{}'", value)),
+                    }
+                }
+                "--retain" => {
+                    let value = next_value(&mut args, &arg)?;
+                    options.retain = crate::rendering::Retention::parse(&value)
+                        .ok_or_else(|| format!("Invalid retention '{}'", value))?;
+                }
+                "--alert" => {
+                    let rule = crate::alerts::Rule::parse(&next_value(&mut args, &arg)?)
+                        .map_err(|e| format!("Invalid alert: {}", e))?;
+                    options.alerts.push(rule);
+                }
+                "--alert-sound" => options.alert_sound = true,
+                "--new-window" => options.new_window = true,
+                "--demo" => options.demo = true,
+                "--restore" => options.restore = true,
+                "--follow" => options.follow = true,
+                "--pick-radius" => {
+                    let value = next_value(&mut args, &arg)?;
+                    match value.parse::<f32>() {
+                        Ok(radius) if radius > 0.0 => options.pick_radius = radius,
+                        _ => return Err(format!("Invalid pick radius '{}'", value)),
+                    }
+                }
+                "--copy-format" => {
+                    let value = next_value(&mut args, &arg)?;
+                    options.copy_format = crate::readout::CopyFormat::parse(&value)
+                        .ok_or_else(|| format!("Invalid copy format '{}'", value))?;
+                }
+                "--section-thickness" => {
+                    let value = next_value(&mut args, &arg)?;
+                    match value.parse::<f64>() {
+                        Ok(thickness) if thickness > 0.0 => options.section_thickness = thickness,
+                        _ => return Err(format!("Invalid section thickness '{}'", value)),
+                    }
+                }
+                "--memory-budget" => {
+                    let value = next_value(&mut args, &arg)?;
+                    match value.parse::<f64>() {
+                        Ok(megabytes) if megabytes > 0.0 => {
+                            options.memory_budget = Some((megabytes * 1e6) as usize)
+                        }
+                        _ => return Err(format!("Invalid memory budget '{}'", value)),
+                    }
+                }
+                "--monitor" => {
+                    let value = next_value(&mut args, &arg)?;
+                    let monitor = value
+                        .parse::<usize>()
+                        .map_err(|_| format!("Invalid monitor '{}'", value))?;
+                    options.monitor = Some(monitor);
+                }
+                "--evict" => options.evict = true,
+                "--watch" => {
+                    options.watch = Some(std::path::PathBuf::from(next_value(&mut args, &arg)?))
+                }
+                "--watch-remove" => options.watch_remove = true,
+                "--fullscreen" => options.fullscreen = true,
+                "--presentation" => options.presentation = true,
+                "--on-top" => options.on_top = parse_layers(&next_value(&mut args, &arg)?)?,
+                "--path" => options.paths = parse_layers(&next_value(&mut args, &arg)?)?,
+                "--frames" if options.bench.is_none() => {
+                    return Err(format!("'{}' is only valid with bench", arg))
+                }
+                "--points" if options.generate.is_none() => {
+                    return Err(format!("'{}' is only valid with gen", arg))
+                }
+                "--to" | "--voxel" | "--crop" if options.convert.is_none() => {
+                    return Err(format!("'{}' is only valid with convert", arg))
+                }
+                "--views" | "--layers" if options.matrix.is_none() => {
+                    return Err(format!("'{}' is only valid with matrix", arg))
+                }
+                "--output"
+                    if options.bench.is_none()
+                        && options.generate.is_none()
+                        && options.convert.is_none()
+                        && options.matrix.is_none() =>
+                {
+                    return Err(format!(
+                        "'{}' is only valid with bench, gen, convert or matrix",
+                        arg
+                    ))
+                }
+                "--frames" => {
+                    let value = next_value(&mut args, &arg)?;
+                    match value.parse::<u32>() {
+                        Ok(frames) if frames > 0 => options.bench.as_mut().unwrap().frames = frames,
+                        _ => return Err(format!("Invalid frame count '{}'", value)),
+                    }
+                }
+                "--points" => {
+                    let value = next_value(&mut args, &arg)?;
+                    match value.parse::<usize>() {
+                        Ok(points) if points > 0 => {
+                            options.generate.as_mut().unwrap().points = points
+                        }
+                        _ => return Err(format!("Invalid point count '{}'", value)),
+                    }
+                }
+                "--to" => {
+                    let name = next_value(&mut args, &arg)?;
+                    options.convert.as_mut().unwrap().format = crate::convert::FORMAT_NAMES
+                        .iter()
+                        .find(|(format, _)| *format == name)
+                        .map(|(_, format)| *format)
+                        .ok_or_else(|| format!("Unknown output format '{}'", name))?;
+                }
+                "--voxel" => {
+                    let value = next_value(&mut args, &arg)?;
+                    match value.parse::<f64>() {
+                        Ok(size) if size > 0.0 => {
+                            options.convert.as_mut().unwrap().voxel = Some(size)
+                        }
+                        _ => return Err(format!("Invalid voxel size '{}'", value)),
+                    }
+                }
+                "--crop" => {
+                    let value = next_value(&mut args, &arg)?;
+                    let bounds = value
+                        .split(',')
+                        .map(|bound| bound.parse::<f64>())
+                        .collect::<Result<Vec<f64>, _>>()
+                        .ok()
+                        .filter(|bounds| bounds.len() == 6)
+                        .ok_or_else(|| format!("Invalid crop box '{}'", value))?;
+                    options.convert.as_mut().unwrap().crop = Some((
+                        [bounds[0], bounds[1], bounds[2]],
+                        [bounds[3], bounds[4], bounds[5]],
+                    ));
+                }
+                "--views" => {
+                    let presets = crate::matrix::parse_presets(&next_value(&mut args, &arg)?)?;
+                    options.matrix.as_mut().unwrap().presets = presets;
+                }
+                "--layers" => {
+                    let sets = crate::matrix::parse_layer_sets(&next_value(&mut args, &arg)?)?;
+                    options.matrix.as_mut().unwrap().layer_sets = sets;
+                }
+                "--output" => {
+                    let output = std::path::PathBuf::from(next_value(&mut args, &arg)?);
+                    match (
+                        &mut options.bench,
+                        &mut options.generate,
+                        &mut options.convert,
+                        &mut options.matrix,
+                    ) {
+                        (Some(bench), _, _, _) => bench.output = output,
+                        (_, Some(generate), _, _) => generate.output = output,
+                        (_, _, Some(convert), _) => convert.output = Some(output),
+                        (_, _, _, Some(matrix)) => matrix.output = output,
+                        (None, None, None, None) => unreachable!(),
+                    }
+                }
+                "-h" | "--help" => options.help = true,
+                _ if arg.starts_with('-') => {
+                    return Err(format!("Unrecognised argument '{}'", arg))
+                }
+                _ => options.files.push(std::path::PathBuf::from(arg)),
+            }
+        }
+        if options.record.is_some() && options.serve.is_none() {
+            return Err("--record only records points pushed to --serve".to_string());
+        }
+        return Ok(options);
+    }
+}
+
+fn next_value<I: Iterator<Item = String>>(args: &mut I, flag: &str) -> Result<String, String> {
+    args.next()
+        .ok_or_else(|| format!("Missing value for '{}'", flag))
+}
+
+fn parse_backend(name: &str) -> Result<wgpu::BackendBit, String> {
+    match name.to_lowercase().as_str() {
+        "primary" => Ok(wgpu::BackendBit::PRIMARY),
+        "secondary" => Ok(wgpu::BackendBit::SECONDARY),
+        "vulkan" => Ok(wgpu::BackendBit::VULKAN),
+        "dx12" => Ok(wgpu::BackendBit::DX12),
+        "dx11" => Ok(wgpu::BackendBit::DX11),
+        "metal" => Ok(wgpu::BackendBit::METAL),
+        "gl" => Ok(wgpu::BackendBit::GL),
+        _ => Err(format!("Unknown backend '{}'", name)),
+    }
+}
+
+fn parse_power_preference(name: &str) -> Result<wgpu::PowerPreference, String> {
+    match name.to_lowercase().as_str() {
+        "default" => Ok(wgpu::PowerPreference::Default),
+        "low" => Ok(wgpu::PowerPreference::LowPower),
+        "high" => Ok(wgpu::PowerPreference::HighPerformance),
+        _ => Err(format!("Unknown power preference '{}'", name)),
+    }
+}
+
+fn parse_input_frame(name: &str) -> Result<crate::geo::InputFrame, String> {
+    match name.to_lowercase().as_str() {
+        "cartesian" => Ok(crate::geo::InputFrame::Cartesian),
+        "geodetic" => Ok(crate::geo::InputFrame::Geodetic),
+        "ecef" => Ok(crate::geo::InputFrame::Ecef),
+        _ => Err(format!("Unknown input frame '{}'", name)),
+    }
+}
+
+fn parse_up_axis(name: &str) -> Result<crate::rendering::UpAxis, String> {
+    match name.to_lowercase().as_str() {
+        "y" => Ok(crate::rendering::UpAxis::Y),
+        "z" => Ok(crate::rendering::UpAxis::Z),
+        _ => Err(format!("Unknown up axis '{}'", name)),
+    }
+}
+
+fn parse_stereo(name: &str) -> Result<crate::rendering::viewport::StereoMode, String> {
+    match name.to_lowercase().as_str() {
+        "off" => Ok(crate::rendering::viewport::StereoMode::Off),
+        "anaglyph" => Ok(crate::rendering::viewport::StereoMode::Anaglyph),
+        "sbs" => Ok(crate::rendering::viewport::StereoMode::SideBySide),
+        _ => Err(format!("Unknown stereo mode '{}'", name)),
+    }
+}
+
+fn parse_layers(text: &str) -> Result<Vec<usize>, String> {
+    text.split(',')
+        .map(|layer| layer.trim().parse::<usize>())
+        .collect::<Result<Vec<usize>, _>>()
+        .map_err(|_| format!("Invalid layers '{}'", text))
+}
+
+fn parse_layer_pair(text: &str) -> Result<[usize; 2], String> {
+    let layers = parse_layers(text)?;
+    match layers.as_slice() {
+        [a, b] if a != b => Ok([*a, *b]),
+        _ => Err(format!("Expected two different layers but got '{}'", text)),
+    }
+}
+
+fn parse_dataset(name: &str) -> Result<crate::rendering::defaults::Dataset, String> {
+    crate::rendering::defaults::DATASET_NAMES
+        .iter()
+        .find(|(n, _)| *n == name.to_lowercase())
+        .map(|(_, dataset)| *dataset)
+        .ok_or_else(|| format!("Unknown dataset '{}'", name))
+}
+
+fn parse_present_mode(name: &str) -> Result<wgpu::PresentMode, String> {
+    match name.to_lowercase().as_str() {
+        "mailbox" => Ok(wgpu::PresentMode::Mailbox),
+        "fifo" => Ok(wgpu::PresentMode::Fifo),
+        "immediate" => Ok(wgpu::PresentMode::Immediate),
+        _ => Err(format!("Unknown present mode '{}'", name)),
+    }
+}
@@ -0,0 +1,68 @@
+use crate::rendering::{Line, Vertex};
+
+/// A point found under the cursor.
+pub struct Pick {
+    /// Index of the line the point belongs to.
+    pub line: usize,
+    /// Index of the point within the line's vertices.
+    pub index: usize,
+    pub vertex: Vertex,
+    /// True world position of the point.
+    pub position: nalgebra::Vector3<f64>,
+}
+
+/// Find the point closest to the camera that projects within `radius` pixels of `cursor`.
+/// `viewport` is the size of the view in pixels and `view_proj` the camera's view-projection.
+/// `origin` is the world position the scene is drawn relative to, see `Renderer::origin`.
+pub fn pick(
+    lines: &[Line],
+    origin: &nalgebra::Vector3<f64>,
+    view_proj: &nalgebra::Matrix4<f32>,
+    cursor: nalgebra::Point2<f32>,
+    viewport: nalgebra::Vector2<f32>,
+    radius: f32,
+) -> Option<Pick> {
+    let mut best: Option<(f32, Pick)> = None;
+    for (line_index, line) in lines.iter().enumerate() {
+        let offset: nalgebra::Vector3<f32> = nalgebra::convert(line.origin - origin);
+        let view_proj = view_proj * nalgebra::Matrix4::new_translation(&offset);
+        for &index in &line.indicies {
+            let vertex = line.verticies[index as usize];
+            let clip = view_proj * nalgebra::Vector4::from(vertex.position);
+            if clip.w <= 0.0 {
+                continue;
+            }
+            let depth = clip.z / clip.w;
+            if !(0.0..=1.0).contains(&depth) {
+                continue;
+            }
+            let screen = nalgebra::Point2::new(
+                (clip.x / clip.w + 1.0) * 0.5 * viewport.x,
+                (1.0 - clip.y / clip.w) * 0.5 * viewport.y,
+            );
+            if nalgebra::distance(&screen, &cursor) > radius {
+                continue;
+            }
+            if best
+                .as_ref()
+                .is_none_or(|(best_depth, _)| depth < *best_depth)
+            {
+                best = Some((
+                    depth,
+                    Pick {
+                        line: line_index,
+                        index: index as usize,
+                        vertex: vertex,
+                        position: line.origin
+                            + nalgebra::Vector3::new(
+                                vertex.position[0] as f64,
+                                vertex.position[1] as f64,
+                                vertex.position[2] as f64,
+                            ),
+                    },
+                ));
+            }
+        }
+    }
+    best.map(|(_, pick)| pick)
+}
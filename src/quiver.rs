@@ -0,0 +1,99 @@
+//! Vector fields such as wind, flow or forces, read from CSV rows of position, direction and
+//! magnitude and drawn as arrows coloured and scaled by their magnitude.
+
+use crate::rendering::glyphs::{GlyphLayer, Instance, Shape};
+
+/// Parse rows of X, Y, Z, direction DX, DY, DZ and optionally magnitude, in scene coordinates,
+/// which is the length of the direction if left out. A header row is skipped, as are rows with
+/// no direction.
+///
+/// Each arrow starts at its position and is `scale` long per unit of magnitude, by default so
+/// the longest is about as long as the spacing of the arrows, and is coloured with viridis across
+/// the range of magnitudes.
+pub fn parse(text: &str, scale: Option<f64>) -> Result<GlyphLayer, String> {
+    let mut rows = Vec::new();
+    for (i, row) in text.lines().enumerate() {
+        let fields: Vec<&str> = row.split(',').map(|field| field.trim()).collect();
+        if row.trim().is_empty() || (i == 0 && fields[0].parse::<f64>().is_err()) {
+            continue;
+        }
+        if fields.len() != 6 && fields.len() != 7 {
+            return Err(format!(
+                "Vector row {} needs 6 or 7 cols: X, Y, Z, DX, DY, DZ and optionally magnitude",
+                i + 1
+            ));
+        }
+        let values = fields
+            .iter()
+            .map(|field| field.parse::<f64>())
+            .collect::<Result<Vec<f64>, _>>()
+            .map_err(|_| format!("Invalid number in vector row {}", i + 1))?;
+        let position = nalgebra::Vector3::new(values[0], values[1], values[2]);
+        let direction = nalgebra::Vector3::new(values[3], values[4], values[5]);
+        let magnitude = values.get(6).cloned().unwrap_or_else(|| direction.norm());
+        if let Some(direction) = direction.try_normalize(0.0) {
+            rows.push((position, direction, magnitude));
+        }
+    }
+    // Relative to the first arrow, so distant fields keep their precision as f32
+    let origin = rows
+        .first()
+        .map_or(nalgebra::Vector3::zeros(), |(position, _, _)| *position);
+    let magnitudes = rows.iter().map(|(_, _, magnitude)| *magnitude);
+    let low = magnitudes.clone().fold(f64::INFINITY, f64::min);
+    let high = magnitudes.fold(f64::NEG_INFINITY, f64::max);
+    let longest = low.abs().max(high.abs());
+    let scale = match scale {
+        Some(scale) => scale,
+        None if longest > 0.0 => spacing(&rows) / longest,
+        None => 1.0,
+    };
+    let span = if high > low { high - low } else { 1.0 };
+    let instances = rows
+        .iter()
+        .map(|(position, direction, magnitude)| {
+            // Turned from X to the direction, taking the long way round if they're opposite
+            let rotation =
+                nalgebra::Rotation3::rotation_between(&nalgebra::Vector3::x(), direction)
+                    .unwrap_or_else(|| {
+                        nalgebra::Rotation3::from_axis_angle(
+                            &nalgebra::Vector3::z_axis(),
+                            std::f64::consts::PI,
+                        )
+                    });
+            let transform = nalgebra::Matrix4::new_translation(&(position - origin))
+                * rotation.to_homogeneous()
+                * nalgebra::Matrix4::new_scaling(magnitude.abs() * scale);
+            let [r, g, b] = crate::expression::viridis(((magnitude - low) / span) as f32);
+            Instance::new(nalgebra::convert(transform), [r, g, b, 1.0])
+        })
+        .collect();
+    return Ok(GlyphLayer {
+        shape: Shape::Arrow,
+        instances: instances,
+        origin: origin,
+        wireframe: false,
+    });
+}
+
+/// Roughly the distance between neighbouring arrows, if they were spread evenly through their
+/// bounding box, ignoring axes it's flat along, e.g. for a field on a plane.
+fn spacing(rows: &[(nalgebra::Vector3<f64>, nalgebra::Vector3<f64>, f64)]) -> f64 {
+    let mut low = nalgebra::Vector3::repeat(f64::INFINITY);
+    let mut high = nalgebra::Vector3::repeat(f64::NEG_INFINITY);
+    for (position, _, _) in rows {
+        low = low.zip_map(position, f64::min);
+        high = high.zip_map(position, f64::max);
+    }
+    let extent = high - low;
+    let extents: Vec<f64> = extent
+        .iter()
+        .cloned()
+        .filter(|&length| length > extent.norm() * 1e-6)
+        .collect();
+    if extents.is_empty() {
+        return 1.0;
+    }
+    let volume: f64 = extents.iter().product();
+    return (volume / rows.len() as f64).powf(1.0 / extents.len() as f64);
+}
@@ -0,0 +1,100 @@
+//! Snippets of picked coordinates for the clipboard, to paste into reports and issue trackers.
+
+use crate::picking::Pick;
+use crate::rendering::Line;
+
+/// How coordinates are written when copied.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum CopyFormat {
+    /// A header row and a row of values, which spreadsheets paste into cells.
+    Csv,
+    Json,
+}
+
+impl CopyFormat {
+    pub fn parse(text: &str) -> Option<Self> {
+        match text.to_lowercase().as_str() {
+            "csv" => Some(CopyFormat::Csv),
+            "json" => Some(CopyFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+/// The last point picked or cross-section measured.
+pub enum Readout {
+    Point {
+        layer: usize,
+        index: usize,
+        /// The point's ID in the file, if it has one.
+        id: Option<u32>,
+        position: nalgebra::Vector3<f64>,
+    },
+    Section {
+        start: nalgebra::Vector3<f64>,
+        end: nalgebra::Vector3<f64>,
+    },
+}
+
+impl Readout {
+    pub fn point(lines: &[Line], pick: &Pick) -> Self {
+        Readout::Point {
+            layer: pick.line,
+            index: pick.index,
+            id: lines[pick.line].ids.as_ref().map(|ids| ids[pick.index]),
+            position: pick.position,
+        }
+    }
+
+    pub fn format(&self, format: CopyFormat) -> String {
+        match self {
+            Readout::Point {
+                layer,
+                index,
+                id,
+                position: p,
+            } => match format {
+                CopyFormat::Csv => format!(
+                    "layer,point,id,x,y,z\n{},{},{},{},{},{}\n",
+                    layer,
+                    index,
+                    id.map_or(String::new(), |id| id.to_string()),
+                    p.x,
+                    p.y,
+                    p.z
+                ),
+                CopyFormat::Json => format!(
+                    "{{\"layer\":{},\"point\":{},\"id\":{},\"position\":[{},{},{}]}}",
+                    layer,
+                    index,
+                    id.map_or("null".to_string(), |id| id.to_string()),
+                    p.x,
+                    p.y,
+                    p.z
+                ),
+            },
+            Readout::Section { start: a, end: b } => match format {
+                CopyFormat::Csv => format!(
+                    "start_x,start_y,start_z,end_x,end_y,end_z,length\n{},{},{},{},{},{},{}\n",
+                    a.x,
+                    a.y,
+                    a.z,
+                    b.x,
+                    b.y,
+                    b.z,
+                    (b - a).norm()
+                ),
+                CopyFormat::Json => format!(
+                    "{{\"start\":[{},{},{}],\"end\":[{},{},{}],\"length\":{}}}",
+                    a.x,
+                    a.y,
+                    a.z,
+                    b.x,
+                    b.y,
+                    b.z,
+                    (b - a).norm()
+                ),
+            },
+        }
+    }
+}
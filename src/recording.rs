@@ -0,0 +1,129 @@
+//! Recording of points pushed to the remote control server, and replaying them later through the
+//! same path for post-mortems.
+//!
+//! A recording is a sequence of entries, each a header line `@SECONDS BYTES` giving the time since
+//! recording started and the length of the body, followed by the body as it was posted to
+//! `/points` and a newline.
+
+use crate::error::RscatError;
+use std::io::{BufRead, Read, Write};
+
+pub struct Recorder {
+    file: std::sync::Mutex<std::io::BufWriter<std::fs::File>>,
+    start: std::time::Instant,
+}
+
+impl Recorder {
+    pub fn create(path: &std::path::Path) -> std::io::Result<Self> {
+        let file = std::fs::File::create(path)?;
+        info!("Recording pushed points to {}", path.display());
+        return Ok(Recorder {
+            file: std::sync::Mutex::new(std::io::BufWriter::new(file)),
+            start: std::time::Instant::now(),
+        });
+    }
+
+    /// Append the body of a `/points` request.
+    pub fn record(&self, body: &[u8]) {
+        let elapsed = self.start.elapsed().as_secs_f64();
+        let mut file = self.file.lock().unwrap();
+        let written = writeln!(file, "@{:.6} {}", elapsed, body.len())
+            .and_then(|_| file.write_all(body))
+            .and_then(|_| file.write_all(b"\n"))
+            // Flush each entry so a crash still leaves a usable recording
+            .and_then(|_| file.flush());
+        if let Err(e) = written {
+            error!("Failed to record pushed points: {}", e);
+        }
+    }
+}
+
+/// Replay a recording in a background thread, pushing each entry as a new layer at its recorded
+/// time divided by `speed`.
+pub fn replay(
+    path: &std::path::Path,
+    speed: f64,
+    proxy: winit::event_loop::EventLoopProxy<crate::remote::Request>,
+) -> Result<(), RscatError> {
+    let file = std::fs::File::open(path)?;
+    let size = file.metadata()?.len();
+    let mut reader = std::io::BufReader::new(file);
+    let path = path.to_path_buf();
+    std::thread::spawn(move || {
+        let start = std::time::Instant::now();
+        let converter = crate::geo::Converter::new(crate::geo::InputFrame::Cartesian, None);
+        loop {
+            let (elapsed, body) = match read_entry(&mut reader, size) {
+                Ok(Some(entry)) => entry,
+                Ok(None) => break,
+                Err(e) => {
+                    error!("Stopped replaying {}: {}", path.display(), e);
+                    return;
+                }
+            };
+            let due = match crate::input::seconds(elapsed / speed) {
+                Some(due) => due,
+                None => {
+                    error!(
+                        "Stopped replaying {}: invalid time {}s",
+                        path.display(),
+                        elapsed
+                    );
+                    return;
+                }
+            };
+            if let Some(wait) = due.checked_sub(start.elapsed()) {
+                std::thread::sleep(wait);
+            }
+            let command = match crate::loaders::csv::parse(&body[..], &converter) {
+                Ok(line) => crate::remote::Command::PushPoints(line),
+                Err(e) => {
+                    warn!("Skipping recorded points at {:.3}s: {}", elapsed, e);
+                    continue;
+                }
+            };
+            let response = crate::remote::dispatch(command, &proxy);
+            if response.status != 200 {
+                error!("{}", String::from_utf8_lossy(&response.body));
+                return;
+            }
+        }
+        info!("Finished replaying {}", path.display());
+    });
+    return Ok(());
+}
+
+/// The next entry's time and body, or `None` at the end of the recording, which is `size` bytes
+/// long.
+fn read_entry<R: BufRead>(reader: &mut R, size: u64) -> Result<Option<(f64, Vec<u8>)>, RscatError> {
+    let mut header = String::new();
+    if reader.read_line(&mut header)? == 0 {
+        return Ok(None);
+    }
+    let invalid = || RscatError::Format(format!("Invalid recording entry '{}'", header.trim()));
+    if !header.starts_with('@') {
+        return Err(invalid());
+    }
+    let mut fields = header[1..].trim().split(' ');
+    let elapsed = fields
+        .next()
+        .and_then(|field| field.parse::<f64>().ok())
+        .ok_or_else(invalid)?;
+    // No body is longer than the file, or than the server accepts
+    let len = fields
+        .next()
+        .and_then(|field| field.parse::<u64>().ok())
+        .filter(|&len| len < size && len <= crate::remote::MAX_BODY)
+        .ok_or_else(invalid)?;
+    // The body plus its trailing newline, grown as it is read rather than trusting the length
+    let mut body = Vec::new();
+    reader.take(len + 1).read_to_end(&mut body)?;
+    if body.len() as u64 != len + 1 {
+        return Err(RscatError::Format(format!(
+            "Recording entry '{}' ends early",
+            header.trim()
+        )));
+    }
+    body.pop();
+    return Ok(Some((elapsed, body)));
+}
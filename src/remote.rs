@@ -0,0 +1,1475 @@
+//! A small embedded HTTP server so scripts can drive a running viewer.
+//!
+//! Endpoints:
+//! - `GET /layers` lists the loaded layers as JSON
+//! - `POST /load` loads the files listed one path per line in the body, replacing the current
+//!   layers
+//! - `POST /points` appends a layer from CSV rows of X, Y, Z, R, G, B, Size and any attributes in
+//!   scene coordinates, or `POST /points?layer=N` appends the rows to layer N
+//! - `GET /camera` returns the camera as the parameters `POST /camera` takes, in degrees
+//! - `GET /capabilities` reports the GPU in use and what the renderer asked of it, see
+//!   `rendering::capabilities`
+//! - `POST /camera?target=X,Y,Z&range=R&azimuth=DEG&elevation=DEG`, any subset of parameters, or
+//!   `view=top|front|side|iso|AZ:EL` for a camera preset's direction, see `matrix`. Elevation is
+//!   clamped to between -90 and 90 degrees
+//! - `POST /grading?exposure=STOPS&gamma=G&points=srgb|linear` changes how colours are shown,
+//!   see `rendering::Grading`, any subset of parameters
+//! - `POST /layer?index=N&depth=normal|no-write|on-top&priority=P&tint=off|multiply|replace`
+//!   `&color=R,G,B&alpha=A&path=true|false&animation=off|pulse|blink|jitter` changes how a layer
+//!   is drawn, any subset of parameters but `index`. `colors=unit|byte|auto&order=rgb|bgr` sets
+//!   the range and channel order the layer's colours are stored in, e.g. for 0-255 BGR colours.
+//!   `evict=true|false` sets whether a layer loaded from a file drops its host copy of the
+//!   points, see `--evict`. `shape=square|circle|ring|cross` sets what each point is drawn as,
+//!   and `min_size=PX&max_size=PX` clamp its size on screen, where `max_size=inf` is no limit.
+//!   `labels=N` labels every Nth point with its ID or index, or none for 0, and
+//!   `label_range=FIRST,LAST|all` only labels the points from index FIRST to LAST
+//! - `POST /group?name=NAME&layers=0,1,2&visible=true|false&alpha=A&translate=X,Y,Z` moves the
+//!   listed layers into the named group, recorded as their `group` metadata, then shows, hides,
+//!   fades or moves every layer of the group, any subset of parameters but `name`
+//! - `POST /duplicate?layer=N` adds a copy of layer N, drawn the same way, returning its index
+//! - `POST /merge?layers=0,1,2` adds a layer of the listed layers' points, drawn like the first
+//!   of them, and hides the listed layers, returning the new layer's index
+//! - `POST /derive?layer=N` adds a layer made by applying the expression in the body to layer N,
+//!   see `expression`
+//! - `POST /voxelize?layer=N&size=S` adds a voxel layer of cubes of size S holding layer N's
+//!   points
+//! - `POST /voxels?size=S&threshold=T` adds a voxel layer from CSV rows of voxel centre X, Y, Z,
+//!   R, G, B and optionally occupancy, see `voxels`
+//! - `POST /vectors?scale=S` adds a glyph layer of arrows from CSV rows of X, Y, Z, DX, DY, DZ
+//!   and optionally magnitude, see `quiver`
+//! - `POST /ellipsoids?sigma=S&color=R,G,B&alpha=A&wireframe=true|false` adds a glyph layer of
+//!   ellipsoids from CSV rows of centre and covariance or radii and orientation, see `ellipsoids`
+//! - `POST /cameras?depth=D&color=R,G,B&alpha=A` adds a glyph layer of wireframe frustums for
+//!   the camera poses in the COLMAP `images.txt` or CSV file whose path is the body, see
+//!   `cameras`
+//! - `POST /snap?layer=N&camera=I` moves the view to camera I of the camera glyph layer N
+//! - `POST /volume?dims=X,Y,Z&type=f32&spacing=SX,SY,SZ&origin=X,Y,Z` adds a volume layer from
+//!   a `.npy` 3D array or, given its dimensions and sample type, raw samples in the body, see
+//!   `volume`. `spacing` is the distance between voxel centres and `origin` the world position of
+//!   the first voxel's centre
+//! - `POST /volume-layer?index=N&transfer=grey|hot|viridis|P:R,G,B,A;...&range=LOW,HIGH`
+//!   `&opacity=O&clip=X0,Y0,Z0,X1,Y1,Z1|off&slice=x:X,z:Z|off&visible=true|false` changes how a
+//!   volume layer is drawn, any subset of parameters but `index`
+//! - `POST /isosurface?volume=N&threshold=T&color=R,G,B&alpha=A` adds a glyph layer of the
+//!   surface where volume layer N crosses T, see `isosurface`
+//! - `GET /mesh?index=N` returns glyph layer N as a binary PLY mesh
+//! - `GET /screenshot` returns the current view as a binary PPM image, with `?exact=true` drawing
+//!   every point even where interactive frames cut corners
+//! - `GET /depth` returns the current view's depth buffer as a PFM image
+//! - `GET /pick?x=X&y=Y` returns the point nearest window pixel X, Y within `--pick-radius`, like
+//!   Ctrl-clicking it, as JSON of its layer, point index, ID and world position, or 404 if there
+//!   is none
+//! - `GET /point-ids` returns which point each pixel of the current view shows, as CSV rows of
+//!   pixel x, pixel y, layer and point index
+//! - `GET /profile` returns the cross-section profile, see `section`, as CSV rows of distance
+//!   along the section, height, offset from the section plane, world X, Y, Z, layer and point
+//! - `GET /svg` returns the visible points and paths of the current view as an SVG image
+//! - `POST /matrix?views=iso,top,AZ:EL&layers=all,0,1+2` renders a screenshot per camera preset
+//!   and layer combination into the directory in the body, relative to `--matrix-dir`, with an
+//!   HTML contact sheet, see `matrix`. `&exact=true` draws them like `/screenshot?exact=true`
+//!
+//! Requests from web pages, which carry an `Origin` other than the server itself, and requests
+//! naming the server by anything but an IP address or `localhost`, as DNS rebinding would, are
+//! refused, so a page open in a browser can't drive the viewer.
+
+use std::io::{BufRead, Read, Write};
+
+/// Something a remote client asked the viewer to do.
+pub enum Command {
+    ListLayers,
+    Load(Vec<std::path::PathBuf>),
+    PushPoints(crate::rendering::Line),
+    /// Add points to the end of an existing layer.
+    AppendPoints(usize, crate::rendering::Line),
+    /// Report the camera's target, range and direction.
+    Camera,
+    /// Report the adapter in use and what was requested of it.
+    Capabilities,
+    SetCamera(CameraChange),
+    SetGrading(GradingChange),
+    SetLayer(LayerChange),
+    SetGroup(GroupChange),
+    /// Add a layer of the points of one layer, or several which are then hidden.
+    CopyLayers(Vec<usize>),
+    /// Add a layer derived from an existing one, evaluated off the event loop.
+    Derive(usize, crate::expression::Program),
+    /// Add a voxel layer of the cells of the given size holding a point layer's points.
+    Voxelize(usize, f64),
+    PushGlyphs(crate::rendering::glyphs::GlyphLayer),
+    /// Add a glyph layer of camera frustums, remembering the poses to snap the view to.
+    PushCameras(
+        crate::rendering::glyphs::GlyphLayer,
+        Vec<crate::cameras::CameraPose>,
+    ),
+    /// View the scene from a camera of a camera layer.
+    SnapToCamera(usize, usize),
+    PushVolume(crate::rendering::volume::Volume),
+    SetVolume(VolumeChange),
+    /// Add a glyph layer of the isosurface of a volume layer at a threshold, in a colour,
+    /// extracted off the event loop.
+    Isosurface(usize, f32, [f32; 4]),
+    /// Export a glyph layer as a PLY mesh.
+    Mesh(usize),
+    /// The current view, drawn exactly if set, see `Renderer::exact`.
+    Screenshot(bool),
+    Depth,
+    /// Pick the point at a window position, in pixels from the top left.
+    Pick(f64, f64),
+    PointIds,
+    /// Export the cross-section profile as CSV.
+    Profile,
+    Svg,
+    Matrix(crate::matrix::MatrixOptions),
+    /// An entry of an input recording being replayed, see `input`.
+    ReplayInput(crate::input::Entry),
+    InputReplayFinished,
+}
+
+/// Camera parameters to change, `None` leaves the current value.
+#[derive(Debug, Copy, Clone)]
+pub struct CameraChange {
+    pub target: Option<nalgebra::Point3<f32>>,
+    pub range: Option<f32>,
+    pub azimuth: Option<f32>,
+    pub elevation: Option<f32>,
+}
+
+/// Changes to `rendering::Grading`, `None` leaves the current value.
+#[derive(Debug, Copy, Clone)]
+pub struct GradingChange {
+    pub exposure: Option<f32>,
+    pub gamma: Option<f32>,
+    pub srgb_points: Option<bool>,
+}
+
+/// Drawing options to change for the layer at `index`, `None` leaves the current value.
+#[derive(Debug, Copy, Clone)]
+pub struct LayerChange {
+    pub index: usize,
+    pub depth: Option<crate::rendering::DepthMode>,
+    pub priority: Option<i32>,
+    pub tint: Option<crate::rendering::TintMode>,
+    pub color: Option<[f32; 3]>,
+    pub alpha: Option<f32>,
+    pub path: Option<bool>,
+    pub retain: Option<crate::rendering::Retention>,
+    pub animation: Option<crate::rendering::Animation>,
+    pub evict: Option<bool>,
+    pub color_range: Option<crate::rendering::ColorRange>,
+    pub channel_order: Option<crate::rendering::ChannelOrder>,
+    pub shape: Option<crate::rendering::PointShape>,
+    pub min_size: Option<f32>,
+    pub max_size: Option<f32>,
+    pub labels: Option<usize>,
+    /// `Some(None)` labels points of any index.
+    pub label_range: Option<Option<[usize; 2]>>,
+}
+
+impl LayerChange {
+    /// Set the options the change sets on `line`, leaving the others as they are.
+    pub fn apply(&self, line: &mut crate::rendering::LineBuffers) {
+        line.depth = self.depth.unwrap_or(line.depth);
+        line.priority = self.priority.unwrap_or(line.priority);
+        line.tint.mode = self.tint.unwrap_or(line.tint.mode);
+        line.tint.color = self.color.unwrap_or(line.tint.color);
+        line.tint.alpha = self.alpha.unwrap_or(line.tint.alpha);
+        line.path = self.path.unwrap_or(line.path);
+        line.retention = self.retain.unwrap_or(line.retention);
+        line.animation = self.animation.unwrap_or(line.animation);
+        line.evict = self.evict.unwrap_or(line.evict);
+        line.color_range = self.color_range.unwrap_or(line.color_range);
+        line.channel_order = self.channel_order.unwrap_or(line.channel_order);
+        line.shape = self.shape.unwrap_or(line.shape);
+        line.min_size = self.min_size.unwrap_or(line.min_size);
+        line.max_size = self.max_size.unwrap_or(line.max_size);
+        line.labels = self.labels.unwrap_or(line.labels);
+        line.label_range = self.label_range.unwrap_or(line.label_range);
+    }
+}
+
+/// Layers to add to the group `name`, and changes to every layer in it. `None` leaves the current
+/// value.
+#[derive(Debug, Clone)]
+pub struct GroupChange {
+    pub name: String,
+    /// Layers to move into the group from whichever group they were in.
+    pub layers: Vec<usize>,
+    pub visible: Option<bool>,
+    pub alpha: Option<f32>,
+    /// World offset to move the group's layers by.
+    pub translate: Option<nalgebra::Vector3<f64>>,
+}
+
+/// Drawing options to change for the volume layer at `index`, `None` leaves the current value.
+#[derive(Debug, Clone)]
+pub struct VolumeChange {
+    pub index: usize,
+    pub transfer: Option<crate::rendering::volume::TransferFunction>,
+    pub window: Option<[f32; 2]>,
+    pub opacity: Option<f32>,
+    /// `Some(None)` removes the clipping box.
+    pub clip: Option<Option<[[f64; 3]; 2]>>,
+    pub slices: Option<[Option<f64>; 3]>,
+    pub visible: Option<bool>,
+}
+
+pub struct Response {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    pub fn ok(content_type: &'static str, body: Vec<u8>) -> Self {
+        Response {
+            status: 200,
+            content_type: content_type,
+            body: body,
+        }
+    }
+
+    pub fn error(status: u16, message: &str) -> Self {
+        Response {
+            status: status,
+            content_type: "text/plain",
+            body: message.as_bytes().to_vec(),
+        }
+    }
+}
+
+/// A command sent to the event loop, along with where to send its response.
+pub struct Request {
+    pub command: Command,
+    pub reply: Reply,
+}
+
+pub struct Reply(std::sync::mpsc::Sender<Response>);
+
+impl Reply {
+    pub fn send(self, response: Response) {
+        // The client may have given up waiting, in which case there's nobody to tell
+        let _ = self.0.send(response);
+    }
+}
+
+/// How long a connection waits for the event loop to handle its command.
+const RESPONSE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// How long a client may stall while sending its request or reading the response.
+const STREAM_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// The largest request body accepted, enough for a 512³ volume of f32 samples.
+pub const MAX_BODY: u64 = 1 << 30;
+
+/// The longest request line and headers accepted, together.
+const MAX_HEAD: u64 = 64 * 1024;
+
+/// The most headers a request may have.
+const MAX_HEADERS: usize = 100;
+
+/// Start serving on `address` in a background thread, forwarding commands to the event loop.
+/// Bodies posted to `/points` are also written to `recorder`, if there is one. `/matrix` only
+/// writes under `matrix_dir`, and is refused without one.
+pub fn serve(
+    address: &str,
+    proxy: winit::event_loop::EventLoopProxy<Request>,
+    recorder: Option<crate::recording::Recorder>,
+    matrix_dir: Option<std::path::PathBuf>,
+) -> Result<(), crate::error::RscatError> {
+    let recorder = std::sync::Arc::new(recorder);
+    let matrix_dir = std::sync::Arc::new(matrix_dir);
+    let network = |e: std::io::Error| crate::error::RscatError::Network {
+        address: address.to_string(),
+        source: e,
+    };
+    let listener = std::net::TcpListener::bind(address).map_err(network)?;
+    info!(
+        "Remote control listening on http://{}",
+        listener.local_addr().map_err(network)?
+    );
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(e) => {
+                    warn!("Failed to accept remote control connection: {}", e);
+                    continue;
+                }
+            };
+            let proxy = proxy.clone();
+            let recorder = recorder.clone();
+            let matrix_dir = matrix_dir.clone();
+            std::thread::spawn(move || {
+                let result = handle_connection(
+                    stream,
+                    &proxy,
+                    recorder.as_ref().as_ref(),
+                    matrix_dir.as_ref().as_deref(),
+                );
+                if let Err(e) = result {
+                    warn!("Remote control connection failed: {}", e);
+                }
+            });
+        }
+    });
+    return Ok(());
+}
+
+fn handle_connection(
+    stream: std::net::TcpStream,
+    proxy: &winit::event_loop::EventLoopProxy<Request>,
+    recorder: Option<&crate::recording::Recorder>,
+    matrix_dir: Option<&std::path::Path>,
+) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(STREAM_TIMEOUT))?;
+    stream.set_write_timeout(Some(STREAM_TIMEOUT))?;
+    let mut reader = std::io::BufReader::new(stream.try_clone()?);
+
+    // Only so much of the head is read, so a client can't grow it without limit
+    let mut head = (&mut reader).take(MAX_HEAD);
+    let mut request_line = String::new();
+    head.read_line(&mut request_line)?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or("").to_string();
+    let target = parts.next().unwrap_or("").to_string();
+
+    let mut content_length = 0u64;
+    let mut host = None;
+    let mut origin = None;
+    let mut headers = 0;
+    loop {
+        let mut header = String::new();
+        if head.read_line(&mut header)? == 0 || header.trim().is_empty() {
+            break;
+        }
+        headers += 1;
+        if headers > MAX_HEADERS {
+            break;
+        }
+        if let Some(split) = header.find(':') {
+            let name = header[..split].trim();
+            let value = header[split + 1..].trim();
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.parse().unwrap_or(0);
+            } else if name.eq_ignore_ascii_case("host") {
+                host = Some(value.to_string());
+            } else if name.eq_ignore_ascii_case("origin") {
+                origin = Some(value.to_string());
+            }
+        }
+    }
+    // A head that used up the whole limit was cut off before the blank line that ends it
+    if headers > MAX_HEADERS || head.limit() == 0 {
+        let message = format!(
+            "Requests are limited to {} headers and {} bytes before the body",
+            MAX_HEADERS, MAX_HEAD
+        );
+        return write_response(stream, Response::error(431, &message));
+    }
+    if !trusted_client(host.as_deref(), origin.as_deref()) {
+        let response = Response::error(403, "Requests from web pages are not accepted");
+        return write_response(stream, response);
+    }
+    if content_length > MAX_BODY {
+        let message = format!("Bodies are limited to {} bytes", MAX_BODY);
+        return write_response(stream, Response::error(413, &message));
+    }
+    // Grown as the body arrives rather than trusting the length up front
+    let mut body = Vec::new();
+    reader.take(content_length).read_to_end(&mut body)?;
+    if body.len() as u64 != content_length {
+        return Err(std::io::ErrorKind::UnexpectedEof.into());
+    }
+
+    if let Some(recorder) = recorder {
+        if method == "POST" && target.split('?').next() == Some("/points") {
+            recorder.record(&body);
+        }
+    }
+    let response = match parse_command(&method, &target, body, matrix_dir) {
+        Ok(command) => dispatch(command, proxy),
+        Err(response) => response,
+    };
+    return write_response(stream, response);
+}
+
+/// Whether a request with these `Host` and `Origin` headers comes from a local script rather
+/// than a web page. Browsers send an `Origin` with cross-site requests, and a `Host` of the
+/// page's domain name when it has been rebound to this machine's address.
+fn trusted_client(host: Option<&str>, origin: Option<&str>) -> bool {
+    if let Some(host) = host {
+        // Leave out the port, and the brackets around IPv6 addresses
+        let name = match host.rfind(']') {
+            Some(end) if host.starts_with('[') => &host[1..end],
+            _ => host.split(':').next().unwrap_or(""),
+        };
+        if !name.eq_ignore_ascii_case("localhost") && name.parse::<std::net::IpAddr>().is_err() {
+            return false;
+        }
+    }
+    return match (origin, host) {
+        (None, _) => true,
+        (Some(origin), Some(host)) => origin == format!("http://{}", host),
+        (Some(_), None) => false,
+    };
+}
+
+fn parse_command(
+    method: &str,
+    target: &str,
+    body: Vec<u8>,
+    matrix_dir: Option<&std::path::Path>,
+) -> Result<Command, Response> {
+    let (path, query) = match target.find('?') {
+        Some(split) => (&target[..split], &target[split + 1..]),
+        None => (target, ""),
+    };
+    match (method, path) {
+        ("GET", "/layers") => Ok(Command::ListLayers),
+        ("GET", "/screenshot") => parse_screenshot(query)
+            .map(Command::Screenshot)
+            .map_err(|message| Response::error(400, &message)),
+        ("GET", "/depth") => Ok(Command::Depth),
+        ("GET", "/pick") => parse_pick(query)
+            .map(|(x, y)| Command::Pick(x, y))
+            .map_err(|message| Response::error(400, &message)),
+        ("GET", "/point-ids") => Ok(Command::PointIds),
+        ("GET", "/camera") => Ok(Command::Camera),
+        ("GET", "/capabilities") => Ok(Command::Capabilities),
+        ("GET", "/profile") => Ok(Command::Profile),
+        ("GET", "/svg") => Ok(Command::Svg),
+        ("GET", "/mesh") => {
+            match parse_index(query).map_err(|message| Response::error(400, &message))? {
+                Some(index) => Ok(Command::Mesh(index)),
+                None => Err(Response::error(400, "index is required")),
+            }
+        }
+        ("POST", "/load") => {
+            let paths =
+                String::from_utf8(body).map_err(|_| Response::error(400, "Paths must be UTF-8"))?;
+            Ok(Command::Load(
+                paths
+                    .lines()
+                    .map(|line| line.trim())
+                    .filter(|line| !line.is_empty())
+                    .map(std::path::PathBuf::from)
+                    .collect(),
+            ))
+        }
+        ("POST", "/points") => {
+            let line = crate::loaders::csv::parse(&body[..], &cartesian())
+                .map_err(|e| Response::error(400, &e.to_string()))?;
+            match parse_append_layer(query).map_err(|message| Response::error(400, &message))? {
+                Some(layer) => Ok(Command::AppendPoints(layer, line)),
+                None => Ok(Command::PushPoints(line)),
+            }
+        }
+        ("POST", "/derive") => {
+            let source = String::from_utf8(body)
+                .map_err(|_| Response::error(400, "The expression must be UTF-8"))?;
+            let program = crate::expression::Program::parse(&source)
+                .map_err(|e| Response::error(400, &e.to_string()))?;
+            match parse_append_layer(query).map_err(|message| Response::error(400, &message))? {
+                Some(layer) => Ok(Command::Derive(layer, program)),
+                None => Err(Response::error(400, "layer is required")),
+            }
+        }
+        ("POST", "/matrix") => {
+            let matrix_dir = matrix_dir.ok_or_else(|| {
+                Response::error(
+                    403,
+                    "Start rscat with --matrix-dir to render matrices remotely",
+                )
+            })?;
+            let output = String::from_utf8(body)
+                .map_err(|_| Response::error(400, "The output directory must be UTF-8"))?;
+            let output = matrix_output(matrix_dir, output.trim())
+                .map_err(|message| Response::error(400, &message))?;
+            parse_matrix(query, output)
+                .map(Command::Matrix)
+                .map_err(|message| Response::error(400, &message))
+        }
+        ("POST", "/voxelize") => {
+            let (layer, size, _) =
+                parse_voxels(query).map_err(|message| Response::error(400, &message))?;
+            match layer {
+                Some(layer) => Ok(Command::Voxelize(layer, size)),
+                None => Err(Response::error(400, "layer is required")),
+            }
+        }
+        ("POST", "/voxels") => {
+            let (layer, size, threshold) =
+                parse_voxels(query).map_err(|message| Response::error(400, &message))?;
+            if layer.is_some() {
+                return Err(Response::error(400, "Unknown voxels parameter 'layer'"));
+            }
+            let text = String::from_utf8(body)
+                .map_err(|_| Response::error(400, "Voxels must be UTF-8"))?;
+            crate::voxels::parse(&text, size, threshold)
+                .map(Command::PushGlyphs)
+                .map_err(|message| Response::error(400, &message))
+        }
+        ("POST", "/vectors") => {
+            let scale = parse_vectors(query).map_err(|message| Response::error(400, &message))?;
+            let text = String::from_utf8(body)
+                .map_err(|_| Response::error(400, "Vectors must be UTF-8"))?;
+            crate::quiver::parse(&text, scale)
+                .map(Command::PushGlyphs)
+                .map_err(|message| Response::error(400, &message))
+        }
+        ("POST", "/ellipsoids") => {
+            let (sigma, color, wireframe) =
+                parse_ellipsoids(query).map_err(|message| Response::error(400, &message))?;
+            let text = String::from_utf8(body)
+                .map_err(|_| Response::error(400, "Ellipsoids must be UTF-8"))?;
+            crate::ellipsoids::parse(&text, sigma, color, wireframe)
+                .map(Command::PushGlyphs)
+                .map_err(|message| Response::error(400, &message))
+        }
+        ("POST", "/cameras") => {
+            let (depth, color) =
+                parse_cameras(query).map_err(|message| Response::error(400, &message))?;
+            let path =
+                String::from_utf8(body).map_err(|_| Response::error(400, "Path must be UTF-8"))?;
+            let poses = crate::cameras::load(std::path::Path::new(path.trim()))
+                .map_err(|message| Response::error(400, &message))?;
+            let frustums = crate::cameras::frustums(&poses, depth, color);
+            Ok(Command::PushCameras(frustums, poses))
+        }
+        ("POST", "/snap") => parse_snap(query)
+            .map(|(layer, camera)| Command::SnapToCamera(layer, camera))
+            .map_err(|message| Response::error(400, &message)),
+        ("POST", "/volume") => parse_volume(query, &body)
+            .map(Command::PushVolume)
+            .map_err(|message| Response::error(400, &message)),
+        ("POST", "/volume-layer") => parse_volume_layer(query)
+            .map(Command::SetVolume)
+            .map_err(|message| Response::error(400, &message)),
+        ("POST", "/isosurface") => parse_isosurface(query)
+            .map(|(volume, threshold, color)| Command::Isosurface(volume, threshold, color))
+            .map_err(|message| Response::error(400, &message)),
+        ("POST", "/camera") => parse_camera(query)
+            .map(Command::SetCamera)
+            .map_err(|message| Response::error(400, &message)),
+        ("POST", "/grading") => parse_grading(query)
+            .map(Command::SetGrading)
+            .map_err(|message| Response::error(400, &message)),
+        ("POST", "/layer") => parse_layer(query)
+            .map(Command::SetLayer)
+            .map_err(|message| Response::error(400, &message)),
+        ("POST", "/duplicate") => match parse_append_layer(query) {
+            Ok(Some(layer)) => Ok(Command::CopyLayers(vec![layer])),
+            Ok(None) => Err(Response::error(400, "layer is required")),
+            Err(message) => Err(Response::error(400, &message)),
+        },
+        ("POST", "/merge") => parse_layer_list(query)
+            .map(Command::CopyLayers)
+            .map_err(|message| Response::error(400, &message)),
+        ("POST", "/group") => parse_group(query)
+            .map(Command::SetGroup)
+            .map_err(|message| Response::error(400, &message)),
+        _ => Err(Response::error(404, "Unknown endpoint")),
+    }
+}
+
+fn cartesian() -> crate::geo::Converter {
+    crate::geo::Converter::new(crate::geo::InputFrame::Cartesian, None)
+}
+
+/// The camera as a `/camera` query, with the azimuth from 0 up to 360 degrees.
+pub fn camera_query(camera: &crate::rendering::OrbitCamera) -> String {
+    let target = camera.target();
+    return format!(
+        "target={},{},{}&range={}&azimuth={}&elevation={}",
+        target.x,
+        target.y,
+        target.z,
+        camera.range(),
+        camera.azimuth().to_degrees().rem_euclid(360.0),
+        camera.elevation().to_degrees(),
+    );
+}
+
+pub fn parse_camera(query: &str) -> Result<CameraChange, String> {
+    let mut change = CameraChange {
+        target: None,
+        range: None,
+        azimuth: None,
+        elevation: None,
+    };
+    for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+        let mut split = pair.splitn(2, '=');
+        let key = split.next().unwrap_or("");
+        let value = split.next().unwrap_or("");
+        // Non-finite values would break every view, and be autosaved into the next session
+        let number = |text: &str| match text.parse::<f32>() {
+            Ok(number) if number.is_finite() => Ok(number),
+            _ => Err(format!("Invalid number '{}' for '{}'", text, key)),
+        };
+        match key {
+            "target" => {
+                let coords = value
+                    .split(',')
+                    .map(number)
+                    .collect::<Result<Vec<f32>, String>>()?;
+                if coords.len() != 3 {
+                    return Err("target needs 3 coordinates".to_string());
+                }
+                change.target = Some(nalgebra::Point3::new(coords[0], coords[1], coords[2]));
+            }
+            "range" => match number(value)? {
+                range if range > 0.0 => change.range = Some(range),
+                _ => return Err(format!("Invalid range '{}'", value)),
+            },
+            "azimuth" => change.azimuth = Some(number(value)?.to_radians()),
+            "elevation" => change.elevation = Some(number(value)?.to_radians()),
+            "view" => {
+                let preset = crate::matrix::Preset::parse(value)?;
+                if !preset.azimuth.is_finite() || !preset.elevation.is_finite() {
+                    return Err(format!("Invalid view '{}'", value));
+                }
+                change.azimuth = Some(preset.azimuth.to_radians());
+                change.elevation = Some(preset.elevation.to_radians());
+            }
+            _ => return Err(format!("Unknown camera parameter '{}'", key)),
+        }
+    }
+    return Ok(change);
+}
+
+fn parse_grading(query: &str) -> Result<GradingChange, String> {
+    let mut change = GradingChange {
+        exposure: None,
+        gamma: None,
+        srgb_points: None,
+    };
+    for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+        let mut split = pair.splitn(2, '=');
+        let key = split.next().unwrap_or("");
+        let value = split.next().unwrap_or("");
+        match key {
+            "exposure" => match value.parse::<f32>() {
+                Ok(exposure) if exposure.is_finite() => change.exposure = Some(exposure),
+                _ => return Err(format!("Invalid exposure '{}'", value)),
+            },
+            "gamma" => match value.parse::<f32>() {
+                Ok(gamma) if gamma > 0.0 => change.gamma = Some(gamma),
+                _ => return Err(format!("Invalid gamma '{}'", value)),
+            },
+            "points" => match value {
+                "srgb" => change.srgb_points = Some(true),
+                "linear" => change.srgb_points = Some(false),
+                _ => {
+                    return Err(format!(
+                        "Invalid points '{}', expected srgb or linear",
+                        value
+                    ))
+                }
+            },
+            _ => return Err(format!("Unknown grading parameter '{}'", key)),
+        }
+    }
+    return Ok(change);
+}
+
+/// The directory `output` names under `matrix_dir`. It must be relative and stay inside.
+fn matrix_output(matrix_dir: &std::path::Path, output: &str) -> Result<std::path::PathBuf, String> {
+    if output.is_empty() {
+        return Err("An output directory is required".to_string());
+    }
+    let path = std::path::Path::new(output);
+    for component in path.components() {
+        match component {
+            std::path::Component::Normal(_) | std::path::Component::CurDir => {}
+            _ => {
+                return Err(format!(
+                    "Invalid output directory '{}', expected a relative path without '..'",
+                    output
+                ))
+            }
+        }
+    }
+    return Ok(matrix_dir.join(path));
+}
+
+fn parse_matrix(
+    query: &str,
+    output: std::path::PathBuf,
+) -> Result<crate::matrix::MatrixOptions, String> {
+    let mut options = crate::matrix::MatrixOptions::default();
+    options.output = output;
+    for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+        let mut split = pair.splitn(2, '=');
+        let key = split.next().unwrap_or("");
+        let value = split.next().unwrap_or("");
+        match key {
+            "views" => options.presets = crate::matrix::parse_presets(value)?,
+            "layers" => options.layer_sets = crate::matrix::parse_layer_sets(value)?,
+            "exact" => {
+                options.exact = value
+                    .parse::<bool>()
+                    .map_err(|_| format!("Invalid exact '{}', expected true or false", value))?
+            }
+            _ => return Err(format!("Unknown matrix parameter '{}'", key)),
+        }
+    }
+    return Ok(options);
+}
+
+/// Whether a screenshot is drawn exactly.
+fn parse_screenshot(query: &str) -> Result<bool, String> {
+    let mut exact = false;
+    for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+        let mut split = pair.splitn(2, '=');
+        let key = split.next().unwrap_or("");
+        let value = split.next().unwrap_or("");
+        match key {
+            "exact" => {
+                exact = value
+                    .parse::<bool>()
+                    .map_err(|_| format!("Invalid exact '{}', expected true or false", value))?
+            }
+            _ => return Err(format!("Unknown screenshot parameter '{}'", key)),
+        }
+    }
+    return Ok(exact);
+}
+
+/// The window position of a pick, both of which are required.
+fn parse_pick(query: &str) -> Result<(f64, f64), String> {
+    let mut x = None;
+    let mut y = None;
+    for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+        let mut split = pair.splitn(2, '=');
+        let key = split.next().unwrap_or("");
+        let value = split.next().unwrap_or("");
+        let coordinate = match value.parse::<f64>() {
+            Ok(coordinate) if coordinate.is_finite() => coordinate,
+            _ => return Err(format!("Invalid {} '{}'", key, value)),
+        };
+        match key {
+            "x" => x = Some(coordinate),
+            "y" => y = Some(coordinate),
+            _ => return Err(format!("Unknown pick parameter '{}'", key)),
+        }
+    }
+    match (x, y) {
+        (Some(x), Some(y)) => return Ok((x, y)),
+        _ => return Err("x and y are required".to_string()),
+    }
+}
+
+/// The layer, voxel size and occupancy threshold of a voxel query. The size is required.
+fn parse_voxels(query: &str) -> Result<(Option<usize>, f64, f32), String> {
+    let mut layer = None;
+    let mut size = None;
+    let mut threshold = crate::voxels::DEFAULT_THRESHOLD;
+    for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+        let mut split = pair.splitn(2, '=');
+        let key = split.next().unwrap_or("");
+        let value = split.next().unwrap_or("");
+        match key {
+            "layer" => {
+                let index = value
+                    .parse::<usize>()
+                    .map_err(|_| format!("Invalid layer index '{}'", value))?;
+                layer = Some(index);
+            }
+            "size" => match value.parse::<f64>() {
+                Ok(voxel) if voxel > 0.0 => size = Some(voxel),
+                _ => return Err(format!("Invalid voxel size '{}'", value)),
+            },
+            "threshold" => {
+                threshold = value
+                    .parse::<f32>()
+                    .map_err(|_| format!("Invalid threshold '{}'", value))?;
+            }
+            _ => return Err(format!("Unknown voxels parameter '{}'", key)),
+        }
+    }
+    return Ok((layer, size.ok_or("size is required")?, threshold));
+}
+
+/// The length of the arrows per unit of magnitude, if given.
+fn parse_vectors(query: &str) -> Result<Option<f64>, String> {
+    let mut scale = None;
+    for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+        let mut split = pair.splitn(2, '=');
+        let key = split.next().unwrap_or("");
+        let value = split.next().unwrap_or("");
+        match key {
+            "scale" => match value.parse::<f64>() {
+                Ok(length) if length > 0.0 => scale = Some(length),
+                _ => return Err(format!("Invalid arrow scale '{}'", value)),
+            },
+            _ => return Err(format!("Unknown vectors parameter '{}'", key)),
+        }
+    }
+    return Ok(scale);
+}
+
+/// How many standard deviations out to draw covariances, the colour and whether to draw
+/// wireframes.
+fn parse_ellipsoids(query: &str) -> Result<(f64, [f32; 4], bool), String> {
+    let mut sigma = 1.0;
+    let mut color = [0.8, 0.8, 0.8, 1.0];
+    let mut wireframe = false;
+    for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+        let mut split = pair.splitn(2, '=');
+        let key = split.next().unwrap_or("");
+        let value = split.next().unwrap_or("");
+        match key {
+            "sigma" => match value.parse::<f64>() {
+                Ok(deviations) if deviations > 0.0 => sigma = deviations,
+                _ => return Err(format!("Invalid sigma '{}'", value)),
+            },
+            "color" => {
+                let channels = value
+                    .split(',')
+                    .map(|channel| channel.parse::<f32>())
+                    .collect::<Result<Vec<f32>, _>>()
+                    .map_err(|_| format!("Invalid color '{}'", value))?;
+                if channels.len() != 3 {
+                    return Err("color needs 3 channels".to_string());
+                }
+                color = [channels[0], channels[1], channels[2], color[3]];
+            }
+            "alpha" => {
+                let opacity = value
+                    .parse::<f32>()
+                    .ok()
+                    .filter(|opacity| !opacity.is_nan())
+                    .ok_or_else(|| format!("Invalid alpha '{}'", value))?;
+                color[3] = opacity.clamp(0.0, 1.0);
+            }
+            "wireframe" => {
+                wireframe = value.parse::<bool>().map_err(|_| {
+                    format!("Invalid wireframe '{}', expected true or false", value)
+                })?;
+            }
+            _ => return Err(format!("Unknown ellipsoids parameter '{}'", key)),
+        }
+    }
+    return Ok((sigma, color, wireframe));
+}
+
+/// How far in front of the cameras to draw their frustums, if given, and their colour.
+fn parse_cameras(query: &str) -> Result<(Option<f64>, [f32; 4]), String> {
+    let mut depth = None;
+    let mut color = [1.0, 0.3, 0.3, 1.0];
+    for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+        let mut split = pair.splitn(2, '=');
+        let key = split.next().unwrap_or("");
+        let value = split.next().unwrap_or("");
+        match key {
+            "depth" => match value.parse::<f64>() {
+                Ok(distance) if distance > 0.0 => depth = Some(distance),
+                _ => return Err(format!("Invalid frustum depth '{}'", value)),
+            },
+            "color" => {
+                let channels = value
+                    .split(',')
+                    .map(|channel| channel.parse::<f32>())
+                    .collect::<Result<Vec<f32>, _>>()
+                    .map_err(|_| format!("Invalid color '{}'", value))?;
+                if channels.len() != 3 {
+                    return Err("color needs 3 channels".to_string());
+                }
+                color = [channels[0], channels[1], channels[2], color[3]];
+            }
+            "alpha" => {
+                let opacity = value
+                    .parse::<f32>()
+                    .ok()
+                    .filter(|opacity| !opacity.is_nan())
+                    .ok_or_else(|| format!("Invalid alpha '{}'", value))?;
+                color[3] = opacity.clamp(0.0, 1.0);
+            }
+            _ => return Err(format!("Unknown cameras parameter '{}'", key)),
+        }
+    }
+    return Ok((depth, color));
+}
+
+/// The camera layer and the camera in it to view the scene from.
+fn parse_snap(query: &str) -> Result<(usize, usize), String> {
+    let mut layer = None;
+    let mut camera = None;
+    for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+        let mut split = pair.splitn(2, '=');
+        let key = split.next().unwrap_or("");
+        let value = split.next().unwrap_or("");
+        match key {
+            "layer" => {
+                let index = value
+                    .parse::<usize>()
+                    .map_err(|_| format!("Invalid layer index '{}'", value))?;
+                layer = Some(index);
+            }
+            "camera" => {
+                let index = value
+                    .parse::<usize>()
+                    .map_err(|_| format!("Invalid camera index '{}'", value))?;
+                camera = Some(index);
+            }
+            _ => return Err(format!("Unknown snap parameter '{}'", key)),
+        }
+    }
+    return Ok((
+        layer.ok_or("layer is required")?,
+        camera.ok_or("camera is required")?,
+    ));
+}
+
+/// A volume from a `.npy` body, or a raw one given `dims` and `type`, placed by `spacing` and
+/// `origin`.
+fn parse_volume(query: &str, body: &[u8]) -> Result<crate::rendering::volume::Volume, String> {
+    let mut dims = None;
+    let mut sample_type = None;
+    let mut spacing = [1.0; 3];
+    let mut origin = nalgebra::Vector3::zeros();
+    for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+        let mut split = pair.splitn(2, '=');
+        let key = split.next().unwrap_or("");
+        let value = split.next().unwrap_or("");
+        match key {
+            "dims" => {
+                let lengths = value
+                    .split(',')
+                    .map(|length| length.parse::<usize>())
+                    .collect::<Result<Vec<usize>, _>>()
+                    .map_err(|_| format!("Invalid dims '{}'", value))?;
+                if lengths.len() != 3 {
+                    return Err("dims needs 3 lengths".to_string());
+                }
+                dims = Some([lengths[0], lengths[1], lengths[2]]);
+            }
+            "type" => {
+                let parsed = crate::volume::SampleType::parse(value)
+                    .ok_or_else(|| format!("Unknown sample type '{}'", value))?;
+                sample_type = Some(parsed);
+            }
+            "spacing" => {
+                let distances =
+                    parse_coordinates(value).map_err(|_| format!("Invalid spacing '{}'", value))?;
+                spacing = match distances.as_slice() {
+                    [distance] => [*distance; 3],
+                    [x, y, z] => [*x, *y, *z],
+                    _ => return Err("spacing needs 1 or 3 distances".to_string()),
+                };
+                if spacing.iter().any(|&distance| distance <= 0.0) {
+                    return Err("spacing must be positive".to_string());
+                }
+            }
+            "origin" => {
+                let coords =
+                    parse_coordinates(value).map_err(|_| format!("Invalid origin '{}'", value))?;
+                if coords.len() != 3 {
+                    return Err("origin needs 3 coordinates".to_string());
+                }
+                origin = nalgebra::Vector3::new(coords[0], coords[1], coords[2]);
+            }
+            _ => return Err(format!("Unknown volume parameter '{}'", key)),
+        }
+    }
+    let mut volume = match (crate::volume::is_npy(body), dims, sample_type) {
+        (true, None, None) => crate::volume::parse_npy(body)?,
+        (true, _, _) => return Err("dims and type are only for raw volumes".to_string()),
+        (false, Some(dims), Some(sample_type)) => {
+            crate::volume::parse_raw(body, dims, sample_type)?
+        }
+        (false, _, _) => return Err("Raw volumes need dims and type".to_string()),
+    };
+    volume.spacing = spacing;
+    volume.origin = origin;
+    return Ok(volume);
+}
+
+fn parse_volume_layer(query: &str) -> Result<VolumeChange, String> {
+    let mut change = VolumeChange {
+        index: 0,
+        transfer: None,
+        window: None,
+        opacity: None,
+        clip: None,
+        slices: None,
+        visible: None,
+    };
+    let mut index = None;
+    for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+        let mut split = pair.splitn(2, '=');
+        let key = split.next().unwrap_or("");
+        let value = split.next().unwrap_or("");
+        match key {
+            "index" => {
+                let layer = value
+                    .parse::<usize>()
+                    .map_err(|_| format!("Invalid layer index '{}'", value))?;
+                index = Some(layer);
+            }
+            "transfer" => {
+                change.transfer = Some(crate::rendering::volume::TransferFunction::parse(value)?);
+            }
+            "range" => {
+                let bounds = value
+                    .split(',')
+                    .map(|bound| bound.parse::<f32>())
+                    .collect::<Result<Vec<f32>, _>>()
+                    .map_err(|_| format!("Invalid range '{}'", value))?;
+                match bounds.as_slice() {
+                    [low, high] if low < high => change.window = Some([*low, *high]),
+                    _ => return Err("range needs a low and a higher high value".to_string()),
+                }
+            }
+            "opacity" => {
+                let scale = value
+                    .parse::<f32>()
+                    .map_err(|_| format!("Invalid opacity '{}'", value))?;
+                change.opacity = Some(scale.max(0.0));
+            }
+            "clip" if value == "off" => change.clip = Some(None),
+            "clip" => {
+                let coords = parse_coordinates(value)
+                    .map_err(|_| format!("Invalid clip box '{}'", value))?;
+                if coords.len() != 6 {
+                    return Err("clip needs 6 coordinates".to_string());
+                }
+                let corners = [
+                    [coords[0], coords[1], coords[2]],
+                    [coords[3], coords[4], coords[5]],
+                ];
+                change.clip = Some(Some(corners));
+            }
+            "slice" if value == "off" => change.slices = Some([None; 3]),
+            "slice" => {
+                let mut slices = [None; 3];
+                for plane in value.split(',') {
+                    let mut split = plane.splitn(2, ':');
+                    let axis = match split.next().unwrap_or("") {
+                        "x" => 0,
+                        "y" => 1,
+                        "z" => 2,
+                        _ => return Err(format!("Invalid slicing plane '{}'", plane)),
+                    };
+                    let position = split
+                        .next()
+                        .unwrap_or("")
+                        .parse::<f64>()
+                        .map_err(|_| format!("Invalid slicing plane '{}'", plane))?;
+                    slices[axis] = Some(position);
+                }
+                change.slices = Some(slices);
+            }
+            "visible" => {
+                let shown = value
+                    .parse::<bool>()
+                    .map_err(|_| format!("Invalid visible '{}', expected true or false", value))?;
+                change.visible = Some(shown);
+            }
+            _ => return Err(format!("Unknown volume layer parameter '{}'", key)),
+        }
+    }
+    change.index = index.ok_or("index is required")?;
+    return Ok(change);
+}
+
+/// The volume layer, threshold and colour of an isosurface query. The colour defaults to light
+/// grey.
+fn parse_isosurface(query: &str) -> Result<(usize, f32, [f32; 4]), String> {
+    let mut volume = None;
+    let mut threshold = None;
+    let mut color = [0.8, 0.8, 0.8, 1.0];
+    for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+        let mut split = pair.splitn(2, '=');
+        let key = split.next().unwrap_or("");
+        let value = split.next().unwrap_or("");
+        match key {
+            "volume" => {
+                let index = value
+                    .parse::<usize>()
+                    .map_err(|_| format!("Invalid volume index '{}'", value))?;
+                volume = Some(index);
+            }
+            "threshold" => {
+                let level = value
+                    .parse::<f32>()
+                    .map_err(|_| format!("Invalid threshold '{}'", value))?;
+                threshold = Some(level);
+            }
+            "color" => {
+                let channels = value
+                    .split(',')
+                    .map(|channel| channel.parse::<f32>())
+                    .collect::<Result<Vec<f32>, _>>()
+                    .map_err(|_| format!("Invalid color '{}'", value))?;
+                if channels.len() != 3 {
+                    return Err("color needs 3 channels".to_string());
+                }
+                color = [channels[0], channels[1], channels[2], color[3]];
+            }
+            "alpha" => {
+                let opacity = value
+                    .parse::<f32>()
+                    .ok()
+                    .filter(|opacity| !opacity.is_nan())
+                    .ok_or_else(|| format!("Invalid alpha '{}'", value))?;
+                color[3] = opacity.clamp(0.0, 1.0);
+            }
+            _ => return Err(format!("Unknown isosurface parameter '{}'", key)),
+        }
+    }
+    return Ok((
+        volume.ok_or("volume is required")?,
+        threshold.ok_or("threshold is required")?,
+        color,
+    ));
+}
+
+/// The layer named by an `index=N` query, if any.
+fn parse_index(query: &str) -> Result<Option<usize>, String> {
+    let mut index = None;
+    for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+        let mut split = pair.splitn(2, '=');
+        let key = split.next().unwrap_or("");
+        let value = split.next().unwrap_or("");
+        match key {
+            "index" => {
+                let layer = value
+                    .parse::<usize>()
+                    .map_err(|_| format!("Invalid layer index '{}'", value))?;
+                index = Some(layer);
+            }
+            _ => return Err(format!("Unknown parameter '{}'", key)),
+        }
+    }
+    return Ok(index);
+}
+
+fn parse_coordinates(text: &str) -> Result<Vec<f64>, std::num::ParseFloatError> {
+    text.split(',').map(|coord| coord.parse::<f64>()).collect()
+}
+
+/// The layer named by a `layer=N` query, if any.
+fn parse_append_layer(query: &str) -> Result<Option<usize>, String> {
+    let mut layer = None;
+    for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+        let mut split = pair.splitn(2, '=');
+        let key = split.next().unwrap_or("");
+        let value = split.next().unwrap_or("");
+        match key {
+            "layer" => {
+                let index = value
+                    .parse::<usize>()
+                    .map_err(|_| format!("Invalid layer index '{}'", value))?;
+                layer = Some(index);
+            }
+            _ => return Err(format!("Unknown points parameter '{}'", key)),
+        }
+    }
+    return Ok(layer);
+}
+
+/// Decode the `%XX` escapes and `+` spaces of a query value, e.g. a group name.
+fn decode_query_value(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = value
+            .get(i + 1..i + 3)
+            .filter(|_| bytes[i] == b'%')
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], escaped) {
+            (_, Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (b'+', None) => {
+                decoded.push(b' ');
+                i += 1;
+            }
+            (byte, None) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    return String::from_utf8_lossy(&decoded).into_owned();
+}
+
+fn parse_indices(text: &str) -> Result<Vec<usize>, String> {
+    text.split(',')
+        .map(|layer| layer.parse::<usize>())
+        .collect::<Result<Vec<usize>, _>>()
+        .map_err(|_| format!("Invalid layer indices '{}'", text))
+}
+
+/// The layers named by a `layers=0,1,2` query.
+fn parse_layer_list(query: &str) -> Result<Vec<usize>, String> {
+    let mut layers = None;
+    for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+        let mut split = pair.splitn(2, '=');
+        let key = split.next().unwrap_or("");
+        let value = split.next().unwrap_or("");
+        match key {
+            "layers" => layers = Some(parse_indices(value)?),
+            _ => return Err(format!("Unknown parameter '{}'", key)),
+        }
+    }
+    return layers.ok_or_else(|| "layers is required".to_string());
+}
+
+fn parse_group(query: &str) -> Result<GroupChange, String> {
+    let mut change = GroupChange {
+        name: String::new(),
+        layers: Vec::new(),
+        visible: None,
+        alpha: None,
+        translate: None,
+    };
+    for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+        let mut split = pair.splitn(2, '=');
+        let key = split.next().unwrap_or("");
+        let value = split.next().unwrap_or("");
+        match key {
+            "name" => change.name = decode_query_value(value),
+            "layers" => change.layers = parse_indices(value)?,
+            "visible" => {
+                let shown = value
+                    .parse::<bool>()
+                    .map_err(|_| format!("Invalid visible '{}', expected true or false", value))?;
+                change.visible = Some(shown);
+            }
+            "alpha" => {
+                let opacity = value
+                    .parse::<f32>()
+                    .ok()
+                    .filter(|opacity| !opacity.is_nan())
+                    .ok_or_else(|| format!("Invalid alpha '{}'", value))?;
+                change.alpha = Some(opacity.clamp(0.0, 1.0));
+            }
+            "translate" => match parse_coordinates(value)
+                .as_ref()
+                .map(|coords| coords.as_slice())
+            {
+                Ok([x, y, z]) => change.translate = Some(nalgebra::Vector3::new(*x, *y, *z)),
+                _ => return Err(format!("Invalid translation '{}', expected X,Y,Z", value)),
+            },
+            _ => return Err(format!("Unknown group parameter '{}'", key)),
+        }
+    }
+    if change.name.is_empty() {
+        return Err("name is required".to_string());
+    }
+    return Ok(change);
+}
+
+pub fn parse_layer(query: &str) -> Result<LayerChange, String> {
+    let mut index = None;
+    let mut depth = None;
+    let mut priority = None;
+    let mut tint = None;
+    let mut color = None;
+    let mut alpha = None;
+    let mut path = None;
+    let mut retain = None;
+    let mut animation = None;
+    let mut evict = None;
+    let mut color_range = None;
+    let mut channel_order = None;
+    let mut shape = None;
+    let mut min_size = None;
+    let mut max_size = None;
+    let mut labels = None;
+    let mut label_range = None;
+    for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+        let mut split = pair.splitn(2, '=');
+        let key = split.next().unwrap_or("");
+        let value = split.next().unwrap_or("");
+        match key {
+            "index" => {
+                let layer = value
+                    .parse::<usize>()
+                    .map_err(|_| format!("Invalid layer index '{}'", value))?;
+                index = Some(layer);
+            }
+            "depth" => {
+                let mode = crate::rendering::DEPTH_MODE_NAMES
+                    .iter()
+                    .find(|(name, _)| *name == value)
+                    .map(|(_, mode)| *mode)
+                    .ok_or_else(|| format!("Unknown depth mode '{}'", value))?;
+                depth = Some(mode);
+            }
+            "priority" => {
+                let order = value
+                    .parse::<i32>()
+                    .map_err(|_| format!("Invalid priority '{}'", value))?;
+                priority = Some(order);
+            }
+            "tint" => {
+                let mode = crate::rendering::TINT_MODE_NAMES
+                    .iter()
+                    .find(|(name, _)| *name == value)
+                    .map(|(_, mode)| *mode)
+                    .ok_or_else(|| format!("Unknown tint mode '{}'", value))?;
+                tint = Some(mode);
+            }
+            "color" => {
+                let channels = value
+                    .split(',')
+                    .map(|channel| channel.parse::<f32>())
+                    .collect::<Result<Vec<f32>, _>>()
+                    .map_err(|_| format!("Invalid color '{}'", value))?;
+                if channels.len() != 3 {
+                    return Err("color needs 3 channels".to_string());
+                }
+                color = Some([channels[0], channels[1], channels[2]]);
+            }
+            "alpha" => {
+                let opacity = value
+                    .parse::<f32>()
+                    .ok()
+                    .filter(|opacity| !opacity.is_nan())
+                    .ok_or_else(|| format!("Invalid alpha '{}'", value))?;
+                alpha = Some(opacity.clamp(0.0, 1.0));
+            }
+            "path" => {
+                let joined = value
+                    .parse::<bool>()
+                    .map_err(|_| format!("Invalid path '{}', expected true or false", value))?;
+                path = Some(joined);
+            }
+            "retain" => {
+                let retention = crate::rendering::Retention::parse(value)
+                    .ok_or_else(|| format!("Invalid retention '{}'", value))?;
+                retain = Some(retention);
+            }
+            "animation" => {
+                let effect = crate::rendering::ANIMATION_NAMES
+                    .iter()
+                    .find(|(name, _)| *name == value)
+                    .map(|(_, effect)| *effect)
+                    .ok_or_else(|| format!("Unknown animation '{}'", value))?;
+                animation = Some(effect);
+            }
+            "evict" => {
+                let dropped = value
+                    .parse::<bool>()
+                    .map_err(|_| format!("Invalid evict '{}', expected true or false", value))?;
+                evict = Some(dropped);
+            }
+            "colors" => {
+                let range = crate::rendering::COLOR_RANGE_NAMES
+                    .iter()
+                    .find(|(name, _)| *name == value)
+                    .map(|(_, range)| *range)
+                    .ok_or_else(|| format!("Unknown color range '{}'", value))?;
+                color_range = Some(range);
+            }
+            "order" => {
+                let order = crate::rendering::CHANNEL_ORDER_NAMES
+                    .iter()
+                    .find(|(name, _)| *name == value)
+                    .map(|(_, order)| *order)
+                    .ok_or_else(|| format!("Unknown channel order '{}'", value))?;
+                channel_order = Some(order);
+            }
+            "shape" => {
+                let point_shape = crate::rendering::POINT_SHAPE_NAMES
+                    .iter()
+                    .find(|(name, _)| *name == value)
+                    .map(|(_, shape)| *shape)
+                    .ok_or_else(|| format!("Unknown point shape '{}'", value))?;
+                shape = Some(point_shape);
+            }
+            "min_size" => min_size = Some(parse_point_size(value)?),
+            "max_size" => max_size = Some(parse_point_size(value)?),
+            "labels" => {
+                let every = value
+                    .parse::<usize>()
+                    .map_err(|_| format!("Invalid labels '{}'", value))?;
+                labels = Some(every);
+            }
+            "label_range" if value == "all" => label_range = Some(None),
+            "label_range" => {
+                let indices = value
+                    .split(',')
+                    .map(|index| index.parse::<usize>())
+                    .collect::<Result<Vec<usize>, _>>()
+                    .map_err(|_| format!("Invalid label range '{}'", value))?;
+                match indices.as_slice() {
+                    &[first, last] if first <= last => label_range = Some(Some([first, last])),
+                    _ => return Err(format!("Invalid label range '{}'", value)),
+                }
+            }
+            _ => return Err(format!("Unknown layer parameter '{}'", key)),
+        }
+    }
+    return Ok(LayerChange {
+        index: index.ok_or("index is required")?,
+        depth: depth,
+        priority: priority,
+        tint: tint,
+        color: color,
+        alpha: alpha,
+        path: path,
+        retain: retain,
+        animation: animation,
+        evict: evict,
+        color_range: color_range,
+        channel_order: channel_order,
+        shape: shape,
+        min_size: min_size,
+        max_size: max_size,
+        labels: labels,
+        label_range: label_range,
+    });
+}
+
+/// Parse a point size on screen in pixels, which may be `inf`.
+fn parse_point_size(value: &str) -> Result<f32, String> {
+    match value.parse::<f32>() {
+        Ok(size) if size >= 0.0 => return Ok(size),
+        _ => return Err(format!("Invalid point size '{}'", value)),
+    }
+}
+
+/// Send a command to the event loop and wait for it to be handled.
+pub fn dispatch(command: Command, proxy: &winit::event_loop::EventLoopProxy<Request>) -> Response {
+    let (reply, response) = std::sync::mpsc::channel();
+    let request = Request {
+        command: command,
+        reply: Reply(reply),
+    };
+    if proxy.send_event(request).is_err() {
+        return Response::error(503, "Viewer is closing");
+    }
+    return response
+        .recv_timeout(RESPONSE_TIMEOUT)
+        .unwrap_or_else(|_| Response::error(504, "Viewer did not respond"));
+}
+
+fn write_response(mut stream: std::net::TcpStream, response: Response) -> std::io::Result<()> {
+    let reason = match response.status {
+        200 => "OK",
+        400 => "Bad Request",
+        403 => "Forbidden",
+        404 => "Not Found",
+        413 => "Payload Too Large",
+        431 => "Request Header Fields Too Large",
+        503 => "Service Unavailable",
+        504 => "Gateway Timeout",
+        _ => "Internal Server Error",
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        response.status,
+        reason,
+        response.content_type,
+        response.body.len()
+    )?;
+    stream.write_all(&response.body)?;
+    return stream.flush();
+}
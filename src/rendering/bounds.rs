@@ -0,0 +1,92 @@
+use super::Vertex;
+
+/// Axis aligned bounding box in world space.
+#[derive(Debug, Copy, Clone)]
+pub struct Aabb {
+    pub min: nalgebra::Point3<f32>,
+    pub max: nalgebra::Point3<f32>,
+}
+
+impl Aabb {
+    /// Returns `None` for an empty slice.
+    pub fn from_vertices<'a, I: IntoIterator<Item = &'a Vertex>>(vertices: I) -> Option<Self> {
+        let mut vertices = vertices.into_iter();
+        let first = vertices.next()?;
+        let start = nalgebra::Point3::new(first.position[0], first.position[1], first.position[2]);
+        let mut aabb = Aabb {
+            min: start,
+            max: start,
+        };
+        for vertex in vertices {
+            for axis in 0..3 {
+                aabb.min[axis] = aabb.min[axis].min(vertex.position[axis]);
+                aabb.max[axis] = aabb.max[axis].max(vertex.position[axis]);
+            }
+        }
+        return Some(aabb);
+    }
+
+    /// The smallest box containing both.
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        let mut aabb = *self;
+        for axis in 0..3 {
+            aabb.min[axis] = aabb.min[axis].min(other.min[axis]);
+            aabb.max[axis] = aabb.max[axis].max(other.max[axis]);
+        }
+        return aabb;
+    }
+
+    /// The twelve edges as pairs of vertices, for drawing as a line list.
+    pub fn wireframe(&self, color: [f32; 4]) -> Vec<Vertex> {
+        // Corners differing in exactly one axis, see `corners`
+        #[rustfmt::skip]
+        const EDGES: [(usize, usize); 12] = [
+            (0, 1), (2, 3), (4, 5), (6, 7),
+            (0, 2), (1, 3), (4, 6), (5, 7),
+            (0, 4), (1, 5), (2, 6), (3, 7),
+        ];
+        let corners = self.corners();
+        let vertex = |corner: usize| Vertex {
+            position: *corners[corner].to_homogeneous().as_ref(),
+            color: color,
+            size: 1.0,
+        };
+        EDGES
+            .iter()
+            .flat_map(|&(a, b)| vec![vertex(a), vertex(b)])
+            .collect()
+    }
+
+    pub fn corners(&self) -> [nalgebra::Point3<f32>; 8] {
+        let (min, max) = (self.min, self.max);
+        [
+            nalgebra::Point3::new(min.x, min.y, min.z),
+            nalgebra::Point3::new(max.x, min.y, min.z),
+            nalgebra::Point3::new(min.x, max.y, min.z),
+            nalgebra::Point3::new(max.x, max.y, min.z),
+            nalgebra::Point3::new(min.x, min.y, max.z),
+            nalgebra::Point3::new(max.x, min.y, max.z),
+            nalgebra::Point3::new(min.x, max.y, max.z),
+            nalgebra::Point3::new(max.x, max.y, max.z),
+        ]
+    }
+
+    /// Conservative frustum test: a box is only reported as hidden when all of its corners lie
+    /// outside the same clip plane, so some boxes just outside the view still count as visible.
+    pub fn is_visible(&self, view_proj: &nalgebra::Matrix4<f32>) -> bool {
+        let corners = self.corners();
+        let mut clip = [nalgebra::Vector4::<f32>::zeros(); 8];
+        for (i, corner) in corners.iter().enumerate() {
+            clip[i] = view_proj * corner.to_homogeneous();
+        }
+        let all_outside = |outside: fn(&nalgebra::Vector4<f32>) -> bool| clip.iter().all(outside);
+
+        // wgpu clip space: -w <= x, y <= w and 0 <= z <= w
+        !(all_outside(|c| c.x < -c.w)
+            || all_outside(|c| c.x > c.w)
+            || all_outside(|c| c.y < -c.w)
+            || all_outside(|c| c.y > c.w)
+            || all_outside(|c| c.z < 0.0)
+            || all_outside(|c| c.z > c.w))
+    }
+}
@@ -0,0 +1,71 @@
+//! What's asked of the GPU the renderer runs on, negotiated down from the defaults so that
+//! integrated and software adapters that refuse them still start.
+
+/// Bind groups the pipelines use at most: the shared uniforms, plus one for point IDs or volumes.
+const REQUIRED_BIND_GROUPS: u32 = 2;
+
+/// Points per GPU buffer on adapters sharing system memory, instead of `super::CHUNK_SIZE`, since
+/// large single allocations are what such adapters fail first.
+const SHARED_MEMORY_CHUNK_SIZE: usize = 1 << 20;
+
+/// The adapter in use and what the renderer asks of it.
+pub struct Capabilities {
+    pub adapter: wgpu::AdapterInfo,
+    /// Why another adapter than the requested one is in use, if it is.
+    pub fallback: Option<String>,
+    pub extensions: wgpu::Extensions,
+    pub limits: wgpu::Limits,
+    /// Most points stored in a single GPU buffer.
+    pub chunk_size: usize,
+}
+
+impl Capabilities {
+    /// Decide what to request from `adapter`. wgpu can't say which extensions and limits an
+    /// adapter supports, and requesting one it lacks fails outright, so only what's needed is
+    /// requested: no extensions, as no sampler filters anisotropically, and only the bind groups
+    /// used.
+    pub fn negotiate(adapter: wgpu::AdapterInfo, fallback: Option<String>) -> Self {
+        let shared_memory = matches!(
+            adapter.device_type,
+            wgpu::DeviceType::IntegratedGpu | wgpu::DeviceType::Cpu
+        );
+        Capabilities {
+            adapter: adapter,
+            fallback: fallback,
+            extensions: wgpu::Extensions {
+                anisotropic_filtering: false,
+            },
+            limits: wgpu::Limits {
+                max_bind_groups: REQUIRED_BIND_GROUPS,
+            },
+            chunk_size: if shared_memory {
+                SHARED_MEMORY_CHUNK_SIZE
+            } else {
+                super::CHUNK_SIZE
+            },
+        }
+    }
+
+    pub fn device_descriptor(&self) -> wgpu::DeviceDescriptor {
+        wgpu::DeviceDescriptor {
+            extensions: self.extensions,
+            limits: self.limits,
+        }
+    }
+
+    /// One `name: value` line per capability, for the log and `GET /capabilities`.
+    pub fn report(&self) -> String {
+        let mut report = format!(
+            "adapter: {}\ndevice type: {:?}\nbackend: {:?}\n",
+            self.adapter.name, self.adapter.device_type, self.adapter.backend
+        );
+        if let Some(fallback) = &self.fallback {
+            report += &format!("fallback: {}\n", fallback);
+        }
+        report += &format!(
+            "anisotropic filtering: {}\nmax bind groups: {}\npoints per buffer: {}\n",
+            self.extensions.anisotropic_filtering, self.limits.max_bind_groups, self.chunk_size
+        );
+        return report;
+    }
+}
@@ -0,0 +1,222 @@
+/// Rows copied out of a texture must be padded to a multiple of this many bytes.
+pub const BYTES_PER_ROW_ALIGNMENT: u32 = 256;
+
+/// An offscreen colour target the scene can be rendered into and then read back to the CPU.
+pub struct CaptureTarget {
+    pub view: wgpu::TextureView,
+    texture: wgpu::Texture,
+    width: u32,
+    height: u32,
+}
+
+/// 8-bit RGBA pixels, row-major with no padding.
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub rgba: Vec<u8>,
+}
+
+impl Image {
+    /// Encode as a binary PPM, dropping the alpha channel.
+    pub fn to_ppm(&self) -> Vec<u8> {
+        let mut ppm = format!("P6\n{} {}\n255\n", self.width, self.height).into_bytes();
+        for pixel in self.rgba.chunks(4) {
+            ppm.extend_from_slice(&pixel[..3]);
+        }
+        return ppm;
+    }
+
+    /// Encode as an RGB PNG, dropping the alpha channel. The image data is stored uncompressed,
+    /// which every decoder accepts, to avoid depending on a compression library.
+    pub fn to_png(&self) -> Vec<u8> {
+        // Each row starts with its filter type, 0 for none
+        let mut raw = Vec::with_capacity((self.width as usize * 3 + 1) * self.height as usize);
+        for row in self.rgba.chunks(self.width as usize * 4) {
+            raw.push(0);
+            for pixel in row.chunks(4) {
+                raw.extend_from_slice(&pixel[..3]);
+            }
+        }
+        // A zlib stream of stored deflate blocks, each at most 65535 bytes
+        let mut zlib = vec![0x78, 0x01];
+        let blocks = raw.chunks(0xffff).count();
+        for (i, block) in raw.chunks(0xffff).enumerate() {
+            zlib.push((i + 1 == blocks) as u8);
+            zlib.extend_from_slice(&(block.len() as u16).to_le_bytes());
+            zlib.extend_from_slice(&(!(block.len() as u16)).to_le_bytes());
+            zlib.extend_from_slice(block);
+        }
+        zlib.extend_from_slice(&adler32(&raw).to_be_bytes());
+
+        let mut header = Vec::with_capacity(13);
+        header.extend_from_slice(&self.width.to_be_bytes());
+        header.extend_from_slice(&self.height.to_be_bytes());
+        // 8 bits per channel, RGB, then the default compression, filtering and no interlacing
+        header.extend_from_slice(&[8, 2, 0, 0, 0]);
+        let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+        write_png_chunk(&mut png, b"IHDR", &header);
+        write_png_chunk(&mut png, b"IDAT", &zlib);
+        write_png_chunk(&mut png, b"IEND", &[]);
+        return png;
+    }
+}
+
+fn write_png_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    let mut crc = !0_u32;
+    for &byte in kind.iter().chain(data) {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    png.extend_from_slice(&(!crc).to_be_bytes());
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1_u32, 0_u32);
+    for &byte in data {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    return (b << 16) | a;
+}
+
+/// A texture copied into a buffer, waiting to be read.
+pub struct Readback {
+    buffer: wgpu::Buffer,
+    size: wgpu::BufferAddress,
+    unpadded_bytes_per_row: u32,
+    padded_bytes_per_row: u32,
+    height: u32,
+}
+
+impl super::Renderer {
+    /// Create a capture target matching the current swap chain, so the depth texture can be shared.
+    pub fn create_capture_target(&self) -> CaptureTarget {
+        let texture = self.device.create_texture(&wgpu::TextureDescriptor {
+            format: self.sc_desc.format,
+            usage: wgpu::TextureUsage::OUTPUT_ATTACHMENT | wgpu::TextureUsage::COPY_SRC,
+            size: wgpu::Extent3d {
+                width: self.sc_desc.width,
+                height: self.sc_desc.height,
+                depth: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            label: None,
+            array_layer_count: 1,
+        });
+        CaptureTarget {
+            view: texture.create_default_view(),
+            texture: texture,
+            width: self.sc_desc.width,
+            height: self.sc_desc.height,
+        }
+    }
+
+    /// Submit the commands that rendered into `target` and block until the image is read back.
+    pub fn read_capture(
+        &self,
+        mut command_encoder: wgpu::CommandEncoder,
+        target: &CaptureTarget,
+    ) -> Option<Image> {
+        let readback = self.copy_to_readback(
+            &mut command_encoder,
+            &target.texture,
+            target.width,
+            target.height,
+            4,
+        );
+        self.queue.submit(&[command_encoder.finish()]);
+        let pixels = self.read_back(readback)?;
+
+        let mut rgba = Vec::with_capacity(pixels.len());
+        for pixel in pixels.chunks(4) {
+            // The swap chain format is BGRA, and what is on screen is always opaque
+            rgba.extend_from_slice(&[pixel[2], pixel[1], pixel[0], 255]);
+        }
+        Some(Image {
+            width: target.width,
+            height: target.height,
+            rgba: rgba,
+        })
+    }
+
+    /// Encode a copy of `texture` into a buffer that can be mapped by `read_back` once the
+    /// commands have been submitted.
+    pub fn copy_to_readback(
+        &self,
+        command_encoder: &mut wgpu::CommandEncoder,
+        texture: &wgpu::Texture,
+        width: u32,
+        height: u32,
+        bytes_per_pixel: u32,
+    ) -> Readback {
+        let unpadded_bytes_per_row = width * bytes_per_pixel;
+        let padded_bytes_per_row =
+            unpadded_bytes_per_row.div_ceil(BYTES_PER_ROW_ALIGNMENT) * BYTES_PER_ROW_ALIGNMENT;
+        let size = (padded_bytes_per_row * height) as wgpu::BufferAddress;
+        let buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: size,
+            usage: wgpu::BufferUsage::MAP_READ | wgpu::BufferUsage::COPY_DST,
+        });
+        command_encoder.copy_texture_to_buffer(
+            wgpu::TextureCopyView {
+                texture: texture,
+                mip_level: 0,
+                array_layer: 0,
+                origin: wgpu::Origin3d::ZERO,
+            },
+            wgpu::BufferCopyView {
+                buffer: &buffer,
+                offset: 0,
+                bytes_per_row: padded_bytes_per_row,
+                rows_per_image: height,
+            },
+            wgpu::Extent3d {
+                width: width,
+                height: height,
+                depth: 1,
+            },
+        );
+        Readback {
+            buffer: buffer,
+            size: size,
+            unpadded_bytes_per_row: unpadded_bytes_per_row,
+            padded_bytes_per_row: padded_bytes_per_row,
+            height: height,
+        }
+    }
+
+    /// Block until a copy from `copy_to_readback` is done, and return its rows without padding.
+    pub fn read_back(&self, readback: Readback) -> Option<Vec<u8>> {
+        let mapping = readback.buffer.map_read(0, readback.size);
+        self.device.poll(wgpu::Maintain::Wait);
+        let mapping = match futures::executor::block_on(mapping) {
+            Ok(mapping) => mapping,
+            Err(_) => {
+                error!("Failed to map the capture buffer");
+                return None;
+            }
+        };
+        let mut pixels =
+            Vec::with_capacity((readback.unpadded_bytes_per_row * readback.height) as usize);
+        for row in mapping
+            .as_slice()
+            .chunks(readback.padded_bytes_per_row as usize)
+            .take(readback.height as usize)
+        {
+            pixels.extend_from_slice(&row[..readback.unpadded_bytes_per_row as usize]);
+        }
+        Some(pixels)
+    }
+}
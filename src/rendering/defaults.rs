@@ -27,17 +27,24 @@ pub fn get_random_walk(r: f32,g: f32,b: f32,n: i32) -> Line{
     let line = Line{        
         indicies: render_all_vertices(&verts),
         verticies: verts,
+        origin: nalgebra::Vector3::zeros(),
+        attributes: Vec::new(),
+        ids: None,
+        metadata: vec![("source".to_string(), "random walk".to_string())],
     };
 
     return line;
 }
 
-pub fn get_sinc_vertices() -> Vec<Vertex> {
+/// A sinc surface sampled on a square grid over -100..100 with about `points` points.
+pub fn get_sinc_vertices(points: usize) -> Vec<Vertex> {
+    let side = (points as f64).sqrt().round().max(1.0) as i32;
+    let spacing = 200.0 / side as f32;
     let mut vertices = Vec::<Vertex>::new();
-    for x_idx in -1000..1000 {
-        for y_idx in -1000..1000 {
-            let x = x_idx as f32 /10.0;
-            let y = y_idx as f32/10.0;
+    for x_idx in -side / 2..side - side / 2 {
+        for y_idx in -side / 2..side - side / 2 {
+            let x = x_idx as f32 * spacing;
+            let y = y_idx as f32 * spacing;
             let distance = (x * x + y * y).sqrt();
             let z = if distance == 0_f32 {
                 1_f32
@@ -53,10 +60,162 @@ pub fn get_sinc_vertices() -> Vec<Vertex> {
         }
     }
 
-    // Test - One million and thirty one points!
     return vertices;
 }
 
+/// Points spread evenly over three concentric spheres, one colour each.
+pub fn get_sphere_shells(points: usize) -> Line {
+    const SHELLS: [(f32, [f32; 4]); 3] = [
+        (10.0, [1.0, 0.3, 0.3, 1.0]),
+        (20.0, [0.3, 1.0, 0.3, 1.0]),
+        (30.0, [0.3, 0.3, 1.0, 1.0]),
+    ];
+    let mut rng = rand::thread_rng();
+    let mut vertices = Vec::<Vertex>::with_capacity(points);
+    for i in 0..points {
+        let (radius, color) = SHELLS[i % SHELLS.len()];
+        // Uniform on the sphere: uniform height and uniform angle around the axis
+        let z: f32 = rng.gen_range(-1.0, 1.0);
+        let angle: f32 = rng.gen_range(0.0, 2.0 * std::f32::consts::PI);
+        let ring = (1.0 - z * z).sqrt();
+        vertices.push(Vertex {
+            position: [
+                radius * ring * angle.cos(),
+                radius * ring * angle.sin(),
+                radius * z,
+                1.0,
+            ],
+            color: color,
+            size: 1.0,
+        });
+    }
+    return line_from_vertices(vertices, Vec::new());
+}
+
+/// Points in eight Gaussian clusters of random position, spread and colour.
+pub fn get_gaussian_clusters(points: usize) -> Line {
+    const CLUSTERS: usize = 8;
+    let mut rng = rand::thread_rng();
+    let clusters: Vec<([f32; 3], f32, [f32; 4])> = (0..CLUSTERS)
+        .map(|_| {
+            (
+                [
+                    rng.gen_range(-50.0, 50.0),
+                    rng.gen_range(-50.0, 50.0),
+                    rng.gen_range(-50.0, 50.0),
+                ],
+                rng.gen_range(2.0, 8.0),
+                [rng.gen(), rng.gen(), rng.gen(), 1.0],
+            )
+        })
+        .collect();
+    let mut vertices = Vec::<Vertex>::with_capacity(points);
+    for i in 0..points {
+        let (center, sigma, color) = clusters[i % CLUSTERS];
+        let mut position = [0.0, 0.0, 0.0, 1.0];
+        for axis in 0..3 {
+            position[axis] = center[axis] + sigma * standard_normal(&mut rng);
+        }
+        vertices.push(Vertex {
+            position: position,
+            color: color,
+            size: 1.0,
+        });
+    }
+    return line_from_vertices(vertices, Vec::new());
+}
+
+/// The Lorenz attractor traced from a fixed start, coloured and given a `time` attribute by how
+/// far along the trajectory each point is.
+pub fn get_lorenz_attractor(points: usize) -> Line {
+    const SIGMA: f32 = 10.0;
+    const RHO: f32 = 28.0;
+    const BETA: f32 = 8.0 / 3.0;
+    const STEP: f32 = 0.005;
+    let (mut x, mut y, mut z) = (1.0_f32, 1.0_f32, 1.0_f32);
+    let mut vertices = Vec::<Vertex>::with_capacity(points);
+    let mut time = Vec::<f32>::with_capacity(points);
+    for i in 0..points {
+        let dx = SIGMA * (y - x);
+        let dy = x * (RHO - z) - y;
+        let dz = x * y - BETA * z;
+        x += dx * STEP;
+        y += dy * STEP;
+        z += dz * STEP;
+        let t = i as f32 / points as f32;
+        vertices.push(Vertex {
+            position: [x, y, z, 1.0],
+            color: [t, 0.5, 1.0 - t, 1.0],
+            size: 1.0,
+        });
+        time.push(i as f32 * STEP);
+    }
+    let time = super::Attribute {
+        name: "time".to_string(),
+        values: time,
+    };
+    return line_from_vertices(vertices, vec![time]);
+}
+
+/// Standard normal sample by the Box-Muller transform.
+fn standard_normal<R: Rng>(rng: &mut R) -> f32 {
+    let u: f32 = 1.0 - rng.gen::<f32>();
+    let v: f32 = rng.gen();
+    return (-2.0 * u.ln()).sqrt() * (2.0 * std::f32::consts::PI * v).cos();
+}
+
+fn line_from_vertices(vertices: Vec<Vertex>, attributes: Vec<super::Attribute>) -> Line {
+    Line {
+        indicies: render_all_vertices(&vertices),
+        verticies: vertices,
+        origin: nalgebra::Vector3::zeros(),
+        attributes: attributes,
+        ids: None,
+        metadata: vec![("source".to_string(), "generated".to_string())],
+    }
+}
+
+/// Test data that `rscat gen` can write out.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Dataset {
+    RandomWalk,
+    Sinc,
+    Spheres,
+    Clusters,
+    Lorenz,
+}
+
+/// Names used for datasets on the command line.
+pub const DATASET_NAMES: &[(&str, Dataset)] = &[
+    ("random-walk", Dataset::RandomWalk),
+    ("sinc", Dataset::Sinc),
+    ("spheres", Dataset::Spheres),
+    ("clusters", Dataset::Clusters),
+    ("lorenz", Dataset::Lorenz),
+];
+
+impl Dataset {
+    pub fn generate(self, points: usize) -> Line {
+        match self {
+            Dataset::RandomWalk => get_random_walk(1.0, 1.0, 1.0, points as i32),
+            Dataset::Sinc => line_from_vertices(get_sinc_vertices(points), Vec::new()),
+            Dataset::Spheres => get_sphere_shells(points),
+            Dataset::Clusters => get_gaussian_clusters(points),
+            Dataset::Lorenz => get_lorenz_attractor(points),
+        }
+    }
+}
+
+/// The scene shown when no files are given: three random walks over a sinc surface.
+pub fn demo_scene() -> Vec<Line> {
+    vec![
+        get_random_walk(1.0, 0.0, 0.0, 1000000),
+        get_random_walk(0.0, 1.0, 0.0, 1000000),
+        get_random_walk(0.0, 0.0, 1.0, 1000000),
+        line_from_vertices(get_sinc_vertices(4_000_000), Vec::new()),
+    ]
+}
+
 pub fn axes() -> Vec<Vertex> {
     let mut vertices = Vec::<Vertex>::new();
     vertices.push(Vertex {
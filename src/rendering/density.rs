@@ -0,0 +1,332 @@
+use super::{viewport, LineBuffers, GEN_DIR};
+
+/// Counts are accumulated as floats so dense regions don't saturate.
+const ACCUMULATION_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::R32Float;
+
+#[repr(C, align(16))]
+#[derive(Debug, Copy, Clone)]
+struct DensityUniform {
+    saturation: f32,
+}
+
+/// Resources for the density heatmap: points are additively counted per pixel into a float
+/// target, which is then mapped through a colormap onto the screen.
+pub struct Density {
+    /// Point count per pixel that maps to the top of the colormap. Counts are log scaled.
+    pub saturation: f32,
+    accumulate_pipeline: wgpu::RenderPipeline,
+    colormap_pipeline: wgpu::RenderPipeline,
+    colormap_bind_group_layout: wgpu::BindGroupLayout,
+    colormap_bind_group: wgpu::BindGroup,
+    sampler: wgpu::Sampler,
+    uniform_buffer: wgpu::Buffer,
+    texture: wgpu::Texture,
+    texture_view: wgpu::TextureView,
+}
+
+impl Density {
+    pub fn new(
+        device: &wgpu::Device,
+        camera_pipeline_layout: &wgpu::PipelineLayout,
+        vs_bytes: &[u8],
+        size: winit::dpi::PhysicalSize<u32>,
+    ) -> Self {
+        let fs_bytes = GEN_DIR
+            .get_file("shaders/density.frag.spv")
+            .unwrap()
+            .contents();
+        let additive = wgpu::BlendDescriptor {
+            src_factor: wgpu::BlendFactor::One,
+            dst_factor: wgpu::BlendFactor::One,
+            operation: wgpu::BlendOperation::Add,
+        };
+        let accumulate_pipeline = super::create_vertex_pipeline(
+            device,
+            camera_pipeline_layout,
+            (vs_bytes, fs_bytes),
+            wgpu::PrimitiveTopology::PointList,
+            wgpu::ColorStateDescriptor {
+                format: ACCUMULATION_FORMAT,
+                color_blend: additive.clone(),
+                alpha_blend: additive,
+                write_mask: wgpu::ColorWrite::ALL,
+            },
+            // Every point counts, hidden or not
+            None,
+            1,
+        );
+
+        let colormap_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                bindings: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStage::FRAGMENT,
+                        ty: wgpu::BindingType::SampledTexture {
+                            dimension: wgpu::TextureViewDimension::D2,
+                            component_type: wgpu::TextureComponentType::Float,
+                            multisampled: false,
+                        },
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStage::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler { comparison: false },
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: wgpu::ShaderStage::FRAGMENT,
+                        ty: wgpu::BindingType::UniformBuffer { dynamic: false },
+                    },
+                ],
+                label: None,
+            });
+        let colormap_pipeline = create_colormap_pipeline(device, &colormap_bind_group_layout);
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Nearest,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            lod_min_clamp: 0.0,
+            lod_max_clamp: 0.0,
+            compare: wgpu::CompareFunction::Undefined,
+        });
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: std::mem::size_of::<DensityUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+        });
+        let texture = create_accumulation_texture(device, size);
+        let texture_view = texture.create_default_view();
+        let colormap_bind_group = create_colormap_bind_group(
+            device,
+            &colormap_bind_group_layout,
+            &texture_view,
+            &sampler,
+            &uniform_buffer,
+        );
+
+        Density {
+            saturation: 100.0,
+            accumulate_pipeline: accumulate_pipeline,
+            colormap_pipeline: colormap_pipeline,
+            colormap_bind_group_layout: colormap_bind_group_layout,
+            colormap_bind_group: colormap_bind_group,
+            sampler: sampler,
+            uniform_buffer: uniform_buffer,
+            texture: texture,
+            texture_view: texture_view,
+        }
+    }
+
+    pub fn resize(&mut self, device: &wgpu::Device, size: winit::dpi::PhysicalSize<u32>) {
+        self.texture = create_accumulation_texture(device, size);
+        self.texture_view = self.texture.create_default_view();
+        self.colormap_bind_group = create_colormap_bind_group(
+            device,
+            &self.colormap_bind_group_layout,
+            &self.texture_view,
+            &self.sampler,
+            &self.uniform_buffer,
+        );
+    }
+}
+
+impl super::Renderer {
+    /// Count the points of `lines` seen from `view`, replacing any previous counts. The camera
+    /// uniform must already hold the view's camera.
+    pub fn accumulate_density(
+        &mut self,
+        command_encoder: &mut wgpu::CommandEncoder,
+        lines: &[LineBuffers],
+        view: &viewport::View,
+    ) {
+        let view_proj = view.camera.view_projection();
+        let mut render_pass = command_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
+                attachment: &self.density.texture_view,
+                resolve_target: None,
+                load_op: wgpu::LoadOp::Clear,
+                store_op: wgpu::StoreOp::Store,
+                clear_color: wgpu::Color::TRANSPARENT,
+            }],
+            depth_stencil_attachment: None,
+        });
+        render_pass.set_pipeline(&self.density.accumulate_pipeline);
+        set_viewport(&mut render_pass, &view.rect);
+        render_pass.set_bind_group(0, &self.uniforms_bind_group, &[]);
+        for line in lines.iter().filter(|line| line.visible) {
+            super::draw_chunks(
+                &mut render_pass,
+                line,
+                None,
+                &self.missing_attribute_buffer,
+                &view_proj,
+                !self.exact,
+                &mut self.stats,
+            );
+        }
+    }
+
+    /// Colour the view's part of `texture_view` by the counts from `accumulate_density`. Pixels
+    /// without any points are left untouched unless `clear` is set.
+    pub fn resolve_density(
+        &mut self,
+        command_encoder: &mut wgpu::CommandEncoder,
+        texture_view: &wgpu::TextureView,
+        view: &viewport::View,
+        clear: bool,
+    ) {
+        let uniform = DensityUniform {
+            saturation: self.density.saturation,
+        };
+        let staging_buffer = self.device.create_buffer_with_data(
+            super::u8_slice_from_slice(std::slice::from_ref(&uniform)),
+            wgpu::BufferUsage::COPY_SRC,
+        );
+        command_encoder.copy_buffer_to_buffer(
+            &staging_buffer,
+            0,
+            &self.density.uniform_buffer,
+            0,
+            std::mem::size_of::<DensityUniform>() as wgpu::BufferAddress,
+        );
+
+        let mut render_pass = command_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
+                attachment: texture_view,
+                resolve_target: None,
+                load_op: if clear {
+                    wgpu::LoadOp::Clear
+                } else {
+                    wgpu::LoadOp::Load
+                },
+                store_op: wgpu::StoreOp::Store,
+                clear_color: wgpu::Color::TRANSPARENT,
+            }],
+            depth_stencil_attachment: None,
+        });
+        render_pass.set_pipeline(&self.density.colormap_pipeline);
+        set_viewport(&mut render_pass, &view.rect);
+        render_pass.set_bind_group(0, &self.density.colormap_bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+fn set_viewport(render_pass: &mut wgpu::RenderPass, rect: &viewport::Rect) {
+    render_pass.set_viewport(
+        rect.x as f32,
+        rect.y as f32,
+        rect.width as f32,
+        rect.height as f32,
+        0.0,
+        1.0,
+    );
+}
+
+fn create_accumulation_texture(
+    device: &wgpu::Device,
+    size: winit::dpi::PhysicalSize<u32>,
+) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        format: ACCUMULATION_FORMAT,
+        usage: wgpu::TextureUsage::OUTPUT_ATTACHMENT | wgpu::TextureUsage::SAMPLED,
+        size: wgpu::Extent3d {
+            width: size.width.max(1),
+            height: size.height.max(1),
+            depth: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        label: None,
+        array_layer_count: 1,
+    })
+}
+
+fn create_colormap_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    texture_view: &wgpu::TextureView,
+    sampler: &wgpu::Sampler,
+    uniform_buffer: &wgpu::Buffer,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout: layout,
+        bindings: &[
+            wgpu::Binding {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(texture_view),
+            },
+            wgpu::Binding {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(sampler),
+            },
+            wgpu::Binding {
+                binding: 2,
+                resource: wgpu::BindingResource::Buffer {
+                    buffer: uniform_buffer,
+                    range: 0..std::mem::size_of::<DensityUniform>() as wgpu::BufferAddress,
+                },
+            },
+        ],
+        label: None,
+    })
+}
+
+fn create_colormap_pipeline(
+    device: &wgpu::Device,
+    bind_group_layout: &wgpu::BindGroupLayout,
+) -> wgpu::RenderPipeline {
+    let vs_bytes = GEN_DIR
+        .get_file("shaders/fullscreen.vert.spv")
+        .unwrap()
+        .contents();
+    let fs_bytes = GEN_DIR
+        .get_file("shaders/density_colormap.frag.spv")
+        .unwrap()
+        .contents();
+    let vs_module =
+        device.create_shader_module(&wgpu::read_spirv(std::io::Cursor::new(vs_bytes)).unwrap());
+    let fs_module =
+        device.create_shader_module(&wgpu::read_spirv(std::io::Cursor::new(fs_bytes)).unwrap());
+    let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        bind_group_layouts: &[bind_group_layout],
+    });
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        layout: &layout,
+        vertex_stage: wgpu::ProgrammableStageDescriptor {
+            module: &vs_module,
+            entry_point: "main",
+        },
+        fragment_stage: Some(wgpu::ProgrammableStageDescriptor {
+            module: &fs_module,
+            entry_point: "main",
+        }),
+        rasterization_state: Some(wgpu::RasterizationStateDescriptor {
+            front_face: wgpu::FrontFace::Ccw,
+            cull_mode: wgpu::CullMode::None,
+            depth_bias: 0,
+            depth_bias_slope_scale: 0.0,
+            depth_bias_clamp: 0.0,
+        }),
+        primitive_topology: wgpu::PrimitiveTopology::TriangleList,
+        color_states: &[wgpu::ColorStateDescriptor {
+            format: wgpu::TextureFormat::Bgra8UnormSrgb,
+            color_blend: wgpu::BlendDescriptor::REPLACE,
+            alpha_blend: wgpu::BlendDescriptor::REPLACE,
+            write_mask: wgpu::ColorWrite::ALL,
+        }],
+        depth_stencil_state: None,
+        vertex_state: wgpu::VertexStateDescriptor {
+            index_format: wgpu::IndexFormat::Uint32,
+            vertex_buffers: &[],
+        },
+        sample_count: 1,
+        sample_mask: !0,
+        alpha_to_coverage_enabled: false,
+    })
+}
@@ -0,0 +1,301 @@
+//! Per-pixel exports of the current view other than its colours: the depth buffer, and which point
+//! each pixel shows, for occlusion analysis and mapping screenshots back to source points.
+
+use super::{viewport, LineBuffers, GEN_DIR};
+
+const POINT_ID_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rg32Uint;
+
+#[repr(C, align(16))]
+#[derive(Debug, Copy, Clone)]
+struct PointIdUniform {
+    layer_id: u32,
+    first_point: u32,
+    /// The layer's clamps on the point size on screen, see `LineBuffers::min_size`.
+    min_size: f32,
+    max_size: f32,
+}
+
+/// What each pixel of the view shows, row-major from the top left.
+pub struct PointMap {
+    pub width: u32,
+    pub height: u32,
+    /// Depth buffer values, from 0 at the near plane to 1 at the far plane or where there is no
+    /// point.
+    pub depth: Vec<f32>,
+    /// The layer index plus one, and the point's position in the layer's draw order. Pixels
+    /// without a point have a layer of zero.
+    pub ids: Vec<[u32; 2]>,
+}
+
+impl PointMap {
+    /// Encode the depth as a little endian greyscale PFM image.
+    pub fn depth_to_pfm(&self) -> Vec<u8> {
+        let mut pfm = format!("Pf\n{} {}\n-1.0\n", self.width, self.height).into_bytes();
+        // PFM rows run from the bottom up
+        for row in self.depth.chunks(self.width as usize).rev() {
+            for value in row {
+                pfm.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+        return pfm;
+    }
+
+    /// CSV rows of pixel x, pixel y, layer and point for every pixel that shows a point.
+    pub fn ids_to_csv(&self) -> Vec<u8> {
+        let mut csv = String::from("x,y,layer,point\n");
+        for (i, id) in self.ids.iter().enumerate() {
+            if id[0] == 0 {
+                continue;
+            }
+            let x = i % self.width as usize;
+            let y = i / self.width as usize;
+            csv += &format!("{},{},{},{}\n", x, y, id[0] - 1, id[1]);
+        }
+        return csv.into_bytes();
+    }
+}
+
+impl super::Renderer {
+    /// Draw which point is nearest the camera at each pixel of every view, and read that back
+    /// along with the depth buffer. Points are drawn as in `render`, but without stereo colour
+    /// masks or depth modes.
+    pub fn capture_point_map(
+        &mut self,
+        viewports: &viewport::Viewports,
+        lines: &[LineBuffers],
+    ) -> Option<PointMap> {
+        let width = self.sc_desc.width;
+        let height = self.sc_desc.height;
+        let id_texture = self.create_readable_texture(POINT_ID_FORMAT);
+        let id_view = id_texture.create_default_view();
+        let depth_texture = self.create_readable_texture(wgpu::TextureFormat::Depth32Float);
+        let depth_view = depth_texture.create_default_view();
+
+        let id_bind_group_layout =
+            self.device
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    bindings: &[wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStage::VERTEX,
+                        ty: wgpu::BindingType::UniformBuffer { dynamic: false },
+                    }],
+                    label: None,
+                });
+        let uniform_size = std::mem::size_of::<PointIdUniform>() as wgpu::BufferAddress;
+        let uniform_buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: uniform_size,
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+        });
+        let id_bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &id_bind_group_layout,
+            bindings: &[wgpu::Binding {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer {
+                    buffer: &uniform_buffer,
+                    range: 0..uniform_size,
+                },
+            }],
+            label: None,
+        });
+        let pipeline = self.create_point_id_pipeline(&id_bind_group_layout);
+
+        let mut command_encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        // Clear both targets, even if nothing is drawn
+        command_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            color_attachments: &[color_attachment(&id_view, wgpu::LoadOp::Clear)],
+            depth_stencil_attachment: Some(depth_attachment(&depth_view, wgpu::LoadOp::Clear)),
+        });
+        self.update_attributes(&mut command_encoder);
+        for view in viewports.views() {
+            self.update_camera(&mut command_encoder, &view.camera);
+            let view_proj = view.camera.view_projection();
+            for (layer, line) in lines.iter().enumerate().filter(|(_, line)| line.visible) {
+                let view = match viewports.comparison {
+                    Some(comparison) => comparison.clip(&view, layer),
+                    None => view,
+                };
+                let slot = self
+                    .attribute
+                    .as_ref()
+                    .and_then(|name| line.attributes.iter().position(|a| a == name));
+                let mut first_point = 0_u32;
+                for chunk in line.chunks.iter() {
+                    let points = chunk.vertex_count - chunk.first;
+                    let uniform = PointIdUniform {
+                        layer_id: layer as u32 + 1,
+                        first_point: first_point.wrapping_sub(chunk.first),
+                        min_size: line.min_size,
+                        max_size: line.max_size,
+                    };
+                    first_point += points;
+                    if points == 0 || !chunk.bounds.is_visible(&view_proj) {
+                        continue;
+                    }
+                    // Each chunk needs its own uniform, so its own pass
+                    let staging_buffer = self.device.create_buffer_with_data(
+                        super::u8_slice_from_slice(std::slice::from_ref(&uniform)),
+                        wgpu::BufferUsage::COPY_SRC,
+                    );
+                    command_encoder.copy_buffer_to_buffer(
+                        &staging_buffer,
+                        0,
+                        &uniform_buffer,
+                        0,
+                        uniform_size,
+                    );
+                    let mut render_pass =
+                        command_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                            color_attachments: &[color_attachment(&id_view, wgpu::LoadOp::Load)],
+                            depth_stencil_attachment: Some(depth_attachment(
+                                &depth_view,
+                                wgpu::LoadOp::Load,
+                            )),
+                        });
+                    render_pass.set_pipeline(&pipeline);
+                    render_pass.set_viewport(
+                        view.rect.x as f32,
+                        view.rect.y as f32,
+                        view.rect.width as f32,
+                        view.rect.height as f32,
+                        0.0,
+                        1.0,
+                    );
+                    render_pass.set_scissor_rect(
+                        view.clip.x,
+                        view.clip.y,
+                        view.clip.width,
+                        view.clip.height,
+                    );
+                    render_pass.set_bind_group(0, &self.uniforms_bind_group, &[]);
+                    render_pass.set_bind_group(1, &id_bind_group, &[]);
+                    render_pass.set_vertex_buffer(0, &chunk.vertex_buffer, 0, 0);
+                    let attribute_buffer = slot.map_or(&self.missing_attribute_buffer, |i| {
+                        &chunk.attribute_buffers[i]
+                    });
+                    render_pass.set_vertex_buffer(1, attribute_buffer, 0, 0);
+                    render_pass.draw(chunk.first..chunk.vertex_count, 0..1);
+                }
+            }
+        }
+
+        let ids = self.copy_to_readback(&mut command_encoder, &id_texture, width, height, 8);
+        let depth = self.copy_to_readback(&mut command_encoder, &depth_texture, width, height, 4);
+        self.queue.submit(&[command_encoder.finish()]);
+        let ids = self.read_back(ids)?;
+        let depth = self.read_back(depth)?;
+        let word = |bytes: &[u8]| [bytes[0], bytes[1], bytes[2], bytes[3]];
+        return Some(PointMap {
+            width: width,
+            height: height,
+            depth: depth
+                .chunks(4)
+                .map(|bytes| f32::from_le_bytes(word(bytes)))
+                .collect(),
+            ids: ids
+                .chunks(8)
+                .map(|bytes| {
+                    [
+                        u32::from_le_bytes(word(&bytes[..4])),
+                        u32::from_le_bytes(word(&bytes[4..])),
+                    ]
+                })
+                .collect(),
+        });
+    }
+
+    /// A render target the size of the swap chain that can be copied out.
+    fn create_readable_texture(&self, format: wgpu::TextureFormat) -> wgpu::Texture {
+        self.device.create_texture(&wgpu::TextureDescriptor {
+            format: format,
+            usage: wgpu::TextureUsage::OUTPUT_ATTACHMENT | wgpu::TextureUsage::COPY_SRC,
+            size: wgpu::Extent3d {
+                width: self.sc_desc.width,
+                height: self.sc_desc.height,
+                depth: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            label: None,
+            array_layer_count: 1,
+        })
+    }
+
+    fn create_point_id_pipeline(
+        &self,
+        id_bind_group_layout: &wgpu::BindGroupLayout,
+    ) -> wgpu::RenderPipeline {
+        let pipeline_layout = self
+            .device
+            .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                bind_group_layouts: &[&self.uniforms_bind_group_layout, id_bind_group_layout],
+            });
+        let replace = wgpu::BlendDescriptor {
+            src_factor: wgpu::BlendFactor::One,
+            dst_factor: wgpu::BlendFactor::Zero,
+            operation: wgpu::BlendOperation::Add,
+        };
+        super::create_vertex_pipeline(
+            &self.device,
+            &pipeline_layout,
+            (
+                GEN_DIR
+                    .get_file("shaders/point_id.vert.spv")
+                    .unwrap()
+                    .contents(),
+                GEN_DIR
+                    .get_file("shaders/point_id.frag.spv")
+                    .unwrap()
+                    .contents(),
+            ),
+            wgpu::PrimitiveTopology::PointList,
+            wgpu::ColorStateDescriptor {
+                format: POINT_ID_FORMAT,
+                color_blend: replace.clone(),
+                alpha_blend: replace,
+                write_mask: wgpu::ColorWrite::ALL,
+            },
+            Some(wgpu::DepthStencilStateDescriptor {
+                format: wgpu::TextureFormat::Depth32Float,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil_front: wgpu::StencilStateFaceDescriptor::IGNORE,
+                stencil_back: wgpu::StencilStateFaceDescriptor::IGNORE,
+                stencil_read_mask: 0,
+                stencil_write_mask: 0,
+            }),
+            1,
+        )
+    }
+}
+
+fn color_attachment<'a>(
+    view: &'a wgpu::TextureView,
+    load_op: wgpu::LoadOp,
+) -> wgpu::RenderPassColorAttachmentDescriptor<'a> {
+    wgpu::RenderPassColorAttachmentDescriptor {
+        attachment: view,
+        resolve_target: None,
+        load_op: load_op,
+        store_op: wgpu::StoreOp::Store,
+        clear_color: wgpu::Color::TRANSPARENT,
+    }
+}
+
+fn depth_attachment<'a>(
+    view: &'a wgpu::TextureView,
+    load_op: wgpu::LoadOp,
+) -> wgpu::RenderPassDepthStencilAttachmentDescriptor<'a> {
+    wgpu::RenderPassDepthStencilAttachmentDescriptor {
+        attachment: view,
+        depth_load_op: load_op,
+        depth_store_op: wgpu::StoreOp::Store,
+        clear_depth: 1.0,
+        stencil_load_op: load_op,
+        stencil_store_op: wgpu::StoreOp::Store,
+        clear_stencil: 0,
+    }
+}
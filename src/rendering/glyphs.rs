@@ -0,0 +1,517 @@
+//! Layers of small meshes, one per record, drawn with instancing: each glyph is a shared mesh
+//! placed by its own transform and drawn in its own colour.
+
+use super::{viewport, Animation, ColorInput, PointStyle, Tint, GEN_DIR};
+
+/// The mesh every glyph in a layer is drawn with.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Shape {
+    /// A unit cube centred on the origin, e.g. for voxels.
+    Cube,
+    /// A sphere of unit radius centred on the origin, e.g. stretched into ellipsoids.
+    Sphere,
+    /// A pyramid with its apex at the origin and its base the square from -1 to 1 in X and Y at
+    /// Z = 1, e.g. stretched into camera frustums.
+    Frustum,
+    /// An arrow of unit length from the origin along X, e.g. for vector fields.
+    Arrow,
+    /// A mesh of its own, e.g. an isosurface.
+    Mesh(Mesh),
+}
+
+/// An indexed triangle mesh, with a normal per vertex.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mesh {
+    pub positions: Vec<[f32; 3]>,
+    pub normals: Vec<[f32; 3]>,
+    pub triangles: Vec<[u32; 3]>,
+}
+
+/// One glyph: its mesh is transformed into place from the layer's local coordinates.
+#[repr(C, align(16))]
+#[derive(Debug, Copy, Clone)]
+pub struct Instance {
+    pub transform: [[f32; 4]; 4],
+    /// Alpha makes the glyph translucent.
+    pub color: [f32; 4],
+}
+
+impl Instance {
+    pub fn new(transform: nalgebra::Matrix4<f32>, color: [f32; 4]) -> Self {
+        Instance {
+            transform: *transform.as_ref(),
+            color: color,
+        }
+    }
+}
+
+/// A glyph layer on the CPU, in local coordinates around `origin` like a `Line`.
+pub struct GlyphLayer {
+    pub shape: Shape,
+    pub instances: Vec<Instance>,
+    pub origin: nalgebra::Vector3<f64>,
+    /// Whether to draw the edges of each glyph's mesh rather than its faces.
+    pub wireframe: bool,
+}
+
+/// GPU-resident copy of a `GlyphLayer`, created by `Renderer::upload_glyphs`.
+pub struct GlyphBuffers {
+    mesh_buffer: wgpu::Buffer,
+    mesh_vertices: u32,
+    /// Line list of the mesh's edges, for drawing it as a wireframe.
+    edge_buffer: wgpu::Buffer,
+    edge_vertices: u32,
+    instance_buffer: wgpu::Buffer,
+    instances: u32,
+    pub tint: Tint,
+    pub visible: bool,
+    pub wireframe: bool,
+}
+
+#[repr(C, align(16))]
+#[derive(Debug, Copy, Clone)]
+struct MeshVertex {
+    position: [f32; 4],
+    normal: [f32; 4],
+}
+
+impl Shape {
+    /// The shape's mesh, in each glyph's local coordinates.
+    pub fn mesh(&self) -> std::borrow::Cow<'_, Mesh> {
+        match self {
+            Shape::Cube => std::borrow::Cow::Owned(cube_mesh()),
+            Shape::Sphere => std::borrow::Cow::Owned(sphere_mesh()),
+            Shape::Frustum => std::borrow::Cow::Owned(frustum_mesh()),
+            Shape::Arrow => std::borrow::Cow::Owned(arrow_mesh()),
+            Shape::Mesh(mesh) => std::borrow::Cow::Borrowed(mesh),
+        }
+    }
+}
+
+/// Triangle list of `mesh`, with a normal per vertex for shading.
+fn mesh_vertices(mesh: &Mesh) -> Vec<MeshVertex> {
+    return mesh
+        .triangles
+        .iter()
+        .flatten()
+        .map(|&index| {
+            let position = mesh.positions[index as usize];
+            let normal = mesh.normals[index as usize];
+            MeshVertex {
+                position: [position[0], position[1], position[2], 1.0],
+                normal: [normal[0], normal[1], normal[2], 0.0],
+            }
+        })
+        .collect();
+}
+
+/// Line list of the edges of `mesh` between faces at an angle, so quads split into triangles
+/// are drawn whole.
+fn edge_vertices(mesh: &Mesh) -> Vec<MeshVertex> {
+    let vertex = |index: u32| {
+        let position = mesh.positions[index as usize];
+        let normal = mesh.normals[index as usize];
+        MeshVertex {
+            position: [position[0], position[1], position[2], 1.0],
+            normal: [normal[0], normal[1], normal[2], 0.0],
+        }
+    };
+    let face_normal = |triangle: &[u32; 3]| {
+        let corner = |i: usize| nalgebra::Vector3::from(mesh.positions[triangle[i] as usize]);
+        (corner(1) - corner(0))
+            .cross(&(corner(2) - corner(0)))
+            .try_normalize(0.0)
+    };
+    // The normals of the faces on each edge, keyed by its lower vertex first
+    let mut edges = std::collections::BTreeMap::<(u32, u32), Vec<_>>::new();
+    for triangle in &mesh.triangles {
+        for i in 0..3 {
+            let (a, b) = (triangle[i], triangle[(i + 1) % 3]);
+            let faces = edges.entry((a.min(b), a.max(b))).or_insert_with(Vec::new);
+            faces.push(face_normal(triangle));
+        }
+    }
+    let mut vertices = Vec::new();
+    for ((a, b), faces) in edges {
+        let flat = match faces[..] {
+            [Some(first), Some(second)] => first.dot(&second).abs() > 1.0 - 1e-5,
+            _ => false,
+        };
+        if !flat {
+            vertices.push(vertex(a));
+            vertices.push(vertex(b));
+        }
+    }
+    return vertices;
+}
+
+fn cube_mesh() -> Mesh {
+    let mut mesh = Mesh {
+        positions: Vec::with_capacity(24),
+        normals: Vec::with_capacity(24),
+        triangles: Vec::with_capacity(12),
+    };
+    for axis in 0..3 {
+        for &side in &[-0.5_f32, 0.5] {
+            // The two axes spanning this face
+            let (u, v) = ((axis + 1) % 3, (axis + 2) % 3);
+            let first = mesh.positions.len() as u32;
+            for &(a, b) in &[(-0.5, -0.5), (0.5, -0.5), (0.5, 0.5), (-0.5, 0.5)] {
+                let mut position = [0.0; 3];
+                position[axis] = side;
+                position[u] = a;
+                position[v] = b;
+                let mut normal = [0.0; 3];
+                normal[axis] = side * 2.0;
+                mesh.positions.push(position);
+                mesh.normals.push(normal);
+            }
+            mesh.triangles.push([first, first + 1, first + 2]);
+            mesh.triangles.push([first, first + 2, first + 3]);
+        }
+    }
+    return mesh;
+}
+
+/// Rings of latitude and longitude joined into quads, each split into two triangles.
+fn sphere_mesh() -> Mesh {
+    const RINGS: u32 = 12;
+    const SEGMENTS: u32 = 24;
+    let mut mesh = Mesh {
+        positions: Vec::new(),
+        normals: Vec::new(),
+        triangles: Vec::new(),
+    };
+    for ring in 0..=RINGS {
+        let polar = ring as f32 / RINGS as f32 * std::f32::consts::PI;
+        for segment in 0..=SEGMENTS {
+            let azimuth = segment as f32 / SEGMENTS as f32 * 2.0 * std::f32::consts::PI;
+            let position = [
+                polar.sin() * azimuth.cos(),
+                polar.sin() * azimuth.sin(),
+                polar.cos(),
+            ];
+            mesh.positions.push(position);
+            mesh.normals.push(position);
+        }
+    }
+    let index = |ring: u32, segment: u32| ring * (SEGMENTS + 1) + segment;
+    for ring in 0..RINGS {
+        for segment in 0..SEGMENTS {
+            let (a, b) = (index(ring, segment), index(ring, segment + 1));
+            let (c, d) = (index(ring + 1, segment + 1), index(ring + 1, segment));
+            // The quads touching the poles are triangles already
+            if ring != 0 {
+                mesh.triangles.push([a, d, b]);
+            }
+            if ring != RINGS - 1 {
+                mesh.triangles.push([b, d, c]);
+            }
+        }
+    }
+    return mesh;
+}
+
+fn frustum_mesh() -> Mesh {
+    let corners = [[-1.0, -1.0], [1.0, -1.0], [1.0, 1.0], [-1.0, 1.0]];
+    let mut mesh = Mesh {
+        positions: Vec::new(),
+        normals: Vec::new(),
+        triangles: Vec::new(),
+    };
+    // Each side separately so it has its own normal, then the base
+    for i in 0..4 {
+        let [x0, y0] = corners[i];
+        let [x1, y1] = corners[(i + 1) % 4];
+        let a = nalgebra::Vector3::new(x0, y0, 1.0);
+        let b = nalgebra::Vector3::new(x1, y1, 1.0);
+        let normal = b.cross(&a).normalize();
+        let first = mesh.positions.len() as u32;
+        for position in &[nalgebra::Vector3::zeros(), a, b] {
+            mesh.positions.push([position.x, position.y, position.z]);
+            mesh.normals.push([normal.x, normal.y, normal.z]);
+        }
+        mesh.triangles.push([first, first + 1, first + 2]);
+    }
+    let first = mesh.positions.len() as u32;
+    for &[x, y] in &corners {
+        mesh.positions.push([x, y, 1.0]);
+        mesh.normals.push([0.0, 0.0, 1.0]);
+    }
+    mesh.triangles.push([first, first + 1, first + 2]);
+    mesh.triangles.push([first, first + 2, first + 3]);
+    return mesh;
+}
+
+/// A round shaft with a cone for its head.
+fn arrow_mesh() -> Mesh {
+    const SEGMENTS: u32 = 12;
+    const SHAFT_RADIUS: f32 = 0.03;
+    const HEAD_RADIUS: f32 = 0.08;
+    const HEAD_START: f32 = 0.75;
+    let mut mesh = Mesh {
+        positions: Vec::new(),
+        normals: Vec::new(),
+        triangles: Vec::new(),
+    };
+    // Slanted so the cone is shaded smoothly along its side
+    let cone_slope = HEAD_RADIUS / (1.0 - HEAD_START);
+    for i in 0..SEGMENTS {
+        let angle = |i: u32| i as f32 / SEGMENTS as f32 * 2.0 * std::f32::consts::PI;
+        let first = mesh.positions.len() as u32;
+        for &angle in &[angle(i), angle(i + 1)] {
+            let (y, z) = (angle.cos(), angle.sin());
+            let cone_normal = nalgebra::Vector3::new(cone_slope, y, z).normalize();
+            let vertices = [
+                // The shaft's end cap and side
+                ([0.0, 0.0, 0.0], [-1.0, 0.0, 0.0]),
+                ([0.0, SHAFT_RADIUS * y, SHAFT_RADIUS * z], [-1.0, 0.0, 0.0]),
+                ([0.0, SHAFT_RADIUS * y, SHAFT_RADIUS * z], [0.0, y, z]),
+                (
+                    [HEAD_START, SHAFT_RADIUS * y, SHAFT_RADIUS * z],
+                    [0.0, y, z],
+                ),
+                // The back of the head and its cone
+                ([HEAD_START, 0.0, 0.0], [-1.0, 0.0, 0.0]),
+                (
+                    [HEAD_START, HEAD_RADIUS * y, HEAD_RADIUS * z],
+                    [-1.0, 0.0, 0.0],
+                ),
+                (
+                    [HEAD_START, HEAD_RADIUS * y, HEAD_RADIUS * z],
+                    [cone_normal.x, cone_normal.y, cone_normal.z],
+                ),
+                (
+                    [1.0, 0.0, 0.0],
+                    [cone_normal.x, cone_normal.y, cone_normal.z],
+                ),
+            ];
+            for &(position, normal) in &vertices {
+                mesh.positions.push(position);
+                mesh.normals.push(normal);
+            }
+        }
+        // Vertex `v` of this segment's first edge is `first + v`, of its second `first + 8 + v`
+        let (a, b) = (first, first + 8);
+        mesh.triangles.push([a, b + 1, a + 1]);
+        mesh.triangles.push([a + 2, a + 3, b + 3]);
+        mesh.triangles.push([a + 2, b + 3, b + 2]);
+        mesh.triangles.push([a + 4, b + 5, a + 5]);
+        mesh.triangles.push([a + 6, a + 7, b + 6]);
+    }
+    return mesh;
+}
+
+/// A glyph pipeline drawing either the triangles of glyphs' meshes or the lines of their edges.
+pub fn create_pipeline(
+    device: &wgpu::Device,
+    pipeline_layout: &wgpu::PipelineLayout,
+    topology: wgpu::PrimitiveTopology,
+) -> wgpu::RenderPipeline {
+    let vs_bytes = GEN_DIR
+        .get_file("shaders/glyph.vert.spv")
+        .unwrap()
+        .contents();
+    // Glyphs are shaded per vertex, so the wireframe's pass-through fragment shader will do
+    let fs_bytes = GEN_DIR
+        .get_file("shaders/wireframe.frag.spv")
+        .unwrap()
+        .contents();
+    let vs_module =
+        device.create_shader_module(&wgpu::read_spirv(std::io::Cursor::new(vs_bytes)).unwrap());
+    let fs_module =
+        device.create_shader_module(&wgpu::read_spirv(std::io::Cursor::new(fs_bytes)).unwrap());
+    let vec4 = |location: u32, offset: usize| wgpu::VertexAttributeDescriptor {
+        format: wgpu::VertexFormat::Float4,
+        offset: offset as wgpu::BufferAddress,
+        shader_location: location,
+    };
+    let transform = memoffset::offset_of!(Instance, transform);
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        layout: pipeline_layout,
+        vertex_stage: wgpu::ProgrammableStageDescriptor {
+            module: &vs_module,
+            entry_point: "main",
+        },
+        fragment_stage: Some(wgpu::ProgrammableStageDescriptor {
+            module: &fs_module,
+            entry_point: "main",
+        }),
+        rasterization_state: Some(wgpu::RasterizationStateDescriptor {
+            front_face: wgpu::FrontFace::Ccw,
+            cull_mode: wgpu::CullMode::None,
+            depth_bias: 0,
+            depth_bias_slope_scale: 0.0,
+            depth_bias_clamp: 0.0,
+        }),
+        primitive_topology: topology,
+        color_states: &[wgpu::ColorStateDescriptor {
+            format: wgpu::TextureFormat::Bgra8UnormSrgb,
+            color_blend: wgpu::BlendDescriptor {
+                src_factor: wgpu::BlendFactor::SrcAlpha,
+                dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+                operation: wgpu::BlendOperation::Add,
+            },
+            alpha_blend: wgpu::BlendDescriptor {
+                src_factor: wgpu::BlendFactor::One,
+                dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+                operation: wgpu::BlendOperation::Add,
+            },
+            write_mask: wgpu::ColorWrite::ALL,
+        }],
+        depth_stencil_state: Some(wgpu::DepthStencilStateDescriptor {
+            format: wgpu::TextureFormat::Depth32Float,
+            depth_write_enabled: true,
+            depth_compare: wgpu::CompareFunction::Less,
+            stencil_front: wgpu::StencilStateFaceDescriptor::IGNORE,
+            stencil_back: wgpu::StencilStateFaceDescriptor::IGNORE,
+            stencil_read_mask: 0,
+            stencil_write_mask: 0,
+        }),
+        vertex_state: wgpu::VertexStateDescriptor {
+            index_format: wgpu::IndexFormat::Uint32,
+            vertex_buffers: &[
+                wgpu::VertexBufferDescriptor {
+                    stride: std::mem::size_of::<MeshVertex>() as wgpu::BufferAddress,
+                    step_mode: wgpu::InputStepMode::Vertex,
+                    attributes: &[
+                        vec4(0, memoffset::offset_of!(MeshVertex, position)),
+                        vec4(1, memoffset::offset_of!(MeshVertex, normal)),
+                    ],
+                },
+                wgpu::VertexBufferDescriptor {
+                    stride: std::mem::size_of::<Instance>() as wgpu::BufferAddress,
+                    step_mode: wgpu::InputStepMode::Instance,
+                    // A matrix takes up one location per column
+                    attributes: &[
+                        vec4(2, transform),
+                        vec4(3, transform + 16),
+                        vec4(4, transform + 32),
+                        vec4(5, transform + 48),
+                        vec4(6, memoffset::offset_of!(Instance, color)),
+                    ],
+                },
+            ],
+        },
+        sample_count: 1,
+        sample_mask: !0,
+        alpha_to_coverage_enabled: false,
+    })
+}
+
+impl super::Renderer {
+    /// Upload a glyph layer, offset from its own origin to the scene's.
+    pub fn upload_glyphs(&mut self, layer: &GlyphLayer) -> GlyphBuffers {
+        let offset: nalgebra::Vector3<f32> = nalgebra::convert(layer.origin - self.origin);
+        let to_scene = nalgebra::Matrix4::new_translation(&offset);
+        let instances: Vec<Instance> = layer
+            .instances
+            .iter()
+            .map(|instance| Instance {
+                transform: *(to_scene * nalgebra::Matrix4::from(instance.transform)).as_ref(),
+                color: instance.color,
+            })
+            .collect();
+        let mesh = layer.shape.mesh();
+        let edges = edge_vertices(&mesh);
+        let mesh = mesh_vertices(&mesh);
+        self.stats.record_upload(std::mem::size_of_val(&mesh[..]));
+        self.stats.record_upload(std::mem::size_of_val(&edges[..]));
+        self.stats
+            .record_upload(std::mem::size_of_val(&instances[..]));
+        // wgpu won't create empty buffers, so an empty layer keeps a single unused instance
+        let placeholder = [Instance::new(nalgebra::Matrix4::zeros(), [0.0; 4])];
+        let instance_data = if instances.is_empty() {
+            &placeholder[..]
+        } else {
+            &instances[..]
+        };
+        return GlyphBuffers {
+            mesh_buffer: self.device.create_buffer_with_data(
+                super::u8_slice_from_slice(&mesh),
+                wgpu::BufferUsage::VERTEX,
+            ),
+            mesh_vertices: mesh.len() as u32,
+            edge_buffer: self.device.create_buffer_with_data(
+                super::u8_slice_from_slice(&edges),
+                wgpu::BufferUsage::VERTEX,
+            ),
+            edge_vertices: edges.len() as u32,
+            instance_buffer: self.device.create_buffer_with_data(
+                super::u8_slice_from_slice(instance_data),
+                wgpu::BufferUsage::VERTEX,
+            ),
+            instances: instances.len() as u32,
+            tint: Tint::none(),
+            visible: true,
+            wireframe: layer.wireframe,
+        };
+    }
+
+    /// Draw a glyph layer into the view, hidden by and hiding the points around it. The camera
+    /// uniform must already hold the view's camera.
+    pub fn render_glyphs(
+        &mut self,
+        command_encoder: &mut wgpu::CommandEncoder,
+        texture_view: &wgpu::TextureView,
+        glyphs: &GlyphBuffers,
+        view: &viewport::View,
+    ) {
+        if !glyphs.visible || glyphs.instances == 0 {
+            return;
+        }
+        self.update_tint(
+            command_encoder,
+            &glyphs.tint,
+            Animation::Off,
+            ColorInput::linear(),
+            PointStyle::none(),
+        );
+        let mut render_pass = command_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
+                attachment: texture_view,
+                resolve_target: None,
+                load_op: wgpu::LoadOp::Load,
+                store_op: wgpu::StoreOp::Store,
+                clear_color: wgpu::Color::TRANSPARENT,
+            }],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachmentDescriptor {
+                attachment: &self.depth_texture_view,
+                depth_load_op: wgpu::LoadOp::Load,
+                depth_store_op: wgpu::StoreOp::Store,
+                clear_depth: 1.0,
+                stencil_load_op: wgpu::LoadOp::Load,
+                stencil_store_op: wgpu::StoreOp::Store,
+                clear_stencil: 0,
+            }),
+        });
+        let (pipeline, buffer, vertices) = if glyphs.wireframe {
+            (
+                &self.glyph_wireframe_pipeline,
+                &glyphs.edge_buffer,
+                glyphs.edge_vertices,
+            )
+        } else {
+            (
+                &self.glyph_pipeline,
+                &glyphs.mesh_buffer,
+                glyphs.mesh_vertices,
+            )
+        };
+        render_pass.set_pipeline(pipeline);
+        render_pass.set_viewport(
+            view.rect.x as f32,
+            view.rect.y as f32,
+            view.rect.width as f32,
+            view.rect.height as f32,
+            0.0,
+            1.0,
+        );
+        render_pass.set_scissor_rect(view.clip.x, view.clip.y, view.clip.width, view.clip.height);
+        render_pass.set_bind_group(0, &self.uniforms_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, buffer, 0, 0);
+        render_pass.set_vertex_buffer(1, &glyphs.instance_buffer, 0, 0);
+        render_pass.draw(0..vertices, 0..glyphs.instances);
+        self.stats.record_draw(glyphs.instances as usize);
+    }
+}
@@ -0,0 +1,57 @@
+use crate::error::RscatError;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Polls a directory of shader sources and reports when any of them has been modified.
+pub struct ShaderWatcher {
+    dir: PathBuf,
+    last_modified: Option<SystemTime>,
+}
+
+impl ShaderWatcher {
+    pub fn new(dir: PathBuf) -> Self {
+        let mut watcher = ShaderWatcher {
+            dir: dir,
+            last_modified: None,
+        };
+        // Take the initial timestamp so we don't reload straight away
+        watcher.last_modified = watcher.newest_modification();
+        watcher
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Returns true if a shader has changed since the last call.
+    pub fn poll(&mut self) -> bool {
+        let newest = self.newest_modification();
+        if newest > self.last_modified {
+            self.last_modified = newest;
+            return true;
+        }
+        return false;
+    }
+
+    fn newest_modification(&self) -> Option<SystemTime> {
+        std::fs::read_dir(&self.dir)
+            .ok()?
+            .filter_map(|entry| entry.ok()?.metadata().ok()?.modified().ok())
+            .max()
+    }
+}
+
+pub fn compile_shader(
+    path: &Path,
+    shader_type: glsl_to_spirv::ShaderType,
+) -> Result<Vec<u8>, RscatError> {
+    use std::io::Read;
+
+    let source = std::fs::read_to_string(path)?;
+    let mut compiled_file = glsl_to_spirv::compile(&source, shader_type)
+        .map_err(|e| RscatError::Gpu(format!("{}: {}", path.display(), e)))?;
+
+    let mut compiled_bytes = Vec::new();
+    compiled_file.read_to_end(&mut compiled_bytes)?;
+    return Ok(compiled_bytes);
+}
@@ -0,0 +1,154 @@
+//! Point labels for debugging, e.g. to find the row of an anomaly in the source file. A layer
+//! labels every Nth point, optionally only within a range of indices, see `LineBuffers::labels`.
+//! Points are labelled with their ID, like picking reports them, or their index in layers without
+//! IDs. Labels are drawn over everything, whether or not their point is hidden behind others.
+
+use super::overlay::{self, Canvas, Overlay, GLYPH_HEIGHT};
+use super::{draw_order, svg, viewport, Line, LineBuffers};
+
+/// Most labels drawn at once, so a layer labelled too densely doesn't stall every frame.
+const MAX_LABELS: usize = 2000;
+/// Most points projected to place labels each frame, however few land in view. Beyond it layers
+/// label fewer of their points, still on multiples of their own spacing.
+const MAX_PROJECTED: usize = 100_000;
+/// Gap between a point and its label, in overlay pixels.
+const OFFSET: i64 = 3;
+const OUTLINE: [u8; 4] = [0, 0, 0, 255];
+
+/// Text at a position in overlay pixels.
+#[derive(Debug, Clone, PartialEq)]
+struct Label {
+    x: usize,
+    y: usize,
+    text: String,
+}
+
+pub struct PointLabels {
+    overlay: Overlay,
+    /// The labels in `overlay`, and the scale and target size they were drawn at.
+    uploaded: Option<(Vec<Label>, usize, u32, u32)>,
+}
+
+impl PointLabels {
+    pub fn new(device: &wgpu::Device) -> Self {
+        PointLabels {
+            overlay: Overlay::new(device),
+            uploaded: None,
+        }
+    }
+}
+
+/// The indices of the points of `line` that `buffers` labels, which must label some.
+fn labelled(line: &Line, buffers: &LineBuffers) -> std::iter::StepBy<std::ops::Range<usize>> {
+    let every = buffers.labels;
+    let [first, last] = buffers.label_range.unwrap_or([0, usize::MAX]);
+    let end = last.saturating_add(1).min(line.verticies.len());
+    // The first multiple of `every` in the range
+    let first = first.min(end);
+    let start = first + (every - first % every) % every;
+    return (start.min(end)..end).step_by(every);
+}
+
+impl super::Renderer {
+    /// Label the points of the layers that ask for it, in each of `views`.
+    pub fn render_labels(
+        &mut self,
+        command_encoder: &mut wgpu::CommandEncoder,
+        texture_view: &wgpu::TextureView,
+        views: &[viewport::View],
+        comparison: Option<viewport::Comparison>,
+        lines: &[Line],
+        buffers: &[LineBuffers],
+    ) {
+        if buffers.iter().all(|buffers| buffers.labels == 0) {
+            return;
+        }
+        let scale = overlay::scale(self.sc_desc.height);
+        let views: Vec<&viewport::View> = views.iter().filter(|view| !view.profile).collect();
+        let candidates: usize = buffers
+            .iter()
+            .zip(lines)
+            .filter(|(style, _)| style.labels > 0)
+            .map(|(style, line)| labelled(line, style).len())
+            .sum();
+        let stride = (candidates * views.len()).div_ceil(MAX_PROJECTED).max(1);
+        let mut labels = Vec::new();
+        'views: for view in views {
+            for layer in draw_order(buffers) {
+                let (line, style) = (&lines[layer], &buffers[layer]);
+                if style.labels == 0 {
+                    continue;
+                }
+                let view = match comparison {
+                    Some(comparison) => comparison.clip(view, layer),
+                    None => *view,
+                };
+                let offset: nalgebra::Vector3<f32> = nalgebra::convert(line.origin - self.origin);
+                let view_proj =
+                    view.camera.view_projection() * nalgebra::Matrix4::new_translation(&offset);
+                for index in labelled(line, style).step_by(stride) {
+                    let vertex = &line.verticies[index];
+                    let screen = match svg::project(&view_proj, vertex, &view) {
+                        Some((screen, _)) if view.clip.contains(screen) => screen,
+                        _ => continue,
+                    };
+                    // Right of the point, with room for the outline
+                    let x = screen.x as i64 / scale as i64 + OFFSET;
+                    let y = screen.y as i64 / scale as i64 - GLYPH_HEIGHT as i64 / 2;
+                    if y < 1 {
+                        continue;
+                    }
+                    let text = match &line.ids {
+                        Some(ids) => ids[index].to_string(),
+                        None => index.to_string(),
+                    };
+                    labels.push(Label {
+                        x: x as usize,
+                        y: y as usize,
+                        text: text,
+                    });
+                    if labels.len() == MAX_LABELS {
+                        break 'views;
+                    }
+                }
+            }
+        }
+        if labels.is_empty() {
+            return;
+        }
+
+        let (width, height) = (self.sc_desc.width, self.sc_desc.height);
+        let point_labels = &mut self.point_labels;
+        let key = (labels, scale, width, height);
+        if point_labels.uploaded.as_ref() != Some(&key) {
+            let mut canvas = Canvas::new(
+                width as usize / scale,
+                height as usize / scale,
+                [0, 0, 0, 0],
+            );
+            for label in &key.0 {
+                for (dx, dy) in [(0, 1), (2, 1), (1, 0), (1, 2)] {
+                    canvas.draw_text(label.x + dx - 1, label.y + dy - 1, &label.text, OUTLINE);
+                }
+            }
+            for label in &key.0 {
+                canvas.draw_text(label.x, label.y, &label.text, overlay::WHITE);
+            }
+            point_labels.overlay.upload(
+                &self.device,
+                &self.overlay_pipeline,
+                command_encoder,
+                &canvas.to_image(scale),
+            );
+            point_labels.uploaded = Some(key);
+        }
+        point_labels.overlay.render(
+            &self.device,
+            &self.overlay_pipeline,
+            command_encoder,
+            texture_view,
+            0,
+            0,
+        );
+    }
+}
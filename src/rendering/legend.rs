@@ -0,0 +1,164 @@
+//! A legend for the active colormap, drawn in the bottom right corner of the window and of
+//! screenshots so an image says what its colours mean.
+
+use super::overlay::{self, Canvas, Overlay, ADVANCE, BACKING, GLYPH_HEIGHT, MARGIN, WHITE};
+use super::{capture, linear_to_srgb, srgb_to_linear, AttributeTarget, RenderMode};
+
+const PADDING: usize = 4;
+const BAR_WIDTH: usize = 120;
+const BAR_HEIGHT: usize = 10;
+/// Longest title shown in full, in characters.
+const MAX_TITLE: usize = 32;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Colormap {
+    /// The grey scale the active attribute colours points with.
+    Grey,
+    /// The density heatmap's colours.
+    Viridis,
+}
+
+impl Colormap {
+    /// Linear colour at `t` from 0 to 1 along the colormap, as the shaders compute it.
+    fn color(self, t: f32) -> [f32; 3] {
+        match self {
+            Colormap::Grey => [t, t, t],
+            Colormap::Viridis => {
+                // The viridis fit gives sRGB colours
+                let [red, green, blue] = crate::expression::viridis(t);
+                [
+                    srgb_to_linear(red),
+                    srgb_to_linear(green),
+                    srgb_to_linear(blue),
+                ]
+            }
+        }
+    }
+}
+
+/// What the legend shows: a bar of the colormap labelled with the values at its ends.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Legend {
+    pub title: String,
+    pub range: [f32; 2],
+    pub colormap: Colormap,
+}
+
+impl Legend {
+    /// Draw the legend with each of its pixels `scale` pixels across.
+    pub fn rasterize(&self, scale: usize) -> capture::Image {
+        let title = if self.title.chars().count() > MAX_TITLE {
+            let start: String = self.title.chars().take(MAX_TITLE - 3).collect();
+            format!("{}...", start)
+        } else {
+            self.title.clone()
+        };
+        let min = overlay::format_value(self.range[0]);
+        let max = overlay::format_value(self.range[1]);
+        let inner_width = BAR_WIDTH
+            .max(overlay::text_width(&title))
+            .max(overlay::text_width(&min) + ADVANCE + overlay::text_width(&max));
+        let bar_y = PADDING + GLYPH_HEIGHT + 3;
+        let labels_y = bar_y + BAR_HEIGHT + 3;
+        let (width, height) = (inner_width + 2 * PADDING, labels_y + GLYPH_HEIGHT + PADDING);
+        let mut canvas = Canvas::new(width, height, BACKING);
+
+        canvas.draw_text(PADDING, PADDING, &title, WHITE);
+        let srgb = |linear: f32| (linear_to_srgb(linear) * 255.0).round() as u8;
+        for x in 0..inner_width {
+            let t = x as f32 / (inner_width - 1) as f32;
+            let [red, green, blue] = self.colormap.color(t);
+            for y in bar_y..bar_y + BAR_HEIGHT {
+                canvas.set(PADDING + x, y, [srgb(red), srgb(green), srgb(blue), 255]);
+            }
+        }
+        canvas.draw_text(PADDING, labels_y, &min, WHITE);
+        let max_x = PADDING + inner_width - overlay::text_width(&max);
+        canvas.draw_text(max_x, labels_y, &max, WHITE);
+        return canvas.to_image(scale);
+    }
+}
+
+/// Resources for drawing the legend over the scene.
+pub struct LegendOverlay {
+    /// Whether the legend is drawn while a colormap is active.
+    pub visible: bool,
+    overlay: Overlay,
+    /// The legend in `overlay`, and the scale it was drawn at.
+    uploaded: Option<(Legend, usize)>,
+}
+
+impl LegendOverlay {
+    pub fn new(device: &wgpu::Device) -> Self {
+        LegendOverlay {
+            visible: true,
+            overlay: Overlay::new(device),
+            uploaded: None,
+        }
+    }
+}
+
+impl super::Renderer {
+    /// The legend for the colormap currently applied, if any: the density heatmap's, or the grey
+    /// scale of the attribute points are coloured by.
+    pub fn legend(&self) -> Option<Legend> {
+        if self.mode == RenderMode::Density {
+            return Some(Legend {
+                title: "Points per pixel (log)".to_string(),
+                range: [0.0, self.density.saturation],
+                colormap: Colormap::Viridis,
+            });
+        }
+        if self.attribute_target != AttributeTarget::Color {
+            return None;
+        }
+        let attribute = self.attribute.as_ref()?;
+        let (_, range) = self
+            .attribute_ranges
+            .iter()
+            .find(|(name, range)| name == attribute && range[0] <= range[1])?;
+        return Some(Legend {
+            title: attribute.clone(),
+            range: *range,
+            colormap: Colormap::Grey,
+        });
+    }
+
+    /// Draw the legend in the bottom right corner of `texture_view`, which must be the size of the
+    /// swap chain, if a colormap is active and the legend is visible.
+    pub fn render_legend(
+        &mut self,
+        command_encoder: &mut wgpu::CommandEncoder,
+        texture_view: &wgpu::TextureView,
+    ) {
+        let legend = match self.legend() {
+            Some(legend) if self.legend_overlay.visible => legend,
+            _ => return,
+        };
+        let scale = overlay::scale(self.sc_desc.height);
+        let legend_overlay = &mut self.legend_overlay;
+        if legend_overlay.uploaded != Some((legend.clone(), scale)) {
+            legend_overlay.overlay.upload(
+                &self.device,
+                &self.overlay_pipeline,
+                command_encoder,
+                &legend.rasterize(scale),
+            );
+            legend_overlay.uploaded = Some((legend, scale));
+        }
+        let (width, height) = legend_overlay.overlay.size().unwrap();
+        let margin = (MARGIN * scale) as u32;
+        // Leave it out rather than cover a window too small to show it
+        if width + margin > self.sc_desc.width || height + margin > self.sc_desc.height {
+            return;
+        }
+        legend_overlay.overlay.render(
+            &self.device,
+            &self.overlay_pipeline,
+            command_encoder,
+            texture_view,
+            self.sc_desc.width - width - margin,
+            self.sc_desc.height - height - margin,
+        );
+    }
+}
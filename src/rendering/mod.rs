@@ -1,4 +1,22 @@
+pub mod bounds;
+pub mod capabilities;
+pub mod capture;
 pub mod defaults;
+pub mod density;
+pub mod export;
+pub mod glyphs;
+#[cfg(feature = "hot-reload")]
+pub mod hot_reload;
+pub mod labels;
+pub mod legend;
+pub mod overlay;
+pub mod pipelines;
+pub mod scale_bar;
+pub mod stats;
+pub mod svg;
+pub mod viewport;
+pub mod volume;
+pub mod wireframe;
 
 use include_dir::{include_dir, Dir};
 const GEN_DIR: Dir = include_dir!("gen");
@@ -11,9 +29,576 @@ pub struct Vertex {
     pub size: f32,
 }
 
+#[derive(Clone)]
 pub struct Line {
     pub indicies: Vec<u32>,
     pub verticies: Vec<Vertex>,
+    /// World position of the vertices' local origin. Large coordinates are stored relative to this
+    /// so they keep their precision as f32.
+    pub origin: nalgebra::Vector3<f64>,
+    /// Named scalars per vertex, such as lidar return intensity, indexed like `verticies`.
+    pub attributes: Vec<Attribute>,
+    /// User-defined point IDs, such as track or detection IDs, indexed like `verticies`. Picking
+    /// reports these rather than where the point is in the line when there are any.
+    pub ids: Option<Vec<u32>>,
+    /// Where the line came from, such as its source file and format, as names and values.
+    pub metadata: Vec<(String, String)>,
+}
+
+#[derive(Clone)]
+pub struct Attribute {
+    pub name: String,
+    pub values: Vec<f32>,
+}
+
+/// Maximum number of points stored in a single GPU buffer. Larger lines are split into several
+/// chunks so we stay within buffer size limits. Adapters sharing system memory get smaller chunks,
+/// see `capabilities::Capabilities::chunk_size`.
+const CHUNK_SIZE: usize = 1 << 22;
+
+/// Smallest chunk `Renderer::append` allocates, so a stream of small appends doesn't make a chunk
+/// (and a draw call) per append.
+const APPEND_CHUNK_SIZE: usize = 1 << 16;
+
+impl Line {
+    /// Append the points of `other`, keeping this line's origin. Attributes this line doesn't
+    /// have are dropped, and those `other` lacks are NaN for its points. IDs are only kept if
+    /// both lines have them.
+    pub fn append(&mut self, other: &Line) {
+        let offset: nalgebra::Vector3<f32> = nalgebra::convert(other.origin - self.origin);
+        let start = self.verticies.len() as u32;
+        self.verticies.extend(other.verticies.iter().map(|vertex| {
+            let mut vertex = *vertex;
+            for axis in 0..3 {
+                vertex.position[axis] += offset[axis];
+            }
+            vertex
+        }));
+        self.indicies
+            .extend(other.indicies.iter().map(|&index| start + index));
+        for attribute in self.attributes.iter_mut() {
+            match other.attributes.iter().find(|a| a.name == attribute.name) {
+                Some(values) => attribute.values.extend_from_slice(&values.values),
+                None => attribute.values.resize(self.verticies.len(), f32::NAN),
+            }
+        }
+        match (&mut self.ids, &other.ids) {
+            (Some(ids), Some(other_ids)) => ids.extend_from_slice(other_ids),
+            _ => self.ids = None,
+        }
+    }
+
+    /// Bytes of memory the line's points take up.
+    pub fn memory_size(&self) -> usize {
+        let attributes: usize = self
+            .attributes
+            .iter()
+            .map(|attribute| std::mem::size_of_val(&attribute.values[..]))
+            .sum();
+        let ids = self
+            .ids
+            .as_ref()
+            .map_or(0, |ids| std::mem::size_of_val(&ids[..]));
+        return std::mem::size_of_val(&self.verticies[..])
+            + std::mem::size_of_val(&self.indicies[..])
+            + attributes
+            + ids;
+    }
+
+    /// Drop the vertices, attribute values and IDs, e.g. once they are on the GPU and can be
+    /// loaded from their file again. The draw order is kept, so the line still knows how many
+    /// points it has.
+    pub fn evict(&mut self) {
+        self.verticies = Vec::new();
+        for attribute in self.attributes.iter_mut() {
+            attribute.values = Vec::new();
+        }
+        if self.ids.is_some() {
+            self.ids = Some(Vec::new());
+        }
+    }
+
+    /// Whether `evict` dropped the points, so they must be loaded again before they are read.
+    pub fn is_evicted(&self) -> bool {
+        self.verticies.is_empty() && !self.indicies.is_empty()
+    }
+
+    /// Remove the first `count` points in draw order, along with their vertices.
+    pub fn drain_front(&mut self, count: usize) {
+        let count = count.min(self.indicies.len());
+        self.indicies.drain(..count);
+        self.compact();
+    }
+
+    /// Keep every other point in draw order, starting with the first, and give back the memory
+    /// of the rest.
+    pub fn thin(&mut self) {
+        let mut position = 0;
+        self.indicies.retain(|_| {
+            position += 1;
+            position % 2 == 1
+        });
+        self.compact();
+        self.indicies.shrink_to_fit();
+        self.verticies.shrink_to_fit();
+        for attribute in self.attributes.iter_mut() {
+            attribute.values.shrink_to_fit();
+        }
+        if let Some(ids) = &mut self.ids {
+            ids.shrink_to_fit();
+        }
+    }
+
+    /// Remove the vertices no point refers to.
+    fn compact(&mut self) {
+        let mut keep = vec![false; self.verticies.len()];
+        for &index in &self.indicies {
+            keep[index as usize] = true;
+        }
+        let mut remap = Vec::with_capacity(keep.len());
+        let mut kept = 0;
+        for &k in &keep {
+            remap.push(kept);
+            kept += k as u32;
+        }
+        let mut keep_vertex = keep.iter();
+        self.verticies.retain(|_| *keep_vertex.next().unwrap());
+        for attribute in self.attributes.iter_mut() {
+            let mut keep_value = keep.iter();
+            attribute.values.retain(|_| *keep_value.next().unwrap());
+        }
+        if let Some(ids) = &mut self.ids {
+            let mut keep_id = keep.iter();
+            ids.retain(|_| *keep_id.next().unwrap());
+        }
+        for index in self.indicies.iter_mut() {
+            *index = remap[*index as usize];
+        }
+    }
+}
+
+/// GPU-resident copy of a `Line`, created by `Renderer::upload`.
+pub struct LineBuffers {
+    chunks: Vec<Chunk>,
+    /// Names of the line's attributes, in the order of each chunk's `attribute_buffers`.
+    attributes: Vec<String>,
+    /// Bounding box wireframes, see `wireframe::bounds_vertices`. `None` for an empty line.
+    bounds_buffer: Option<wgpu::Buffer>,
+    pub depth: DepthMode,
+    pub tint: Tint,
+    /// Whether to join the points with lines in draw order, e.g. for GPS tracks.
+    pub path: bool,
+    /// Layers are drawn in increasing priority, and in load order within the same priority.
+    pub priority: i32,
+    pub retention: Retention,
+    /// Hidden layers are left out of drawing and exports, e.g. for screenshots of a subset.
+    pub visible: bool,
+    pub animation: Animation,
+    /// How the stored colours are read, so files with other conventions draw correctly.
+    pub color_range: ColorRange,
+    pub channel_order: ChannelOrder,
+    pub shape: PointShape,
+    /// Clamps on each point's size on screen, in pixels, so distant points don't vanish and near
+    /// ones don't cover the view. A minimum over the maximum wins.
+    pub min_size: f32,
+    pub max_size: f32,
+    /// Label every this many points with their ID or index, 0 for no labels, see `labels`.
+    pub labels: usize,
+    /// The first and last index labelled, `None` for all of them.
+    pub label_range: Option<[usize; 2]>,
+    /// The largest colour channel of any point uploaded, for `ColorRange::Auto`.
+    max_channel: f32,
+    /// Whether the `Line` drops its points once they are uploaded and nothing needs them on the
+    /// CPU, see `Line::evict`. Only layers loaded from a file, and still holding what it held, can
+    /// be evicted, since that's where the points come back from.
+    pub evict: bool,
+    /// When points were last pushed or appended to the layer remotely, if ever, so alerts can
+    /// tell a live layer that stopped receiving points, see `alerts`.
+    pub last_pushed: Option<std::time::Instant>,
+    /// Until when an alert blinks the layer, whatever its own animation.
+    pub flash_until: Option<std::time::Instant>,
+    /// When each upload or append arrived and how many points it brought that are still kept,
+    /// oldest first.
+    arrivals: std::collections::VecDeque<(std::time::Instant, usize)>,
+    /// When any of the points were last drawn on screen rather than culled, see `draw_chunks`.
+    last_drawn: std::cell::Cell<std::time::Instant>,
+    /// What `render` drew of the points since `reset_draws`.
+    draws: std::cell::Cell<stats::LayerDraws>,
+}
+
+impl LineBuffers {
+    /// Number of points currently held.
+    pub fn points(&self) -> usize {
+        self.chunks
+            .iter()
+            .map(|chunk| (chunk.vertex_count - chunk.first) as usize)
+            .sum()
+    }
+
+    /// Bytes of GPU memory allocated for the points, including room left for appends.
+    pub fn memory_size(&self) -> usize {
+        let point_size = std::mem::size_of::<Vertex>() + 4 * self.attributes.len();
+        return self
+            .chunks
+            .iter()
+            .map(|chunk| chunk.capacity as usize * point_size)
+            .sum();
+    }
+
+    /// What the stored colour channels are multiplied by to bring them to 0 to 1.
+    pub fn color_scale(&self) -> f32 {
+        let byte = match self.color_range {
+            ColorRange::Unit => false,
+            ColorRange::Byte => true,
+            ColorRange::Auto => self.max_channel > 1.0,
+        };
+        return if byte { 1.0 / 255.0 } else { 1.0 };
+    }
+
+    /// A point's colour as drawn before its tint, in RGB order from 0 to 1.
+    pub fn read_color(&self, vertex: &Vertex) -> [f32; 3] {
+        let [red, green, blue, _] = vertex.color;
+        let scale = self.color_scale();
+        return match self.channel_order {
+            ChannelOrder::Rgb => [red * scale, green * scale, blue * scale],
+            ChannelOrder::Bgr => [blue * scale, green * scale, red * scale],
+        };
+    }
+
+    /// What was drawn of the points since `reset_draws`, across every view, e.g. to tell whether
+    /// culling and the memory budget leave anything out.
+    pub fn draws(&self) -> stats::LayerDraws {
+        self.draws.get()
+    }
+
+    /// Start counting the draws of a new frame.
+    pub fn reset_draws(&self) {
+        self.draws.set(stats::LayerDraws::default());
+    }
+
+    /// When the layer was last in view, or uploaded if it hasn't been since.
+    pub fn last_drawn(&self) -> std::time::Instant {
+        self.last_drawn.get()
+    }
+
+    /// How many of the oldest points fall outside the retention policy at `now`.
+    pub fn expired(&self, now: std::time::Instant) -> usize {
+        match self.retention {
+            Retention::All => 0,
+            Retention::Points(keep) => self.points().saturating_sub(keep),
+            Retention::Seconds(keep) => self
+                .arrivals
+                .iter()
+                .take_while(|&&(arrived, _)| now.saturating_duration_since(arrived) > keep)
+                .map(|&(_, count)| count)
+                .sum(),
+        }
+    }
+
+    /// The layer's animation, or a blink while an alert flashes it.
+    pub fn current_animation(&self) -> Animation {
+        match self.flash_until {
+            Some(until) if std::time::Instant::now() < until => Animation::Blink,
+            _ => self.animation,
+        }
+    }
+
+    /// When the oldest points will expire, if the layer keeps points for a time.
+    pub fn next_expiry(&self) -> Option<std::time::Instant> {
+        match self.retention {
+            Retention::Seconds(keep) => self.arrivals.front().map(|&(arrived, _)| arrived + keep),
+            _ => None,
+        }
+    }
+}
+
+/// How much of a layer to keep as points are appended to it, so long-running live sessions don't
+/// grow without bound.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Retention {
+    All,
+    /// Only the newest this many points.
+    Points(usize),
+    /// Only the points that arrived within this long.
+    Seconds(std::time::Duration),
+}
+
+impl Retention {
+    /// Parse `all`, a number of points, or a number of seconds followed by `s`, e.g. `30s`.
+    pub fn parse(value: &str) -> Option<Self> {
+        if value == "all" {
+            return Some(Retention::All);
+        }
+        if let Some(seconds) = value.strip_suffix('s') {
+            return seconds
+                .parse::<f64>()
+                .ok()
+                .and_then(crate::input::seconds)
+                .map(Retention::Seconds);
+        }
+        return value.parse::<usize>().ok().map(Retention::Points);
+    }
+}
+
+/// How a layer interacts with the depth buffer, e.g. to keep reference geometry visible inside a
+/// dense cloud.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum DepthMode {
+    Normal,
+    /// Hidden behind earlier layers, but doesn't hide anything drawn after it.
+    NoWrite,
+    /// Drawn over everything, after all the layers that aren't.
+    OnTop,
+}
+
+/// Names used for depth modes on the command line and by the remote API.
+pub const DEPTH_MODE_NAMES: &[(&str, DepthMode)] = &[
+    ("normal", DepthMode::Normal),
+    ("no-write", DepthMode::NoWrite),
+    ("on-top", DepthMode::OnTop),
+];
+
+/// How a layer's own colours are changed when it is drawn.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum TintMode {
+    Off,
+    /// Multiply each point's colour by the tint colour.
+    Multiply,
+    /// Draw every point in the tint colour.
+    Replace,
+}
+
+/// Names used for tint modes by the remote API.
+pub const TINT_MODE_NAMES: &[(&str, TintMode)] = &[
+    ("off", TintMode::Off),
+    ("multiply", TintMode::Multiply),
+    ("replace", TintMode::Replace),
+];
+
+/// The range a layer's stored colour channels are in.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ColorRange {
+    /// 0 to 1, as the renderer expects.
+    Unit,
+    /// 0 to 255, as many scanners write.
+    Byte,
+    /// 0 to 255 if any channel of the layer is over 1, otherwise 0 to 1.
+    Auto,
+}
+
+/// Names used for colour ranges by the remote API.
+pub const COLOR_RANGE_NAMES: &[(&str, ColorRange)] = &[
+    ("unit", ColorRange::Unit),
+    ("byte", ColorRange::Byte),
+    ("auto", ColorRange::Auto),
+];
+
+/// The order a layer's stored colour channels are in.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ChannelOrder {
+    Rgb,
+    Bgr,
+}
+
+/// Names used for channel orders by the remote API.
+pub const CHANNEL_ORDER_NAMES: &[(&str, ChannelOrder)] =
+    &[("rgb", ChannelOrder::Rgb), ("bgr", ChannelOrder::Bgr)];
+
+/// How the colours drawn are turned into what the sRGB swap chain shows.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Grading {
+    /// Whether point colours are sRGB encoded, as colours taken from images and most tools are,
+    /// rather than linear. sRGB colours are decoded to linear before shading and blending.
+    pub srgb_points: bool,
+    /// Stops of exposure, each doubling the brightness of points and glyphs.
+    pub exposure: f32,
+    /// Gamma applied after exposure, where 1 leaves colours as they are and more brightens the
+    /// dark tones.
+    pub gamma: f32,
+}
+
+impl Grading {
+    /// A linear colour with the exposure and gamma applied.
+    pub fn apply(&self, color: [f32; 3]) -> [f32; 3] {
+        let multiplier = 2.0_f32.powf(self.exposure);
+        let mut graded = color;
+        for channel in graded.iter_mut() {
+            *channel = (*channel * multiplier).max(0.0).powf(1.0 / self.gamma);
+        }
+        return graded;
+    }
+}
+
+/// Decode an sRGB encoded channel from 0 to 1 to linear.
+pub fn srgb_to_linear(encoded: f32) -> f32 {
+    if encoded <= 0.040_45 {
+        return encoded / 12.92;
+    }
+    return ((encoded + 0.055) / 1.055).powf(2.4);
+}
+
+/// Encode a linear channel as sRGB, clamped to 0 to 1.
+pub fn linear_to_srgb(linear: f32) -> f32 {
+    let linear = linear.clamp(0.0, 1.0);
+    if linear <= 0.003_130_8 {
+        return linear * 12.92;
+    }
+    return 1.055 * linear.powf(1.0 / 2.4) - 0.055;
+}
+
+/// The shape each point of a layer is drawn as, with anti-aliased edges.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum PointShape {
+    Square,
+    Circle,
+    Ring,
+    Cross,
+}
+
+/// Names used for point shapes by the remote API.
+pub const POINT_SHAPE_NAMES: &[(&str, PointShape)] = &[
+    ("square", PointShape::Square),
+    ("circle", PointShape::Circle),
+    ("ring", PointShape::Ring),
+    ("cross", PointShape::Cross),
+];
+
+/// How much larger points are drawn in the depth pre-pass, so they close the gaps between the
+/// points of a surface.
+const PREPASS_SIZE_SCALE: f32 = 1.5;
+/// How far the depth pre-pass pushes points away from the camera, as a fraction of their
+/// distance, so the points of the front surface itself still pass the depth test.
+const PREPASS_DEPTH_OFFSET: f32 = 0.01;
+
+/// How the points drawn next are shaped and sized, see `update_tint`.
+#[derive(Debug, Copy, Clone)]
+struct PointStyle {
+    shape: PointShape,
+    /// Clamps on each point's size on screen, in pixels.
+    min_size: f32,
+    max_size: f32,
+    /// What the clamped size is multiplied by.
+    size_scale: f32,
+    /// How far points are pushed away from the camera, as a fraction of their distance.
+    depth_offset: f32,
+}
+
+impl PointStyle {
+    /// For what isn't drawn as points, e.g. glyphs and wireframes.
+    fn none() -> Self {
+        PointStyle {
+            shape: PointShape::Circle,
+            min_size: 0.0,
+            max_size: f32::INFINITY,
+            size_scale: 1.0,
+            depth_offset: 0.0,
+        }
+    }
+
+    /// The layer's points as they are drawn.
+    fn of(line: &LineBuffers) -> Self {
+        PointStyle {
+            shape: line.shape,
+            min_size: line.min_size,
+            max_size: line.max_size,
+            size_scale: 1.0,
+            depth_offset: 0.0,
+        }
+    }
+}
+
+/// The colour input settings of what's drawn next, see `update_tint`.
+#[derive(Debug, Copy, Clone)]
+struct ColorInput {
+    /// What the stored colours are multiplied by, see `LineBuffers::color_scale`.
+    scale: f32,
+    order: ChannelOrder,
+    srgb: bool,
+}
+
+impl ColorInput {
+    /// Colours the renderer made itself, already linear RGB from 0 to 1.
+    fn linear() -> Self {
+        ColorInput {
+            scale: 1.0,
+            order: ChannelOrder::Rgb,
+            srgb: false,
+        }
+    }
+}
+
+/// A time-varying effect the point shader applies to a layer, e.g. to draw attention to it.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Animation {
+    Off,
+    /// Points grow and shrink once a second, e.g. for a selection.
+    Pulse,
+    /// The layer fades out for half of every second, e.g. for an alert.
+    Blink,
+    /// Points wobble around their positions, each out of step with the others.
+    Jitter,
+}
+
+/// Names used for animations by the remote API.
+pub const ANIMATION_NAMES: &[(&str, Animation)] = &[
+    ("off", Animation::Off),
+    ("pulse", Animation::Pulse),
+    ("blink", Animation::Blink),
+    ("jitter", Animation::Jitter),
+];
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Tint {
+    pub mode: TintMode,
+    pub color: [f32; 3],
+    /// Opacity of the whole layer, whatever the mode.
+    pub alpha: f32,
+}
+
+impl Tint {
+    pub fn none() -> Self {
+        Tint {
+            mode: TintMode::Off,
+            color: [1.0, 1.0, 1.0],
+            alpha: 1.0,
+        }
+    }
+}
+
+/// A fully saturated colour for layer `index` of `count`, with hues spread evenly around the
+/// colour wheel so every layer is easy to tell apart.
+pub fn distinct_color(index: usize, count: usize) -> [f32; 3] {
+    let hue = 6.0 * index as f32 / count.max(1) as f32;
+    let rising = 1.0 - (hue % 2.0 - 1.0).abs();
+    match hue as u32 {
+        0 => [1.0, rising, 0.0],
+        1 => [rising, 1.0, 0.0],
+        2 => [0.0, 1.0, rising],
+        3 => [0.0, rising, 1.0],
+        4 => [rising, 0.0, 1.0],
+        _ => [1.0, 0.0, rising],
+    }
+}
+
+/// The order to draw the visible `lines` in: by priority, with on-top layers last.
+pub fn draw_order(lines: &[LineBuffers]) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..lines.len()).filter(|&i| lines[i].visible).collect();
+    // Stable, so equal layers keep their load order
+    order.sort_by_key(|&i| (lines[i].depth == DepthMode::OnTop, lines[i].priority));
+    return order;
+}
+
+struct Chunk {
+    vertex_buffer: wgpu::Buffer,
+    attribute_buffers: Vec<wgpu::Buffer>,
+    /// Points before this have been trimmed, see `Renderer::trim`, and aren't drawn.
+    first: u32,
+    vertex_count: u32,
+    /// Points the buffers have room for. Chunks made by `upload` are full, those made by
+    /// `append` leave room for more.
+    capacity: u32,
+    /// Still covers trimmed points until the chunk is emptied and reused.
+    bounds: bounds::Aabb,
 }
 
 
@@ -25,11 +610,50 @@ pub struct Renderer {
     pub sc_desc: wgpu::SwapChainDescriptor,
     pub swap_chain: wgpu::SwapChain,
     pub camera_uniform_buffer: wgpu::Buffer,
-    pub camera: OrbitCamera,
+    attribute_uniform_buffer: wgpu::Buffer,
+    tint_uniform_buffer: wgpu::Buffer,
     pub uniforms_bind_group_layout: wgpu::BindGroupLayout,
-    pub render_pipeline: wgpu::RenderPipeline,
+    pub uniforms_bind_group: wgpu::BindGroup,
+    pub pipeline_layout: wgpu::PipelineLayout,
+    /// Pipelines for points, paths, the depth pre-pass and wireframes, made as they are used.
+    pipelines: pipelines::PipelineCache,
+    /// Whether `render_depth_prepass` draws anything, hiding points that show through surfaces.
+    pub depth_prepass: bool,
+    /// Draw every resident point, without culling chunks by their bounds, which ignore the size
+    /// of points near the edges of the view, or the depth pre-pass. Set while capturing figures
+    /// that must be complete, at the cost of a slower frame.
+    pub exact: bool,
+    pub mode: RenderMode,
+    /// The attribute driving `attribute_target`, if any. Points without it are drawn as usual.
+    pub attribute: Option<String>,
+    pub attribute_target: AttributeTarget,
+    /// Smallest and largest value of each attribute uploaded since `reset_attributes`.
+    attribute_ranges: Vec<(String, [f32; 2])>,
+    /// NaN values bound in place of the active attribute for lines that don't have it.
+    missing_attribute_buffer: wgpu::Buffer,
+    missing_attribute_len: usize,
+    pub density: density::Density,
+    pub legend_overlay: legend::LegendOverlay,
+    pub scale_bar: scale_bar::ScaleBar,
+    point_labels: labels::PointLabels,
+    overlay_pipeline: overlay::OverlayPipeline,
+    glyph_pipeline: wgpu::RenderPipeline,
+    /// Draws the edges of glyphs instead of their faces, for `GlyphBuffers::wireframe`.
+    glyph_wireframe_pipeline: wgpu::RenderPipeline,
+    raymarcher: volume::Raymarcher,
+    pub bounds_overlay: wireframe::BoundsOverlay,
     pub depth_texture: wgpu::Texture,
     pub depth_texture_view: wgpu::TextureView,
+    pub stats: stats::FrameStats,
+    /// World position the scene is drawn relative to. Each line is offset from its own origin to
+    /// this one when it is uploaded.
+    pub origin: nalgebra::Vector3<f64>,
+    /// Whether layer animations play. When off every layer is drawn still, and the event loop
+    /// can go back to waiting for input.
+    pub animate: bool,
+    animation_start: std::time::Instant,
+    pub grading: Grading,
+    pub capabilities: capabilities::Capabilities,
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -42,6 +666,72 @@ pub struct OrbitCamera {
     range: f32,
     azimuth: f32,
     elevation: f32,
+    orthographic: bool,
+    /// Rotation of the view about the viewing direction, in radians.
+    roll: f32,
+    pub up_axis: UpAxis,
+    /// Sideways offset of the eye for stereo rendering, in world units. The camera still looks
+    /// at the target so the two eyes converge there.
+    eye_shift: f32,
+    /// Height in pixels of the viewport the camera is drawn into, used to turn cursor movement
+    /// into world units.
+    viewport_height: f32,
+    /// Fraction of the outstanding movement still left after 1/60th of a second. 0 applies
+    /// movement immediately, values closer to 1 make navigation smoother and glide for longer.
+    pub damping: f32,
+    pending: Motion,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum RenderMode {
+    Points,
+    /// Colour each pixel by how many points cover it, see `density`.
+    Density,
+}
+
+/// Which attachments a render pass clears before drawing.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Clear {
+    Everything,
+    /// Keep the colour but start with fresh depth, e.g. for a view that overlaps an earlier one.
+    Depth,
+    Nothing,
+}
+
+/// Which world axis points up on screen when the camera isn't rolled.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum UpAxis {
+    Y,
+    Z,
+}
+
+impl UpAxis {
+    /// Transform from world space into the Z-up space the camera orbits in.
+    fn to_z_up(self) -> nalgebra::Matrix4<f32> {
+        match self {
+            UpAxis::Z => nalgebra::Matrix4::identity(),
+            // Rotate +Y onto +Z, keeping the coordinate system right handed
+            UpAxis::Y => nalgebra::Matrix4::from_euler_angles(90_f32.to_radians(), 0.0, 0.0),
+        }
+    }
+}
+
+/// Camera movement that has been requested but not applied yet.
+#[derive(Debug, Copy, Clone)]
+struct Motion {
+    orbit: nalgebra::Vector2<f32>,
+    focus: nalgebra::Vector2<f32>,
+    zoom: f32,
+}
+
+impl Motion {
+    fn zero() -> Self {
+        Motion {
+            orbit: nalgebra::Vector2::zeros(),
+            focus: nalgebra::Vector2::zeros(),
+            zoom: 0.0,
+        }
+    }
 }
 
 #[repr(C, align(16))]
@@ -51,6 +741,64 @@ pub struct CameraUniform {
     view_proj: [[f32; 4]; 4],
 }
 
+/// What the active attribute controls.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum AttributeTarget {
+    /// A grey scale across the attribute's range.
+    Color,
+    /// Point size, from a quarter to twice the point's own size across the attribute's range.
+    Size,
+}
+
+impl AttributeTarget {
+    pub fn next(self) -> Self {
+        match self {
+            AttributeTarget::Color => AttributeTarget::Size,
+            AttributeTarget::Size => AttributeTarget::Color,
+        }
+    }
+}
+
+/// How the active attribute is applied, shared by every view.
+#[repr(C, align(16))]
+#[derive(Debug, Copy, Clone)]
+struct AttributeUniform {
+    range: [f32; 2],
+    /// 0 for no attribute, otherwise 1 plus the `AttributeTarget`.
+    target: u32,
+}
+
+/// The `Tint`, `Animation` and `PointShape` of the layer being drawn, and how its colours are
+/// read.
+#[repr(C, align(16))]
+#[derive(Debug, Copy, Clone)]
+struct TintUniform {
+    /// The tint colour, with the layer's alpha.
+    color: [f32; 4],
+    /// The `TintMode`.
+    mode: u32,
+    /// The `Animation`.
+    animation: u32,
+    /// Seconds since the renderer started, driving the animation.
+    time: f32,
+    /// What the point colours are multiplied by, see `LineBuffers::color_scale`.
+    color_scale: f32,
+    /// The `ChannelOrder` of the point colours.
+    channel_order: u32,
+    /// 1 if the point colours are sRGB encoded.
+    srgb: u32,
+    /// What colours are multiplied by, from `Grading::exposure`.
+    exposure: f32,
+    gamma: f32,
+    /// The `PointShape`.
+    shape: u32,
+    /// Clamps on the point size on screen, in pixels.
+    min_size: f32,
+    max_size: f32,
+    size_scale: f32,
+    depth_offset: f32,
+}
+
 pub trait Camera {
     fn generate_uniform(&self) -> CameraUniform;
 }
@@ -70,43 +818,232 @@ impl OrbitCamera {
             // ... from a 45/45 degree perspective
             azimuth: 45.0_f32.to_radians(),
             elevation: 45.0_f32.to_radians(),
+            orthographic: false,
+            roll: 0.0,
+            up_axis: UpAxis::Z,
+            eye_shift: 0.0,
+            viewport_height: 1.0,
+            damping: 0.0,
+            pending: Motion::zero(),
         }
     }
 
-    pub fn set_aspect(&mut self, aspect: f32) {
-        self.aspect = aspect;
+    /// An orthographic camera looking at the origin from a fixed direction, e.g. a top view.
+    pub fn orthographic(aspect: f32, azimuth: f32, elevation: f32) -> Self {
+        OrbitCamera {
+            azimuth: azimuth.to_radians(),
+            elevation: elevation.to_radians(),
+            orthographic: true,
+            ..OrbitCamera::default(aspect)
+        }
+    }
+
+    pub fn set_viewport_size(&mut self, width: u32, height: u32) {
+        let height = height.max(1);
+        self.aspect = width as f32 / height as f32;
+        self.viewport_height = height as f32;
     }
 
     pub fn move_longitudinally(&mut self, delta: f32) {
-        self.range = self.range * (0.75_f32).powf(delta);
+        if self.damping > 0.0 {
+            self.pending.zoom += delta;
+        } else {
+            self.apply_zoom(delta);
+        }
     }
 
     pub fn move_on_orbit(&mut self, delta: nalgebra::Vector2<f32>) {
+        if self.damping > 0.0 {
+            self.pending.orbit += delta;
+        } else {
+            self.apply_orbit(delta);
+        }
+    }
+
+    pub fn set_target(&mut self, target: nalgebra::Point3<f32>) {
+        self.target = target;
+    }
+
+    pub fn set_range(&mut self, range: f32) {
+        self.range = range;
+    }
+
+    /// In radians.
+    pub fn set_azimuth(&mut self, azimuth: f32) {
+        self.azimuth = azimuth;
+    }
+
+    /// In radians, clamped to between straight down and straight up.
+    pub fn set_elevation(&mut self, elevation: f32) {
+        self.elevation = clamp_elevation(elevation);
+    }
+
+    /// Look at the middle of `aabb`, given in world space, from far enough away to see all of it.
+    pub fn frame(&mut self, aabb: &bounds::Aabb) {
+        let center = nalgebra::center(&aabb.min, &aabb.max);
+        self.target =
+            nalgebra::Point3::from_homogeneous(self.up_axis.to_z_up() * center.to_homogeneous())
+                .unwrap();
+        // Fit the bounding sphere into the narrower of the view's height and width
+        let radius = nalgebra::distance(&aabb.min, &aabb.max) / 2.0;
+        let half_extent = (self.fovy / 2.0).tan().abs() * self.aspect.min(1.0);
+        self.range = (radius / half_extent).max(self.znear * 2.0);
+    }
+
+    /// Look from `eye` along `forward` with `up` towards the top of the screen, all in scene
+    /// space, through a vertical field of view of `fovy` radians, e.g. to see what a photo's
+    /// camera saw. The target is put at the current range ahead.
+    pub fn look_from(
+        &mut self,
+        eye: nalgebra::Point3<f32>,
+        forward: nalgebra::Vector3<f32>,
+        up: nalgebra::Vector3<f32>,
+        fovy: f32,
+    ) {
+        let to_z_up = self.up_axis.to_z_up();
+        let eye = to_z_up.transform_point(&eye);
+        let forward = to_z_up.transform_vector(&forward).normalize();
+        let up = to_z_up.transform_vector(&up);
+        self.target = eye + forward * self.range;
+        // The eye is at the azimuth and elevation of the reverse of the view direction
+        self.elevation = (-forward.z).clamp(-1.0, 1.0).asin();
+        self.azimuth = (-forward.y).atan2(-forward.x);
+        self.fovy = fovy;
+        self.eye_shift = 0.0;
+        self.pending = Motion::zero();
+        // The roll turning the unrolled screen axes to the requested up
+        self.roll = 0.0;
+        let (right, unrolled_up) = self.screen_axes();
+        self.roll = up.dot(&right).atan2(up.dot(&unrolled_up));
+    }
+
+    pub fn roll(&mut self, delta: f32) {
+        self.roll = (self.roll + delta) % 360_f32.to_radians();
+    }
+
+    /// Pan by `delta` pixels so the point at the target distance follows the cursor.
+    pub fn move_focus(&mut self, delta: nalgebra::Vector2<f32>) {
+        let delta = delta * self.pixel_size();
+        if self.damping > 0.0 {
+            self.pending.focus += delta;
+        } else {
+            self.apply_focus(delta);
+        }
+    }
+
+    /// Advance smoothed movement by `dt`. Returns whether the camera moved.
+    pub fn step(&mut self, dt: std::time::Duration) -> bool {
+        // Below this the remaining movement isn't visible, so stop rather than decay forever
+        const EPSILON: f32 = 1e-3;
+        let pending = self.pending;
+        if pending.orbit.norm() < EPSILON
+            && pending.focus.norm() < EPSILON
+            && pending.zoom.abs() < EPSILON
+        {
+            self.pending = Motion::zero();
+            return false;
+        }
+        // Frame rate independent exponential decay
+        let fraction = 1.0 - self.damping.powf(dt.as_secs_f32() * 60.0);
+        self.apply_orbit(pending.orbit * fraction);
+        self.apply_focus(pending.focus * fraction);
+        self.apply_zoom(pending.zoom * fraction);
+        self.pending.orbit -= pending.orbit * fraction;
+        self.pending.focus -= pending.focus * fraction;
+        self.pending.zoom -= pending.zoom * fraction;
+        return true;
+    }
+
+    fn apply_zoom(&mut self, delta: f32) {
+        self.range = self.range * (0.75_f32).powf(delta);
+    }
+
+    fn apply_orbit(&mut self, delta: nalgebra::Vector2<f32>) {
         self.azimuth -= delta[0] * 0.01;
         self.elevation += delta[1] * 0.01;
 
-        // Clamp elevation
-        // TODO: Use this: https://github.com/rust-lang/rust/issues/44095
-        self.elevation = match self.elevation {
-            d if d >= 90_f32.to_radians() => 90_f32.to_radians(),
-            d if d <= -270_f32.to_radians() => -270_f32.to_radians(),
-            _ => self.elevation,
-        };
+        self.elevation = clamp_elevation(self.elevation);
 
         // Wrap azimuth
         self.azimuth = self.azimuth % 360_f32.to_radians();
     }
 
-    pub fn move_focus(&mut self, delta: nalgebra::Vector2<f32>) {
-        #[rustfmt::skip]
-        let transform = nalgebra::Matrix3x2::new(
-            -self.azimuth.sin(),  self.azimuth.cos(),
-            self.azimuth.cos(), self.azimuth.sin(),
-            0.0,                 0.0
-        );
-        let world_space_delta = transform * delta * 0.1;
-        self.target -= world_space_delta;
+    /// Move the target within the view plane by `delta` world units.
+    fn apply_focus(&mut self, delta: nalgebra::Vector2<f32>) {
+        let (right, up) = self.screen_axes();
+        // Screen y points down
+        self.target -= right * delta.x - up * delta.y;
+    }
+
+    /// A copy of this camera for one eye of a stereo pair, `shift` world units to the right.
+    pub fn with_eye_shift(mut self, shift: f32) -> Self {
+        self.eye_shift = shift;
+        return self;
+    }
+
+    /// Distance from the camera to its target.
+    pub fn range(&self) -> f32 {
+        self.range
+    }
+
+    /// World units a pixel spans at the target distance, or anywhere in an orthographic view.
+    pub fn pixel_size(&self) -> f32 {
+        // Height of the view at the target distance, which is also the orthographic extent
+        let view_height = 2.0 * self.range * (self.fovy / 2.0).tan().abs();
+        view_height / self.viewport_height
+    }
+
+    /// How far the world direction `direction` runs towards the right and the top of the screen,
+    /// ignoring perspective.
+    pub fn screen_direction(&self, direction: nalgebra::Vector3<f32>) -> nalgebra::Vector2<f32> {
+        let direction = self.up_axis.to_z_up().transform_vector(&direction);
+        let (right, up) = self.screen_axes();
+        nalgebra::Vector2::new(direction.dot(&right), direction.dot(&up))
     }
+
+    /// The point orbited, in the Z-up orbit space `set_target` takes.
+    pub fn target(&self) -> nalgebra::Point3<f32> {
+        self.target
+    }
+
+    /// In radians.
+    pub fn azimuth(&self) -> f32 {
+        self.azimuth
+    }
+
+    /// In radians.
+    pub fn elevation(&self) -> f32 {
+        self.elevation
+    }
+
+    /// World directions of the screen's right and up axes, in the Z-up orbit space.
+    fn screen_axes(&self) -> (nalgebra::Vector3<f32>, nalgebra::Vector3<f32>) {
+        let (sin_az, cos_az) = self.azimuth.sin_cos();
+        let (sin_el, cos_el) = self.elevation.sin_cos();
+        let right = nalgebra::Vector3::new(-sin_az, cos_az, 0.0);
+        let up = nalgebra::Vector3::new(-sin_el * cos_az, -sin_el * sin_az, cos_el);
+        // Rotate both by the roll
+        let (sin_roll, cos_roll) = self.roll.sin_cos();
+        (
+            right * cos_roll - up * sin_roll,
+            up * cos_roll + right * sin_roll,
+        )
+    }
+}
+
+/// Clamp an elevation in radians to between straight down and straight up, so orbiting stops at
+/// exactly the top and bottom views rather than turning the view upside down past them.
+fn clamp_elevation(elevation: f32) -> f32 {
+    let limit = 90_f32.to_radians();
+    return elevation.max(-limit).min(limit);
+}
+
+/// The largest colour channel of `vertices`, 0 if there are none.
+fn max_channel(vertices: &[Vertex]) -> f32 {
+    return vertices
+        .iter()
+        .flat_map(|vertex| vertex.color[..3].iter())
+        .fold(0.0, |max: f32, &channel| max.max(channel));
 }
 
 fn cartesian_from_polar<T: nalgebra::base::Scalar + num_traits::real::Real>(
@@ -121,15 +1058,46 @@ fn cartesian_from_polar<T: nalgebra::base::Scalar + num_traits::real::Real>(
     return nalgebra::Vector3::<T>::new(x, y, z);
 }
 
-impl Camera for OrbitCamera {
-    fn generate_uniform(&self) -> CameraUniform {
+impl OrbitCamera {
+    /// Camera position in the Z-up orbit space.
+    fn eye(&self) -> nalgebra::Point3<f32> {
+        let (right, _) = self.screen_axes();
+        self.target
+            + cartesian_from_polar(self.range, self.azimuth, self.elevation)
+            + right * self.eye_shift
+    }
+
+    /// Camera position in world space.
+    fn world_eye(&self) -> nalgebra::Point3<f32> {
+        let to_world = self.up_axis.to_z_up().transpose();
+        nalgebra::Point3::from_homogeneous(to_world * self.eye().to_homogeneous()).unwrap()
+    }
+
+    pub fn view_projection(&self) -> nalgebra::Matrix4<f32> {
         let delta = 0.01;
-        let eye = self.target + cartesian_from_polar(self.range, self.azimuth, self.elevation);
-        let up = self.target
-            + cartesian_from_polar(self.range, self.azimuth, self.elevation + delta)
-            - eye;
+        let eye = self.eye();
+        let up = cartesian_from_polar(self.range, self.azimuth, self.elevation + delta)
+            - cartesian_from_polar(self.range, self.azimuth, self.elevation);
+        let forward = nalgebra::Unit::new_normalize(self.target - eye);
+        let up = nalgebra::Rotation3::from_axis_angle(&forward, self.roll) * up;
         let view = nalgebra::Isometry3::look_at_rh(&eye, &self.target, &up);
-        let projection = nalgebra::Perspective3::new(self.aspect, self.fovy, self.znear, self.zfar);
+        let projection = if self.orthographic {
+            // Match the extent the perspective projection has at the target distance
+            let half_height = self.range * (self.fovy / 2.0).tan();
+            let half_width = half_height * self.aspect;
+            nalgebra::Orthographic3::new(
+                -half_width,
+                half_width,
+                -half_height,
+                half_height,
+                self.znear,
+                self.zfar,
+            )
+            .to_homogeneous()
+        } else {
+            nalgebra::Perspective3::new(self.aspect, self.fovy, self.znear, self.zfar)
+                .to_homogeneous()
+        };
 
         // https://matthewwellings.com/blog/the-new-vulkan-coordinate-system/
         // TODO: Check if this means that every platform will need a different transform matrix?
@@ -141,138 +1109,152 @@ impl Camera for OrbitCamera {
             0.0,   0.0, 0.5, 1.0,
         );
 
+        opengl_to_wgpu_matrix * projection * view.to_homogeneous() * self.up_axis.to_z_up()
+    }
+}
+
+impl Camera for OrbitCamera {
+    fn generate_uniform(&self) -> CameraUniform {
         CameraUniform {
-            camera_pos: *eye.to_homogeneous().as_ref(),
-            view_proj: *(opengl_to_wgpu_matrix * projection.as_matrix() * view.to_homogeneous())
-                .as_ref(),
+            camera_pos: *self.world_eye().to_homogeneous().as_ref(),
+            view_proj: *self.view_projection().as_ref(),
         }
     }
 }
 
 impl Renderer {
-    pub fn new(surface: wgpu::Surface, size: winit::dpi::PhysicalSize<u32>) -> Self {
-        let adapter = futures::executor::block_on(wgpu::Adapter::request(
-            &wgpu::RequestAdapterOptions {
-                // TODO: Make this configurable
-                power_preference: wgpu::PowerPreference::Default,
-                compatible_surface: Some(&surface),
-            },
-            wgpu::BackendBit::PRIMARY,
-        ))
-        .unwrap();
+    pub fn new(
+        surface: wgpu::Surface,
+        size: winit::dpi::PhysicalSize<u32>,
+        options: &crate::options::Options,
+    ) -> Result<Self, crate::error::RscatError> {
+        let (adapter, fallback) = select_adapter(&surface, options).ok_or_else(|| {
+            crate::error::RscatError::Gpu("No adapter can draw to the window".to_string())
+        })?;
+        let adapter_info = adapter.get_info();
+        info!(
+            "Using adapter '{}' ({:?}, {:?})",
+            adapter_info.name, adapter_info.device_type, adapter_info.backend
+        );
+        if let Some(fallback) = &fallback {
+            warn!("Fell back to {}", fallback);
+        }
+        let capabilities = capabilities::Capabilities::negotiate(adapter_info, fallback);
+        info!("GPU capabilities:\n{}", capabilities.report());
 
         let (device, queue) =
-            futures::executor::block_on(adapter.request_device(&wgpu::DeviceDescriptor {
-                extensions: wgpu::Extensions {
-                    anisotropic_filtering: true,
-                },
-                limits: wgpu::Limits::default(),
-            }));
+            futures::executor::block_on(adapter.request_device(&capabilities.device_descriptor()));
 
         let sc_desc = wgpu::SwapChainDescriptor {
             usage: wgpu::TextureUsage::OUTPUT_ATTACHMENT,
             format: wgpu::TextureFormat::Bgra8UnormSrgb,
             width: size.width,
             height: size.height,
-            present_mode: wgpu::PresentMode::Mailbox,
+            present_mode: options.present_mode,
         };
 
         let swap_chain = device.create_swap_chain(&surface, &sc_desc);
 
-        let camera = OrbitCamera::default(size.width as f32 / size.height as f32);
+        // Written by `update_camera` before each view is drawn
+        let camera_uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: std::mem::size_of::<CameraUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+        });
+
+        // Written by `update_attributes` before each frame
+        let attribute_uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: std::mem::size_of::<AttributeUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+        });
 
-        let camera_uniform_buffer = device.create_buffer_with_data(
-            u8_slice_from_slice(std::slice::from_ref(&camera.generate_uniform())),
-            wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
-        );
+        // Written by `update_tint` before each layer is drawn
+        let tint_uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: std::mem::size_of::<TintUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+        });
 
         let uniforms_bind_group_layout =
             device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                bindings: &[wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: wgpu::ShaderStage::VERTEX,
-                    ty: wgpu::BindingType::UniformBuffer { dynamic: false },
-                }],
+                bindings: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStage::VERTEX,
+                        ty: wgpu::BindingType::UniformBuffer { dynamic: false },
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStage::VERTEX,
+                        ty: wgpu::BindingType::UniformBuffer { dynamic: false },
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: wgpu::ShaderStage::VERTEX,
+                        ty: wgpu::BindingType::UniformBuffer { dynamic: false },
+                    },
+                ],
                 label: None,
             });
 
+        let uniforms_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &uniforms_bind_group_layout,
+            bindings: &[
+                wgpu::Binding {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Buffer {
+                        buffer: &camera_uniform_buffer,
+                        range: 0..std::mem::size_of::<CameraUniform>() as wgpu::BufferAddress,
+                    },
+                },
+                wgpu::Binding {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Buffer {
+                        buffer: &attribute_uniform_buffer,
+                        range: 0..std::mem::size_of::<AttributeUniform>() as wgpu::BufferAddress,
+                    },
+                },
+                wgpu::Binding {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Buffer {
+                        buffer: &tint_uniform_buffer,
+                        range: 0..std::mem::size_of::<TintUniform>() as wgpu::BufferAddress,
+                    },
+                },
+            ],
+            label: None,
+        });
+
         let vs_bytes = GEN_DIR
             .get_file("shaders/shader.vert.spv")
             .unwrap()
             .contents();
-        let vs_module = device
-            .create_shader_module(&wgpu::read_spirv(std::io::Cursor::new(&vs_bytes[..])).unwrap());
-
         let fs_bytes = GEN_DIR
             .get_file("shaders/shader.frag.spv")
             .unwrap()
             .contents();
-        let fs_module = device
-            .create_shader_module(&wgpu::read_spirv(std::io::Cursor::new(&fs_bytes[..])).unwrap());
         let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
             bind_group_layouts: &[&uniforms_bind_group_layout],
         });
-        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            layout: &pipeline_layout,
-            vertex_stage: wgpu::ProgrammableStageDescriptor {
-                module: &vs_module,
-                entry_point: "main",
-            },
-            fragment_stage: Some(wgpu::ProgrammableStageDescriptor {
-                module: &fs_module,
-                entry_point: "main",
-            }),
-            rasterization_state: Some(wgpu::RasterizationStateDescriptor {
-                front_face: wgpu::FrontFace::Ccw,
-                cull_mode: wgpu::CullMode::None,
-                depth_bias: 0,
-                depth_bias_slope_scale: 0.0,
-                depth_bias_clamp: 0.0,
-            }),
-            primitive_topology: wgpu::PrimitiveTopology::PointList,
-            color_states: &[wgpu::ColorStateDescriptor {
-                format: wgpu::TextureFormat::Bgra8UnormSrgb,
-                color_blend: wgpu::BlendDescriptor::REPLACE,
-                alpha_blend: wgpu::BlendDescriptor::REPLACE,
-                write_mask: wgpu::ColorWrite::ALL,
-            }],
-            depth_stencil_state: Some(wgpu::DepthStencilStateDescriptor {
-                format: wgpu::TextureFormat::Depth32Float,
-                depth_write_enabled: true,
-                depth_compare: wgpu::CompareFunction::Less,
-                stencil_front: wgpu::StencilStateFaceDescriptor::IGNORE,
-                stencil_back: wgpu::StencilStateFaceDescriptor::IGNORE,
-                stencil_read_mask: 0,
-                stencil_write_mask: 0,
-            }),
-            vertex_state: wgpu::VertexStateDescriptor {
-                index_format: wgpu::IndexFormat::Uint32,
-                vertex_buffers: &[wgpu::VertexBufferDescriptor {
-                    stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
-                    step_mode: wgpu::InputStepMode::Vertex,
-                    attributes: &[
-                        wgpu::VertexAttributeDescriptor {
-                            format: wgpu::VertexFormat::Float4,
-                            offset: memoffset::offset_of!(Vertex, position) as wgpu::BufferAddress,
-                            shader_location: 0,
-                        },
-                        wgpu::VertexAttributeDescriptor {
-                            format: wgpu::VertexFormat::Float4,
-                            offset: memoffset::offset_of!(Vertex, color) as wgpu::BufferAddress,
-                            shader_location: 1,
-                        },
-                        wgpu::VertexAttributeDescriptor {
-                            format: wgpu::VertexFormat::Float4,
-                            offset: memoffset::offset_of!(Vertex, size) as wgpu::BufferAddress,
-                            shader_location: 2,
-                        },
-                    ],
-                }],
-            },
-            sample_count: 1,
-            sample_mask: !0,
-            alpha_to_coverage_enabled: false,
-        });
+        let pipelines = pipelines::PipelineCache::new(sc_desc.format, vs_bytes, fs_bytes);
+        let density = density::Density::new(&device, &pipeline_layout, vs_bytes, size);
+        let legend_overlay = legend::LegendOverlay::new(&device);
+        let overlay_pipeline = overlay::OverlayPipeline::new(&device);
+        let point_labels = labels::PointLabels::new(&device);
+        // Geographic input is converted to metres east, north and up
+        let unit = match options.input_frame {
+            crate::geo::InputFrame::Cartesian => "",
+            _ => "m",
+        };
+        let glyph_pipeline = glyphs::create_pipeline(
+            &device,
+            &pipeline_layout,
+            wgpu::PrimitiveTopology::TriangleList,
+        );
+        let glyph_wireframe_pipeline =
+            glyphs::create_pipeline(&device, &pipeline_layout, wgpu::PrimitiveTopology::LineList);
+        let raymarcher = volume::Raymarcher::new(&device, &uniforms_bind_group_layout);
 
         let depth_texture = device.create_texture(&wgpu::TextureDescriptor {
             format: wgpu::TextureFormat::Depth32Float,
@@ -290,29 +1272,74 @@ impl Renderer {
         });
 
         let depth_texture_view = depth_texture.create_default_view();
+        // Grown by `upload` to fit the largest chunk
+        let missing_attribute_buffer = device
+            .create_buffer_with_data(u8_slice_from_slice(&[f32::NAN]), wgpu::BufferUsage::VERTEX);
 
-        Self {
+        Ok(Self {
             surface: surface,
             adapter: adapter,
             device: device,
             queue: queue,
             sc_desc: sc_desc,
             swap_chain: swap_chain,
-            camera: camera,
             camera_uniform_buffer: camera_uniform_buffer,
+            attribute_uniform_buffer: attribute_uniform_buffer,
+            tint_uniform_buffer: tint_uniform_buffer,
             uniforms_bind_group_layout: uniforms_bind_group_layout,
-            render_pipeline: render_pipeline,
+            uniforms_bind_group: uniforms_bind_group,
+            pipeline_layout: pipeline_layout,
+            pipelines: pipelines,
+            depth_prepass: options.depth_prepass,
+            exact: false,
+            mode: RenderMode::Points,
+            attribute: None,
+            attribute_target: AttributeTarget::Color,
+            attribute_ranges: Vec::new(),
+            missing_attribute_buffer: missing_attribute_buffer,
+            missing_attribute_len: 1,
+            density: density,
+            legend_overlay: legend_overlay,
+            scale_bar: scale_bar::ScaleBar::new(unit),
+            point_labels: point_labels,
+            overlay_pipeline: overlay_pipeline,
+            glyph_pipeline: glyph_pipeline,
+            glyph_wireframe_pipeline: glyph_wireframe_pipeline,
+            raymarcher: raymarcher,
+            bounds_overlay: wireframe::BoundsOverlay::Off,
             depth_texture: depth_texture,
             depth_texture_view: depth_texture_view,
-        }
+            stats: stats::FrameStats::new(),
+            origin: nalgebra::Vector3::zeros(),
+            animate: true,
+            animation_start: std::time::Instant::now(),
+            grading: Grading {
+                srgb_points: !options.linear_colors,
+                exposure: options.exposure,
+                gamma: options.gamma,
+            },
+            capabilities: capabilities,
+        })
+    }
+
+    /// Whether any of `lines` is animated, so the view needs redrawing continuously.
+    pub fn animating(&self, lines: &[LineBuffers]) -> bool {
+        self.animate
+            && lines
+                .iter()
+                .any(|line| line.visible && line.current_animation() != Animation::Off)
     }
 
     pub fn resize(&mut self, size: winit::dpi::PhysicalSize<u32>) {
+        // A minimised window reports a zero size, which isn't a valid swap chain. Keep the old one
+        // until we are restored.
+        if size.width == 0 || size.height == 0 {
+            return;
+        }
         self.sc_desc.width = size.width;
         self.sc_desc.height = size.height;
-        self.camera
-            .set_aspect(size.width as f32 / size.height as f32);
         self.swap_chain = self.device.create_swap_chain(&self.surface, &self.sc_desc);
+        self.pipelines.set_format(self.sc_desc.format);
         self.depth_texture = self.device.create_texture(&wgpu::TextureDescriptor {
             format: wgpu::TextureFormat::Depth32Float,
             usage: wgpu::TextureUsage::OUTPUT_ATTACHMENT,
@@ -329,68 +1356,887 @@ impl Renderer {
         });
 
         self.depth_texture_view = self.depth_texture.create_default_view();
+        self.density.resize(&self.device, size);
     }
 
-    pub fn render(
-        &self,
+    /// Acquire the next swap chain texture, recreating the swap chain once if it has timed out or
+    /// become outdated (e.g. mid-resize or after the driver reset it).
+    pub fn next_frame(&mut self) -> Option<wgpu::SwapChainOutput> {
+        match self.swap_chain.get_next_texture() {
+            Ok(frame) => return Some(frame),
+            Err(wgpu::TimeOut) => {
+                warn!("Timed out acquiring the next swap chain texture, recreating the swap chain")
+            }
+        }
+        self.swap_chain = self.device.create_swap_chain(&self.surface, &self.sc_desc);
+        self.swap_chain.get_next_texture().ok()
+    }
+
+    /// Recompile the shaders from source, rebuilding the pipelines that use them as they are next
+    /// drawn with. On a compile error the current shaders are kept so a typo doesn't take the
+    /// viewer down.
+    #[cfg(feature = "hot-reload")]
+    pub fn reload_shaders(&mut self, shader_dir: &std::path::Path) {
+        let compiled = hot_reload::compile_shader(
+            &shader_dir.join("shader.vert"),
+            glsl_to_spirv::ShaderType::Vertex,
+        )
+        .and_then(|vs_bytes| {
+            hot_reload::compile_shader(
+                &shader_dir.join("shader.frag"),
+                glsl_to_spirv::ShaderType::Fragment,
+            )
+            .map(|fs_bytes| (vs_bytes, fs_bytes))
+        });
+        match compiled {
+            Ok((vs_bytes, fs_bytes)) => {
+                self.pipelines.set_shaders(vs_bytes, fs_bytes);
+                info!("Reloaded shaders from {}", shader_dir.display());
+            }
+            Err(e) => error!("Failed to reload shaders, keeping the previous ones: {}", e),
+        }
+    }
+
+    /// Copy a line into GPU buffers so it can be drawn without re-uploading every frame.
+    pub fn upload(&mut self, line: &Line) -> LineBuffers {
+        // Points are independent, so a line that draws a subset or reordering of its vertices can
+        // be flattened into draw order. That way no chunk ever needs an index buffer.
+        let sequential = line
+            .indicies
+            .iter()
+            .enumerate()
+            .all(|(i, &index)| index as usize == i);
+        // The difference between the origins is small even when both are large
+        let offset: nalgebra::Vector3<f32> = nalgebra::convert(line.origin - self.origin);
+        let gathered: Vec<Vertex>;
+        let vertices = if sequential && offset == nalgebra::Vector3::zeros() {
+            &line.verticies[..line.indicies.len()]
+        } else {
+            gathered = line
+                .indicies
+                .iter()
+                .map(|&i| {
+                    let mut vertex = line.verticies[i as usize];
+                    for axis in 0..3 {
+                        vertex.position[axis] += offset[axis];
+                    }
+                    vertex
+                })
+                .collect();
+            gathered.as_slice()
+        };
+        // Attributes in draw order, like the vertices
+        let attributes: Vec<Vec<f32>> = line
+            .attributes
+            .iter()
+            .map(|attribute| {
+                line.indicies
+                    .iter()
+                    .map(|&i| attribute.values[i as usize])
+                    .collect()
+            })
+            .collect();
+        for (attribute, values) in line.attributes.iter().zip(attributes.iter()) {
+            self.widen_attribute_range(&attribute.name, values);
+        }
+
+        let mut chunks = Vec::new();
+        // Writable so the chunks can be reused by `append` once trimmed
+        let usage = wgpu::BufferUsage::VERTEX | wgpu::BufferUsage::COPY_DST;
+        let chunk_size = self.capabilities.chunk_size;
+        for (i, chunk_vertices) in vertices.chunks(chunk_size).enumerate() {
+            let vertex_buffer = self
+                .device
+                .create_buffer_with_data(u8_slice_from_slice(chunk_vertices), usage);
+            self.stats
+                .record_upload(std::mem::size_of_val(chunk_vertices));
+            let mut attribute_buffers = Vec::new();
+            for values in &attributes {
+                let chunk_values = &values[i * chunk_size..i * chunk_size + chunk_vertices.len()];
+                attribute_buffers.push(
+                    self.device
+                        .create_buffer_with_data(u8_slice_from_slice(chunk_values), usage),
+                );
+                self.stats
+                    .record_upload(std::mem::size_of_val(chunk_values));
+            }
+            self.reserve_missing_attribute(chunk_vertices.len());
+            chunks.push(Chunk {
+                vertex_buffer: vertex_buffer,
+                attribute_buffers: attribute_buffers,
+                first: 0,
+                vertex_count: chunk_vertices.len() as u32,
+                capacity: chunk_vertices.len() as u32,
+                bounds: bounds::Aabb::from_vertices(chunk_vertices).unwrap(),
+            });
+        }
+        let bounds_buffer = self.create_bounds_buffer(&chunks);
+        LineBuffers {
+            chunks: chunks,
+            bounds_buffer: bounds_buffer,
+            depth: DepthMode::Normal,
+            tint: Tint::none(),
+            path: false,
+            priority: 0,
+            retention: Retention::All,
+            visible: true,
+            animation: Animation::Off,
+            color_range: ColorRange::Unit,
+            channel_order: ChannelOrder::Rgb,
+            shape: PointShape::Circle,
+            min_size: 1.0,
+            max_size: f32::INFINITY,
+            labels: 0,
+            label_range: None,
+            max_channel: max_channel(vertices),
+            evict: false,
+            last_pushed: None,
+            flash_until: None,
+            arrivals: std::iter::once((std::time::Instant::now(), vertices.len())).collect(),
+            last_drawn: std::cell::Cell::new(std::time::Instant::now()),
+            draws: std::cell::Cell::new(stats::LayerDraws::default()),
+            attributes: line
+                .attributes
+                .iter()
+                .map(|attribute| attribute.name.clone())
+                .collect(),
+        }
+    }
+
+    /// Upload the points of `line` from position `from` in draw order onwards into `buffers`,
+    /// which must already hold the points before it. New points go into the spare room of the
+    /// last chunk before any new chunk is made, so streaming into a layer only uploads what's new.
+    pub fn append(&mut self, buffers: &mut LineBuffers, line: &Line, from: usize) {
+        let offset: nalgebra::Vector3<f32> = nalgebra::convert(line.origin - self.origin);
+        let vertices: Vec<Vertex> = line.indicies[from..]
+            .iter()
+            .map(|&i| {
+                let mut vertex = line.verticies[i as usize];
+                for axis in 0..3 {
+                    vertex.position[axis] += offset[axis];
+                }
+                vertex
+            })
+            .collect();
+        let attributes: Vec<Vec<f32>> = buffers
+            .attributes
+            .iter()
+            .map(
+                |name| match line.attributes.iter().find(|a| &a.name == name) {
+                    Some(attribute) => line.indicies[from..]
+                        .iter()
+                        .map(|&i| attribute.values[i as usize])
+                        .collect(),
+                    None => vec![f32::NAN; vertices.len()],
+                },
+            )
+            .collect();
+        for (name, values) in buffers.attributes.iter().zip(attributes.iter()) {
+            self.widen_attribute_range(name, values);
+        }
+
+        let mut command_encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        let mut start = 0;
+        while start < vertices.len() {
+            let room = buffers
+                .chunks
+                .last()
+                .map_or(0, |chunk| (chunk.capacity - chunk.vertex_count) as usize);
+            let end = if room == 0 {
+                let capacity = (vertices.len() - start)
+                    .next_power_of_two()
+                    .max(APPEND_CHUNK_SIZE)
+                    .min(self.capabilities.chunk_size);
+                let end = start + capacity.min(vertices.len() - start);
+                let chunk = self.create_growable_chunk(
+                    capacity,
+                    buffers.attributes.len(),
+                    bounds::Aabb::from_vertices(&vertices[start..end]).unwrap(),
+                );
+                buffers.chunks.push(chunk);
+                end
+            } else {
+                start + room.min(vertices.len() - start)
+            };
+            let chunk = buffers.chunks.last_mut().unwrap();
+            let first = chunk.vertex_count as usize;
+            self.write_buffer(
+                &mut command_encoder,
+                &chunk.vertex_buffer,
+                first * std::mem::size_of::<Vertex>(),
+                &vertices[start..end],
+            );
+            for (buffer, values) in chunk.attribute_buffers.iter().zip(attributes.iter()) {
+                self.write_buffer(
+                    &mut command_encoder,
+                    buffer,
+                    first * std::mem::size_of::<f32>(),
+                    &values[start..end],
+                );
+            }
+            let added = bounds::Aabb::from_vertices(&vertices[start..end]).unwrap();
+            chunk.bounds = if chunk.vertex_count == 0 {
+                added
+            } else {
+                chunk.bounds.union(&added)
+            };
+            chunk.vertex_count += (end - start) as u32;
+            start = end;
+        }
+        self.queue.submit(&[command_encoder.finish()]);
+        buffers.max_channel = buffers.max_channel.max(max_channel(&vertices));
+        buffers
+            .arrivals
+            .push_back((std::time::Instant::now(), vertices.len()));
+        buffers.bounds_buffer = self.create_bounds_buffer(&buffers.chunks);
+    }
+
+    /// Drop the oldest `count` points of `buffers`. A chunk that empties is moved to the end for
+    /// `append` to fill again, so a layer with a rolling window cycles through the same buffers.
+    pub fn trim(&mut self, buffers: &mut LineBuffers, count: usize) {
+        let mut remaining = count;
+        while remaining > 0 {
+            let points = match buffers.arrivals.front_mut() {
+                Some((_, points)) => points,
+                None => break,
+            };
+            let dropped = remaining.min(*points);
+            *points -= dropped;
+            remaining -= dropped;
+            if *points == 0 {
+                buffers.arrivals.pop_front();
+            }
+        }
+
+        let mut remaining = count;
+        while remaining > 0 && !buffers.chunks.is_empty() {
+            let only = buffers.chunks.len() == 1;
+            let chunk = &mut buffers.chunks[0];
+            let dropped = remaining.min((chunk.vertex_count - chunk.first) as usize);
+            chunk.first += dropped as u32;
+            remaining -= dropped;
+            if chunk.first < chunk.vertex_count {
+                break;
+            }
+            if only {
+                chunk.first = 0;
+                chunk.vertex_count = 0;
+                break;
+            }
+            let mut chunk = buffers.chunks.remove(0);
+            let last = buffers.chunks.last().unwrap();
+            // Small chunks, and any while the last still has room, aren't worth keeping
+            if last.vertex_count == last.capacity && chunk.capacity as usize >= APPEND_CHUNK_SIZE {
+                chunk.first = 0;
+                chunk.vertex_count = 0;
+                buffers.chunks.push(chunk);
+            }
+        }
+        buffers.bounds_buffer = self.create_bounds_buffer(&buffers.chunks);
+    }
+
+    /// Replace the points of `buffers` with those of `line` after `Line::thin`, keeping how the
+    /// layer is drawn and when its remaining points arrived.
+    pub fn thin(&mut self, buffers: &mut LineBuffers, line: &Line) {
+        let thinned = self.upload(line);
+        let mut start = 0;
+        for (_, points) in buffers.arrivals.iter_mut() {
+            // Thinning kept the points at even positions in draw order
+            let end = start + *points;
+            *points = end.div_ceil(2) - start.div_ceil(2);
+            start = end;
+        }
+        buffers.arrivals.retain(|&(_, points)| points > 0);
+        buffers.chunks = thinned.chunks;
+        buffers.bounds_buffer = thinned.bounds_buffer;
+    }
+
+    /// Replace the points of `buffers` with all of those of `line`, e.g. after the file it was
+    /// loaded from changed, keeping how the layer is drawn.
+    pub fn replace(&mut self, buffers: &mut LineBuffers, line: &Line) {
+        let uploaded = self.upload(line);
+        buffers.chunks = uploaded.chunks;
+        buffers.attributes = uploaded.attributes;
+        buffers.bounds_buffer = uploaded.bounds_buffer;
+        buffers.arrivals = uploaded.arrivals;
+        buffers.max_channel = uploaded.max_channel;
+    }
+
+    /// An empty chunk with room for `capacity` points, to be filled by `write_buffer`.
+    fn create_growable_chunk(
+        &mut self,
+        capacity: usize,
+        attribute_count: usize,
+        bounds: bounds::Aabb,
+    ) -> Chunk {
+        let usage = wgpu::BufferUsage::VERTEX | wgpu::BufferUsage::COPY_DST;
+        let vertex_buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: (capacity * std::mem::size_of::<Vertex>()) as wgpu::BufferAddress,
+            usage: usage,
+        });
+        let attribute_buffers = (0..attribute_count)
+            .map(|_| {
+                self.device.create_buffer(&wgpu::BufferDescriptor {
+                    label: None,
+                    size: (capacity * std::mem::size_of::<f32>()) as wgpu::BufferAddress,
+                    usage: usage,
+                })
+            })
+            .collect();
+        self.reserve_missing_attribute(capacity);
+        return Chunk {
+            vertex_buffer: vertex_buffer,
+            attribute_buffers: attribute_buffers,
+            first: 0,
+            vertex_count: 0,
+            capacity: capacity as u32,
+            bounds: bounds,
+        };
+    }
+
+    /// Copy `data` into `buffer` at byte `offset` through a staging buffer.
+    fn write_buffer<T>(
+        &mut self,
         command_encoder: &mut wgpu::CommandEncoder,
-        texture_view: &wgpu::TextureView,
-        vertices: &Vec<Vertex>,
-        indices: &Vec<u32>,
-        first_pass: bool
+        buffer: &wgpu::Buffer,
+        offset: usize,
+        data: &[T],
     ) {
-        // It might be expensive to copy these buffers every call?
-        let vertex_buffer = self.device.create_buffer_with_data(
-            u8_slice_from_slice(vertices.as_slice()),
+        let size = std::mem::size_of_val(data);
+        let staging_buffer = self
+            .device
+            .create_buffer_with_data(u8_slice_from_slice(data), wgpu::BufferUsage::COPY_SRC);
+        command_encoder.copy_buffer_to_buffer(
+            &staging_buffer,
+            0,
+            buffer,
+            offset as wgpu::BufferAddress,
+            size as wgpu::BufferAddress,
+        );
+        self.stats.record_upload(size);
+    }
+
+    /// Wireframes of the chunks' bounds and the layer's, see `wireframe::bounds_vertices`.
+    fn create_bounds_buffer(&mut self, chunks: &[Chunk]) -> Option<wgpu::Buffer> {
+        let chunk_bounds: Vec<bounds::Aabb> = chunks.iter().map(|chunk| chunk.bounds).collect();
+        let (first, rest) = chunk_bounds.split_first()?;
+        let layer_bounds = rest.iter().fold(*first, |layer, chunk| layer.union(chunk));
+        let bounds_vertices = wireframe::bounds_vertices(&layer_bounds, &chunk_bounds);
+        self.reserve_missing_attribute(bounds_vertices.len());
+        self.stats
+            .record_upload(std::mem::size_of_val(&bounds_vertices[..]));
+        return Some(self.device.create_buffer_with_data(
+            u8_slice_from_slice(&bounds_vertices),
             wgpu::BufferUsage::VERTEX,
+        ));
+    }
+
+    /// Make sure the missing attribute buffer covers at least `len` vertices.
+    fn reserve_missing_attribute(&mut self, len: usize) {
+        if len > self.missing_attribute_len {
+            self.missing_attribute_len = len;
+            self.missing_attribute_buffer = self.device.create_buffer_with_data(
+                u8_slice_from_slice(&vec![f32::NAN; len]),
+                wgpu::BufferUsage::VERTEX,
+            );
+        }
+    }
+
+    fn widen_attribute_range(&mut self, name: &str, values: &[f32]) {
+        let finite = values.iter().filter(|value| value.is_finite());
+        let (min, max) = finite.fold((f32::INFINITY, f32::NEG_INFINITY), |range, &v| {
+            (range.0.min(v), range.1.max(v))
+        });
+        match self.attribute_ranges.iter_mut().find(|(n, _)| n == name) {
+            Some((_, range)) => *range = [range[0].min(min), range[1].max(max)],
+            None => self.attribute_ranges.push((name.to_string(), [min, max])),
+        }
+    }
+
+    /// Names of the attributes uploaded since `reset_attributes`, in the order first seen.
+    pub fn attribute_names(&self) -> Vec<String> {
+        self.attribute_ranges
+            .iter()
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
+
+    /// Forget the attributes of previously uploaded lines, for when they are all replaced.
+    pub fn reset_attributes(&mut self) {
+        self.attribute_ranges.clear();
+    }
+
+    /// Write the active attribute settings, shared by every view, for the next frame.
+    pub fn update_attributes(&mut self, command_encoder: &mut wgpu::CommandEncoder) {
+        let active = self.attribute.as_ref().and_then(|attribute| {
+            self.attribute_ranges
+                .iter()
+                .find(|(name, range)| name == attribute && range[0] <= range[1])
+        });
+        let uniform = match active {
+            Some((_, range)) => AttributeUniform {
+                range: *range,
+                target: 1 + self.attribute_target as u32,
+            },
+            None => AttributeUniform {
+                range: [0.0, 1.0],
+                target: 0,
+            },
+        };
+        let staging_buffer = self.device.create_buffer_with_data(
+            u8_slice_from_slice(std::slice::from_ref(&uniform)),
+            wgpu::BufferUsage::COPY_SRC,
+        );
+        let size = std::mem::size_of::<AttributeUniform>();
+        command_encoder.copy_buffer_to_buffer(
+            &staging_buffer,
+            0,
+            &self.attribute_uniform_buffer,
+            0,
+            size as wgpu::BufferAddress,
         );
+        self.stats.record_upload(size);
+    }
 
-        let index_buffer = self.device.create_buffer_with_data(
-            u8_slice_from_slice(indices.as_slice()),
-            wgpu::BufferUsage::INDEX,
+    /// Write the tint, animation, colour input, point style and grading for the next layer into
+    /// its uniform buffer. Must be encoded before the render pass that draws the layer.
+    fn update_tint(
+        &mut self,
+        command_encoder: &mut wgpu::CommandEncoder,
+        tint: &Tint,
+        animation: Animation,
+        colors: ColorInput,
+        points: PointStyle,
+    ) {
+        let animation = if self.animate {
+            animation
+        } else {
+            Animation::Off
+        };
+        let uniform = TintUniform {
+            color: [tint.color[0], tint.color[1], tint.color[2], tint.alpha],
+            mode: tint.mode as u32,
+            animation: animation as u32,
+            time: self.animation_start.elapsed().as_secs_f32(),
+            color_scale: colors.scale,
+            channel_order: colors.order as u32,
+            srgb: colors.srgb as u32,
+            exposure: 2.0_f32.powf(self.grading.exposure),
+            gamma: self.grading.gamma,
+            shape: points.shape as u32,
+            min_size: points.min_size,
+            max_size: points.max_size,
+            size_scale: points.size_scale,
+            depth_offset: points.depth_offset,
+        };
+        let staging_buffer = self.device.create_buffer_with_data(
+            u8_slice_from_slice(std::slice::from_ref(&uniform)),
+            wgpu::BufferUsage::COPY_SRC,
         );
-        let camera_uniform_buffer = self.device.create_buffer_with_data(
-            u8_slice_from_slice(std::slice::from_ref(&self.camera.generate_uniform())),
-            wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_SRC,
+        let size = std::mem::size_of::<TintUniform>();
+        command_encoder.copy_buffer_to_buffer(
+            &staging_buffer,
+            0,
+            &self.tint_uniform_buffer,
+            0,
+            size as wgpu::BufferAddress,
         );
-        let uniforms_bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &self.uniforms_bind_group_layout,
-            bindings: &[wgpu::Binding {
-                binding: 0,
-                resource: wgpu::BindingResource::Buffer {
-                    buffer: &camera_uniform_buffer,
-                    range: 0..std::mem::size_of::<CameraUniform>() as wgpu::BufferAddress,
-                },
-            }],
-            label: None,
-        });
+        self.stats.record_upload(size);
+    }
+
+    /// Write a camera into the uniform buffer. Must be encoded before the render passes that draw
+    /// from that camera.
+    pub fn update_camera(
+        &mut self,
+        command_encoder: &mut wgpu::CommandEncoder,
+        camera: &OrbitCamera,
+    ) {
+        let staging_buffer = self.device.create_buffer_with_data(
+            u8_slice_from_slice(std::slice::from_ref(&camera.generate_uniform())),
+            wgpu::BufferUsage::COPY_SRC,
+        );
+        let size = std::mem::size_of::<CameraUniform>();
+        command_encoder.copy_buffer_to_buffer(
+            &staging_buffer,
+            0,
+            &self.camera_uniform_buffer,
+            0,
+            size as wgpu::BufferAddress,
+        );
+        self.stats.record_upload(size);
+    }
+
+    pub fn render(
+        &mut self,
+        command_encoder: &mut wgpu::CommandEncoder,
+        texture_view: &wgpu::TextureView,
+        line: &LineBuffers,
+        view: &viewport::View,
+        clear: Clear,
+    ) {
+        let view_proj = view.camera.view_projection();
+        let clear_color = if clear == Clear::Everything {
+            wgpu::LoadOp::Clear
+        } else {
+            wgpu::LoadOp::Load
+        };
+        let clear_depth = if clear == Clear::Nothing {
+            wgpu::LoadOp::Load
+        } else {
+            wgpu::LoadOp::Clear
+        };
+        let colors = ColorInput {
+            scale: line.color_scale(),
+            order: line.channel_order,
+            srgb: self.grading.srgb_points,
+        };
+        let points = PointStyle::of(line);
+        self.update_tint(
+            command_encoder,
+            &line.tint,
+            line.current_animation(),
+            colors,
+            points,
+        );
+        let point_pipeline = pipelines::PipelineKey::new(
+            wgpu::PrimitiveTopology::PointList,
+            pipelines::Shading::Points,
+            pipelines::Blend::Alpha,
+            line.depth,
+            view.eye,
+        );
+        let path_pipeline = pipelines::PipelineKey {
+            topology: wgpu::PrimitiveTopology::LineStrip,
+            shading: pipelines::Shading::Lines,
+            ..point_pipeline
+        };
+        self.pipelines
+            .prepare(&self.device, &self.pipeline_layout, point_pipeline);
+        if line.path {
+            self.pipelines
+                .prepare(&self.device, &self.pipeline_layout, path_pipeline);
+        }
         {
             let mut render_pass = command_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
                     attachment: &texture_view,
                     resolve_target: None,
-                    load_op: if first_pass {wgpu::LoadOp::Clear} else {wgpu::LoadOp::Load},
+                    load_op: clear_color,
                     store_op: wgpu::StoreOp::Store,
                     clear_color: wgpu::Color::TRANSPARENT,
                 }],
                 depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachmentDescriptor {
                     attachment: &self.depth_texture_view,
-                    depth_load_op: if first_pass {wgpu::LoadOp::Clear} else {wgpu::LoadOp::Load},
+                    depth_load_op: clear_depth,
                     depth_store_op: wgpu::StoreOp::Store,
                     clear_depth: 1.0,
-                    stencil_load_op: if first_pass {wgpu::LoadOp::Clear} else {wgpu::LoadOp::Load},
+                    stencil_load_op: clear_depth,
                     stencil_store_op: wgpu::StoreOp::Store,
                     clear_stencil: 0,
                 }),
             });
-            render_pass.set_pipeline(&self.render_pipeline);
+            render_pass.set_pipeline(self.pipelines.get(point_pipeline));
+            render_pass.set_viewport(
+                view.rect.x as f32,
+                view.rect.y as f32,
+                view.rect.width as f32,
+                view.rect.height as f32,
+                0.0,
+                1.0,
+            );
+            render_pass.set_scissor_rect(
+                view.clip.x,
+                view.clip.y,
+                view.clip.width,
+                view.clip.height,
+            );
 
-            render_pass.set_bind_group(0, &uniforms_bind_group, &[]);
-            render_pass.set_index_buffer(&index_buffer, 0, 0);
-            render_pass.set_vertex_buffer(0, &vertex_buffer, 0, 0);
-            render_pass.draw_indexed(0..indices.len() as u32, 0, 0..1);
+            render_pass.set_bind_group(0, &self.uniforms_bind_group, &[]);
+            let drawn = draw_chunks(
+                &mut render_pass,
+                line,
+                self.attribute.as_deref(),
+                &self.missing_attribute_buffer,
+                &view_proj,
+                !self.exact,
+                &mut self.stats,
+            );
+            let mut draws = line.draws.get();
+            draws.add(drawn);
+            line.draws.set(draws);
+            if line.path {
+                render_pass.set_pipeline(self.pipelines.get(path_pipeline));
+                draw_chunks(
+                    &mut render_pass,
+                    line,
+                    self.attribute.as_deref(),
+                    &self.missing_attribute_buffer,
+                    &view_proj,
+                    !self.exact,
+                    &mut self.stats,
+                );
+            }
         }
     }
+
+    /// Write the depth of `line`'s points, enlarged and pushed slightly away from the camera,
+    /// without drawing their colours. When this is done for every layer before any is drawn,
+    /// points behind the front surface of a dense cloud fail the depth test instead of showing
+    /// through the gaps between its points. Only layers with `DepthMode::Normal` hide what's
+    /// behind them.
+    pub fn render_depth_prepass(
+        &mut self,
+        command_encoder: &mut wgpu::CommandEncoder,
+        texture_view: &wgpu::TextureView,
+        line: &LineBuffers,
+        view: &viewport::View,
+    ) {
+        if !self.depth_prepass || self.exact || line.depth != DepthMode::Normal {
+            return;
+        }
+        let view_proj = view.camera.view_projection();
+        let colors = ColorInput::linear();
+        let points = PointStyle {
+            size_scale: PREPASS_SIZE_SCALE,
+            depth_offset: PREPASS_DEPTH_OFFSET,
+            ..PointStyle::of(line)
+        };
+        self.update_tint(
+            command_encoder,
+            &line.tint,
+            line.current_animation(),
+            colors,
+            points,
+        );
+        let pipeline = pipelines::PipelineKey::new(
+            wgpu::PrimitiveTopology::PointList,
+            pipelines::Shading::Points,
+            pipelines::Blend::DepthOnly,
+            DepthMode::Normal,
+            viewport::Eye::Both,
+        );
+        self.pipelines
+            .prepare(&self.device, &self.pipeline_layout, pipeline);
+        let mut render_pass = command_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
+                attachment: &texture_view,
+                resolve_target: None,
+                load_op: wgpu::LoadOp::Load,
+                store_op: wgpu::StoreOp::Store,
+                clear_color: wgpu::Color::TRANSPARENT,
+            }],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachmentDescriptor {
+                attachment: &self.depth_texture_view,
+                depth_load_op: wgpu::LoadOp::Load,
+                depth_store_op: wgpu::StoreOp::Store,
+                clear_depth: 1.0,
+                stencil_load_op: wgpu::LoadOp::Load,
+                stencil_store_op: wgpu::StoreOp::Store,
+                clear_stencil: 0,
+            }),
+        });
+        render_pass.set_pipeline(self.pipelines.get(pipeline));
+        render_pass.set_viewport(
+            view.rect.x as f32,
+            view.rect.y as f32,
+            view.rect.width as f32,
+            view.rect.height as f32,
+            0.0,
+            1.0,
+        );
+        render_pass.set_scissor_rect(view.clip.x, view.clip.y, view.clip.width, view.clip.height);
+        render_pass.set_bind_group(0, &self.uniforms_bind_group, &[]);
+        draw_chunks(
+            &mut render_pass,
+            line,
+            self.attribute.as_deref(),
+            &self.missing_attribute_buffer,
+            &view_proj,
+            !self.exact,
+            &mut self.stats,
+        );
+    }
+}
+
+/// Draw the chunks of `line` that are in view, skipping the rest unless `cull` is off, and return
+/// what was drawn. The values of `attribute` are bound alongside the vertices, or
+/// `missing_attribute` if the line doesn't have it.
+fn draw_chunks<'a>(
+    render_pass: &mut wgpu::RenderPass<'a>,
+    line: &'a LineBuffers,
+    attribute: Option<&str>,
+    missing_attribute: &'a wgpu::Buffer,
+    view_proj: &nalgebra::Matrix4<f32>,
+    cull: bool,
+    stats: &mut stats::FrameStats,
+) -> stats::LayerDraws {
+    let slot = attribute.and_then(|name| line.attributes.iter().position(|a| a == name));
+    let mut draws = stats::LayerDraws::default();
+    for chunk in line
+        .chunks
+        .iter()
+        .filter(|chunk| chunk.first < chunk.vertex_count)
+    {
+        let points = (chunk.vertex_count - chunk.first) as usize;
+        draws.submitted += points as u64;
+        if cull && !chunk.bounds.is_visible(view_proj) {
+            stats.record_culled(points);
+            continue;
+        }
+        render_pass.set_vertex_buffer(0, &chunk.vertex_buffer, 0, 0);
+        let attribute_buffer = slot.map_or(missing_attribute, |i| &chunk.attribute_buffers[i]);
+        render_pass.set_vertex_buffer(1, attribute_buffer, 0, 0);
+        render_pass.draw(chunk.first..chunk.vertex_count, 0..1);
+        stats.record_draw(points);
+        draws.drawn += points as u64;
+        draws.chunks += 1;
+        line.last_drawn.set(std::time::Instant::now());
+    }
+    return draws;
+}
+
+/// Describe every adapter available on the given backends, in enumeration order.
+pub fn list_adapters(backends: wgpu::BackendBit) -> Vec<wgpu::AdapterInfo> {
+    wgpu::Adapter::enumerate(backends)
+        .iter()
+        .map(|adapter| adapter.get_info())
+        .collect()
+}
+
+/// The requested adapter, otherwise the best one that can draw to `surface`. When none matches
+/// the options, a low power (often integrated) adapter and then any backend are tried, and why
+/// is returned alongside the adapter.
+fn select_adapter(
+    surface: &wgpu::Surface,
+    options: &crate::options::Options,
+) -> Option<(wgpu::Adapter, Option<String>)> {
+    if let Some(requested) = &options.adapter {
+        // Match the index reported by `--list-adapters` if it is a number, otherwise part of the
+        // adapter name, so "1" can't pick a "GTX 1080" ahead of adapter 1
+        let index = requested.parse::<usize>().ok();
+        let name = requested.to_lowercase();
+        let chosen = wgpu::Adapter::enumerate(options.backends)
+            .into_iter()
+            .enumerate()
+            .find(|(i, adapter)| match index {
+                Some(index) => *i == index,
+                None => adapter.get_info().name.to_lowercase().contains(&name),
+            });
+        match chosen {
+            Some((_, adapter)) => return Some((adapter, None)),
+            None => warn!(
+                "No adapter matching '{}', falling back to the default adapter",
+                requested
+            ),
+        }
+    }
+
+    let all_backends = wgpu::BackendBit::PRIMARY | wgpu::BackendBit::SECONDARY;
+    let attempts = [
+        (options.power_preference, options.backends, None),
+        (
+            wgpu::PowerPreference::LowPower,
+            options.backends,
+            Some("a low power adapter"),
+        ),
+        (
+            wgpu::PowerPreference::Default,
+            all_backends,
+            Some("an adapter on any backend"),
+        ),
+    ];
+    for &(power_preference, backends, fallback) in &attempts {
+        let adapter = futures::executor::block_on(wgpu::Adapter::request(
+            &wgpu::RequestAdapterOptions {
+                power_preference: power_preference,
+                compatible_surface: Some(surface),
+            },
+            backends,
+        ));
+        if let Some(adapter) = adapter {
+            return Some((adapter, fallback.map(|fallback| fallback.to_string())));
+        }
+    }
+    return None;
+}
+
+/// A pipeline drawing `Vertex` buffers, plus an attribute buffer, into the given colour and depth
+/// targets. `shaders` are the SPIR-V of the vertex and fragment shaders.
+fn create_vertex_pipeline(
+    device: &wgpu::Device,
+    pipeline_layout: &wgpu::PipelineLayout,
+    (vs_bytes, fs_bytes): (&[u8], &[u8]),
+    primitive_topology: wgpu::PrimitiveTopology,
+    color_state: wgpu::ColorStateDescriptor,
+    depth_stencil_state: Option<wgpu::DepthStencilStateDescriptor>,
+    sample_count: u32,
+) -> wgpu::RenderPipeline {
+    let vs_module =
+        device.create_shader_module(&wgpu::read_spirv(std::io::Cursor::new(vs_bytes)).unwrap());
+    let fs_module =
+        device.create_shader_module(&wgpu::read_spirv(std::io::Cursor::new(fs_bytes)).unwrap());
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        layout: pipeline_layout,
+        vertex_stage: wgpu::ProgrammableStageDescriptor {
+            module: &vs_module,
+            entry_point: "main",
+        },
+        fragment_stage: Some(wgpu::ProgrammableStageDescriptor {
+            module: &fs_module,
+            entry_point: "main",
+        }),
+        rasterization_state: Some(wgpu::RasterizationStateDescriptor {
+            front_face: wgpu::FrontFace::Ccw,
+            cull_mode: wgpu::CullMode::None,
+            depth_bias: 0,
+            depth_bias_slope_scale: 0.0,
+            depth_bias_clamp: 0.0,
+        }),
+        primitive_topology: primitive_topology,
+        color_states: &[color_state],
+        depth_stencil_state: depth_stencil_state,
+        vertex_state: wgpu::VertexStateDescriptor {
+            index_format: wgpu::IndexFormat::Uint32,
+            vertex_buffers: &[
+                wgpu::VertexBufferDescriptor {
+                    stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+                    step_mode: wgpu::InputStepMode::Vertex,
+                    attributes: &[
+                        wgpu::VertexAttributeDescriptor {
+                            format: wgpu::VertexFormat::Float4,
+                            offset: memoffset::offset_of!(Vertex, position) as wgpu::BufferAddress,
+                            shader_location: 0,
+                        },
+                        wgpu::VertexAttributeDescriptor {
+                            format: wgpu::VertexFormat::Float4,
+                            offset: memoffset::offset_of!(Vertex, color) as wgpu::BufferAddress,
+                            shader_location: 1,
+                        },
+                        wgpu::VertexAttributeDescriptor {
+                            format: wgpu::VertexFormat::Float4,
+                            offset: memoffset::offset_of!(Vertex, size) as wgpu::BufferAddress,
+                            shader_location: 2,
+                        },
+                    ],
+                },
+                wgpu::VertexBufferDescriptor {
+                    stride: std::mem::size_of::<f32>() as wgpu::BufferAddress,
+                    step_mode: wgpu::InputStepMode::Vertex,
+                    attributes: &[wgpu::VertexAttributeDescriptor {
+                        format: wgpu::VertexFormat::Float,
+                        offset: 0,
+                        shader_location: 3,
+                    }],
+                },
+            ],
+        },
+        sample_count: sample_count,
+        sample_mask: !0,
+        alpha_to_coverage_enabled: false,
+    })
 }
 
 fn u8_slice_from_slice<T>(data: &[T]) -> &[u8] {
@@ -0,0 +1,440 @@
+//! Images drawn on the CPU and blended over the scene at a pixel position, for the legend, the
+//! scale bar and point labels. Text is drawn with a small built-in bitmap font.
+
+use super::{capture, GEN_DIR};
+
+const TEXTURE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+/// Size of the font's glyphs, in overlay pixels.
+pub const GLYPH_WIDTH: usize = 5;
+pub const GLYPH_HEIGHT: usize = 7;
+/// Horizontal distance between the starts of neighbouring glyphs.
+pub const ADVANCE: usize = GLYPH_WIDTH + 1;
+/// Gap between an overlay and the edge of the target.
+pub const MARGIN: usize = 8;
+/// Window height, in pixels, per overlay pixel, so overlays stay legible on large displays.
+const PIXELS_PER_SCALE: u32 = 540;
+
+pub const WHITE: [u8; 4] = [255, 255, 255, 255];
+/// A translucent backing so text reads over any scene.
+pub const BACKING: [u8; 4] = [0, 0, 0, 160];
+
+/// Rows of each glyph from the top, with the leftmost pixel in bit 4. Letters are drawn in upper
+/// case, except for an `m` for metres.
+const FONT: &[(char, [u8; GLYPH_HEIGHT])] = &[
+    ('0', [0x0e, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0e]),
+    ('1', [0x04, 0x0c, 0x04, 0x04, 0x04, 0x04, 0x0e]),
+    ('2', [0x0e, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1f]),
+    ('3', [0x1f, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0e]),
+    ('4', [0x02, 0x06, 0x0a, 0x12, 0x1f, 0x02, 0x02]),
+    ('5', [0x1f, 0x10, 0x1e, 0x01, 0x01, 0x11, 0x0e]),
+    ('6', [0x06, 0x08, 0x10, 0x1e, 0x11, 0x11, 0x0e]),
+    ('7', [0x1f, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08]),
+    ('8', [0x0e, 0x11, 0x11, 0x0e, 0x11, 0x11, 0x0e]),
+    ('9', [0x0e, 0x11, 0x11, 0x0f, 0x01, 0x02, 0x0c]),
+    ('A', [0x0e, 0x11, 0x11, 0x1f, 0x11, 0x11, 0x11]),
+    ('B', [0x1e, 0x11, 0x11, 0x1e, 0x11, 0x11, 0x1e]),
+    ('C', [0x0e, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0e]),
+    ('D', [0x1c, 0x12, 0x11, 0x11, 0x11, 0x12, 0x1c]),
+    ('E', [0x1f, 0x10, 0x10, 0x1e, 0x10, 0x10, 0x1f]),
+    ('F', [0x1f, 0x10, 0x10, 0x1e, 0x10, 0x10, 0x10]),
+    ('G', [0x0e, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0f]),
+    ('H', [0x11, 0x11, 0x11, 0x1f, 0x11, 0x11, 0x11]),
+    ('I', [0x0e, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0e]),
+    ('J', [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0c]),
+    ('K', [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11]),
+    ('L', [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1f]),
+    ('M', [0x11, 0x1b, 0x15, 0x15, 0x11, 0x11, 0x11]),
+    ('N', [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11]),
+    ('O', [0x0e, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0e]),
+    ('P', [0x1e, 0x11, 0x11, 0x1e, 0x10, 0x10, 0x10]),
+    ('Q', [0x0e, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0d]),
+    ('R', [0x1e, 0x11, 0x11, 0x1e, 0x14, 0x12, 0x11]),
+    ('S', [0x0f, 0x10, 0x10, 0x0e, 0x01, 0x01, 0x1e]),
+    ('T', [0x1f, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04]),
+    ('U', [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0e]),
+    ('V', [0x11, 0x11, 0x11, 0x11, 0x11, 0x0a, 0x04]),
+    ('W', [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0a]),
+    ('X', [0x11, 0x11, 0x0a, 0x04, 0x0a, 0x11, 0x11]),
+    ('Y', [0x11, 0x11, 0x11, 0x0a, 0x04, 0x04, 0x04]),
+    ('Z', [0x1f, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1f]),
+    ('m', [0x00, 0x00, 0x1a, 0x15, 0x15, 0x15, 0x15]),
+    (' ', [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]),
+    ('.', [0x00, 0x00, 0x00, 0x00, 0x00, 0x0c, 0x0c]),
+    (',', [0x00, 0x00, 0x00, 0x00, 0x0c, 0x04, 0x08]),
+    (':', [0x00, 0x0c, 0x0c, 0x00, 0x0c, 0x0c, 0x00]),
+    ('-', [0x00, 0x00, 0x00, 0x1f, 0x00, 0x00, 0x00]),
+    ('+', [0x00, 0x04, 0x04, 0x1f, 0x04, 0x04, 0x00]),
+    ('_', [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1f]),
+    ('/', [0x00, 0x01, 0x02, 0x04, 0x08, 0x10, 0x00]),
+    ('(', [0x02, 0x04, 0x08, 0x08, 0x08, 0x04, 0x02]),
+    (')', [0x08, 0x04, 0x02, 0x02, 0x02, 0x04, 0x08]),
+    ('?', [0x0e, 0x11, 0x01, 0x02, 0x04, 0x00, 0x04]),
+];
+
+/// Overlay pixels per pixel of the font for a target `height` pixels high.
+pub fn scale(height: u32) -> usize {
+    return (height / PIXELS_PER_SCALE).max(1) as usize;
+}
+
+pub fn text_width(text: &str) -> usize {
+    return (text.chars().count() * ADVANCE).saturating_sub(1);
+}
+
+/// A value as short as it can be while still telling close ones apart.
+pub fn format_value(value: f32) -> String {
+    let magnitude = value.abs();
+    if magnitude != 0.0 && !(1e-3..1e5).contains(&magnitude) {
+        return format!("{:.2e}", value);
+    }
+    let text = format!("{:.3}", value);
+    return text.trim_end_matches('0').trim_end_matches('.').to_string();
+}
+
+/// Overlay pixels, row-major as sRGB RGBA.
+pub struct Canvas {
+    pub width: usize,
+    pub height: usize,
+    rgba: Vec<u8>,
+}
+
+impl Canvas {
+    pub fn new(width: usize, height: usize, background: [u8; 4]) -> Self {
+        Canvas {
+            width: width,
+            height: height,
+            rgba: background.repeat(width * height),
+        }
+    }
+
+    /// Set a pixel, leaving out those off the canvas so drawing can run over its edges.
+    pub fn set(&mut self, x: usize, y: usize, rgba: [u8; 4]) {
+        if x >= self.width || y >= self.height {
+            return;
+        }
+        let offset = (y * self.width + x) * 4;
+        self.rgba[offset..offset + 4].copy_from_slice(&rgba);
+    }
+
+    /// Draw `text` with the top left corner of its first glyph at `x`, `y`.
+    pub fn draw_text(&mut self, x: usize, y: usize, text: &str, rgba: [u8; 4]) {
+        for (i, c) in text.chars().enumerate() {
+            let upper = c.to_ascii_uppercase();
+            let rows = FONT
+                .iter()
+                .find(|(glyph, _)| *glyph == c)
+                .or_else(|| FONT.iter().find(|(glyph, _)| *glyph == upper))
+                .or_else(|| FONT.iter().find(|(glyph, _)| *glyph == '?'))
+                .map(|(_, rows)| rows)
+                .unwrap();
+            for (row, bits) in rows.iter().enumerate() {
+                for column in 0..GLYPH_WIDTH {
+                    if bits & (0x10 >> column) != 0 {
+                        self.set(x + i * ADVANCE + column, y + row, rgba);
+                    }
+                }
+            }
+        }
+    }
+
+    /// The canvas with each of its pixels `scale` pixels across.
+    pub fn to_image(&self, scale: usize) -> capture::Image {
+        let (width, height) = (self.width * scale, self.height * scale);
+        let mut rgba = Vec::with_capacity(width * height * 4);
+        for y in 0..height {
+            for x in 0..width {
+                let offset = ((y / scale) * self.width + x / scale) * 4;
+                rgba.extend_from_slice(&self.rgba[offset..offset + 4]);
+            }
+        }
+        return capture::Image {
+            width: width as u32,
+            height: height as u32,
+            rgba: rgba,
+        };
+    }
+}
+
+#[repr(C, align(16))]
+#[derive(Debug, Copy, Clone)]
+struct OverlayUniform {
+    origin: [i32; 2],
+}
+
+/// The pipeline every overlay is drawn with.
+pub struct OverlayPipeline {
+    pipeline: wgpu::RenderPipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+}
+
+impl OverlayPipeline {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            bindings: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::SampledTexture {
+                        dimension: wgpu::TextureViewDimension::D2,
+                        component_type: wgpu::TextureComponentType::Float,
+                        multisampled: false,
+                    },
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler { comparison: false },
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::UniformBuffer { dynamic: false },
+                },
+            ],
+            label: None,
+        });
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Nearest,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            lod_min_clamp: 0.0,
+            lod_max_clamp: 0.0,
+            compare: wgpu::CompareFunction::Undefined,
+        });
+        OverlayPipeline {
+            pipeline: create_pipeline(device, &bind_group_layout),
+            bind_group_layout: bind_group_layout,
+            sampler: sampler,
+        }
+    }
+}
+
+/// The image last uploaded, and the texture it went into.
+struct Uploaded {
+    width: u32,
+    height: u32,
+    bind_group: wgpu::BindGroup,
+    _texture: wgpu::Texture,
+}
+
+/// An image uploaded to be drawn over the scene.
+pub struct Overlay {
+    uniform_buffer: wgpu::Buffer,
+    uploaded: Option<Uploaded>,
+}
+
+impl Overlay {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: std::mem::size_of::<OverlayUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+        });
+        Overlay {
+            uniform_buffer: uniform_buffer,
+            uploaded: None,
+        }
+    }
+
+    /// Width and height of the image uploaded, if any.
+    pub fn size(&self) -> Option<(u32, u32)> {
+        self.uploaded
+            .as_ref()
+            .map(|uploaded| (uploaded.width, uploaded.height))
+    }
+
+    /// Replace the image drawn with `image`.
+    pub fn upload(
+        &mut self,
+        device: &wgpu::Device,
+        pipeline: &OverlayPipeline,
+        command_encoder: &mut wgpu::CommandEncoder,
+        image: &capture::Image,
+    ) {
+        let alignment = capture::BYTES_PER_ROW_ALIGNMENT as usize;
+        let unpadded_bytes_per_row = image.width as usize * 4;
+        let bytes_per_row = unpadded_bytes_per_row.div_ceil(alignment) * alignment;
+        let mut texels = vec![0_u8; bytes_per_row * image.height as usize];
+        for (row, pixels) in image.rgba.chunks(unpadded_bytes_per_row).enumerate() {
+            let offset = row * bytes_per_row;
+            texels[offset..offset + unpadded_bytes_per_row].copy_from_slice(pixels);
+        }
+        let size = wgpu::Extent3d {
+            width: image.width,
+            height: image.height,
+            depth: 1,
+        };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            format: TEXTURE_FORMAT,
+            usage: wgpu::TextureUsage::SAMPLED | wgpu::TextureUsage::COPY_DST,
+            size: size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            label: None,
+            array_layer_count: 1,
+        });
+        let staging_buffer = device.create_buffer_with_data(&texels, wgpu::BufferUsage::COPY_SRC);
+        command_encoder.copy_buffer_to_texture(
+            wgpu::BufferCopyView {
+                buffer: &staging_buffer,
+                offset: 0,
+                bytes_per_row: bytes_per_row as u32,
+                rows_per_image: image.height,
+            },
+            wgpu::TextureCopyView {
+                texture: &texture,
+                mip_level: 0,
+                array_layer: 0,
+                origin: wgpu::Origin3d::ZERO,
+            },
+            size,
+        );
+        let texture_view = texture.create_default_view();
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &pipeline.bind_group_layout,
+            bindings: &[
+                wgpu::Binding {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&texture_view),
+                },
+                wgpu::Binding {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&pipeline.sampler),
+                },
+                wgpu::Binding {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Buffer {
+                        buffer: &self.uniform_buffer,
+                        range: 0..std::mem::size_of::<OverlayUniform>() as wgpu::BufferAddress,
+                    },
+                },
+            ],
+            label: None,
+        });
+        self.uploaded = Some(Uploaded {
+            width: image.width,
+            height: image.height,
+            bind_group: bind_group,
+            _texture: texture,
+        });
+    }
+
+    /// Blend the uploaded image over `texture_view` with its top left corner at `x`, `y`.
+    pub fn render(
+        &self,
+        device: &wgpu::Device,
+        pipeline: &OverlayPipeline,
+        command_encoder: &mut wgpu::CommandEncoder,
+        texture_view: &wgpu::TextureView,
+        x: u32,
+        y: u32,
+    ) {
+        let uploaded = match &self.uploaded {
+            Some(uploaded) => uploaded,
+            None => return,
+        };
+        let uniform = OverlayUniform {
+            origin: [x as i32, y as i32],
+        };
+        let staging_buffer = device.create_buffer_with_data(
+            super::u8_slice_from_slice(std::slice::from_ref(&uniform)),
+            wgpu::BufferUsage::COPY_SRC,
+        );
+        command_encoder.copy_buffer_to_buffer(
+            &staging_buffer,
+            0,
+            &self.uniform_buffer,
+            0,
+            std::mem::size_of::<OverlayUniform>() as wgpu::BufferAddress,
+        );
+
+        let mut render_pass = command_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
+                attachment: texture_view,
+                resolve_target: None,
+                load_op: wgpu::LoadOp::Load,
+                store_op: wgpu::StoreOp::Store,
+                clear_color: wgpu::Color::TRANSPARENT,
+            }],
+            depth_stencil_attachment: None,
+        });
+        render_pass.set_pipeline(&pipeline.pipeline);
+        render_pass.set_viewport(
+            x as f32,
+            y as f32,
+            uploaded.width as f32,
+            uploaded.height as f32,
+            0.0,
+            1.0,
+        );
+        render_pass.set_bind_group(0, &uploaded.bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+fn create_pipeline(
+    device: &wgpu::Device,
+    bind_group_layout: &wgpu::BindGroupLayout,
+) -> wgpu::RenderPipeline {
+    let vs_bytes = GEN_DIR
+        .get_file("shaders/fullscreen.vert.spv")
+        .unwrap()
+        .contents();
+    let fs_bytes = GEN_DIR
+        .get_file("shaders/overlay.frag.spv")
+        .unwrap()
+        .contents();
+    let vs_module =
+        device.create_shader_module(&wgpu::read_spirv(std::io::Cursor::new(vs_bytes)).unwrap());
+    let fs_module =
+        device.create_shader_module(&wgpu::read_spirv(std::io::Cursor::new(fs_bytes)).unwrap());
+    let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        bind_group_layouts: &[bind_group_layout],
+    });
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        layout: &layout,
+        vertex_stage: wgpu::ProgrammableStageDescriptor {
+            module: &vs_module,
+            entry_point: "main",
+        },
+        fragment_stage: Some(wgpu::ProgrammableStageDescriptor {
+            module: &fs_module,
+            entry_point: "main",
+        }),
+        rasterization_state: Some(wgpu::RasterizationStateDescriptor {
+            front_face: wgpu::FrontFace::Ccw,
+            cull_mode: wgpu::CullMode::None,
+            depth_bias: 0,
+            depth_bias_slope_scale: 0.0,
+            depth_bias_clamp: 0.0,
+        }),
+        primitive_topology: wgpu::PrimitiveTopology::TriangleList,
+        color_states: &[wgpu::ColorStateDescriptor {
+            format: wgpu::TextureFormat::Bgra8UnormSrgb,
+            color_blend: wgpu::BlendDescriptor {
+                src_factor: wgpu::BlendFactor::SrcAlpha,
+                dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+                operation: wgpu::BlendOperation::Add,
+            },
+            alpha_blend: wgpu::BlendDescriptor {
+                src_factor: wgpu::BlendFactor::One,
+                dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+                operation: wgpu::BlendOperation::Add,
+            },
+            write_mask: wgpu::ColorWrite::ALL,
+        }],
+        depth_stencil_state: None,
+        vertex_state: wgpu::VertexStateDescriptor {
+            index_format: wgpu::IndexFormat::Uint32,
+            vertex_buffers: &[],
+        },
+        sample_count: 1,
+        sample_mask: !0,
+        alpha_to_coverage_enabled: false,
+    })
+}
@@ -0,0 +1,192 @@
+//! The render pipelines drawing `Vertex` buffers with shader.vert, created the first time they are
+//! needed and kept by what tells them apart.
+
+use std::collections::HashMap;
+
+use super::{viewport, DepthMode, GEN_DIR};
+
+/// The fragment shader a pipeline runs after shader.vert.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Shading {
+    /// shader.frag, drawing each point as its layer's `PointShape`.
+    Points,
+    /// wireframe.frag, passing the vertex colour through, for paths and wireframes, which are
+    /// plain lines rather than rounded off points.
+    Lines,
+}
+
+/// How a pipeline combines what it draws with the colour already there.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Blend {
+    /// Blended by alpha so layers can be made translucent, see `Tint::alpha`.
+    Alpha,
+    Replace,
+    /// Writes depth but no colour, see `Renderer::render_depth_prepass`.
+    DepthOnly,
+}
+
+/// Everything the pipelines in a `PipelineCache` differ in.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct PipelineKey {
+    pub topology: wgpu::PrimitiveTopology,
+    pub shading: Shading,
+    pub blend: Blend,
+    pub depth: DepthMode,
+    /// The left eye only writes the red channel and the right eye the green and blue ones, to
+    /// match red-cyan glasses.
+    pub eye: viewport::Eye,
+    pub sample_count: u32,
+}
+
+impl PipelineKey {
+    /// Points or lines drawn into a single-sampled target.
+    pub fn new(
+        topology: wgpu::PrimitiveTopology,
+        shading: Shading,
+        blend: Blend,
+        depth: DepthMode,
+        eye: viewport::Eye,
+    ) -> Self {
+        PipelineKey {
+            topology: topology,
+            shading: shading,
+            blend: blend,
+            depth: depth,
+            eye: eye,
+            sample_count: 1,
+        }
+    }
+}
+
+pub struct PipelineCache {
+    /// The swap chain format the pipelines draw into.
+    format: wgpu::TextureFormat,
+    /// SPIR-V for shader.vert and shader.frag, replaced when shaders are hot reloaded.
+    vs_bytes: Vec<u8>,
+    fs_bytes: Vec<u8>,
+    pipelines: HashMap<PipelineKey, wgpu::RenderPipeline>,
+}
+
+impl PipelineCache {
+    pub fn new(format: wgpu::TextureFormat, vs_bytes: &[u8], fs_bytes: &[u8]) -> Self {
+        PipelineCache {
+            format: format,
+            vs_bytes: vs_bytes.to_vec(),
+            fs_bytes: fs_bytes.to_vec(),
+            pipelines: HashMap::new(),
+        }
+    }
+
+    /// Drop every pipeline if the swap chain format changed, so they are rebuilt for it as they
+    /// are next used.
+    pub fn set_format(&mut self, format: wgpu::TextureFormat) {
+        if format != self.format {
+            self.format = format;
+            self.pipelines.clear();
+        }
+    }
+
+    /// Use newly compiled shaders from now on.
+    #[cfg(feature = "hot-reload")]
+    pub fn set_shaders(&mut self, vs_bytes: Vec<u8>, fs_bytes: Vec<u8>) {
+        self.vs_bytes = vs_bytes;
+        self.fs_bytes = fs_bytes;
+        self.pipelines.clear();
+    }
+
+    /// Create the pipeline for `key` unless it already exists. Done before a render pass begins,
+    /// since the pass then borrows the pipeline from the cache.
+    pub fn prepare(
+        &mut self,
+        device: &wgpu::Device,
+        pipeline_layout: &wgpu::PipelineLayout,
+        key: PipelineKey,
+    ) {
+        if self.pipelines.contains_key(&key) {
+            return;
+        }
+        let fs_bytes = match key.shading {
+            Shading::Points => &self.fs_bytes[..],
+            Shading::Lines => GEN_DIR
+                .get_file("shaders/wireframe.frag.spv")
+                .unwrap()
+                .contents(),
+        };
+        let pipeline = create_pipeline(
+            device,
+            pipeline_layout,
+            &self.vs_bytes,
+            fs_bytes,
+            self.format,
+            key,
+        );
+        self.pipelines.insert(key, pipeline);
+    }
+
+    /// The pipeline for `key`, which must have been prepared.
+    pub fn get(&self, key: PipelineKey) -> &wgpu::RenderPipeline {
+        self.pipelines
+            .get(&key)
+            .expect("Pipeline used without being prepared")
+    }
+}
+
+fn create_pipeline(
+    device: &wgpu::Device,
+    pipeline_layout: &wgpu::PipelineLayout,
+    vs_bytes: &[u8],
+    fs_bytes: &[u8],
+    format: wgpu::TextureFormat,
+    key: PipelineKey,
+) -> wgpu::RenderPipeline {
+    let write_mask = match (key.blend, key.eye) {
+        (Blend::DepthOnly, _) => wgpu::ColorWrite::empty(),
+        (_, viewport::Eye::Both) => wgpu::ColorWrite::ALL,
+        (_, viewport::Eye::Left) => wgpu::ColorWrite::RED,
+        (_, viewport::Eye::Right) => wgpu::ColorWrite::GREEN | wgpu::ColorWrite::BLUE,
+    };
+    let (color_blend, alpha_blend) = match key.blend {
+        Blend::Alpha => (
+            wgpu::BlendDescriptor {
+                src_factor: wgpu::BlendFactor::SrcAlpha,
+                dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+                operation: wgpu::BlendOperation::Add,
+            },
+            wgpu::BlendDescriptor {
+                src_factor: wgpu::BlendFactor::One,
+                dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+                operation: wgpu::BlendOperation::Add,
+            },
+        ),
+        Blend::Replace | Blend::DepthOnly => (
+            wgpu::BlendDescriptor::REPLACE,
+            wgpu::BlendDescriptor::REPLACE,
+        ),
+    };
+    super::create_vertex_pipeline(
+        device,
+        pipeline_layout,
+        (vs_bytes, fs_bytes),
+        key.topology,
+        wgpu::ColorStateDescriptor {
+            format: format,
+            color_blend: color_blend,
+            alpha_blend: alpha_blend,
+            write_mask: write_mask,
+        },
+        Some(wgpu::DepthStencilStateDescriptor {
+            format: wgpu::TextureFormat::Depth32Float,
+            depth_write_enabled: key.depth == DepthMode::Normal,
+            depth_compare: if key.depth == DepthMode::OnTop {
+                wgpu::CompareFunction::Always
+            } else {
+                wgpu::CompareFunction::Less
+            },
+            stencil_front: wgpu::StencilStateFaceDescriptor::IGNORE,
+            stencil_back: wgpu::StencilStateFaceDescriptor::IGNORE,
+            stencil_read_mask: 0,
+            stencil_write_mask: 0,
+        }),
+        key.sample_count,
+    )
+}
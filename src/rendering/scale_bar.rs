@@ -0,0 +1,248 @@
+//! A scale bar and north arrow in the bottom left corner of each view, for survey data. The bar
+//! spans a round length at the camera's target distance, which is exact everywhere in
+//! orthographic views. North is +Y, as geographic input is converted to east, north and up, or
+//! -Z for Y-up data.
+
+use super::overlay::{self, Canvas, Overlay, BACKING, GLYPH_HEIGHT, GLYPH_WIDTH, MARGIN, WHITE};
+use super::{capture, viewport, OrbitCamera, UpAxis};
+
+const PADDING: usize = 4;
+/// Longest the bar can be, in overlay pixels.
+const MAX_BAR: usize = 100;
+const BAR_THICKNESS: usize = 3;
+/// Height of the ticks at either end of the bar.
+const TICK_HEIGHT: usize = 7;
+/// Width and height of the box the north arrow turns in.
+const COMPASS_SIZE: usize = 31;
+/// Shortest north can be on screen, as a fraction of its length, for the arrow to be drawn.
+/// Looking straight along north there is no direction on screen to point in.
+const MIN_NORTH: f32 = 0.2;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ScaleBarMode {
+    Off,
+    Bar,
+    /// The bar with a north arrow beside it.
+    BarAndNorth,
+}
+
+impl ScaleBarMode {
+    pub fn next(self) -> Self {
+        match self {
+            ScaleBarMode::Off => ScaleBarMode::Bar,
+            ScaleBarMode::Bar => ScaleBarMode::BarAndNorth,
+            ScaleBarMode::BarAndNorth => ScaleBarMode::Off,
+        }
+    }
+}
+
+/// What the scale bar of a view shows.
+#[derive(Debug, Clone, PartialEq)]
+struct Marker {
+    label: String,
+    /// Length of the bar in overlay pixels.
+    bar: usize,
+    /// Direction of north in whole degrees clockwise from the top of the screen.
+    north: Option<i32>,
+    scale: usize,
+}
+
+impl Marker {
+    fn rasterize(&self) -> capture::Image {
+        let compass = match self.north {
+            Some(_) => COMPASS_SIZE + PADDING,
+            None => 0,
+        };
+        let bar_width = (self.bar + 1).max(overlay::text_width(&self.label));
+        let height = PADDING + COMPASS_SIZE.max(GLYPH_HEIGHT + 3 + TICK_HEIGHT) + PADDING;
+        let width = PADDING + compass + bar_width + PADDING;
+        let mut canvas = Canvas::new(width, height, BACKING);
+        if let Some(north) = self.north {
+            draw_north_arrow(&mut canvas, PADDING, PADDING, north);
+        }
+        let x = PADDING + compass;
+        let bottom = height - PADDING;
+        let label_y = bottom - TICK_HEIGHT - 3 - GLYPH_HEIGHT;
+        canvas.draw_text(x, label_y, &self.label, WHITE);
+        for y in bottom - TICK_HEIGHT..bottom {
+            canvas.set(x, y, WHITE);
+            canvas.set(x + self.bar, y, WHITE);
+        }
+        for y in bottom - BAR_THICKNESS..bottom {
+            for offset in 0..=self.bar {
+                canvas.set(x + offset, y, WHITE);
+            }
+        }
+        return canvas.to_image(self.scale);
+    }
+}
+
+/// Draw an arrow pointing `north` degrees clockwise from up, labelled N at its tip, in the
+/// `COMPASS_SIZE` square with its top left corner at `left`, `top`.
+fn draw_north_arrow(canvas: &mut Canvas, left: usize, top: usize, north: i32) {
+    let angle = (north as f32).to_radians();
+    // Canvas y points down
+    let direction = nalgebra::Vector2::new(angle.sin(), -angle.cos());
+    let across = nalgebra::Vector2::new(-direction.y, direction.x);
+    let half = COMPASS_SIZE as f32 / 2.0;
+    let center = nalgebra::Point2::new(left as f32 + half, top as f32 + half);
+    let tail = center - direction * 11.0;
+    let tip = center + direction * 6.0;
+    let head = [tip, center + across * 3.5, center - across * 3.5];
+    for y in top..top + COMPASS_SIZE {
+        for x in left..left + COMPASS_SIZE {
+            let p = nalgebra::Point2::new(x as f32 + 0.5, y as f32 + 0.5);
+            if distance_to_segment(p, tail, center) <= 1.0 || inside_triangle(p, head) {
+                canvas.set(x, y, WHITE);
+            }
+        }
+    }
+    let letter = center + direction * 11.0;
+    canvas.draw_text(
+        (letter.x - GLYPH_WIDTH as f32 / 2.0).round() as usize,
+        (letter.y - GLYPH_HEIGHT as f32 / 2.0).round() as usize,
+        "N",
+        WHITE,
+    );
+}
+
+fn distance_to_segment(
+    p: nalgebra::Point2<f32>,
+    a: nalgebra::Point2<f32>,
+    b: nalgebra::Point2<f32>,
+) -> f32 {
+    let ab = b - a;
+    let t = ((p - a).dot(&ab) / ab.norm_squared()).clamp(0.0, 1.0);
+    return nalgebra::distance(&p, &(a + ab * t));
+}
+
+fn inside_triangle(p: nalgebra::Point2<f32>, corners: [nalgebra::Point2<f32>; 3]) -> bool {
+    let side = |a: nalgebra::Point2<f32>, b: nalgebra::Point2<f32>| {
+        let (ab, ap) = (b - a, p - a);
+        ab.x * ap.y - ab.y * ap.x
+    };
+    let sides = [
+        side(corners[0], corners[1]),
+        side(corners[1], corners[2]),
+        side(corners[2], corners[0]),
+    ];
+    return sides.iter().all(|&s| s >= 0.0) || sides.iter().all(|&s| s <= 0.0);
+}
+
+/// The longest of 1, 2 and 5 times a power of ten that is at most `limit`.
+fn round_length(limit: f32) -> f32 {
+    let power = 10_f32.powf(limit.log10().floor());
+    for factor in [5.0, 2.0] {
+        if factor * power <= limit {
+            return factor * power;
+        }
+    }
+    return power;
+}
+
+pub struct ScaleBar {
+    pub mode: ScaleBarMode,
+    /// Written after lengths, "m" where the scene is known to be in metres.
+    unit: &'static str,
+    /// An overlay for each view, with what it shows.
+    overlays: Vec<(Option<Marker>, Overlay)>,
+}
+
+impl ScaleBar {
+    pub fn new(unit: &'static str) -> Self {
+        ScaleBar {
+            mode: ScaleBarMode::Off,
+            unit: unit,
+            overlays: Vec::new(),
+        }
+    }
+
+    /// What the scale bar shows for `camera`, with overlay pixels `scale` pixels across.
+    fn marker(&self, camera: &OrbitCamera, scale: usize) -> Option<Marker> {
+        // World units per overlay pixel
+        let pixel_size = camera.pixel_size() * scale as f32;
+        if !pixel_size.is_finite() || pixel_size <= 0.0 {
+            return None;
+        }
+        let length = round_length(MAX_BAR as f32 * pixel_size);
+        let label = match self.unit {
+            "" => overlay::format_value(length),
+            unit => format!("{} {}", overlay::format_value(length), unit),
+        };
+        let north = match camera.up_axis {
+            UpAxis::Z => nalgebra::Vector3::y(),
+            UpAxis::Y => -nalgebra::Vector3::z(),
+        };
+        let direction = camera.screen_direction(north);
+        let north = if self.mode == ScaleBarMode::BarAndNorth && direction.norm() >= MIN_NORTH {
+            Some(direction.x.atan2(direction.y).to_degrees().round() as i32)
+        } else {
+            None
+        };
+        return Some(Marker {
+            label: label,
+            bar: (length / pixel_size).round() as usize,
+            north: north,
+            scale: scale,
+        });
+    }
+}
+
+impl super::Renderer {
+    /// Draw the scale bar, and the north arrow if it is shown, in the bottom left corner of each
+    /// of `views`.
+    pub fn render_scale_bar(
+        &mut self,
+        command_encoder: &mut wgpu::CommandEncoder,
+        texture_view: &wgpu::TextureView,
+        views: &[viewport::View],
+    ) {
+        if self.scale_bar.mode == ScaleBarMode::Off {
+            return;
+        }
+        let scale = overlay::scale(self.sc_desc.height);
+        let margin = (MARGIN * scale) as u32;
+        let scale_bar = &mut self.scale_bar;
+        // Anaglyph eyes share a rectangle and a scale bar
+        let mut drawn = Vec::new();
+        for (i, view) in views.iter().enumerate() {
+            let rect = view.rect;
+            let corner = (rect.x, rect.y, rect.width, rect.height);
+            if drawn.contains(&corner) {
+                continue;
+            }
+            drawn.push(corner);
+            let marker = match scale_bar.marker(&view.camera, scale) {
+                Some(marker) => marker,
+                None => continue,
+            };
+            while scale_bar.overlays.len() <= i {
+                scale_bar.overlays.push((None, Overlay::new(&self.device)));
+            }
+            let (uploaded, overlay) = &mut scale_bar.overlays[i];
+            if uploaded.as_ref() != Some(&marker) {
+                let image = marker.rasterize();
+                overlay.upload(
+                    &self.device,
+                    &self.overlay_pipeline,
+                    command_encoder,
+                    &image,
+                );
+                *uploaded = Some(marker);
+            }
+            let (width, height) = overlay.size().unwrap();
+            // Leave it out rather than cover a view too small to show it
+            if width + margin > rect.width || height + margin > rect.height {
+                continue;
+            }
+            overlay.render(
+                &self.device,
+                &self.overlay_pipeline,
+                command_encoder,
+                texture_view,
+                rect.x + margin,
+                rect.y + rect.height - height - margin,
+            );
+        }
+    }
+}
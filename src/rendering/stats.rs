@@ -0,0 +1,89 @@
+use std::time::{Duration, Instant};
+
+/// Counters accumulated by the renderer over a reporting interval.
+pub struct FrameStats {
+    pub frames: u32,
+    pub draw_calls: u32,
+    pub points: u64,
+    pub points_culled: u64,
+    pub bytes_uploaded: u64,
+    pub cpu_time: Duration,
+    interval_start: Instant,
+}
+
+impl FrameStats {
+    pub fn new() -> Self {
+        FrameStats {
+            frames: 0,
+            draw_calls: 0,
+            points: 0,
+            points_culled: 0,
+            bytes_uploaded: 0,
+            cpu_time: Duration::from_secs(0),
+            interval_start: Instant::now(),
+        }
+    }
+
+    pub fn record_draw(&mut self, points: usize) {
+        self.draw_calls += 1;
+        self.points += points as u64;
+    }
+
+    pub fn record_culled(&mut self, points: usize) {
+        self.points_culled += points as u64;
+    }
+
+    pub fn record_upload(&mut self, bytes: usize) {
+        self.bytes_uploaded += bytes as u64;
+    }
+
+    pub fn record_frame(&mut self, cpu_time: Duration) {
+        self.frames += 1;
+        self.cpu_time += cpu_time;
+    }
+
+    /// Returns a per-frame summary and starts a new interval once `interval` has elapsed.
+    pub fn report(&mut self, interval: Duration) -> Option<String> {
+        let elapsed = self.interval_start.elapsed();
+        if elapsed < interval || self.frames == 0 {
+            return None;
+        }
+        let frames = self.frames as f64;
+        let summary = format!(
+            "{:.1} fps, {:.2} ms CPU/frame, {:.0} draw calls/frame, {:.0} points/frame ({:.0} culled), {:.1} MB uploaded/frame",
+            frames / elapsed.as_secs_f64(),
+            self.cpu_time.as_secs_f64() * 1000.0 / frames,
+            self.draw_calls as f64 / frames,
+            self.points as f64 / frames,
+            self.points_culled as f64 / frames,
+            self.bytes_uploaded as f64 / frames / (1024.0 * 1024.0),
+        );
+        *self = FrameStats::new();
+        return Some(summary);
+    }
+}
+
+/// What was drawn of one layer in the last frame, summed over the views.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct LayerDraws {
+    /// Points of the chunks considered for drawing.
+    pub submitted: u64,
+    /// Points of the chunks left after culling those outside the view.
+    pub drawn: u64,
+    pub chunks: u32,
+}
+
+impl LayerDraws {
+    pub fn add(&mut self, other: LayerDraws) {
+        self.submitted += other.submitted;
+        self.drawn += other.drawn;
+        self.chunks += other.chunks;
+    }
+
+    pub fn to_json(self) -> String {
+        format!(
+            "{{\"submitted\":{},\"drawn\":{},\"chunks\":{}}}",
+            self.submitted, self.drawn, self.chunks
+        )
+    }
+}
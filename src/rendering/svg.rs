@@ -0,0 +1,195 @@
+//! Vector export of the current view, for figures that need to stay crisp when scaled.
+
+use super::{
+    draw_order, linear_to_srgb, srgb_to_linear, viewport, DepthMode, Grading, Line, LineBuffers,
+    TintMode, Vertex,
+};
+use std::fmt::Write;
+
+/// Points are thinned to the nearest one in each square cell of this many pixels, which keeps
+/// the file to a manageable size without visibly changing the figure.
+const CELL_SIZE: f64 = 2.0;
+
+/// Something drawn in the SVG, ordered so that later marks are painted over earlier ones.
+struct Mark {
+    /// On-top layers are painted after everything else, whatever their depth.
+    on_top: bool,
+    depth: f32,
+    element: String,
+}
+
+impl super::Renderer {
+    /// The visible points and paths of every view as an SVG document, projected through each
+    /// view's camera and painted back to front.
+    pub fn export_svg(
+        &self,
+        viewports: &viewport::Viewports,
+        lines: &[Line],
+        buffers: &[LineBuffers],
+    ) -> String {
+        let width = self.sc_desc.width;
+        let height = self.sc_desc.height;
+        let mut marks = Vec::new();
+        // The nearest point in each cell of each view, as an index into `marks`
+        let mut cells = std::collections::HashMap::new();
+        for (view_index, view) in viewports.views().iter().enumerate() {
+            let eye = view.camera.world_eye();
+            for layer in draw_order(buffers) {
+                let line = &lines[layer];
+                let style = &buffers[layer];
+                let view = match viewports.comparison {
+                    Some(comparison) => comparison.clip(view, layer),
+                    None => *view,
+                };
+                let offset: nalgebra::Vector3<f32> = nalgebra::convert(line.origin - self.origin);
+                let view_proj =
+                    view.camera.view_projection() * nalgebra::Matrix4::new_translation(&offset);
+                let on_top = style.depth == DepthMode::OnTop;
+                let mut previous: Option<(winit::dpi::PhysicalPosition<f64>, f32)> = None;
+                for &index in &line.indicies {
+                    let vertex = &line.verticies[index as usize];
+                    let projected = project(&view_proj, vertex, &view);
+                    // Paths are broken where they leave the view
+                    let (screen, depth) = match projected {
+                        Some((screen, depth)) if view.clip.contains(screen) => (screen, depth),
+                        _ => {
+                            previous = None;
+                            continue;
+                        }
+                    };
+                    let color = svg_color(vertex, style, &self.grading);
+                    if style.path {
+                        if let Some((from, from_depth)) = previous {
+                            marks.push(Mark {
+                                on_top: on_top,
+                                depth: depth.max(from_depth),
+                                element: format!(
+                                    "<line x1=\"{:.2}\" y1=\"{:.2}\" x2=\"{:.2}\" y2=\"{:.2}\" \
+                                     stroke=\"{}\"{}/>",
+                                    from.x,
+                                    from.y,
+                                    screen.x,
+                                    screen.y,
+                                    color,
+                                    opacity("stroke", style)
+                                ),
+                            });
+                        }
+                        previous = Some((screen, depth));
+                    }
+                    let position = nalgebra::Point3::new(
+                        vertex.position[0] + offset.x,
+                        vertex.position[1] + offset.y,
+                        vertex.position[2] + offset.z,
+                    );
+                    // Sized as in shader.vert
+                    let range = nalgebra::distance(&position, &eye);
+                    let diameter = (vertex.size / range) * (vertex.size / range);
+                    let mark = Mark {
+                        on_top: on_top,
+                        depth: depth,
+                        element: format!(
+                            "<circle cx=\"{:.2}\" cy=\"{:.2}\" r=\"{:.2}\" fill=\"{}\"{}/>",
+                            screen.x,
+                            screen.y,
+                            diameter / 2.0,
+                            color,
+                            opacity("fill", style)
+                        ),
+                    };
+                    let cell = (
+                        view_index,
+                        (screen.x / CELL_SIZE) as i32,
+                        (screen.y / CELL_SIZE) as i32,
+                    );
+                    match cells.get(&cell) {
+                        Some(&existing) => {
+                            let nearest: &Mark = &marks[existing];
+                            if (on_top, -depth) > (nearest.on_top, -nearest.depth) {
+                                marks[existing] = mark;
+                            }
+                        }
+                        None => {
+                            cells.insert(cell, marks.len());
+                            marks.push(mark);
+                        }
+                    }
+                }
+            }
+        }
+        // Painter's algorithm: furthest first, on-top layers last
+        marks.sort_by(|a, b| {
+            (a.on_top, -a.depth)
+                .partial_cmp(&(b.on_top, -b.depth))
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+
+        let mut svg = String::new();
+        let _ = writeln!(
+            svg,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" \
+             viewBox=\"0 0 {} {}\">",
+            width, height, width, height
+        );
+        for mark in &marks {
+            let _ = writeln!(svg, "{}", mark.element);
+        }
+        svg.push_str("</svg>\n");
+        return svg;
+    }
+}
+
+/// Where `vertex` lands in the window in pixels, and its depth, if it is inside the view.
+pub fn project(
+    view_proj: &nalgebra::Matrix4<f32>,
+    vertex: &Vertex,
+    view: &viewport::View,
+) -> Option<(winit::dpi::PhysicalPosition<f64>, f32)> {
+    let clip = view_proj * nalgebra::Vector4::from(vertex.position);
+    if clip.w <= 0.0 {
+        return None;
+    }
+    let depth = clip.z / clip.w;
+    if !(0.0..=1.0).contains(&depth) {
+        return None;
+    }
+    let screen = winit::dpi::PhysicalPosition::new(
+        view.rect.x as f64 + (clip.x / clip.w + 1.0) as f64 * 0.5 * view.rect.width as f64,
+        view.rect.y as f64 + (1.0 - clip.y / clip.w) as f64 * 0.5 * view.rect.height as f64,
+    );
+    return Some((screen, depth));
+}
+
+/// The point's colour after the layer's colour input settings, tint and `grading`, as an sRGB hex
+/// colour like the swap chain shows.
+fn svg_color(vertex: &Vertex, style: &LineBuffers, grading: &Grading) -> String {
+    let mut color = style.read_color(vertex);
+    if grading.srgb_points {
+        for channel in color.iter_mut() {
+            *channel = srgb_to_linear(*channel);
+        }
+    }
+    for (channel, &tint) in color.iter_mut().zip(&style.tint.color) {
+        match style.tint.mode {
+            TintMode::Off => {}
+            TintMode::Multiply => *channel *= tint,
+            TintMode::Replace => *channel = tint,
+        }
+    }
+    let color = grading.apply(color);
+    let srgb = |linear: f32| (linear_to_srgb(linear) * 255.0).round() as u8;
+    return format!(
+        "#{:02x}{:02x}{:02x}",
+        srgb(color[0]),
+        srgb(color[1]),
+        srgb(color[2])
+    );
+}
+
+/// An opacity attribute for translucent layers, nothing for opaque ones.
+fn opacity(property: &str, style: &LineBuffers) -> String {
+    if style.tint.alpha < 1.0 {
+        return format!(" {}-opacity=\"{}\"", property, style.tint.alpha);
+    }
+    return String::new();
+}
@@ -0,0 +1,427 @@
+use super::OrbitCamera;
+
+/// A region of the window in physical pixels.
+#[derive(Debug, Copy, Clone)]
+pub struct Rect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Rect {
+    pub fn contains(&self, position: winit::dpi::PhysicalPosition<f64>) -> bool {
+        position.x >= self.x as f64
+            && position.y >= self.y as f64
+            && position.x < (self.x + self.width) as f64
+            && position.y < (self.y + self.height) as f64
+    }
+}
+
+/// Which eye a view is rendered for.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Eye {
+    Both,
+    Left,
+    Right,
+}
+
+/// A camera and the part of the window it is drawn into.
+#[derive(Debug, Copy, Clone)]
+pub struct View {
+    pub rect: Rect,
+    /// The part of `rect` that is drawn to, everything but one side of a comparison divider.
+    pub clip: Rect,
+    pub camera: OrbitCamera,
+    pub eye: Eye,
+    /// Whether this is the cross-section profile's view, which shows only the profile.
+    pub profile: bool,
+}
+
+impl View {
+    fn new(rect: Rect, camera: OrbitCamera, eye: Eye) -> Self {
+        View {
+            rect: rect,
+            clip: rect,
+            camera: camera,
+            eye: eye,
+            profile: false,
+        }
+    }
+
+    /// The same view clipped to the left and right of a divider `fraction` of the way across.
+    pub fn split(&self, fraction: f32) -> (View, View) {
+        let left_width = (self.clip.width as f32 * fraction.clamp(0.0, 1.0)).round() as u32;
+        let left = View {
+            clip: Rect {
+                width: left_width,
+                ..self.clip
+            },
+            ..*self
+        };
+        let right = View {
+            clip: Rect {
+                x: self.clip.x + left_width,
+                width: self.clip.width - left_width,
+                ..self.clip
+            },
+            ..*self
+        };
+        return (left, right);
+    }
+}
+
+/// Swipe comparison: the first layer is only drawn left of the divider and the second only right
+/// of it, in every view.
+#[derive(Debug, Copy, Clone)]
+pub struct Comparison {
+    pub layers: [usize; 2],
+    /// Position of the divider as a fraction of each view's width.
+    pub split: f32,
+}
+
+impl Comparison {
+    /// How `layer` is drawn in `view`.
+    pub fn clip(&self, view: &View, layer: usize) -> View {
+        let (left, right) = view.split(self.split);
+        if layer == self.layers[0] {
+            return left;
+        }
+        if layer == self.layers[1] {
+            return right;
+        }
+        return *view;
+    }
+}
+
+/// How close, in pixels, the cursor must be to the comparison divider to drag it.
+const DIVIDER_GRAB_DISTANCE: f64 = 8.0;
+
+/// Index of the profile view's camera in `Viewports::cameras`, after the layout's.
+const PROFILE_CAMERA: usize = 4;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum StereoMode {
+    Off,
+    /// Both eyes drawn over each other, in red for the left and cyan for the right.
+    Anaglyph,
+    /// Each eye squeezed into half of the viewport, as 3D TVs and projectors expect.
+    SideBySide,
+}
+
+impl StereoMode {
+    fn next(self) -> Self {
+        match self {
+            StereoMode::Off => StereoMode::Anaglyph,
+            StereoMode::Anaglyph => StereoMode::SideBySide,
+            StereoMode::SideBySide => StereoMode::Off,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Layout {
+    Single,
+    /// Perspective and top views side by side.
+    SideBySide,
+    /// Perspective, top, front and side views in a 2x2 grid.
+    Quad,
+}
+
+impl Layout {
+    fn next(self) -> Self {
+        match self {
+            Layout::Single => Layout::SideBySide,
+            Layout::SideBySide => Layout::Quad,
+            Layout::Quad => Layout::Single,
+        }
+    }
+
+    fn rects(self, width: u32, height: u32) -> Vec<Rect> {
+        let half_width = width / 2;
+        let half_height = height / 2;
+        let rect = |x, y, width, height| Rect {
+            x: x,
+            y: y,
+            width: width,
+            height: height,
+        };
+        match self {
+            Layout::Single => vec![rect(0, 0, width, height)],
+            Layout::SideBySide => vec![
+                rect(0, 0, half_width, height),
+                rect(half_width, 0, width - half_width, height),
+            ],
+            Layout::Quad => vec![
+                rect(0, 0, half_width, half_height),
+                rect(half_width, 0, width - half_width, half_height),
+                rect(0, half_height, half_width, height - half_height),
+                rect(
+                    half_width,
+                    half_height,
+                    width - half_width,
+                    height - half_height,
+                ),
+            ],
+        }
+    }
+}
+
+/// The split-view layout and the camera for each viewport. Navigation applies to the viewport
+/// that was last clicked, and when `linked` the target and zoom are shared between all of them.
+#[derive(Clone)]
+pub struct Viewports {
+    pub layout: Layout,
+    pub linked: bool,
+    pub stereo: StereoMode,
+    /// Distance between the eyes as a fraction of the distance to the target.
+    pub eye_separation: f32,
+    pub comparison: Option<Comparison>,
+    /// Whether the bottom third of the window shows the cross-section profile, see `section`,
+    /// with the layout's views above it.
+    profile: bool,
+    /// A camera per view of the layout, then the profile view's.
+    cameras: [OrbitCamera; 5],
+    active: usize,
+    size: winit::dpi::PhysicalSize<u32>,
+}
+
+impl Viewports {
+    pub fn new(size: winit::dpi::PhysicalSize<u32>) -> Self {
+        Viewports {
+            layout: Layout::Single,
+            linked: true,
+            stereo: StereoMode::Off,
+            eye_separation: 1.0 / 30.0,
+            comparison: None,
+            profile: false,
+            cameras: [
+                OrbitCamera::default(1.0),
+                // Top, front and side
+                OrbitCamera::orthographic(1.0, -90.0, 90.0),
+                OrbitCamera::orthographic(1.0, -90.0, 0.0),
+                OrbitCamera::orthographic(1.0, 0.0, 0.0),
+                // Profiles lie in the XZ plane, so look at them from the front too
+                OrbitCamera::orthographic(1.0, -90.0, 0.0),
+            ],
+            active: 0,
+            size: size,
+        }
+    }
+
+    pub fn resize(&mut self, size: winit::dpi::PhysicalSize<u32>) {
+        self.size = size;
+    }
+
+    pub fn cycle_layout(&mut self) {
+        self.layout = self.layout.next();
+        if self
+            .rects()
+            .iter()
+            .all(|(camera, _)| *camera != self.active)
+        {
+            self.active = 0;
+        }
+    }
+
+    pub fn set_profile(&mut self, profile: bool) {
+        self.profile = profile;
+        if !profile && self.active == PROFILE_CAMERA {
+            self.active = 0;
+        }
+    }
+
+    pub fn profile(&self) -> bool {
+        self.profile
+    }
+
+    pub fn cycle_stereo(&mut self) {
+        self.stereo = self.stereo.next();
+    }
+
+    /// Each view's rect, with the index of its camera.
+    fn rects(&self) -> Vec<(usize, Rect)> {
+        if !self.profile {
+            return self
+                .layout
+                .rects(self.size.width, self.size.height)
+                .into_iter()
+                .enumerate()
+                .collect();
+        }
+        let profile_height = self.size.height / 3;
+        let layout_height = self.size.height - profile_height;
+        let mut rects: Vec<(usize, Rect)> = self
+            .layout
+            .rects(self.size.width, layout_height)
+            .into_iter()
+            .enumerate()
+            .collect();
+        rects.push((
+            PROFILE_CAMERA,
+            Rect {
+                x: 0,
+                y: layout_height,
+                width: self.size.width,
+                height: profile_height,
+            },
+        ));
+        return rects;
+    }
+
+    /// Everything to draw, in order. Stereo modes produce a view per eye for each viewport.
+    pub fn views(&self) -> Vec<View> {
+        let mut views = Vec::new();
+        for (index, rect) in self.rects() {
+            let mut camera = self.cameras[index];
+            camera.set_viewport_size(rect.width, rect.height);
+            if index == PROFILE_CAMERA {
+                // The profile is flat, so it has no use for stereo
+                let mut view = View::new(rect, camera, Eye::Both);
+                view.profile = true;
+                views.push(view);
+                continue;
+            }
+            let half_separation = 0.5 * self.eye_separation * camera.range();
+            let left = camera.with_eye_shift(-half_separation);
+            let right = camera.with_eye_shift(half_separation);
+            match self.stereo {
+                StereoMode::Off => views.push(View::new(rect, camera, Eye::Both)),
+                StereoMode::Anaglyph => {
+                    views.push(View::new(rect, left, Eye::Left));
+                    views.push(View::new(rect, right, Eye::Right));
+                }
+                StereoMode::SideBySide => {
+                    let half_width = rect.width / 2;
+                    let left_rect = Rect {
+                        width: half_width,
+                        ..rect
+                    };
+                    let right_rect = Rect {
+                        x: rect.x + half_width,
+                        width: rect.width - half_width,
+                        ..rect
+                    };
+                    views.push(View::new(left_rect, left, Eye::Both));
+                    views.push(View::new(right_rect, right, Eye::Both));
+                }
+            }
+        }
+        return views;
+    }
+
+    pub fn view_at(&self, position: winit::dpi::PhysicalPosition<f64>) -> Option<View> {
+        self.views()
+            .into_iter()
+            .find(|view| view.rect.contains(position))
+    }
+
+    /// The rect of the view whose comparison divider is under `position`, if any.
+    pub fn divider_at(&self, position: winit::dpi::PhysicalPosition<f64>) -> Option<Rect> {
+        let comparison = self.comparison?;
+        let view = self.view_at(position)?;
+        let divider = view.rect.x as f64 + comparison.split as f64 * view.rect.width as f64;
+        if (position.x - divider).abs() > DIVIDER_GRAB_DISTANCE {
+            return None;
+        }
+        return Some(view.rect);
+    }
+
+    /// Move the comparison divider to `position`, measured within `rect`.
+    pub fn drag_divider(&mut self, rect: Rect, position: winit::dpi::PhysicalPosition<f64>) {
+        if let Some(comparison) = &mut self.comparison {
+            let split = (position.x - rect.x as f64) / rect.width.max(1) as f64;
+            comparison.split = split.clamp(0.0, 1.0) as f32;
+        }
+    }
+
+    /// Make the viewport under `position` the one that receives navigation.
+    pub fn activate_at(&mut self, position: winit::dpi::PhysicalPosition<f64>) {
+        if let Some((index, _)) = self
+            .rects()
+            .into_iter()
+            .find(|(_, rect)| rect.contains(position))
+        {
+            self.active = index;
+        }
+    }
+
+    /// Smooth navigation damping for every viewport's camera, see `OrbitCamera::damping`.
+    pub fn set_damping(&mut self, damping: f32) {
+        for camera in self.cameras.iter_mut() {
+            camera.damping = damping;
+        }
+    }
+
+    pub fn set_up_axis(&mut self, up_axis: super::UpAxis) {
+        for camera in self.cameras.iter_mut() {
+            camera.up_axis = up_axis;
+        }
+    }
+
+    pub fn up_axis(&self) -> super::UpAxis {
+        self.cameras[0].up_axis
+    }
+
+    /// Advance smoothed camera movement. Returns whether any camera moved.
+    pub fn step(&mut self, dt: std::time::Duration) -> bool {
+        let mut moved = false;
+        for camera in self.cameras.iter_mut() {
+            moved |= camera.step(dt);
+        }
+        if moved {
+            self.sync_linked();
+        }
+        return moved;
+    }
+
+    /// The camera of the active viewport, or the first viewport's while the profile's view is
+    /// active, as that camera looks at the profile's coordinates rather than the scene's.
+    pub fn camera(&self) -> &OrbitCamera {
+        if self.active == PROFILE_CAMERA {
+            return &self.cameras[0];
+        }
+        return &self.cameras[self.active];
+    }
+
+    /// Apply a camera movement to the active viewport.
+    pub fn update<F: FnOnce(&mut OrbitCamera)>(&mut self, movement: F) {
+        if let Some((_, rect)) = self.rects().into_iter().find(|(i, _)| *i == self.active) {
+            self.cameras[self.active].set_viewport_size(rect.width, rect.height);
+        }
+        movement(&mut self.cameras[self.active]);
+        self.sync_linked();
+    }
+
+    /// Frame the profile view on `aabb`, the bounds of the profile as drawn.
+    pub fn frame_profile(&mut self, aabb: &super::bounds::Aabb) {
+        let rect = self.rects().into_iter().find(|(i, _)| *i == PROFILE_CAMERA);
+        let camera = &mut self.cameras[PROFILE_CAMERA];
+        if let Some((_, rect)) = rect {
+            camera.set_viewport_size(rect.width, rect.height);
+        }
+        camera.frame(aabb);
+    }
+
+    /// Move the targets of the scene's cameras by `offset`, e.g. to follow the scene when it is
+    /// drawn relative to a new origin. The profile view has its own coordinates, so stays.
+    pub fn translate(&mut self, offset: nalgebra::Vector3<f32>) {
+        for camera in self.cameras[..PROFILE_CAMERA].iter_mut() {
+            camera.target += offset;
+        }
+    }
+
+    fn sync_linked(&mut self) {
+        // The profile is drawn in its own coordinates, so its view isn't linked to the others
+        if self.linked && self.active != PROFILE_CAMERA {
+            let (target, range) = (
+                self.cameras[self.active].target,
+                self.cameras[self.active].range,
+            );
+            for camera in self.cameras[..PROFILE_CAMERA].iter_mut() {
+                camera.target = target;
+                camera.range = range;
+            }
+        }
+    }
+}
@@ -0,0 +1,588 @@
+//! Dense 3D scalar fields, such as simulation outputs, drawn by marching rays through a 3D
+//! texture and mapping the values they pass through to colour and opacity with a transfer
+//! function. Slicing planes cut the volume open to show the values on them.
+
+use super::{viewport, OrbitCamera, GEN_DIR};
+
+/// Most stops a transfer function can have.
+pub const MAX_STOPS: usize = 8;
+
+/// Most voxels along any axis, the smallest 3D texture size GPUs are required to support.
+pub const MAX_DIMENSION: usize = 2048;
+
+/// Values are stored as half floats, normalised to the volume's range, so they can be filtered.
+const TEXTURE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::R16Float;
+
+/// A grid of scalar values on the CPU, with X varying fastest, then Y, then Z.
+#[derive(Clone)]
+pub struct Volume {
+    pub dims: [usize; 3],
+    pub values: Vec<f32>,
+    /// World position of the centre of the first voxel.
+    pub origin: nalgebra::Vector3<f64>,
+    /// Distance between voxel centres along each axis.
+    pub spacing: [f64; 3],
+}
+
+impl Volume {
+    /// Smallest and largest value, ignoring NaNs, or `None` if every value is NaN.
+    pub fn range(&self) -> Option<[f32; 2]> {
+        let mut values = self.values.iter().filter(|value| !value.is_nan());
+        let first = *values.next()?;
+        let range = values.fold([first, first], |range, &value| {
+            [range[0].min(value), range[1].max(value)]
+        });
+        return Some(range);
+    }
+}
+
+/// Maps values to colour and opacity, interpolating between stops.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransferFunction {
+    /// Position from 0 at the bottom of the window to 1 at the top, in increasing order, and
+    /// colour. Alpha is the opacity of one voxel's depth of the volume.
+    pub stops: Vec<(f32, [f32; 4])>,
+}
+
+impl TransferFunction {
+    /// `grey`, `hot`, `viridis`, or stops separated by `;` each written as
+    /// `POSITION:R,G,B,A`, e.g. `0:0,0,1,0;1:1,0,0,0.2`.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let stops = match text.trim() {
+            "grey" => vec![(0.0, [0.0, 0.0, 0.0, 0.0]), (1.0, [1.0, 1.0, 1.0, 0.2])],
+            "hot" => vec![
+                (0.0, [0.0, 0.0, 0.0, 0.0]),
+                (0.35, [0.9, 0.0, 0.0, 0.05]),
+                (0.7, [1.0, 0.8, 0.0, 0.15]),
+                (1.0, [1.0, 1.0, 1.0, 0.3]),
+            ],
+            "viridis" => vec![
+                (0.0, [0.267, 0.005, 0.329, 0.0]),
+                (0.25, [0.229, 0.322, 0.546, 0.05]),
+                (0.5, [0.128, 0.567, 0.551, 0.1]),
+                (0.75, [0.369, 0.789, 0.383, 0.15]),
+                (1.0, [0.993, 0.906, 0.144, 0.25]),
+            ],
+            custom => custom
+                .split(';')
+                .map(parse_stop)
+                .collect::<Result<Vec<(f32, [f32; 4])>, String>>()?,
+        };
+        if stops.is_empty() || stops.len() > MAX_STOPS {
+            return Err(format!(
+                "A transfer function needs 1 to {} stops",
+                MAX_STOPS
+            ));
+        }
+        if stops.windows(2).any(|pair| pair[1].0 < pair[0].0) {
+            return Err("Transfer function stops must be in increasing order".to_string());
+        }
+        return Ok(TransferFunction { stops: stops });
+    }
+}
+
+fn parse_stop(text: &str) -> Result<(f32, [f32; 4]), String> {
+    let invalid = || format!("Invalid transfer function stop '{}'", text);
+    let mut split = text.splitn(2, ':');
+    let position = split
+        .next()
+        .unwrap_or("")
+        .trim()
+        .parse::<f32>()
+        .map_err(|_| invalid())?;
+    let channels = split
+        .next()
+        .unwrap_or("")
+        .split(',')
+        .map(|channel| channel.trim().parse::<f32>())
+        .collect::<Result<Vec<f32>, _>>()
+        .map_err(|_| invalid())?;
+    if channels.len() != 4 {
+        return Err(invalid());
+    }
+    return Ok((
+        position,
+        [channels[0], channels[1], channels[2], channels[3]],
+    ));
+}
+
+/// GPU-resident copy of a `Volume`, created by `Renderer::upload_volume`, and how it is drawn.
+pub struct VolumeBuffers {
+    cube_buffer: wgpu::Buffer,
+    uniform_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    dims: [usize; 3],
+    /// World position of the corner of the box the voxels fill, and the box's size.
+    corner: nalgebra::Vector3<f64>,
+    extent: nalgebra::Vector3<f64>,
+    /// The values stored as 0 and 1 in the texture.
+    range: [f32; 2],
+    pub transfer: TransferFunction,
+    /// Values mapped to the bottom and top of the transfer function, by default the volume's
+    /// range.
+    pub window: [f32; 2],
+    /// Scales the transfer function's opacity.
+    pub opacity: f32,
+    /// Opposite corners, in world coordinates, of the only part of the volume drawn.
+    pub clip: Option<[[f64; 3]; 2]>,
+    /// World position along X, Y and Z of the slicing planes, if any. The nearest plane a ray
+    /// reaches is drawn opaque, hiding the volume behind it.
+    pub slices: [Option<f64>; 3],
+    pub visible: bool,
+}
+
+#[repr(C, align(16))]
+#[derive(Debug, Copy, Clone)]
+struct VolumeUniform {
+    model: [[f32; 4]; 4],
+    inverse_model: [[f32; 4]; 4],
+    clip_min: [f32; 4],
+    clip_max: [f32; 4],
+    slices: [f32; 4],
+    dims: [f32; 4],
+    window: [f32; 4],
+    counts: [u32; 4],
+    stop_colours: [[f32; 4]; MAX_STOPS],
+    stop_positions: [f32; MAX_STOPS],
+}
+
+/// Resources shared by every volume. Which faces of a volume's box rays start from, and whether
+/// they are hidden by the points in front of them, depends on whether the camera is inside it.
+pub struct Raymarcher {
+    bind_group_layout: wgpu::BindGroupLayout,
+    /// Indexed by whether the camera is inside the volume.
+    pipelines: [wgpu::RenderPipeline; 2],
+    sampler: wgpu::Sampler,
+}
+
+impl Raymarcher {
+    pub fn new(device: &wgpu::Device, uniforms_bind_group_layout: &wgpu::BindGroupLayout) -> Self {
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            bindings: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStage::VERTEX | wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::UniformBuffer { dynamic: false },
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::SampledTexture {
+                        dimension: wgpu::TextureViewDimension::D3,
+                        component_type: wgpu::TextureComponentType::Float,
+                        multisampled: false,
+                    },
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler { comparison: false },
+                },
+            ],
+            label: None,
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            bind_group_layouts: &[uniforms_bind_group_layout, &bind_group_layout],
+        });
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            lod_min_clamp: 0.0,
+            lod_max_clamp: 0.0,
+            compare: wgpu::CompareFunction::Undefined,
+        });
+        Raymarcher {
+            pipelines: [
+                // From outside, points in front of the volume hide it
+                create_pipeline(device, &pipeline_layout, wgpu::CompareFunction::LessEqual),
+                // From inside, rays start at the camera so there is no face to test
+                create_pipeline(device, &pipeline_layout, wgpu::CompareFunction::Always),
+            ],
+            bind_group_layout: bind_group_layout,
+            sampler: sampler,
+        }
+    }
+}
+
+fn create_pipeline(
+    device: &wgpu::Device,
+    pipeline_layout: &wgpu::PipelineLayout,
+    depth_compare: wgpu::CompareFunction,
+) -> wgpu::RenderPipeline {
+    let vs_bytes = GEN_DIR
+        .get_file("shaders/volume.vert.spv")
+        .unwrap()
+        .contents();
+    let fs_bytes = GEN_DIR
+        .get_file("shaders/volume.frag.spv")
+        .unwrap()
+        .contents();
+    let vs_module =
+        device.create_shader_module(&wgpu::read_spirv(std::io::Cursor::new(vs_bytes)).unwrap());
+    let fs_module =
+        device.create_shader_module(&wgpu::read_spirv(std::io::Cursor::new(fs_bytes)).unwrap());
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        layout: pipeline_layout,
+        vertex_stage: wgpu::ProgrammableStageDescriptor {
+            module: &vs_module,
+            entry_point: "main",
+        },
+        fragment_stage: Some(wgpu::ProgrammableStageDescriptor {
+            module: &fs_module,
+            entry_point: "main",
+        }),
+        // The fragment shader picks which faces to march from, so it doesn't matter how the
+        // cube is wound
+        rasterization_state: Some(wgpu::RasterizationStateDescriptor {
+            front_face: wgpu::FrontFace::Ccw,
+            cull_mode: wgpu::CullMode::None,
+            depth_bias: 0,
+            depth_bias_slope_scale: 0.0,
+            depth_bias_clamp: 0.0,
+        }),
+        primitive_topology: wgpu::PrimitiveTopology::TriangleList,
+        // The fragment shader outputs premultiplied colour
+        color_states: &[wgpu::ColorStateDescriptor {
+            format: wgpu::TextureFormat::Bgra8UnormSrgb,
+            color_blend: wgpu::BlendDescriptor {
+                src_factor: wgpu::BlendFactor::One,
+                dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+                operation: wgpu::BlendOperation::Add,
+            },
+            alpha_blend: wgpu::BlendDescriptor {
+                src_factor: wgpu::BlendFactor::One,
+                dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+                operation: wgpu::BlendOperation::Add,
+            },
+            write_mask: wgpu::ColorWrite::ALL,
+        }],
+        depth_stencil_state: Some(wgpu::DepthStencilStateDescriptor {
+            format: wgpu::TextureFormat::Depth32Float,
+            depth_write_enabled: false,
+            depth_compare: depth_compare,
+            stencil_front: wgpu::StencilStateFaceDescriptor::IGNORE,
+            stencil_back: wgpu::StencilStateFaceDescriptor::IGNORE,
+            stencil_read_mask: 0,
+            stencil_write_mask: 0,
+        }),
+        vertex_state: wgpu::VertexStateDescriptor {
+            index_format: wgpu::IndexFormat::Uint32,
+            vertex_buffers: &[wgpu::VertexBufferDescriptor {
+                stride: std::mem::size_of::<[f32; 4]>() as wgpu::BufferAddress,
+                step_mode: wgpu::InputStepMode::Vertex,
+                attributes: &[wgpu::VertexAttributeDescriptor {
+                    format: wgpu::VertexFormat::Float4,
+                    offset: 0,
+                    shader_location: 0,
+                }],
+            }],
+        },
+        sample_count: 1,
+        sample_mask: !0,
+        alpha_to_coverage_enabled: false,
+    })
+}
+
+/// Triangle list of the unit cube from 0 to 1, which is also the volume's texture coordinates.
+fn unit_cube() -> Vec<[f32; 4]> {
+    let mut vertices = Vec::with_capacity(36);
+    for axis in 0..3 {
+        for &side in &[0.0, 1.0] {
+            let (u, v) = ((axis + 1) % 3, (axis + 2) % 3);
+            let corner = |a: f32, b: f32| {
+                let mut position = [0.0, 0.0, 0.0, 1.0];
+                position[axis] = side;
+                position[u] = a;
+                position[v] = b;
+                position
+            };
+            let (a, b, c, d) = (
+                corner(0.0, 0.0),
+                corner(1.0, 0.0),
+                corner(1.0, 1.0),
+                corner(0.0, 1.0),
+            );
+            vertices.extend_from_slice(&[a, b, c, a, c, d]);
+        }
+    }
+    return vertices;
+}
+
+/// The bits of the half float nearest `value`, flushing values too small for a normal half float
+/// to zero.
+fn half_float_bits(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32 - 127 + 15;
+    let mantissa = bits & 0x7f_ffff;
+    if exponent <= 0 {
+        return sign;
+    }
+    if exponent >= 31 {
+        return sign | 0x7c00;
+    }
+    // Round to nearest, which may carry into the exponent
+    return sign | (((exponent as u32) << 10) + ((mantissa + 0x1000) >> 13)) as u16;
+}
+
+impl super::Renderer {
+    /// Upload a volume as a 3D texture of its values normalised to its range. NaNs are stored as
+    /// the smallest value.
+    pub fn upload_volume(&mut self, volume: &Volume) -> VolumeBuffers {
+        let range = volume.range().unwrap_or([0.0, 1.0]);
+        let scale = if range[1] > range[0] {
+            1.0 / (range[1] - range[0])
+        } else {
+            0.0
+        };
+        let [width, height, depth] = volume.dims;
+        let alignment = super::capture::BYTES_PER_ROW_ALIGNMENT as usize;
+        let bytes_per_row = (width * 2).div_ceil(alignment) * alignment;
+        let mut texels = vec![0_u8; bytes_per_row * height * depth];
+        for row in 0..height * depth {
+            let values = &volume.values[row * width..(row + 1) * width];
+            for (x, &value) in values.iter().enumerate() {
+                let normalised = if value.is_nan() {
+                    0.0
+                } else {
+                    (value - range[0]) * scale
+                };
+                let offset = row * bytes_per_row + x * 2;
+                texels[offset..offset + 2]
+                    .copy_from_slice(&half_float_bits(normalised).to_ne_bytes());
+            }
+        }
+        let size = wgpu::Extent3d {
+            width: width as u32,
+            height: height as u32,
+            depth: depth as u32,
+        };
+        let texture = self.device.create_texture(&wgpu::TextureDescriptor {
+            format: TEXTURE_FORMAT,
+            usage: wgpu::TextureUsage::SAMPLED | wgpu::TextureUsage::COPY_DST,
+            size: size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D3,
+            label: None,
+            array_layer_count: 1,
+        });
+        let staging_buffer = self
+            .device
+            .create_buffer_with_data(&texels, wgpu::BufferUsage::COPY_SRC);
+        let mut command_encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        command_encoder.copy_buffer_to_texture(
+            wgpu::BufferCopyView {
+                buffer: &staging_buffer,
+                offset: 0,
+                bytes_per_row: bytes_per_row as u32,
+                rows_per_image: height as u32,
+            },
+            wgpu::TextureCopyView {
+                texture: &texture,
+                mip_level: 0,
+                array_layer: 0,
+                origin: wgpu::Origin3d::ZERO,
+            },
+            size,
+        );
+        self.queue.submit(&[command_encoder.finish()]);
+        self.stats.record_upload(texels.len());
+
+        let uniform_size = std::mem::size_of::<VolumeUniform>() as wgpu::BufferAddress;
+        let uniform_buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: uniform_size,
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+        });
+        let texture_view = texture.create_default_view();
+        let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &self.raymarcher.bind_group_layout,
+            bindings: &[
+                wgpu::Binding {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Buffer {
+                        buffer: &uniform_buffer,
+                        range: 0..uniform_size,
+                    },
+                },
+                wgpu::Binding {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&texture_view),
+                },
+                wgpu::Binding {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&self.raymarcher.sampler),
+                },
+            ],
+            label: None,
+        });
+        let mut corner = volume.origin;
+        let mut extent = nalgebra::Vector3::zeros();
+        for axis in 0..3 {
+            // Voxels are centred on their grid positions, so the box reaches half a voxel past
+            corner[axis] -= volume.spacing[axis] / 2.0;
+            extent[axis] = volume.spacing[axis] * volume.dims[axis] as f64;
+        }
+        return VolumeBuffers {
+            cube_buffer: self.device.create_buffer_with_data(
+                super::u8_slice_from_slice(&unit_cube()),
+                wgpu::BufferUsage::VERTEX,
+            ),
+            uniform_buffer: uniform_buffer,
+            bind_group: bind_group,
+            dims: volume.dims,
+            corner: corner,
+            extent: extent,
+            range: range,
+            transfer: TransferFunction::parse("grey").unwrap(),
+            window: range,
+            opacity: 1.0,
+            clip: None,
+            slices: [None; 3],
+            visible: true,
+        };
+    }
+
+    fn update_volume(
+        &mut self,
+        command_encoder: &mut wgpu::CommandEncoder,
+        volume: &VolumeBuffers,
+        camera: &OrbitCamera,
+    ) -> bool {
+        let corner: nalgebra::Vector3<f32> = nalgebra::convert(volume.corner - self.origin);
+        let extent: nalgebra::Vector3<f32> = nalgebra::convert(volume.extent);
+        let model = nalgebra::Matrix4::new_translation(&corner)
+            * nalgebra::Matrix4::new_nonuniform_scaling(&extent);
+        let inverse_model = model
+            .try_inverse()
+            .unwrap_or_else(nalgebra::Matrix4::identity);
+        // Fractions of the box along each axis
+        let fraction =
+            |axis: usize, world: f64| ((world - volume.corner[axis]) / volume.extent[axis]) as f32;
+        let (mut clip_min, mut clip_max) = ([0.0, 0.0, 0.0, 0.0], [1.0, 1.0, 1.0, 0.0]);
+        if let Some([low, high]) = volume.clip {
+            for axis in 0..3 {
+                clip_min[axis] = fraction(axis, low[axis].min(high[axis]));
+                clip_max[axis] = fraction(axis, low[axis].max(high[axis]));
+            }
+        }
+        let mut slices = [0.0; 4];
+        let mut slice_mask = 0;
+        for (axis, slice) in volume.slices.iter().enumerate() {
+            if let Some(position) = *slice {
+                slices[axis] = fraction(axis, position);
+                slice_mask |= 1 << axis;
+            }
+        }
+        let eye = inverse_model.transform_point(&camera.world_eye());
+        let inside = (0..3).all(|axis| eye[axis] >= 0.0 && eye[axis] <= 1.0);
+        let span = volume.range[1] - volume.range[0];
+        let normalise = |value: f32| {
+            if span > 0.0 {
+                (value - volume.range[0]) / span
+            } else {
+                0.0
+            }
+        };
+        let low = normalise(volume.window[0]);
+        // Keep the window from collapsing, which would divide by zero
+        let high = normalise(volume.window[1]).max(low + 1e-6);
+        let mut stop_colours = [[0.0; 4]; MAX_STOPS];
+        let mut stop_positions = [0.0; MAX_STOPS];
+        for (i, (position, colour)) in volume.transfer.stops.iter().enumerate() {
+            stop_positions[i] = *position;
+            stop_colours[i] = *colour;
+        }
+        let uniform = VolumeUniform {
+            model: *model.as_ref(),
+            inverse_model: *inverse_model.as_ref(),
+            clip_min: clip_min,
+            clip_max: clip_max,
+            slices: slices,
+            dims: [
+                volume.dims[0] as f32,
+                volume.dims[1] as f32,
+                volume.dims[2] as f32,
+                0.0,
+            ],
+            window: [low, high, volume.opacity, 0.0],
+            counts: [
+                volume.transfer.stops.len() as u32,
+                inside as u32,
+                slice_mask,
+                0,
+            ],
+            stop_colours: stop_colours,
+            stop_positions: stop_positions,
+        };
+        let staging_buffer = self.device.create_buffer_with_data(
+            super::u8_slice_from_slice(std::slice::from_ref(&uniform)),
+            wgpu::BufferUsage::COPY_SRC,
+        );
+        let size = std::mem::size_of::<VolumeUniform>();
+        command_encoder.copy_buffer_to_buffer(
+            &staging_buffer,
+            0,
+            &volume.uniform_buffer,
+            0,
+            size as wgpu::BufferAddress,
+        );
+        self.stats.record_upload(size);
+        return inside;
+    }
+
+    /// Draw a volume into the view over what has been drawn so far, hidden by the points in
+    /// front of it. The camera uniform must already hold the view's camera.
+    pub fn render_volume(
+        &mut self,
+        command_encoder: &mut wgpu::CommandEncoder,
+        texture_view: &wgpu::TextureView,
+        volume: &VolumeBuffers,
+        view: &viewport::View,
+    ) {
+        if !volume.visible {
+            return;
+        }
+        let inside = self.update_volume(command_encoder, volume, &view.camera);
+        let mut render_pass = command_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
+                attachment: texture_view,
+                resolve_target: None,
+                load_op: wgpu::LoadOp::Load,
+                store_op: wgpu::StoreOp::Store,
+                clear_color: wgpu::Color::TRANSPARENT,
+            }],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachmentDescriptor {
+                attachment: &self.depth_texture_view,
+                depth_load_op: wgpu::LoadOp::Load,
+                depth_store_op: wgpu::StoreOp::Store,
+                clear_depth: 1.0,
+                stencil_load_op: wgpu::LoadOp::Load,
+                stencil_store_op: wgpu::StoreOp::Store,
+                clear_stencil: 0,
+            }),
+        });
+        render_pass.set_pipeline(&self.raymarcher.pipelines[inside as usize]);
+        render_pass.set_viewport(
+            view.rect.x as f32,
+            view.rect.y as f32,
+            view.rect.width as f32,
+            view.rect.height as f32,
+            0.0,
+            1.0,
+        );
+        render_pass.set_scissor_rect(view.clip.x, view.clip.y, view.clip.width, view.clip.height);
+        render_pass.set_bind_group(0, &self.uniforms_bind_group, &[]);
+        render_pass.set_bind_group(1, &volume.bind_group, &[]);
+        render_pass.set_vertex_buffer(0, &volume.cube_buffer, 0, 0);
+        render_pass.draw(0..36, 0..1);
+        self.stats.record_draw(1);
+    }
+}
@@ -0,0 +1,246 @@
+use super::pipelines::{Blend, PipelineKey, Shading};
+use super::{
+    bounds, viewport, Animation, ColorInput, DepthMode, Line, LineBuffers, PointStyle, Tint,
+    UpAxis, Vertex,
+};
+
+/// Which bounding boxes are drawn over the scene.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum BoundsOverlay {
+    Off,
+    /// The box around each layer.
+    Layers,
+    /// Each layer's box plus the boxes of its chunks, coloured by whether they were culled.
+    Chunks,
+}
+
+impl BoundsOverlay {
+    pub fn next(self) -> Self {
+        match self {
+            BoundsOverlay::Off => BoundsOverlay::Layers,
+            BoundsOverlay::Layers => BoundsOverlay::Chunks,
+            BoundsOverlay::Chunks => BoundsOverlay::Off,
+        }
+    }
+}
+
+const LAYER_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+const DRAWN_CHUNK_COLOR: [f32; 4] = [0.0, 1.0, 0.0, 1.0];
+const CULLED_CHUNK_COLOR: [f32; 4] = [1.0, 0.0, 0.0, 1.0];
+
+/// Vertices per box.
+const BOX_VERTICES: u32 = 24;
+
+const GUIDE_COLOR: [f32; 4] = [1.0, 1.0, 0.0, 1.0];
+const OUTLINE_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+/// Size of the highlighted point relative to its own, with the outline making up the difference.
+const HIGHLIGHT_SCALE: f32 = 1.5;
+/// Vertices in the guide lines, see `Highlight`.
+const GUIDE_VERTICES: u32 = 6;
+
+/// Marks the point under the cursor: the point drawn enlarged and outlined over everything, plus
+/// guide lines dropping from it to the ground plane and running along the ground to the axes, so
+/// its position can be read off precisely.
+pub struct Highlight {
+    point: LineBuffers,
+    guides: wgpu::Buffer,
+}
+
+/// Line list of a layer's bounding box, followed by each chunk's box once in the drawn colour and
+/// once in the culled colour, so either can be picked per frame without another upload.
+pub fn bounds_vertices(layer: &bounds::Aabb, chunks: &[bounds::Aabb]) -> Vec<Vertex> {
+    let mut vertices = layer.wireframe(LAYER_COLOR);
+    for chunk in chunks {
+        vertices.extend(chunk.wireframe(DRAWN_CHUNK_COLOR));
+        vertices.extend(chunk.wireframe(CULLED_CHUNK_COLOR));
+    }
+    return vertices;
+}
+
+/// Always on top, so boxes hidden behind points can still be seen.
+fn line_pipeline() -> PipelineKey {
+    PipelineKey::new(
+        wgpu::PrimitiveTopology::LineList,
+        Shading::Lines,
+        Blend::Replace,
+        DepthMode::OnTop,
+        viewport::Eye::Both,
+    )
+}
+
+impl super::Renderer {
+    /// Upload a highlight for `vertex`, a point of a line with the given origin.
+    pub fn upload_highlight(
+        &mut self,
+        vertex: &Vertex,
+        origin: &nalgebra::Vector3<f64>,
+        up_axis: UpAxis,
+    ) -> Highlight {
+        let outline = Vertex {
+            color: OUTLINE_COLOR,
+            size: vertex.size * HIGHLIGHT_SCALE,
+            ..*vertex
+        };
+        let mut point = self.upload(&Line {
+            indicies: vec![0, 1],
+            verticies: vec![outline, *vertex],
+            origin: *origin,
+            attributes: Vec::new(),
+            ids: None,
+            metadata: Vec::new(),
+        });
+        point.depth = DepthMode::OnTop;
+
+        let offset: nalgebra::Vector3<f32> = nalgebra::convert(origin - self.origin);
+        let position = [
+            vertex.position[0] + offset.x,
+            vertex.position[1] + offset.y,
+            vertex.position[2] + offset.z,
+        ];
+        let up = match up_axis {
+            UpAxis::Y => 1,
+            UpAxis::Z => 2,
+        };
+        let mut foot = position;
+        foot[up] = 0.0;
+        let mut ends = vec![position, foot];
+        for axis in (0..3).filter(|&axis| axis != up) {
+            let mut end = foot;
+            end[axis] = 0.0;
+            ends.push(foot);
+            ends.push(end);
+        }
+        let guides: Vec<Vertex> = ends
+            .iter()
+            .map(|end| Vertex {
+                position: [end[0], end[1], end[2], 1.0],
+                color: GUIDE_COLOR,
+                size: 1.0,
+            })
+            .collect();
+        self.reserve_missing_attribute(guides.len());
+        self.stats.record_upload(std::mem::size_of_val(&guides[..]));
+        let guides = self.device.create_buffer_with_data(
+            super::u8_slice_from_slice(&guides),
+            wgpu::BufferUsage::VERTEX,
+        );
+        return Highlight {
+            point: point,
+            guides: guides,
+        };
+    }
+
+    /// Draw a highlight over the view. The camera uniform must already hold the view's camera.
+    pub fn render_highlight(
+        &mut self,
+        command_encoder: &mut wgpu::CommandEncoder,
+        texture_view: &wgpu::TextureView,
+        highlight: &Highlight,
+        view: &viewport::View,
+    ) {
+        let colors = ColorInput::linear();
+        let points = PointStyle::none();
+        self.update_tint(
+            command_encoder,
+            &Tint::none(),
+            Animation::Off,
+            colors,
+            points,
+        );
+        self.pipelines
+            .prepare(&self.device, &self.pipeline_layout, line_pipeline());
+        {
+            let mut render_pass = self.begin_line_pass(command_encoder, texture_view, view);
+            render_pass.set_vertex_buffer(0, &highlight.guides, 0, 0);
+            render_pass.draw(0..GUIDE_VERTICES, 0..1);
+        }
+        self.render(
+            command_encoder,
+            texture_view,
+            &highlight.point,
+            view,
+            super::Clear::Nothing,
+        );
+    }
+
+    /// Draw the bounding boxes of `line` selected by `bounds_overlay` over the view. The camera
+    /// uniform must already hold the view's camera.
+    pub fn render_bounds(
+        &mut self,
+        command_encoder: &mut wgpu::CommandEncoder,
+        texture_view: &wgpu::TextureView,
+        line: &LineBuffers,
+        view: &viewport::View,
+    ) {
+        let bounds_buffer = match (&line.bounds_buffer, self.bounds_overlay) {
+            (Some(buffer), overlay) if overlay != BoundsOverlay::Off => buffer,
+            _ => return,
+        };
+        // Boxes keep their own colours whatever the layer's tint
+        let colors = ColorInput::linear();
+        let points = PointStyle::none();
+        self.update_tint(
+            command_encoder,
+            &Tint::none(),
+            Animation::Off,
+            colors,
+            points,
+        );
+        self.pipelines
+            .prepare(&self.device, &self.pipeline_layout, line_pipeline());
+        let view_proj = view.camera.view_projection();
+        let mut render_pass = self.begin_line_pass(command_encoder, texture_view, view);
+        render_pass.set_vertex_buffer(0, bounds_buffer, 0, 0);
+        render_pass.draw(0..BOX_VERTICES, 0..1);
+        if self.bounds_overlay == BoundsOverlay::Chunks {
+            for (i, chunk) in line.chunks.iter().enumerate() {
+                let mut start = BOX_VERTICES * (1 + 2 * i as u32);
+                if !chunk.bounds.is_visible(&view_proj) {
+                    start += BOX_VERTICES;
+                }
+                render_pass.draw(start..start + BOX_VERTICES, 0..1);
+            }
+        }
+    }
+
+    /// Start a pass drawing line lists over the view, with the vertices still to be bound. The
+    /// line pipeline must already be prepared.
+    fn begin_line_pass<'a>(
+        &'a self,
+        command_encoder: &'a mut wgpu::CommandEncoder,
+        texture_view: &'a wgpu::TextureView,
+        view: &viewport::View,
+    ) -> wgpu::RenderPass<'a> {
+        let mut render_pass = command_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
+                attachment: texture_view,
+                resolve_target: None,
+                load_op: wgpu::LoadOp::Load,
+                store_op: wgpu::StoreOp::Store,
+                clear_color: wgpu::Color::TRANSPARENT,
+            }],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachmentDescriptor {
+                attachment: &self.depth_texture_view,
+                depth_load_op: wgpu::LoadOp::Load,
+                depth_store_op: wgpu::StoreOp::Store,
+                clear_depth: 1.0,
+                stencil_load_op: wgpu::LoadOp::Load,
+                stencil_store_op: wgpu::StoreOp::Store,
+                clear_stencil: 0,
+            }),
+        });
+        render_pass.set_pipeline(self.pipelines.get(line_pipeline()));
+        render_pass.set_viewport(
+            view.rect.x as f32,
+            view.rect.y as f32,
+            view.rect.width as f32,
+            view.rect.height as f32,
+            0.0,
+            1.0,
+        );
+        render_pass.set_scissor_rect(view.clip.x, view.clip.y, view.clip.width, view.clip.height);
+        render_pass.set_bind_group(0, &self.uniforms_bind_group, &[]);
+        render_pass.set_vertex_buffer(1, &self.missing_attribute_buffer, 0, 0);
+        return render_pass;
+    }
+}
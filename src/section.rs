@@ -0,0 +1,139 @@
+//! Cross-section profiles, the usual way of inspecting road and rail corridor scans: the points
+//! within a slab around a vertical plane through two points, flattened into their distance along
+//! the section and their height.
+
+use crate::rendering::{Line, UpAxis, Vertex};
+
+/// A point of a profile, and where it came from.
+pub struct ProfilePoint {
+    /// Distance along the section from its start.
+    pub along: f64,
+    /// Height above the world origin along the up axis.
+    pub height: f64,
+    /// Signed distance from the section plane, positive to the left looking from start to end.
+    pub offset: f64,
+    /// World position.
+    pub position: nalgebra::Vector3<f64>,
+    /// The point as it was drawn, for its colour and size.
+    pub vertex: Vertex,
+    pub layer: usize,
+    /// The point's ID where its layer has IDs, otherwise its index within the layer's vertices.
+    pub point: usize,
+}
+
+pub struct Profile {
+    pub start: nalgebra::Vector3<f64>,
+    /// Width of the slab of points kept, centred on the section plane.
+    pub thickness: f64,
+    pub up_axis: UpAxis,
+    pub points: Vec<ProfilePoint>,
+}
+
+fn up_vector(up_axis: UpAxis) -> nalgebra::Vector3<f64> {
+    match up_axis {
+        UpAxis::Y => nalgebra::Vector3::y(),
+        UpAxis::Z => nalgebra::Vector3::z(),
+    }
+}
+
+impl Profile {
+    /// Extract the drawn points of `lines` within half of `thickness` of the vertical plane
+    /// through the world positions `start` and `end`, and between them along it.
+    pub fn extract(
+        lines: &[Line],
+        start: nalgebra::Vector3<f64>,
+        end: nalgebra::Vector3<f64>,
+        thickness: f64,
+        up_axis: UpAxis,
+    ) -> Result<Self, crate::error::RscatError> {
+        let up = up_vector(up_axis);
+        let span = end - start;
+        let horizontal = span - up * up.dot(&span);
+        let length = horizontal.norm();
+        if length <= f64::EPSILON * span.norm().max(1.0) {
+            return Err(crate::error::RscatError::Invalid(
+                "The ends of the section are directly above each other".to_string(),
+            ));
+        }
+        let direction = horizontal / length;
+        let normal = up.cross(&direction);
+        let mut points = Vec::new();
+        for (layer, line) in lines.iter().enumerate() {
+            for &index in &line.indicies {
+                let p = line.verticies[index as usize].position;
+                let position =
+                    line.origin + nalgebra::Vector3::new(p[0] as f64, p[1] as f64, p[2] as f64);
+                let relative = position - start;
+                let along = relative.dot(&direction);
+                let offset = relative.dot(&normal);
+                if along < 0.0 || along > length || offset.abs() > thickness / 2.0 {
+                    continue;
+                }
+                points.push(ProfilePoint {
+                    along: along,
+                    height: position.dot(&up),
+                    offset: offset,
+                    position: position,
+                    vertex: line.verticies[index as usize],
+                    layer: layer,
+                    point: match &line.ids {
+                        Some(ids) => ids[index as usize] as usize,
+                        None => index as usize,
+                    },
+                });
+            }
+        }
+        return Ok(Profile {
+            start: start,
+            thickness: thickness,
+            up_axis: up_axis,
+            points: points,
+        });
+    }
+
+    /// The profile as a layer to draw, with each point at its distance along the section on the
+    /// X axis and its height, relative to the start's, on the up axis. The layer's origin is
+    /// `origin`, the one the scene is drawn relative to, so it is drawn exactly as stored.
+    pub fn line(&self, origin: &nalgebra::Vector3<f64>) -> Line {
+        let up = up_vector(self.up_axis);
+        let base = self.start.dot(&up);
+        let verticies = self
+            .points
+            .iter()
+            .map(|point| {
+                let mut vertex = point.vertex;
+                let position = nalgebra::Vector3::x() * point.along + up * (point.height - base);
+                vertex.position = [position.x as f32, position.y as f32, position.z as f32, 1.0];
+                vertex
+            })
+            .collect::<Vec<_>>();
+        return Line {
+            indicies: (0..verticies.len() as u32).collect(),
+            verticies: verticies,
+            origin: *origin,
+            attributes: Vec::new(),
+            ids: None,
+            metadata: vec![("source".to_string(), "profile".to_string())],
+        };
+    }
+
+    /// CSV rows of distance along the section, height, offset from the section plane, world
+    /// position, layer and point, with a header.
+    pub fn to_csv(&self) -> Vec<u8> {
+        let mut csv = String::from("distance,height,offset,x,y,z,layer,point\n");
+        for point in &self.points {
+            csv += &format!(
+                "{},{},{},{},{},{},{},{}\n",
+                point.along,
+                point.height,
+                point.offset,
+                point.position.x,
+                point.position.y,
+                point.position.z,
+                point.layer,
+                point.point
+            );
+        }
+        return csv.into_bytes();
+    }
+}
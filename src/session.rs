@@ -0,0 +1,353 @@
+//! Autosave of the session to a recovery file, so a scene that took a while to compose survives
+//! a crash.
+//!
+//! The recovery file, `session.cfg` in the config directory, is rewritten a few seconds after
+//! anything in it changes and removed when the window is closed, so finding it at startup means
+//! the last session ended abnormally. It holds `key = value` lines of:
+//! - `file = PATH` for each file the layers were loaded from, in order
+//! - `camera = QUERY`, the active view's camera as a `/camera` query
+//! - `layer = QUERY` for each layer loaded from a file, as a `/layer` query
+//!
+//! Layers pushed remotely or derived in the viewer have no file to load them from again, so only
+//! the file layers and how they were drawn are restored.
+//!
+//! Only one window autosaves at a time. It claims the recovery file by writing its process ID to
+//! `session.lock` beside it. A window opened with `--new-window` while the owner is still running
+//! leaves the file alone, neither offering it for restoring nor overwriting it. A lock whose
+//! process is gone was left by a crash, and the next window to start takes it over.
+
+use crate::remote::{CameraChange, LayerChange};
+use std::path::PathBuf;
+
+/// How long after a change the recovery file is rewritten, so dragging the camera around doesn't
+/// write it every frame.
+pub const AUTOSAVE_DELAY: std::time::Duration = std::time::Duration::from_secs(5);
+
+pub struct Session {
+    pub files: Vec<PathBuf>,
+    pub camera: CameraChange,
+    /// Indexed among the layers the files load into.
+    pub layers: Vec<LayerChange>,
+}
+
+impl Session {
+    fn path() -> Option<PathBuf> {
+        crate::config::config_dir().map(|dir| dir.join("session.cfg"))
+    }
+
+    fn lock_path() -> Option<PathBuf> {
+        crate::config::config_dir().map(|dir| dir.join("session.lock"))
+    }
+
+    /// Make this process the one that autosaves and recovers the session, unless another that is
+    /// still running already is. Returns whether this process owns the session.
+    pub fn claim() -> bool {
+        let path = match Session::lock_path() {
+            Some(path) => path,
+            None => return false,
+        };
+        if let Some(dir) = path.parent() {
+            if let Err(e) = std::fs::create_dir_all(dir) {
+                warn!("Failed to create {}: {}", dir.display(), e);
+                return false;
+            }
+        }
+        let pid = std::process::id();
+        // Once more after removing a lock left by a crash
+        for _ in 0..2 {
+            let created = std::fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&path);
+            match created {
+                Ok(mut file) => {
+                    use std::io::Write;
+                    if let Err(e) = write!(file, "{}", pid) {
+                        warn!("Failed to write {}: {}", path.display(), e);
+                    }
+                    return true;
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {}
+                Err(e) => {
+                    warn!("Failed to create {}: {}", path.display(), e);
+                    return false;
+                }
+            }
+            let owner = std::fs::read_to_string(&path)
+                .ok()
+                .and_then(|text| text.trim().parse::<u32>().ok());
+            match owner {
+                Some(owner) if owner != pid && running(owner) => {
+                    info!(
+                        "rscat {} is autosaving the session, so this window won't",
+                        owner
+                    );
+                    return false;
+                }
+                _ => {
+                    let _ = std::fs::remove_file(&path);
+                }
+            }
+        }
+        return false;
+    }
+
+    /// Give up the session claimed with `claim`, e.g. on a clean exit.
+    pub fn release() {
+        if let Some(path) = Session::lock_path() {
+            let owner = std::fs::read_to_string(&path).ok();
+            if owner.is_some_and(|owner| owner.trim() == std::process::id().to_string()) {
+                if let Err(e) = std::fs::remove_file(&path) {
+                    warn!("Failed to remove {}: {}", path.display(), e);
+                }
+            }
+        }
+    }
+
+    /// The session left behind by a run that didn't exit cleanly, if there is one.
+    pub fn recover() -> Option<Self> {
+        let path = Session::path()?;
+        if !path.is_file() {
+            return None;
+        }
+        let mut session = Session {
+            files: Vec::new(),
+            camera: CameraChange {
+                target: None,
+                range: None,
+                azimuth: None,
+                elevation: None,
+            },
+            layers: Vec::new(),
+        };
+        for (key, value) in crate::config::read_settings(&path) {
+            let parsed = match key.as_str() {
+                "file" => {
+                    session.files.push(PathBuf::from(value));
+                    Ok(())
+                }
+                "camera" => crate::remote::parse_camera(&value).map(|camera| {
+                    session.camera = camera;
+                }),
+                "layer" => crate::remote::parse_layer(&value).map(|layer| {
+                    session.layers.push(layer);
+                }),
+                _ => Err(format!("Unknown setting '{}'", key)),
+            };
+            if let Err(e) = parsed {
+                warn!("Ignoring '{}' in {}: {}", key, path.display(), e);
+            }
+        }
+        if session.files.is_empty() {
+            return None;
+        }
+        return Some(session);
+    }
+
+    /// Remove the recovery file, e.g. on a clean exit.
+    pub fn discard() {
+        if let Some(path) = Session::path() {
+            if let Err(e) = std::fs::remove_file(&path) {
+                if e.kind() != std::io::ErrorKind::NotFound {
+                    warn!("Failed to remove {}: {}", path.display(), e);
+                }
+            }
+        }
+    }
+
+    /// Reapply the camera, and the drawing options of the layers once `files` have been loaded
+    /// and uploaded again.
+    pub fn apply(
+        &self,
+        line_buffers: &mut [crate::rendering::LineBuffers],
+        viewports: &mut crate::rendering::viewport::Viewports,
+    ) {
+        for change in &self.layers {
+            if let Some(line) = line_buffers.get_mut(change.index) {
+                change.apply(line);
+            }
+        }
+        let camera = self.camera;
+        viewports.update(|orbit| {
+            if let Some(target) = camera.target {
+                orbit.set_target(target);
+            }
+            if let Some(range) = camera.range {
+                orbit.set_range(range);
+            }
+            if let Some(azimuth) = camera.azimuth {
+                orbit.set_azimuth(azimuth);
+            }
+            if let Some(elevation) = camera.elevation {
+                orbit.set_elevation(elevation);
+            }
+        });
+    }
+}
+
+/// Whether process `pid` is still running. Process IDs are reused, so an unrelated process can be
+/// mistaken for a crashed owner, which only means its session isn't offered this time.
+#[cfg(unix)]
+fn running(pid: u32) -> bool {
+    // Signal 0 only checks that the process exists and could be signalled
+    let result = unsafe { libc::kill(pid as libc::pid_t, 0) };
+    return result == 0 || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM);
+}
+
+#[cfg(windows)]
+fn running(pid: u32) -> bool {
+    use winapi::shared::winerror::WAIT_TIMEOUT;
+    use winapi::um::{handleapi, processthreadsapi, synchapi, winnt};
+    unsafe {
+        let process = processthreadsapi::OpenProcess(winnt::SYNCHRONIZE, 0, pid);
+        if process.is_null() {
+            return false;
+        }
+        let running = synchapi::WaitForSingleObject(process, 0) == WAIT_TIMEOUT;
+        handleapi::CloseHandle(process);
+        return running;
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+fn running(_pid: u32) -> bool {
+    return false;
+}
+
+/// The name `value` is written as in the remote API.
+fn name<T: PartialEq>(names: &[(&'static str, T)], value: T) -> &'static str {
+    return names
+        .iter()
+        .find(|(_, named)| *named == value)
+        .map_or("", |(name, _)| *name);
+}
+
+/// `retention` as the remote API writes it.
+fn retention(retention: crate::rendering::Retention) -> String {
+    return match retention {
+        crate::rendering::Retention::All => "all".to_string(),
+        crate::rendering::Retention::Points(points) => points.to_string(),
+        crate::rendering::Retention::Seconds(keep) => format!("{}s", keep.as_secs_f64()),
+    };
+}
+
+/// The recovery file's lines for the current scene.
+fn settings(
+    lines: &[crate::rendering::Line],
+    line_buffers: &[crate::rendering::LineBuffers],
+    camera: &crate::rendering::OrbitCamera,
+) -> Vec<(&'static str, String)> {
+    let mut settings = Vec::new();
+    let mut files: Vec<&str> = Vec::new();
+    let mut index = 0;
+    for (line, buffers) in lines.iter().zip(line_buffers) {
+        // Only the file loaders record a format
+        let metadata = |name: &str| {
+            line.metadata
+                .iter()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.as_str())
+        };
+        let source = match (metadata("source"), metadata("format")) {
+            (Some(source), Some(_)) => source,
+            _ => continue,
+        };
+        if !files.contains(&source) {
+            files.push(source);
+            settings.push(("file", source.to_string()));
+        }
+        let [red, green, blue] = buffers.tint.color;
+        let query = [
+            format!("index={}", index),
+            format!(
+                "depth={}",
+                name(crate::rendering::DEPTH_MODE_NAMES, buffers.depth)
+            ),
+            format!("priority={}", buffers.priority),
+            format!(
+                "tint={}",
+                name(crate::rendering::TINT_MODE_NAMES, buffers.tint.mode)
+            ),
+            format!("color={},{},{}", red, green, blue),
+            format!("alpha={}", buffers.tint.alpha),
+            format!("path={}", buffers.path),
+            format!("retain={}", retention(buffers.retention)),
+            format!(
+                "animation={}",
+                name(crate::rendering::ANIMATION_NAMES, buffers.animation)
+            ),
+            format!(
+                "colors={}",
+                name(crate::rendering::COLOR_RANGE_NAMES, buffers.color_range)
+            ),
+            format!(
+                "order={}",
+                name(crate::rendering::CHANNEL_ORDER_NAMES, buffers.channel_order)
+            ),
+            format!(
+                "shape={}",
+                name(crate::rendering::POINT_SHAPE_NAMES, buffers.shape)
+            ),
+            format!("min_size={}", buffers.min_size),
+            format!("max_size={}", buffers.max_size),
+            format!("evict={}", buffers.evict),
+            format!("labels={}", buffers.labels),
+            match buffers.label_range {
+                Some([first, last]) => format!("label_range={},{}", first, last),
+                None => "label_range=all".to_string(),
+            },
+        ];
+        settings.push(("layer", query.join("&")));
+        index += 1;
+    }
+    settings.push(("camera", crate::remote::camera_query(camera)));
+    return settings;
+}
+
+/// Keeps the recovery file up to date with the scene.
+pub struct Autosave {
+    saved: Vec<(&'static str, String)>,
+    /// When the scene was first seen to differ from the recovery file since it was last written.
+    changed: Option<std::time::Instant>,
+}
+
+impl Autosave {
+    pub fn new() -> Self {
+        Autosave {
+            saved: Vec::new(),
+            changed: None,
+        }
+    }
+
+    /// Write the recovery file if the scene changed at least `AUTOSAVE_DELAY` ago. Returns when to
+    /// check again if a change is still waiting to be written.
+    pub fn update(
+        &mut self,
+        lines: &[crate::rendering::Line],
+        line_buffers: &[crate::rendering::LineBuffers],
+        camera: &crate::rendering::OrbitCamera,
+        now: std::time::Instant,
+    ) -> Option<std::time::Instant> {
+        let settings = settings(lines, line_buffers, camera);
+        if settings == self.saved {
+            self.changed = None;
+            return None;
+        }
+        let changed = *self.changed.get_or_insert(now);
+        if now < changed + AUTOSAVE_DELAY {
+            return Some(changed + AUTOSAVE_DELAY);
+        }
+        let path = Session::path()?;
+        if let Err(e) = crate::config::write_settings(&path, &settings) {
+            warn!(
+                "Failed to autosave the session to {}: {}",
+                path.display(),
+                e
+            );
+        }
+        // Don't retry a failed write until the next change
+        self.saved = settings;
+        self.changed = None;
+        return None;
+    }
+}
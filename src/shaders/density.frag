@@ -0,0 +1,14 @@
+#version 450
+
+layout (location = 0) out float count;
+
+// Every pixel a point covers adds one to that pixel's count. Points are still drawn as circles so
+// the counts match what the normal point rendering shows.
+void main()
+{
+    vec2 center = vec2(0.5, 0.5);
+    if (distance(center, gl_PointCoord) >= 0.5) {
+        discard;
+    }
+    count = 1.0;
+}
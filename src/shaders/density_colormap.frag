@@ -0,0 +1,44 @@
+#version 450
+
+layout (location = 0) out vec4 pixel_colour;
+
+layout(set=0, binding=0) uniform texture2D density;
+layout(set=0, binding=1) uniform sampler density_sampler;
+layout(set=0, binding=2)
+uniform DensityUniform {
+    // Count that maps to the top of the colormap
+    float saturation;
+};
+
+// Polynomial fit of matplotlib's viridis colormap
+vec3 viridis(float t)
+{
+    const vec3 c0 = vec3(0.2777273272234177, 0.005407344544966578, 0.3340998053353061);
+    const vec3 c1 = vec3(0.1050930431085774, 1.404613529898575, 1.384590162594685);
+    const vec3 c2 = vec3(-0.3308618287255563, 0.214847559468213, 0.09509516302823659);
+    const vec3 c3 = vec3(-4.634230498983486, -5.799100973351585, -19.33244095627987);
+    const vec3 c4 = vec3(6.228269936347081, 14.17993336680509, 56.69055260068105);
+    const vec3 c5 = vec3(4.776384997670288, -13.74514537774601, -65.35303263337234);
+    const vec3 c6 = vec3(-5.435455855934631, 4.645852612178535, 26.3124352495832);
+    return c0 + t * (c1 + t * (c2 + t * (c3 + t * (c4 + t * (c5 + t * c6)))));
+}
+
+// The viridis fit gives sRGB colours, and the target encodes linear ones
+vec3 srgb_to_linear(vec3 encoded)
+{
+    vec3 low = encoded / 12.92;
+    vec3 high = pow((encoded + 0.055) / 1.055, vec3(2.4));
+    return mix(high, low, vec3(lessThanEqual(encoded, vec3(0.04045))));
+}
+
+void main()
+{
+    float count = texelFetch(sampler2D(density, density_sampler), ivec2(gl_FragCoord.xy), 0).r;
+    // Leave the background showing where there are no points
+    if (count < 0.5) {
+        discard;
+    }
+    // Log scale, so both sparse and very dense regions are distinguishable
+    float t = clamp(log(1.0 + count) / log(1.0 + saturation), 0.0, 1.0);
+    pixel_colour = vec4(srgb_to_linear(viridis(t)), 1.0);
+}
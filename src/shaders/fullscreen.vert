@@ -0,0 +1,9 @@
+#version 450
+
+// A single triangle covering the whole viewport, generated from the vertex index so no vertex
+// buffer is needed. Draw with 3 vertices.
+void main()
+{
+    vec2 position = vec2((gl_VertexIndex << 1) & 2, gl_VertexIndex & 2);
+    gl_Position = vec4(position * 2.0 - 1.0, 0.0, 1.0);
+}
@@ -0,0 +1,62 @@
+#version 450
+
+layout (location = 0) in vec4 mesh_position;
+layout (location = 1) in vec4 mesh_normal;
+// The glyph's transform, one column per location
+layout (location = 2) in vec4 transform_0;
+layout (location = 3) in vec4 transform_1;
+layout (location = 4) in vec4 transform_2;
+layout (location = 5) in vec4 transform_3;
+layout (location = 6) in vec4 glyph_colour;
+
+layout (location = 0) out vec4 fragment_colour;
+
+layout(set=0, binding=0)
+uniform CameraUniform {
+    vec4 camera_pos;
+    mat4 view_proj;
+};
+
+layout(set=0, binding=2)
+uniform TintUniform {
+    // Alpha is the opacity of the whole layer
+    vec4 tint;
+    // 0 for none, 1 to multiply the colour by the tint and 2 to replace it
+    uint tint_mode;
+    uint animation;
+    float time;
+    float color_scale;
+    uint channel_order;
+    uint srgb;
+    // Multiplies the linear colour, from the exposure in stops
+    float exposure;
+    float gamma;
+    uint shape;
+    float min_size;
+    float max_size;
+    float size_scale;
+    float depth_offset;
+};
+
+void main()
+{
+    mat4 transform = mat4(transform_0, transform_1, transform_2, transform_3);
+    vec4 world_pos = transform * mesh_position;
+    gl_Position = view_proj * world_pos;
+
+    // Light from the camera, so every face the camera sees is lit. The inverse transpose keeps
+    // normals perpendicular to meshes stretched unevenly, such as ellipsoids
+    vec3 normal = normalize(transpose(inverse(mat3(transform))) * mesh_normal.xyz);
+    vec3 to_camera = normalize(camera_pos.xyz - world_pos.xyz);
+    float light = 0.35 + 0.65 * abs(dot(normal, to_camera));
+
+    fragment_colour = glyph_colour;
+    if (tint_mode == 1) {
+        fragment_colour.rgb *= tint.rgb;
+    } else if (tint_mode == 2) {
+        fragment_colour.rgb = tint.rgb;
+    }
+    fragment_colour.rgb *= light;
+    fragment_colour.rgb = pow(max(fragment_colour.rgb * exposure, 0.0), vec3(1.0 / gamma));
+    fragment_colour.a *= tint.a;
+}
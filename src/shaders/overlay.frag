@@ -0,0 +1,17 @@
+#version 450
+
+layout (location = 0) out vec4 pixel_colour;
+
+layout(set=0, binding=0) uniform texture2D overlay;
+layout(set=0, binding=1) uniform sampler overlay_sampler;
+layout(set=0, binding=2)
+uniform OverlayUniform {
+    // Pixel position of the overlay's top left corner in the target
+    ivec2 origin;
+};
+
+void main()
+{
+    ivec2 texel = ivec2(gl_FragCoord.xy) - origin;
+    pixel_colour = texelFetch(sampler2D(overlay, overlay_sampler), texel, 0);
+}
@@ -0,0 +1,13 @@
+#version 450
+
+layout (location = 0) flat in uvec2 point_id;
+layout (location = 0) out uvec2 pixel_id;
+
+// Covers the circle shader.frag draws by default, whatever the layer's point shape
+void main()
+{
+    if (distance(vec2(0.5, 0.5), gl_PointCoord) >= 0.5) {
+        discard;
+    }
+    pixel_id = point_id;
+}
@@ -0,0 +1,50 @@
+#version 450
+
+// Places and sizes points exactly as shader.vert does, but passes on which point each one is so
+// exports can map pixels back to source points.
+
+layout (location = 0) in vec4 vertex_pos;
+layout (location = 1) in vec4 vertex_colour;
+layout (location = 2) in float size;
+// The active attribute, NaN for points without it
+layout (location = 3) in float attribute;
+
+layout (location = 0) flat out uvec2 point_id;
+
+layout(set=0, binding=0)
+uniform CameraUniform {
+    vec4 camera_pos;
+    mat4 view_proj;
+};
+
+layout(set=0, binding=1)
+uniform AttributeUniform {
+    vec2 attribute_range;
+    // 0 when no attribute is active, 1 to colour by it and 2 to size by it
+    uint attribute_target;
+};
+
+layout(set=1, binding=0)
+uniform PointIdUniform {
+    // Layer index plus one, leaving zero for pixels without a point
+    uint layer_id;
+    // Added to the vertex index to give the point's position in the layer's draw order
+    uint first_point;
+    // Clamps on the point size on screen, in pixels
+    float min_size;
+    float max_size;
+};
+
+void main()
+{
+    gl_Position = view_proj * vertex_pos;
+    float range = distance(vertex_pos, camera_pos);
+    float screen_size = (size/range)*(size/range);
+    if (attribute_target == 2 && !isnan(attribute)) {
+        float extent = max(attribute_range.y - attribute_range.x, 1e-6);
+        float t = clamp((attribute - attribute_range.x) / extent, 0.0, 1.0);
+        screen_size *= mix(0.25, 2.0, t);
+    }
+    gl_PointSize = clamp(screen_size, min_size, max(min_size, max_size));
+    point_id = uvec2(layer_id, first_point + uint(gl_VertexIndex));
+}
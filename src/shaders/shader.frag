@@ -1,23 +1,39 @@
-#version 450
-
-layout (location = 0) in vec4 frag_colour;
-layout (location = 0) out vec4 pixel_colour;
-layout (depth_greater) out float gl_FragDepth;
-
-
-// This will handle fragments from a square surrounding the point that we want to draw.
-// We then fill in the center circle of this square with the colour and depth values that were
-// passed in and discard the rest (by un-setting th pixel colour and setting the depth to max).
-void main()
-{
-    vec2 center = vec2(0.5, 0.5);
-    float radius = distance(center, gl_PointCoord);
-
-    if (radius < 0.5) {
-        pixel_colour = frag_colour;
-        gl_FragDepth = gl_FragCoord.z;
-    } else {
-        pixel_colour = vec4(0, 0, 0, 1);
-        gl_FragDepth = 1;
-    }
-}
\ No newline at end of file
+#version 450
+
+layout (location = 0) in vec4 frag_colour;
+layout (location = 1) flat in uint point_shape;
+layout (location = 0) out vec4 pixel_colour;
+layout (depth_greater) out float gl_FragDepth;
+
+
+// Signed distance from the edge of the point's shape, negative inside, in point sizes from the
+// centre of the square sprite.
+float shape_distance(vec2 p)
+{
+    if (point_shape == 0) {
+        return max(abs(p.x), abs(p.y)) - 0.5;
+    } else if (point_shape == 2) {
+        return abs(length(p) - 0.4) - 0.1;
+    } else if (point_shape == 3) {
+        float bars = min(abs(p.x), abs(p.y)) - 0.1;
+        return max(bars, max(abs(p.x), abs(p.y)) - 0.5);
+    }
+    return length(p) - 0.5;
+}
+
+// This will handle fragments from a square surrounding the point that we want to draw.
+// We then fill in the point's shape within this square with the colour and depth values that
+// were passed in, fading the edge over a pixel, and discard the rest. Pushing the rest to max
+// depth isn't enough, since layers drawn on top skip the depth test.
+void main()
+{
+    float d = shape_distance(gl_PointCoord - vec2(0.5, 0.5));
+    float pixel = max(fwidth(d), 1e-4);
+    float coverage = clamp(0.5 - d / pixel, 0.0, 1.0);
+
+    if (coverage <= 0.0) {
+        discard;
+    }
+    pixel_colour = vec4(frag_colour.rgb, frag_colour.a * coverage);
+    gl_FragDepth = gl_FragCoord.z;
+}
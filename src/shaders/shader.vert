@@ -1,22 +1,111 @@
-#version 450
-
-layout (location = 0) in vec4 vertex_pos;
-layout (location = 1) in vec4 vertex_colour;
-layout (location = 2) in float size;
-
-layout (location = 0) out vec4 fragment_colour;
-
-layout(set=0, binding=0)
-uniform CameraUniform {
-    vec4 camera_pos;
-    mat4 view_proj;
-};
-
-void main()
-{
-    fragment_colour = vertex_colour;
-    gl_Position = view_proj * vertex_pos;
-    float range = distance(vertex_pos, camera_pos);
-    float screen_size = (size/range)*(size/range);
-    gl_PointSize = screen_size;
+#version 450
+
+layout (location = 0) in vec4 vertex_pos;
+layout (location = 1) in vec4 vertex_colour;
+layout (location = 2) in float size;
+// The active attribute, NaN for points without it
+layout (location = 3) in float attribute;
+
+layout (location = 0) out vec4 fragment_colour;
+layout (location = 1) flat out uint point_shape;
+
+layout(set=0, binding=0)
+uniform CameraUniform {
+    vec4 camera_pos;
+    mat4 view_proj;
+};
+
+layout(set=0, binding=1)
+uniform AttributeUniform {
+    vec2 attribute_range;
+    // 0 when no attribute is active, 1 to colour by it and 2 to size by it
+    uint attribute_target;
+};
+
+layout(set=0, binding=2)
+uniform TintUniform {
+    // Alpha is the opacity of the whole layer
+    vec4 tint;
+    // 0 for none, 1 to multiply the colour by the tint and 2 to replace it
+    uint tint_mode;
+    // 0 for none, 1 to pulse the point size, 2 to blink and 3 to jitter the points
+    uint animation;
+    // Seconds, driving the animation
+    float time;
+    // What the stored colours are multiplied by to bring them to 0 to 1
+    float color_scale;
+    // 0 for RGB and 1 for BGR stored colours
+    uint channel_order;
+    // 1 if the stored colours are sRGB encoded, so are decoded to linear
+    uint srgb;
+    // Multiplies the linear colour, from the exposure in stops
+    float exposure;
+    float gamma;
+    // 0 for square, 1 for circle, 2 for ring and 3 for cross points
+    uint shape;
+    // Clamps on the point size on screen, in pixels
+    float min_size;
+    float max_size;
+    // Multiplies the clamped point size, e.g. to enlarge points in the depth pre-pass
+    float size_scale;
+    // Pushes points away from the camera by this fraction of their distance
+    float depth_offset;
+};
+
+const float TAU = 6.2831853;
+
+vec3 srgb_to_linear(vec3 encoded)
+{
+    vec3 low = encoded / 12.92;
+    vec3 high = pow((encoded + 0.055) / 1.055, vec3(2.4));
+    return mix(high, low, vec3(lessThanEqual(encoded, vec3(0.04045))));
+}
+
+void main()
+{
+    float t = -1.0;
+    if (attribute_target != 0 && !isnan(attribute)) {
+        float extent = max(attribute_range.y - attribute_range.x, 1e-6);
+        t = clamp((attribute - attribute_range.x) / extent, 0.0, 1.0);
+    }
+    fragment_colour = vertex_colour;
+    if (channel_order == 1) {
+        fragment_colour.rgb = fragment_colour.bgr;
+    }
+    fragment_colour.rgb *= color_scale;
+    if (srgb == 1) {
+        fragment_colour.rgb = srgb_to_linear(clamp(fragment_colour.rgb, 0.0, 1.0));
+    }
+    if (tint_mode == 1) {
+        fragment_colour.rgb *= tint.rgb;
+    } else if (tint_mode == 2) {
+        fragment_colour.rgb = tint.rgb;
+    }
+    if (attribute_target == 1 && t >= 0.0) {
+        fragment_colour = vec4(t, t, t, 1.0);
+    }
+    fragment_colour.rgb = pow(max(fragment_colour.rgb * exposure, 0.0), vec3(1.0 / gamma));
+    fragment_colour.a = tint.a;
+    if (animation == 2 && fract(time) >= 0.5) {
+        fragment_colour.a *= 0.1;
+    }
+    vec4 position = vertex_pos;
+    position.xyz += (vertex_pos.xyz - camera_pos.xyz) * depth_offset;
+    gl_Position = view_proj * position;
+    if (animation == 3) {
+        // A small screen space wobble, with each point out of step with its neighbours
+        float phase = float(gl_VertexIndex) * 12.9898;
+        vec2 wobble = vec2(sin(7.0 * time + phase), cos(5.0 * time + 1.7 * phase));
+        gl_Position.xy += 0.005 * gl_Position.w * wobble;
+    }
+    float range = distance(vertex_pos, camera_pos);
+    float screen_size = (size/range)*(size/range);
+    if (attribute_target == 2 && t >= 0.0) {
+        screen_size *= mix(0.25, 2.0, t);
+    }
+    if (animation == 1) {
+        screen_size *= 1.0 + 0.5 * sin(TAU * time);
+    }
+    gl_PointSize = clamp(screen_size, min_size, max(min_size, max_size)) * size_scale;
+    point_shape = shape;
 }
\ No newline at end of file
@@ -0,0 +1,125 @@
+#version 450
+
+layout (location = 0) in vec3 texture_position;
+layout (location = 1) flat in vec4 camera;
+
+layout (location = 0) out vec4 fragment_colour;
+
+layout(set=1, binding=0)
+uniform VolumeUniform {
+    mat4 model;
+    mat4 inverse_model;
+    // The part of the volume drawn, in texture coordinates
+    vec4 clip_min;
+    vec4 clip_max;
+    // Slicing plane positions along each axis, in texture coordinates
+    vec4 slices;
+    // Voxels along each axis
+    vec4 dims;
+    // Stored values at the bottom and top of the transfer function, and the opacity scale
+    vec4 window;
+    // Transfer function stops, 1 if the camera is inside the volume, and a bit per axis with a
+    // slicing plane
+    uvec4 counts;
+    vec4 stop_colours[8];
+    vec4 stop_positions[2];
+};
+
+layout(set=1, binding=1) uniform texture3D volume_texture;
+layout(set=1, binding=2) uniform sampler volume_sampler;
+
+const int MAX_STEPS = 1024;
+
+float stop_position(uint i)
+{
+    return stop_positions[i / 4u][i % 4u];
+}
+
+vec4 transfer(vec3 position)
+{
+    float value = texture(sampler3D(volume_texture, volume_sampler), position).r;
+    float t = clamp((value - window.x) / (window.y - window.x), 0.0, 1.0);
+    vec4 colour = stop_colours[0];
+    for (uint i = 1u; i < counts.x; i++) {
+        float low = stop_position(i - 1u);
+        float high = stop_position(i);
+        if (t >= low) {
+            float along = clamp((t - low) / max(high - low, 1e-6), 0.0, 1.0);
+            colour = mix(stop_colours[i - 1u], stop_colours[i], along);
+        }
+    }
+    return colour;
+}
+
+// Where the line `start + s * direction` enters and leaves the box from `low` to `high`
+vec2 intersect(vec3 start, vec3 direction, vec3 low, vec3 high)
+{
+    vec3 a = (low - start) / direction;
+    vec3 b = (high - start) / direction;
+    vec3 near = min(a, b);
+    vec3 far = max(a, b);
+    return vec2(max(max(near.x, near.y), near.z), min(min(far.x, far.y), far.z));
+}
+
+void main()
+{
+    vec3 position = texture_position;
+    bool perspective = camera.w != 0.0;
+    vec3 eye = camera.xyz / camera.w;
+    vec3 direction = normalize(perspective ? position - eye : camera.xyz);
+    // Keep divisions by the direction finite
+    direction = mix(direction, vec3(1e-6), equal(direction, vec3(0.0)));
+
+    // Each pixel is covered by the face its ray enters by and the face it leaves by. From
+    // outside the volume rays are marched from where they enter, and from inside it from the
+    // camera to where they leave.
+    vec2 box = intersect(position, direction, vec3(0.0), vec3(1.0));
+    bool inside = counts.y == 1u;
+    if (inside ? abs(box.y) > abs(box.x) : abs(box.x) > abs(box.y)) {
+        discard;
+    }
+    vec2 clip = intersect(position, direction, clip_min.xyz, clip_max.xyz);
+    float start = max(box.x, clip.x);
+    float end = min(box.y, clip.y);
+    if (perspective) {
+        start = max(start, -distance(position, eye));
+    }
+
+    // The nearest slicing plane ends the ray
+    float plane = end + 1.0;
+    for (int axis = 0; axis < 3; axis++) {
+        if ((counts.z & (1u << axis)) != 0u) {
+            float s = (slices[axis] - position[axis]) / direction[axis];
+            if (s >= start && s <= end && s < plane) {
+                plane = s;
+            }
+        }
+    }
+    bool sliced = plane <= end;
+    end = min(end, plane);
+    if (end <= start && !sliced) {
+        discard;
+    }
+
+    // About two samples per voxel passed through
+    float voxels = length(max(end - start, 0.0) * direction * dims.xyz);
+    int steps = int(min(ceil(voxels * 2.0), float(MAX_STEPS)));
+    float step_length = (end - start) / max(float(steps), 1.0);
+    float voxels_per_step = voxels / max(float(steps), 1.0);
+    // Composited front to back, premultiplied by alpha
+    vec4 colour = vec4(0.0);
+    for (int i = 0; i < steps && colour.a < 0.99; i++) {
+        vec4 sampled = transfer(position + (start + (float(i) + 0.5) * step_length) * direction);
+        // Opacity is given per voxel, so correct it for the distance between samples
+        float opacity = clamp(sampled.a * window.z, 0.0, 1.0);
+        float alpha = 1.0 - pow(1.0 - opacity, voxels_per_step);
+        colour.rgb += (1.0 - colour.a) * alpha * sampled.rgb;
+        colour.a += (1.0 - colour.a) * alpha;
+    }
+    if (sliced) {
+        vec4 sampled = transfer(position + end * direction);
+        colour.rgb += (1.0 - colour.a) * sampled.rgb;
+        colour.a = 1.0;
+    }
+    fragment_colour = colour;
+}
@@ -0,0 +1,37 @@
+#version 450
+
+layout (location = 0) in vec4 box_position;
+
+// Position in the volume's texture coordinates, which run from 0 to 1 across its box
+layout (location = 0) out vec3 texture_position;
+// Where rays start in texture coordinates: the camera position for perspective views, or with w
+// of 0 the viewing direction for orthographic ones
+layout (location = 1) flat out vec4 camera;
+
+layout(set=0, binding=0)
+uniform CameraUniform {
+    vec4 camera_pos;
+    mat4 view_proj;
+};
+
+layout(set=1, binding=0)
+uniform VolumeUniform {
+    mat4 model;
+    mat4 inverse_model;
+    vec4 clip_min;
+    vec4 clip_max;
+    vec4 slices;
+    vec4 dims;
+    vec4 window;
+    uvec4 counts;
+    vec4 stop_colours[8];
+    vec4 stop_positions[2];
+};
+
+void main()
+{
+    gl_Position = view_proj * model * box_position;
+    texture_position = box_position.xyz;
+    // Every ray passes through the point that projects to clip space w of 0 on the view axis
+    camera = inverse_model * inverse(view_proj) * vec4(0.0, 0.0, 1.0, 0.0);
+}
@@ -0,0 +1,9 @@
+#version 450
+
+layout (location = 0) in vec4 frag_colour;
+layout (location = 0) out vec4 pixel_colour;
+
+void main()
+{
+    pixel_colour = frag_colour;
+}
@@ -0,0 +1,259 @@
+//! Nearest neighbour queries over a layer's points.
+
+use crate::rendering::Line;
+
+/// A k-d tree over the points of a line, in the line's own coordinates. The tree is stored
+/// implicitly: each range of `points` is split by its middle element along the axis for its
+/// depth, with everything nearer the origin on that axis before it and everything else after.
+pub struct KdTree {
+    points: Vec<([f32; 3], u32)>,
+}
+
+impl KdTree {
+    pub fn build(line: &Line) -> Self {
+        let mut points: Vec<([f32; 3], u32)> = line
+            .indicies
+            .iter()
+            .map(|&index| {
+                let p = line.verticies[index as usize].position;
+                ([p[0], p[1], p[2]], index)
+            })
+            .collect();
+        split(&mut points, 0);
+        return KdTree { points: points };
+    }
+
+    /// The `k` points nearest `p`, as vertex indices and distances, nearest first.
+    pub fn nearest(&self, p: [f32; 3], k: usize) -> Vec<(usize, f32)> {
+        let mut found: Vec<(usize, f32)> = Vec::with_capacity(k + 1);
+        if k > 0 {
+            search_nearest(&self.points, 0, p, k, &mut found);
+        }
+        return found
+            .into_iter()
+            .map(|(index, distance_squared)| (index, distance_squared.sqrt()))
+            .collect();
+    }
+
+    /// Vertex indices of every point within `r` of `p`, in no particular order.
+    pub fn radius(&self, p: [f32; 3], r: f32) -> Vec<usize> {
+        let mut found = Vec::new();
+        search_radius(&self.points, 0, p, r * r, &mut found);
+        return found;
+    }
+}
+
+fn split(points: &mut [([f32; 3], u32)], depth: usize) {
+    if points.len() <= 1 {
+        return;
+    }
+    let axis = depth % 3;
+    let mid = points.len() / 2;
+    points.select_nth_unstable_by(mid, |a, b| {
+        a.0[axis]
+            .partial_cmp(&b.0[axis])
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    let (before, after) = points.split_at_mut(mid);
+    split(before, depth + 1);
+    split(&mut after[1..], depth + 1);
+}
+
+/// Keeps `found` sorted by squared distance and at most `k` long.
+fn search_nearest(
+    points: &[([f32; 3], u32)],
+    depth: usize,
+    p: [f32; 3],
+    k: usize,
+    found: &mut Vec<(usize, f32)>,
+) {
+    if points.is_empty() {
+        return;
+    }
+    let mid = points.len() / 2;
+    let (point, index) = points[mid];
+    let distance_squared = squared_distance(point, p);
+    if found.len() < k || distance_squared < found[found.len() - 1].1 {
+        let at = found
+            .iter()
+            .position(|&(_, other)| distance_squared < other)
+            .unwrap_or(found.len());
+        found.insert(at, (index as usize, distance_squared));
+        found.truncate(k);
+    }
+    let axis = depth % 3;
+    let offset = p[axis] - point[axis];
+    let (near, far) = if offset < 0.0 {
+        (&points[..mid], &points[mid + 1..])
+    } else {
+        (&points[mid + 1..], &points[..mid])
+    };
+    search_nearest(near, depth + 1, p, k, found);
+    // The far side can only hold closer points if the splitting plane is closer
+    if found.len() < k || offset * offset < found[found.len() - 1].1 {
+        search_nearest(far, depth + 1, p, k, found);
+    }
+}
+
+fn search_radius(
+    points: &[([f32; 3], u32)],
+    depth: usize,
+    p: [f32; 3],
+    r_squared: f32,
+    found: &mut Vec<usize>,
+) {
+    if points.is_empty() {
+        return;
+    }
+    let mid = points.len() / 2;
+    let (point, index) = points[mid];
+    if squared_distance(point, p) <= r_squared {
+        found.push(index as usize);
+    }
+    let axis = depth % 3;
+    let offset = p[axis] - point[axis];
+    if offset <= 0.0 || offset * offset <= r_squared {
+        search_radius(&points[..mid], depth + 1, p, r_squared, found);
+    }
+    if offset >= 0.0 || offset * offset <= r_squared {
+        search_radius(&points[mid + 1..], depth + 1, p, r_squared, found);
+    }
+}
+
+fn squared_distance(a: [f32; 3], b: [f32; 3]) -> f32 {
+    (0..3)
+        .map(|axis| (a[axis] - b[axis]) * (a[axis] - b[axis]))
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rendering::Vertex;
+    use rand::{Rng, SeedableRng};
+
+    fn line(positions: &[[f32; 3]]) -> Line {
+        let vertices: Vec<Vertex> = positions
+            .iter()
+            .map(|p| Vertex {
+                position: [p[0], p[1], p[2], 1.0],
+                color: [1.0, 1.0, 1.0, 1.0],
+                size: 1.0,
+            })
+            .collect();
+        Line {
+            indicies: (0..vertices.len() as u32).collect(),
+            verticies: vertices,
+            origin: nalgebra::Vector3::zeros(),
+            attributes: Vec::new(),
+            ids: None,
+            metadata: Vec::new(),
+        }
+    }
+
+    /// Random points in a cube, on a coarse grid so many share coordinates, plus exact copies of
+    /// some of them.
+    fn random_points(rng: &mut rand::rngs::StdRng, count: usize) -> Vec<[f32; 3]> {
+        let mut points: Vec<[f32; 3]> = (0..count)
+            .map(|_| {
+                [
+                    rng.gen_range(0, 20) as f32 * 0.5,
+                    rng.gen_range(0, 20) as f32 * 0.5,
+                    rng.gen::<f32>() * 10.0,
+                ]
+            })
+            .collect();
+        for _ in 0..count / 4 {
+            let copy = points[rng.gen_range(0, points.len())];
+            points.push(copy);
+        }
+        return points;
+    }
+
+    /// Every point's distance to `p`, nearest first.
+    fn brute_force_distances(points: &[[f32; 3]], p: [f32; 3]) -> Vec<f32> {
+        let mut distances: Vec<f32> = points
+            .iter()
+            .map(|&point| squared_distance(point, p).sqrt())
+            .collect();
+        distances.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        return distances;
+    }
+
+    #[test]
+    fn nearest_matches_brute_force() {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(1);
+        for &count in &[1, 2, 7, 100, 1000] {
+            let points = random_points(&mut rng, count);
+            let tree = KdTree::build(&line(&points));
+            for _ in 0..50 {
+                let p = [
+                    rng.gen::<f32>() * 12.0 - 1.0,
+                    rng.gen::<f32>() * 12.0 - 1.0,
+                    rng.gen::<f32>() * 12.0 - 1.0,
+                ];
+                let expected = brute_force_distances(&points, p);
+                for &k in &[1, 5, 20] {
+                    let found = tree.nearest(p, k);
+                    assert_eq!(found.len(), k.min(points.len()));
+                    // Ties can be broken either way, so compare distances rather than indices
+                    for (i, &(index, distance)) in found.iter().enumerate() {
+                        assert_eq!(distance, squared_distance(points[index], p).sqrt());
+                        assert_eq!(distance, expected[i]);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn radius_matches_brute_force() {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(2);
+        for &count in &[1, 2, 7, 100, 1000] {
+            let points = random_points(&mut rng, count);
+            let tree = KdTree::build(&line(&points));
+            for _ in 0..50 {
+                // Query from the points themselves too, to include points exactly at the centre
+                let p = if rng.gen() {
+                    points[rng.gen_range(0, points.len())]
+                } else {
+                    [
+                        rng.gen::<f32>() * 10.0,
+                        rng.gen::<f32>() * 10.0,
+                        rng.gen::<f32>() * 10.0,
+                    ]
+                };
+                for &r in &[0.0, 0.5, 2.0, 20.0] {
+                    let mut found = tree.radius(p, r);
+                    found.sort_unstable();
+                    let expected: Vec<usize> = (0..points.len())
+                        .filter(|&i| squared_distance(points[i], p) <= r * r)
+                        .collect();
+                    assert_eq!(found, expected);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn duplicate_points() {
+        let points = vec![[1.0, 2.0, 3.0]; 10];
+        let tree = KdTree::build(&line(&points));
+        let found = tree.nearest([1.0, 2.0, 3.0], 4);
+        assert_eq!(found.len(), 4);
+        assert!(found.iter().all(|&(_, distance)| distance == 0.0));
+        let mut within = tree.radius([1.0, 2.0, 3.0], 0.0);
+        within.sort_unstable();
+        assert_eq!(within, (0..10).collect::<Vec<usize>>());
+        assert!(tree.radius([1.0, 2.0, 4.0], 0.5).is_empty());
+    }
+
+    #[test]
+    fn empty_tree() {
+        let tree = KdTree::build(&line(&[]));
+        assert!(tree.nearest([0.0, 0.0, 0.0], 3).is_empty());
+        assert!(tree.radius([0.0, 0.0, 0.0], 100.0).is_empty());
+        let tree = KdTree::build(&line(&[[0.0, 0.0, 0.0]]));
+        assert!(tree.nearest([0.0, 0.0, 0.0], 0).is_empty());
+    }
+}
@@ -0,0 +1,97 @@
+//! Touchscreen gestures: dragging one finger orbits, dragging two pans and pinching zooms.
+
+use winit::dpi::PhysicalPosition;
+use winit::event::TouchPhase;
+
+/// A camera movement made by the fingers on the screen.
+#[derive(Debug, Copy, Clone)]
+pub enum Gesture {
+    /// One finger moved by this many pixels.
+    Orbit(nalgebra::Vector2<f32>),
+    /// The middle of two fingers moved by `pan` pixels while they spread apart or pinched
+    /// together by `zoom`, in wheel lines towards the target.
+    PanZoom {
+        pan: nalgebra::Vector2<f32>,
+        zoom: f32,
+    },
+}
+
+/// The fingers currently on the screen, by their touch ID.
+pub struct Touches {
+    fingers: Vec<(u64, PhysicalPosition<f64>)>,
+}
+
+impl Touches {
+    pub fn new() -> Self {
+        Touches {
+            fingers: Vec::new(),
+        }
+    }
+
+    /// Where the first finger is, e.g. to pick the view touched.
+    pub fn first(&self) -> Option<PhysicalPosition<f64>> {
+        self.fingers.first().map(|(_, position)| *position)
+    }
+
+    /// Track a touch event, returning the gesture it makes. A third finger on the screen stops
+    /// the gesture until fingers are lifted again.
+    pub fn update(
+        &mut self,
+        phase: TouchPhase,
+        id: u64,
+        position: PhysicalPosition<f64>,
+    ) -> Option<Gesture> {
+        let finger = self.fingers.iter().position(|(finger, _)| *finger == id);
+        match (phase, finger) {
+            (TouchPhase::Started, None) => {
+                self.fingers.push((id, position));
+                return None;
+            }
+            (TouchPhase::Moved, Some(finger)) => {
+                let before = self.fingers.clone();
+                self.fingers[finger].1 = position;
+                return gesture(&before, &self.fingers);
+            }
+            (TouchPhase::Ended, Some(finger)) | (TouchPhase::Cancelled, Some(finger)) => {
+                self.fingers.remove(finger);
+                return None;
+            }
+            _ => return None,
+        }
+    }
+}
+
+fn to_vector(position: PhysicalPosition<f64>) -> nalgebra::Vector2<f32> {
+    nalgebra::Vector2::new(position.x as f32, position.y as f32)
+}
+
+/// The gesture made by the fingers moving from `before` to `after`.
+fn gesture(
+    before: &[(u64, PhysicalPosition<f64>)],
+    after: &[(u64, PhysicalPosition<f64>)],
+) -> Option<Gesture> {
+    match (before, after) {
+        ([(_, from)], [(_, to)]) => {
+            return Some(Gesture::Orbit(to_vector(*to) - to_vector(*from)));
+        }
+        ([(_, from_a), (_, from_b)], [(_, to_a), (_, to_b)]) => {
+            let (from_a, from_b) = (to_vector(*from_a), to_vector(*from_b));
+            let (to_a, to_b) = (to_vector(*to_a), to_vector(*to_b));
+            let pan = (to_a + to_b - from_a - from_b) / 2.0;
+            let spread = (to_a - to_b).norm() / (from_a - from_b).norm();
+            if !spread.is_finite() || spread <= 0.0 {
+                return Some(Gesture::PanZoom {
+                    pan: pan,
+                    zoom: 0.0,
+                });
+            }
+            // Each wheel line zooms by 4/3, so this scales the scene with the fingers' spread
+            let zoom = spread.ln() / (4.0_f32 / 3.0).ln();
+            return Some(Gesture::PanZoom {
+                pan: pan,
+                zoom: zoom,
+            });
+        }
+        _ => return None,
+    }
+}
@@ -0,0 +1,212 @@
+//! Reading dense 3D scalar grids, such as simulation outputs, either as NumPy `.npy` arrays or
+//! as raw samples whose dimensions and type are given separately.
+
+use crate::rendering::volume::{Volume, MAX_DIMENSION};
+
+/// How each sample of a grid is stored.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SampleType {
+    pub kind: SampleKind,
+    /// Bytes per sample.
+    pub size: usize,
+    pub big_endian: bool,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum SampleKind {
+    Unsigned,
+    Signed,
+    Float,
+}
+
+/// Sample types of raw grids, which are little endian, with their kind and size in bytes.
+pub const SAMPLE_TYPE_NAMES: [(&str, SampleKind, usize); 8] = [
+    ("u8", SampleKind::Unsigned, 1),
+    ("i8", SampleKind::Signed, 1),
+    ("u16", SampleKind::Unsigned, 2),
+    ("i16", SampleKind::Signed, 2),
+    ("u32", SampleKind::Unsigned, 4),
+    ("i32", SampleKind::Signed, 4),
+    ("f32", SampleKind::Float, 4),
+    ("f64", SampleKind::Float, 8),
+];
+
+impl SampleType {
+    /// One of `SAMPLE_TYPE_NAMES`.
+    pub fn parse(name: &str) -> Option<Self> {
+        return SAMPLE_TYPE_NAMES
+            .iter()
+            .find(|(type_name, _, _)| *type_name == name)
+            .map(|&(_, kind, size)| SampleType {
+                kind: kind,
+                size: size,
+                big_endian: false,
+            });
+    }
+
+    /// A NumPy type description such as `<f4`.
+    fn from_descr(descr: &str) -> Option<Self> {
+        let mut chars = descr.chars();
+        let big_endian = match chars.next()? {
+            '>' => true,
+            // Native order, taken to be little endian like every platform rscat runs on
+            '<' | '|' | '=' => false,
+            _ => return None,
+        };
+        let kind = match chars.next()? {
+            'u' => SampleKind::Unsigned,
+            'i' => SampleKind::Signed,
+            'f' => SampleKind::Float,
+            _ => return None,
+        };
+        let size = chars.as_str().parse::<usize>().ok()?;
+        match (kind, size) {
+            (SampleKind::Float, 4) | (SampleKind::Float, 8) => {}
+            (SampleKind::Float, _) => return None,
+            (_, 1) | (_, 2) | (_, 4) => {}
+            _ => return None,
+        }
+        return Some(SampleType {
+            kind: kind,
+            size: size,
+            big_endian: big_endian,
+        });
+    }
+
+    fn decode(&self, bytes: &[u8]) -> f32 {
+        let mut buffer = [0_u8; 8];
+        buffer[..self.size].copy_from_slice(bytes);
+        if self.big_endian {
+            buffer[..self.size].reverse();
+        }
+        match (self.kind, self.size) {
+            (SampleKind::Unsigned, 1) => buffer[0] as f32,
+            (SampleKind::Signed, 1) => buffer[0] as i8 as f32,
+            (SampleKind::Unsigned, 2) => u16::from_le_bytes([buffer[0], buffer[1]]) as f32,
+            (SampleKind::Signed, 2) => i16::from_le_bytes([buffer[0], buffer[1]]) as f32,
+            (SampleKind::Unsigned, 4) => {
+                u32::from_le_bytes([buffer[0], buffer[1], buffer[2], buffer[3]]) as f32
+            }
+            (SampleKind::Signed, 4) => {
+                i32::from_le_bytes([buffer[0], buffer[1], buffer[2], buffer[3]]) as f32
+            }
+            (SampleKind::Float, 4) => {
+                f32::from_le_bytes([buffer[0], buffer[1], buffer[2], buffer[3]])
+            }
+            _ => f64::from_le_bytes(buffer) as f32,
+        }
+    }
+}
+
+/// Whether `bytes` start like a NumPy `.npy` file.
+pub fn is_npy(bytes: &[u8]) -> bool {
+    return bytes.starts_with(b"\x93NUMPY");
+}
+
+/// Read a grid of `dims` samples of `sample_type`, X varying fastest. The grid starts at the
+/// origin with unit spacing.
+pub fn parse_raw(
+    bytes: &[u8],
+    dims: [usize; 3],
+    sample_type: SampleType,
+) -> Result<Volume, String> {
+    check_dims(dims)?;
+    let count = dims.iter().product::<usize>();
+    if bytes.len() != count * sample_type.size {
+        return Err(format!(
+            "Expected {} bytes for {}x{}x{} samples, got {}",
+            count * sample_type.size,
+            dims[0],
+            dims[1],
+            dims[2],
+            bytes.len()
+        ));
+    }
+    return Ok(Volume {
+        dims: dims,
+        values: bytes
+            .chunks(sample_type.size)
+            .map(|sample| sample_type.decode(sample))
+            .collect(),
+        origin: nalgebra::Vector3::zeros(),
+        spacing: [1.0; 3],
+    });
+}
+
+/// Read a 3D `.npy` array of integers or floats. The last axis of a C ordered array, the default
+/// in NumPy, is X, and the first axis of a Fortran ordered one. The grid starts at the origin
+/// with unit spacing.
+pub fn parse_npy(bytes: &[u8]) -> Result<Volume, String> {
+    if !is_npy(bytes) || bytes.len() < 10 {
+        return Err("Not a .npy file".to_string());
+    }
+    // Version 1 has a 2 byte header length, later versions 4 bytes
+    let (header_start, header_length) = match bytes[6] {
+        1 => (10, u16::from_le_bytes([bytes[8], bytes[9]]) as usize),
+        _ if bytes.len() >= 12 => (
+            12,
+            u32::from_le_bytes([bytes[8], bytes[9], bytes[10], bytes[11]]) as usize,
+        ),
+        _ => return Err("Truncated .npy header".to_string()),
+    };
+    let header = bytes
+        .get(header_start..header_start + header_length)
+        .ok_or("Truncated .npy header")?;
+    let header = String::from_utf8_lossy(header);
+    let descr = header_value(&header, "descr").ok_or("No type in .npy header")?;
+    let sample_type = SampleType::from_descr(descr.trim_matches(|c| c == '\'' || c == '"'))
+        .ok_or_else(|| format!("Unsupported .npy type {}", descr))?;
+    let fortran_order =
+        header_value(&header, "fortran_order").ok_or("No order in .npy header")? == "True";
+    let shape = header_value(&header, "shape").ok_or("No shape in .npy header")?;
+    let shape = shape
+        .trim_matches(|c| c == '(' || c == ')')
+        .split(',')
+        .map(|length| length.trim())
+        .filter(|length| !length.is_empty())
+        .map(|length| length.parse::<usize>())
+        .collect::<Result<Vec<usize>, _>>()
+        .map_err(|_| format!("Invalid .npy shape {}", shape))?;
+    if shape.len() != 3 {
+        return Err(format!(
+            "Expected a 3D array, got {} dimensions",
+            shape.len()
+        ));
+    }
+    let dims = if fortran_order {
+        [shape[0], shape[1], shape[2]]
+    } else {
+        [shape[2], shape[1], shape[0]]
+    };
+    return parse_raw(&bytes[header_start + header_length..], dims, sample_type);
+}
+
+/// The text of a value in the Python dictionary literal of a `.npy` header.
+fn header_value<'a>(header: &'a str, key: &str) -> Option<&'a str> {
+    let start = header.find(&format!("'{}'", key))? + key.len() + 2;
+    let rest = header[start..].trim_start();
+    if !rest.starts_with(':') {
+        return None;
+    }
+    let rest = rest[1..].trim_start();
+    // Tuples contain commas, so end them at their closing bracket
+    let end = if rest.starts_with('(') {
+        rest.find(')')? + 1
+    } else {
+        rest.find([',', '}'])?
+    };
+    return Some(rest[..end].trim());
+}
+
+fn check_dims(dims: [usize; 3]) -> Result<(), String> {
+    if dims
+        .iter()
+        .any(|&length| length == 0 || length > MAX_DIMENSION)
+    {
+        return Err(format!(
+            "Volumes must have 1 to {} voxels along each axis, got {}x{}x{}",
+            MAX_DIMENSION, dims[0], dims[1], dims[2]
+        ));
+    }
+    return Ok(());
+}
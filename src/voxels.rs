@@ -0,0 +1,113 @@
+//! Occupancy grids drawn as cubes, either made by voxelizing a point layer or read from CSV rows of
+//! voxel centres, e.g. as exported from a robotics mapping stack.
+
+use crate::rendering::glyphs::{GlyphLayer, Instance, Shape};
+use crate::rendering::Line;
+
+/// Voxels less likely than this to be occupied aren't drawn, so free and unknown space stays
+/// clear.
+pub const DEFAULT_THRESHOLD: f32 = 0.5;
+
+/// A cube for each cell of a grid of `size` aligned to the world axes that holds any of `line`'s
+/// points, in the average colour of those points.
+pub fn voxelize(line: &Line, size: f64) -> GlyphLayer {
+    let mut cells = std::collections::HashMap::<[i64; 3], ([f32; 3], usize)>::new();
+    for &index in &line.indicies {
+        let vertex = &line.verticies[index as usize];
+        let cell = [0, 1, 2].map(|axis| {
+            let world = line.origin[axis] + vertex.position[axis] as f64;
+            (world / size).floor() as i64
+        });
+        let (color, count) = cells.entry(cell).or_insert(([0.0; 3], 0));
+        for (sum, &channel) in color.iter_mut().zip(&vertex.color) {
+            *sum += channel;
+        }
+        *count += 1;
+    }
+    // Sorted so the same points always give the same layer
+    let mut cells: Vec<_> = cells.into_iter().collect();
+    cells.sort_by_key(|(cell, _)| *cell);
+    let instances = cells
+        .iter()
+        .map(|(cell, (color, count))| {
+            let mut center = nalgebra::Vector3::zeros();
+            for axis in 0..3 {
+                center[axis] = ((cell[axis] as f64 + 0.5) * size - line.origin[axis]) as f32;
+            }
+            let count = *count as f32;
+            cube(
+                center,
+                size as f32,
+                [color[0] / count, color[1] / count, color[2] / count, 1.0],
+            )
+        })
+        .collect();
+    return GlyphLayer {
+        shape: Shape::Cube,
+        instances: instances,
+        origin: line.origin,
+        wireframe: false,
+    };
+}
+
+/// Parse rows of voxel centre X, Y, Z, R, G, B and optionally the probability the voxel is
+/// occupied, in scene coordinates. A header row is skipped. Voxels less likely to be occupied than
+/// `threshold` are left out and the rest are drawn with their occupancy as opacity.
+pub fn parse(text: &str, size: f64, threshold: f32) -> Result<GlyphLayer, String> {
+    let mut rows = Vec::new();
+    for (i, row) in text.lines().enumerate() {
+        let fields: Vec<&str> = row.split(',').map(|field| field.trim()).collect();
+        if row.trim().is_empty() || (i == 0 && fields[0].parse::<f64>().is_err()) {
+            continue;
+        }
+        if fields.len() != 6 && fields.len() != 7 {
+            return Err(format!(
+                "Voxel row {} needs 6 or 7 cols: X, Y, Z, R, G, B and optionally occupancy",
+                i + 1
+            ));
+        }
+        let values = fields
+            .iter()
+            .map(|field| field.parse::<f64>())
+            .collect::<Result<Vec<f64>, _>>()
+            .map_err(|_| format!("Invalid number in voxel row {}", i + 1))?;
+        rows.push(values);
+    }
+    // Relative to the first voxel, so distant grids keep their precision as f32
+    let origin = rows.first().map_or(nalgebra::Vector3::zeros(), |row| {
+        nalgebra::Vector3::new(row[0], row[1], row[2])
+    });
+    let instances = rows
+        .iter()
+        .filter_map(|row| {
+            let occupancy = row.get(6).map_or(1.0, |&occupancy| occupancy as f32);
+            if occupancy < threshold {
+                return None;
+            }
+            let center = nalgebra::Vector3::new(
+                (row[0] - origin.x) as f32,
+                (row[1] - origin.y) as f32,
+                (row[2] - origin.z) as f32,
+            );
+            let color = [
+                row[3] as f32,
+                row[4] as f32,
+                row[5] as f32,
+                occupancy.min(1.0),
+            ];
+            return Some(cube(center, size as f32, color));
+        })
+        .collect();
+    return Ok(GlyphLayer {
+        shape: Shape::Cube,
+        instances: instances,
+        origin: origin,
+        wireframe: false,
+    });
+}
+
+fn cube(center: nalgebra::Vector3<f32>, size: f32, color: [f32; 4]) -> Instance {
+    let transform =
+        nalgebra::Matrix4::new_translation(&center) * nalgebra::Matrix4::new_scaling(size);
+    return Instance::new(transform, color);
+}
@@ -0,0 +1,97 @@
+//! Watch-folder ingest, so rscat can be the always-on display at the end of an automated
+//! processing pipeline: files that appear in a directory are loaded once they stop changing.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+/// How often the directory is listed.
+pub const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A file's size and modification time, which stop changing once it has been written.
+type Stamp = (u64, Option<SystemTime>);
+
+pub enum FolderChange {
+    /// A new file, or one rewritten since it was loaded, that has stopped changing.
+    Added(PathBuf),
+    Removed(PathBuf),
+}
+
+pub struct FolderWatcher {
+    dir: PathBuf,
+    /// Files reported as added, and their stamp then.
+    loaded: HashMap<PathBuf, Stamp>,
+    /// Files that are new or changed, with their stamp at the last poll. They are only reported
+    /// once a poll finds the same stamp, so a file still being written isn't loaded half-way.
+    pending: HashMap<PathBuf, Stamp>,
+    last_poll: Option<Instant>,
+}
+
+impl FolderWatcher {
+    pub fn new(dir: PathBuf) -> Self {
+        FolderWatcher {
+            dir: dir,
+            loaded: HashMap::new(),
+            pending: HashMap::new(),
+            last_poll: None,
+        }
+    }
+
+    /// The files that settled or were deleted since the last poll, at most once every
+    /// `POLL_INTERVAL`. Files that `supported` turns down are ignored. Files already in the
+    /// directory count as new, so they are loaded at start.
+    pub fn poll(&mut self, supported: impl Fn(&Path) -> bool) -> Vec<FolderChange> {
+        let now = Instant::now();
+        if self
+            .last_poll
+            .is_some_and(|last| now - last < POLL_INTERVAL)
+        {
+            return Vec::new();
+        }
+        self.last_poll = Some(now);
+        let entries = match std::fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(_) => return Vec::new(),
+        };
+        let mut present = HashMap::new();
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let path = entry.path();
+            let metadata = match entry.metadata() {
+                Ok(metadata) if metadata.is_file() && supported(&path) => metadata,
+                _ => continue,
+            };
+            present.insert(path, (metadata.len(), metadata.modified().ok()));
+        }
+
+        let mut changes = Vec::new();
+        let mut removed: Vec<PathBuf> = self
+            .loaded
+            .keys()
+            .filter(|path| !present.contains_key(*path))
+            .cloned()
+            .collect();
+        removed.sort();
+        for path in removed {
+            self.loaded.remove(&path);
+            changes.push(FolderChange::Removed(path));
+        }
+        self.pending.retain(|path, _| present.contains_key(path));
+        let mut added = Vec::new();
+        for (path, stamp) in present {
+            if self.loaded.get(&path) == Some(&stamp) {
+                continue;
+            }
+            if self.pending.get(&path) == Some(&stamp) {
+                self.pending.remove(&path);
+                self.loaded.insert(path.clone(), stamp);
+                added.push(path);
+            } else {
+                self.pending.insert(path, stamp);
+            }
+        }
+        // Load in name order, e.g. for numbered tiles
+        added.sort();
+        changes.extend(added.into_iter().map(FolderChange::Added));
+        return changes;
+    }
+}